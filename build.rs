@@ -0,0 +1,62 @@
+use std::{
+    path::PathBuf,
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+#[path = "src/utils/date.rs"]
+mod date;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+
+    // Watch only the git files that exist, since a missing path makes cargo rerun every build
+    if let Some(git_dir) = command_output("git", &["rev-parse", "--git-dir"]) {
+        let git_dir = PathBuf::from(git_dir);
+        for path in [git_dir.join("HEAD"), git_dir.join("refs/heads")] {
+            if path.exists() {
+                println!("cargo:rerun-if-changed={}", path.display());
+            }
+        }
+    }
+
+    let git_sha = command_output("git", &["rev-parse", "--short=10", "HEAD"])
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=DE_BUILD_GIT_SHA={git_sha}");
+
+    // Honor SOURCE_DATE_EPOCH for reproducible builds
+    let timestamp = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or_default()
+        });
+    println!(
+        "cargo:rustc-env=DE_BUILD_DATE={}",
+        date::format_date(timestamp)
+    );
+
+    let target = std::env::var("TARGET").unwrap_or_else(|_| "unknown".to_string());
+    println!("cargo:rustc-env=DE_BUILD_TARGET={target}");
+
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let rustc_version =
+        command_output(&rustc, &["--version"]).unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=DE_BUILD_RUSTC_VERSION={rustc_version}");
+}
+
+// The build script has no log file to record commands in
+#[allow(clippy::disallowed_methods)]
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let output = String::from_utf8(output.stdout).ok()?;
+    Some(output.trim().to_string()).filter(|output| !output.is_empty())
+}
@@ -2,15 +2,45 @@ use std::path::PathBuf;
 
 use clap::{Parser, Subcommand};
 
-use crate::types::Slug;
+use crate::{
+    config::UpdateChannel,
+    constants::LONG_VERSION,
+    types::{HumanDuration, ProjectArg, Slug},
+    workspace::OnConflict,
+};
 
 #[derive(Debug, Parser)]
-#[command(version, about, long_about = None)]
+#[command(version, long_version = LONG_VERSION, about, long_about = None)]
 pub struct Cli {
     /// Increase verbosity for debugging purposes.
     #[arg(short, long, action = clap::ArgAction::Count)]
     pub verbose: u8,
 
+    /// Disable colored output. Also enabled by setting the `NO_COLOR` environment variable.
+    #[arg(long, global = true)]
+    pub no_color: bool,
+
+    /// Use ASCII symbols and no spinners or progress bars, e.g. for CI logs.
+    #[arg(long, global = true)]
+    pub plain: bool,
+
+    /// Append what de runs, including every git and Docker command, to a log file in the de
+    /// data directory. Also enabled by setting the `DE_LOG` environment variable.
+    #[arg(long, global = true)]
+    pub log_file: bool,
+
+    /// The workspace for this command, unless the command is given one itself. Overrides the
+    /// current project's and the active workspace. Goes before the command, or after it for
+    /// commands without a workspace argument of their own, e.g. `de task list --workspace api`.
+    #[arg(short, long, global = true)]
+    pub workspace: Option<Slug>,
+
+    /// Work as if de was started in this directory when looking for the current project,
+    /// e.g. `de --dir services/api run test`. Paths given to commands stay relative to the
+    /// actual working directory. Must come before the command.
+    #[arg(long, value_name = "PATH")]
+    pub dir: Option<PathBuf>,
+
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -29,6 +59,15 @@ pub enum Commands {
         /// The name of the project. Defaults to the current directory name.
         #[arg(short, long)]
         name: Option<Slug>,
+
+        /// What to do if the project ID is already registered with a different directory.
+        #[arg(long, value_enum, default_value_t = OnConflict::Error)]
+        on_conflict: OnConflict,
+
+        /// Also run `git init`, write a `.gitignore` that ignores `.env`, and commit `de.toml`,
+        /// unless the directory is already a repository.
+        #[arg(long)]
+        git: bool,
     },
 
     /// Spin up projects. If workspace is provided, spins up all projects in the workspace.
@@ -40,6 +79,15 @@ pub enum Commands {
         /// Skip confirmation prompts and proceed with starting.
         #[arg(short, long)]
         yes: bool,
+
+        /// Run the current project in the foreground, streaming its logs until Ctrl-C.
+        /// Dependencies are still started in the background.
+        #[arg(long, conflicts_with = "workspace")]
+        attach: bool,
+
+        /// Stop the project's services once the foreground run ends.
+        #[arg(long, requires = "attach")]
+        down_on_exit: bool,
     },
 
     /// Spin down all projects in the workspace.
@@ -48,25 +96,134 @@ pub enum Commands {
         #[arg(short, long)]
         workspace: Option<Slug>,
 
+        /// Stop every workspace that has running services.
+        #[arg(long, conflicts_with = "workspace")]
+        all: bool,
+
         /// Skip confirmation prompts and proceed with stopping.
         #[arg(short, long)]
         yes: bool,
+
+        /// How long to wait for services to shut down before they are killed, e.g. `30s`.
+        /// Rounded up to whole seconds.
+        #[arg(short, long, value_name = "DURATION")]
+        timeout: Option<HumanDuration>,
+    },
+
+    /// Take down the workspace's Docker Compose projects and remove their volumes or images.
+    Prune {
+        /// The name of the workspace to prune. Defaults to the active workspace.
+        #[arg(short, long)]
+        workspace: Option<Slug>,
+
+        /// Remove the volumes of the services, including their data.
+        #[arg(long)]
+        volumes: bool,
+
+        /// Remove the images built for the services.
+        #[arg(long)]
+        images: bool,
+
+        /// Skip the confirmation prompt.
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Remove data de can recreate, such as task caches and log files. Workspaces and the de
+    /// configuration are never removed. Without flags, removes the cache and the logs.
+    Clean {
+        /// Remove the cache, e.g. the inputs hashes of `de run --if-changed`.
+        #[arg(long)]
+        cache: bool,
+
+        /// Remove the log files written with `de --log-file` or `DE_LOG`.
+        #[arg(long)]
+        logs: bool,
+
+        /// Also remove the installed shims, which `de shim add` has to recreate.
+        #[arg(long, conflicts_with_all = ["cache", "logs"])]
+        all: bool,
+
+        /// Skip the confirmation prompt of `--all`.
+        #[arg(short, long, requires = "all")]
+        yes: bool,
     },
 
     /// Run a command in the context of the current project.
     Run {
-        /// The command to run listed in config file.
-        command: Slug,
+        /// The command to run listed in config file. Defaults to the project's `default_task`
+        /// or task named `default`, then the workspace's.
+        command: Option<Slug>,
 
         /// The name of the project to run the command in. Defaults to the current project.
+        /// Use "all" to run the task in every project of the workspace that defines it. A path,
+        /// e.g. `./services/api`, runs the project there, even if it isn't registered.
         #[arg(short, long)]
-        project: Option<Slug>,
+        project: Option<ProjectArg>,
 
         /// The name of the workspace to run the command in. Defaults to the active workspace.
         #[arg(short, long)]
         workspace: Option<Slug>,
 
-        /// Additional arguments to pass to the command.
+        /// Kill the task and fail if it runs longer than this, e.g. `90s` or `5m`. When stdin is
+        /// not a terminal, processes the task started are killed with it.
+        #[arg(long, value_name = "DURATION")]
+        timeout: Option<HumanDuration>,
+
+        /// Print the command line, working directory and names of the variables set instead of
+        /// running the task.
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Run the task in this directory, relative to the project directory. It must stay
+        /// inside the project. Only applies to project tasks that run on the host.
+        #[arg(long, value_name = "DIR")]
+        cwd: Option<PathBuf>,
+
+        /// Set an environment variable for the task, overriding the project and workspace
+        /// environment (repeatable).
+        #[arg(long, value_name = "KEY=VALUE", value_parser = parse_env_var)]
+        env: Vec<(String, String)>,
+
+        /// Rerun the project task whenever files in the project change, until Ctrl-C.
+        /// `.git`, `target` and `node_modules` are not watched.
+        #[arg(long, conflicts_with_all = ["dry_run", "timeout"])]
+        watch: bool,
+
+        /// Only watch files matching this glob, relative to the project directory (repeatable).
+        /// Patterns without a `/` match file names in any directory, e.g. `*.rs`.
+        #[arg(long, value_name = "GLOB", requires = "watch")]
+        watch_path: Vec<String>,
+
+        /// Start the service of a Docker Compose task if it isn't running, instead of asking.
+        #[arg(long)]
+        auto_start: bool,
+
+        /// Skip the task if the files matching its `inputs` are unchanged since its last
+        /// successful run, and its `outputs` still exist.
+        #[arg(long, conflicts_with = "watch")]
+        if_changed: bool,
+
+        /// Run only this task, without the tasks in its `depends_on`, e.g. because they already
+        /// ran.
+        #[arg(long)]
+        no_deps: bool,
+
+        /// How many of the task's prerequisites to run at once, where they don't depend on each
+        /// other. Their output is shown in one piece, in a stable order, once they're done.
+        #[arg(short, long, default_value_t = 1, conflicts_with = "no_deps")]
+        jobs: usize,
+
+        /// Also write everything the task prints to stdout and stderr to this file.
+        #[arg(long, value_name = "PATH", conflicts_with_all = ["watch", "capture"])]
+        tee: Option<PathBuf>,
+
+        /// Write everything the task prints to stdout and stderr to this file instead of the
+        /// terminal.
+        #[arg(long, value_name = "PATH", conflicts_with = "watch")]
+        capture: Option<PathBuf>,
+
+        /// Additional arguments to pass to the command, after `--` (e.g. `de run tool -- --help`).
         #[arg(last = true)]
         args: Vec<String>,
     },
@@ -74,12 +231,36 @@ pub enum Commands {
     /// Execute a command in a project's context.
     Exec {
         /// The name of the project to execute the command in.
-        project: Slug,
+        #[arg(required_unless_present = "workspace_root")]
+        project: Option<Slug>,
+
+        /// Run the command once in the workspace root directory instead of a project.
+        #[arg(long, conflicts_with = "project")]
+        workspace_root: bool,
 
         /// The name of the workspace to execute the command in. Defaults to the active workspace.
         #[clap(short, long)]
         workspace: Option<Slug>,
 
+        /// Set an environment variable for the command, overriding the project environment
+        /// (repeatable).
+        #[arg(long, value_name = "KEY=VALUE", value_parser = parse_env_var)]
+        env: Vec<(String, String)>,
+
+        /// Don't add the variables of the project's `.env` file to the environment of the
+        /// command. `--env` values are still set.
+        #[arg(long, conflicts_with = "service")]
+        no_env: bool,
+
+        /// Run the command in this Docker Compose service of the project, with `docker-compose exec`.
+        #[arg(short, long, conflicts_with = "workspace_root")]
+        service: Option<String>,
+
+        /// Don't allocate a TTY in the service, e.g. when piping output. A TTY is only allocated
+        /// when stdin is a terminal anyway.
+        #[arg(long, requires = "service")]
+        no_tty: bool,
+
         /// The command to execute.
         #[clap(last = true)]
         command: Vec<String>,
@@ -91,16 +272,70 @@ pub enum Commands {
         #[clap(short, long)]
         workspace: Option<Slug>,
 
+        /// The order to run the command in the projects in.
+        #[arg(long, value_enum, default_value_t = ExecOrder::Name)]
+        order: ExecOrder,
+
+        /// Run the command in this directory, relative to each project directory. Projects
+        /// without it are skipped.
+        #[arg(long, value_name = "DIR")]
+        subdir: Option<PathBuf>,
+
+        /// Don't add the variables of each project's `.env` file to the environment of the
+        /// command.
+        #[arg(long)]
+        no_env: bool,
+
         /// The command to execute.
         #[clap(last = true)]
         command: Vec<String>,
     },
 
+    /// Print the environment a task in the project would receive.
+    Env {
+        /// The name of the project to print the environment of. Defaults to the current project.
+        #[arg(short, long)]
+        project: Option<Slug>,
+
+        /// The workspace to look the project up in. Defaults to the current workspace.
+        #[arg(short, long)]
+        workspace: Option<Option<Slug>>,
+
+        /// The output format.
+        #[arg(long, value_enum, default_value_t = EnvFormat::Shell)]
+        format: EnvFormat,
+
+        /// Print values of secret-looking variables (`*SECRET*`, `*TOKEN*`, `*PASSWORD*`) unmasked.
+        #[arg(long)]
+        show_secrets: bool,
+    },
+
     /// List all projects of the current workspace.
     List {
         /// The name of the workspace to list projects from. Defaults to the current workspace.
         #[arg(short, long)]
         workspace: Option<Slug>,
+
+        /// Only list projects whose directory no longer exists, one per line as `<id>\t<dir>`.
+        #[arg(long)]
+        missing: bool,
+
+        /// Remove the missing projects from the workspace configuration.
+        #[arg(long, requires = "missing")]
+        prune: bool,
+
+        /// Skip the confirmation prompt when pruning.
+        #[arg(short, long, requires = "prune")]
+        yes: bool,
+
+        /// How to order the projects.
+        #[arg(long, value_enum, default_value_t = ListSort::Name, conflicts_with = "missing")]
+        sort: ListSort,
+
+        /// Print one project per line as `<id>\t<dir>\t<present>`, without colors or headers.
+        /// This format is kept stable for scripts.
+        #[arg(long, conflicts_with = "missing")]
+        porcelain: bool,
     },
 
     /// Scan de projects and update the workspace configs.
@@ -111,6 +346,25 @@ pub enum Commands {
         /// The name of the workspace to discover projects in. Defaults to all workspaces.
         #[arg(short, long)]
         workspace: Option<Slug>,
+
+        /// Also remove registrations whose directory no longer contains a `de.toml`, from
+        /// `--workspace` or else the active workspace.
+        #[arg(long)]
+        prune: bool,
+
+        /// Prune the registrations of every workspace instead of a single one.
+        #[arg(long, requires = "prune", conflicts_with = "workspace")]
+        all: bool,
+
+        /// Create a minimal `de.toml` in directories that have a Docker Compose file but no
+        /// manifest, and register them in `--workspace` (prompted for if not given).
+        #[arg(long)]
+        init_missing: bool,
+
+        /// Follow symbolic links to directories while scanning. Directories reached more than
+        /// once, e.g. through a link cycle, are only scanned the first time.
+        #[arg(long)]
+        follow_symlinks: bool,
     },
 
     /// Update workspace registrations and project configurations.
@@ -125,13 +379,36 @@ pub enum Commands {
     },
 
     /// Manage the workspace setup and configuration.
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
     Setup {
-        /// The snapshot file to create or apply to the workspace.
-        snapshot: PathBuf,
+        #[command(subcommand)]
+        command: Option<SetupCommands>,
+
+        /// The snapshot file to create or apply to the workspace, or an `http(s)://` URL to
+        /// download it from.
+        #[arg(required = true)]
+        snapshot: Option<PathBuf>,
 
         /// The directory to apply the snapshot to. Defaults to the current directory.
         #[arg(short, long)]
         target_dir: Option<PathBuf>,
+
+        /// Check out the latest branch tip instead of the commit recorded in the snapshot.
+        #[arg(long)]
+        latest: bool,
+
+        /// How many projects to clone at once. Defaults to the number of CPUs.
+        #[arg(short, long)]
+        jobs: Option<usize>,
+
+        /// Only set up this project from the snapshot. Can be given multiple times.
+        #[arg(long, value_name = "PROJECT")]
+        only: Vec<Slug>,
+
+        /// Skip the steps that read stdin from a file in the snapshot, e.g. in CI where those
+        /// files are placeholders. The skipped steps are listed at the end.
+        #[arg(long)]
+        no_input: bool,
     },
 
     /// Manage tasks defined in the project.
@@ -141,7 +418,6 @@ pub enum Commands {
     },
 
     /// Manage shims for de commands.
-    #[cfg(target_family = "unix")]
     Shim {
         #[command(subcommand)]
         command: ShimCommands,
@@ -164,12 +440,81 @@ pub enum Commands {
     Doctor {
         /// The name of the workspace to diagnose. Defaults to the active workspace.
         workspace: Option<Slug>,
+
+        /// The output format. `json` prints every check and the totals for use in CI.
+        #[arg(long, value_enum, default_value_t = DoctorFormat::Text)]
+        format: DoctorFormat,
+
+        /// Also exit with a non-zero status if there are only warnings.
+        #[arg(long)]
+        strict: bool,
+
+        /// Only run these sections of checks, e.g. `workspace` on a CI runner without Docker.
+        #[arg(long, value_enum, value_delimiter = ',', value_name = "SECTION")]
+        only: Vec<DoctorSection>,
+
+        /// Hide results below this severity. The totals and the exit status still count every
+        /// check that ran.
+        #[arg(long, value_enum, default_value_t = DoctorSeverity::Info, value_name = "LEVEL")]
+        min_severity: DoctorSeverity,
+    },
+
+    /// Show an overview of the de environment and the active workspace.
+    Info,
+
+    /// Check a project manifest for syntax errors and unknown fields (e.g. typos).
+    Validate {
+        /// The project in the active workspace to check. Defaults to the current project.
+        #[arg(short, long)]
+        project: Option<Slug>,
+    },
+
+    /// Print the JSON Schema of `de.toml` or a workspace configuration, for editor completion.
+    Schema {
+        /// The file to print the schema of.
+        #[arg(value_enum)]
+        kind: SchemaKind,
     },
 
     /// Show the status of the current workspace and projects.
     Status {
         /// The name of the workspace to show status for. Defaults to the active workspace.
         workspace: Option<Slug>,
+
+        /// Only show projects with uncommitted changes.
+        #[arg(long)]
+        dirty: bool,
+
+        /// Only show projects with Docker Compose services that are down.
+        #[arg(long)]
+        downed: bool,
+
+        /// Only show projects that are behind their remote.
+        #[arg(long)]
+        behind: bool,
+
+        /// Only show projects that are not on the workspace's default branch.
+        #[arg(long)]
+        off_branch: bool,
+
+        /// Fetch every project's remote first, so ahead/behind counts are up to date. Slower, as
+        /// it needs network access.
+        #[arg(long)]
+        fetch: bool,
+
+        /// Gather the git status of every project again, instead of reusing results from the
+        /// last two seconds for repositories whose HEAD and index didn't change.
+        #[arg(long)]
+        no_cache: bool,
+
+        /// Exit with a non-zero code if a project has uncommitted changes or unpushed commits,
+        /// e.g. to fail CI. Shorthand for `--fail-on dirty,unpushed`.
+        #[arg(long)]
+        exit_code: bool,
+
+        /// Exit with a non-zero code if a project matches one of these conditions.
+        #[arg(long, value_enum, value_delimiter = ',', value_name = "CHECK")]
+        fail_on: Vec<StatusCheck>,
     },
 
     /// Manage git repositories in the workspace.
@@ -180,7 +525,8 @@ pub enum Commands {
 
     /// Manage the configuration of the de CLI.
     Config {
-        /// The property key to set or get (e.g., "active").
+        /// The property key to set or get (e.g., "active"), or "origin" to show which source
+        /// set each value of the project manifest.
         key: String,
 
         /// The value to set for the property. If omitted, prints the current value.
@@ -189,6 +535,11 @@ pub enum Commands {
         /// Whether to unset the property instead of setting it.
         #[arg(short, long)]
         unset: bool,
+
+        /// With "origin", the project in the active workspace to show. Defaults to the current
+        /// project.
+        #[arg(short, long)]
+        project: Option<Slug>,
     },
 
     #[command(external_subcommand)]
@@ -209,16 +560,66 @@ pub enum GitCommands {
         /// What to do if there are uncommitted changes.
         #[arg(long)]
         on_dirty: Option<OnDirtyAction>,
+
+        /// Leave stashed changes in the stash if switching a project fails after they were
+        /// stashed, instead of popping them back onto the original branch.
+        #[arg(long)]
+        keep_stash_on_failure: bool,
+
+        /// The message of the stashes made for uncommitted changes, so `git stash list` shows
+        /// what they were for. Defaults to `de switch auto-stash <branch> <time>`.
+        #[arg(long, value_name = "MSG")]
+        stash_message: Option<String>,
+
+        /// Fetch each project's remotes before looking for the branch (the default).
+        #[arg(long, overrides_with = "no_fetch")]
+        fetch: bool,
+
+        /// Only consider branches that are already known locally.
+        #[arg(long, overrides_with = "fetch")]
+        no_fetch: bool,
+
+        /// How many projects to switch at once.
+        #[arg(short, long, default_value_t = 1)]
+        jobs: usize,
+
+        /// Print the branch each project would end up on, without switching or stashing.
+        #[arg(long)]
+        dry_run: bool,
+
+        /// A task to run in each project after it was switched, e.g. to reinstall dependencies.
+        /// Projects are visited in dependency order; skipped and failed projects are left out.
+        #[arg(long, value_name = "TASK")]
+        run: Option<Slug>,
+
+        /// Also write the outcome for each project (branch, stashed, conflicted, skipped) to
+        /// this file as JSON, e.g. for CI to report on.
+        #[arg(long, value_name = "FILE", conflicts_with = "dry_run")]
+        report: Option<PathBuf>,
     },
 
     /// Reset all projects to a clean state on a base branch before starting new work.
     BaseReset {
-        /// The base branch to reset to. Defaults to the workspace's default branch or 'dev'.
+        /// The base branch to reset to. Defaults to each project's default branch.
         base_branch: Option<String>,
 
         /// What to do if there are uncommitted changes.
         #[arg(short = 'd', long, value_enum, default_value_t = OnDirtyAction::Prompt)]
         on_dirty: OnDirtyAction,
+
+        /// What to do if the current branch has unpushed commits. Asks by default with
+        /// `--on-dirty prompt`, and aborts otherwise, so unattended runs never wait for input.
+        #[arg(long, value_enum)]
+        on_unpushed: Option<OnUnpushedAction>,
+
+        /// Fail on the first fetch error instead of retrying transient failures.
+        #[arg(long)]
+        no_retry: bool,
+
+        /// The message of the stashes made for uncommitted changes, so `git stash list` shows
+        /// what they were for. Defaults to `de base-reset auto-stash <branch> <time>`.
+        #[arg(long, value_name = "MSG")]
+        stash_message: Option<String>,
     },
 }
 
@@ -234,16 +635,163 @@ pub enum OnDirtyAction {
     Abort,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, clap::ValueEnum)]
+pub enum StatusCheck {
+    /// A project has uncommitted changes.
+    Dirty,
+    /// A project has commits that aren't pushed to its remote.
+    Unpushed,
+    /// A project has Docker Compose services that are down.
+    Downed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OnUnpushedAction {
+    /// Push the commits, then reset the project.
+    Push,
+    /// Leave the project as it is.
+    Skip,
+    /// Stop processing the remaining projects.
+    Abort,
+    /// Reset the project anyway, leaving the commits only in the reflog.
+    Proceed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExecOrder {
+    /// Alphabetically by project ID.
+    Name,
+    /// Dependencies first, like `de start`.
+    Startup,
+    /// Dependents first, like `de stop`.
+    Shutdown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ListSort {
+    /// Alphabetically by project ID.
+    Name,
+    /// In startup order, dependencies first.
+    Deps,
+    /// Present projects first, then missing ones.
+    Status,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SchemaKind {
+    /// A project manifest (`de.toml`).
+    Manifest,
+    /// A workspace configuration.
+    Workspace,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SetupCommands {
+    /// Show the projects, steps, git config and step environments of a snapshot without applying it.
+    Inspect {
+        /// The snapshot file to inspect.
+        snapshot: PathBuf,
+
+        /// The output format. `dotenv` prints only the step environments.
+        #[arg(long, value_enum, default_value_t = SnapshotInspectFormat::Text)]
+        format: SnapshotInspectFormat,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SnapshotInspectFormat {
+    /// A human-readable summary.
+    Text,
+    /// The full snapshot manifest as JSON.
+    Json,
+    /// `KEY="value"` lines per step.
+    Dotenv,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum DoctorFormat {
+    /// Human-readable sections.
+    Text,
+    /// A JSON object with all checks and the error and warning totals.
+    Json,
+}
+
+/// A section of checks run by `de doctor`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum DoctorSection {
+    /// Docker and Docker Compose.
+    System,
+    /// The manifest of the current project.
+    Project,
+    /// The workspace configuration and its projects, e.g. the dependency graph.
+    Workspace,
+    /// The installed shims.
+    Shims,
+}
+
+/// The severity of a `de doctor` result, from least to most severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
+pub enum DoctorSeverity {
+    /// Passed checks and notes.
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum TaskListFormat {
+    /// A human-readable list.
+    Text,
+    /// A JSON array of tasks with their name, command, service, description and kind.
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum EnvFormat {
+    /// `export KEY='value'` lines.
+    Shell,
+    /// A JSON object.
+    Json,
+    /// `KEY="value"` lines.
+    Dotenv,
+}
+
 #[derive(Debug, Subcommand)]
 pub enum TaskCommands {
-    /// Check if a task is defined in the project.
+    /// Check if a task is defined in the project or the active workspace, like `de run` resolves it.
+    ///
+    /// Exits with status 0 if the task exists and non-zero otherwise, printing nothing to stdout
+    /// unless `--verbose` is passed.
     Check {
         /// The name of the task to check.
         task: Slug,
+
+        /// Look the task up like `de run --workspace`: in the current project only if it
+        /// belongs to this workspace, then in the workspace's tasks.
+        #[arg(short, long)]
+        workspace: Option<Slug>,
+
+        /// Print a message when the task exists.
+        #[arg(short, long, conflicts_with = "quiet")]
+        verbose: bool,
+
+        /// Don't report a missing task on stderr either, only through the exit status.
+        #[arg(short, long)]
+        quiet: bool,
     },
 
     /// List all tasks defined in the project.
-    List,
+    List {
+        /// The output format. `json` is meant for editors building task menus.
+        #[arg(long, value_enum, default_value_t = TaskListFormat::Text)]
+        format: TaskListFormat,
+    },
+
+    /// Open the file defining a task in `$EDITOR`, resolving it like `de run`.
+    Edit {
+        /// The name of the task to edit.
+        task: Slug,
+    },
 
     /// Add a task to the project or workspace configuration.
     Add {
@@ -253,15 +801,25 @@ pub enum TaskCommands {
         /// The command to execute for the task.
         task_command: String,
 
-        /// The Docker Compose service to execute the command in (for project tasks).
+        /// The Docker Compose service to execute the command in. Only for project tasks, so with
+        /// `--workspace` it needs `--project` too.
         #[clap(short, long)]
         service: Option<String>,
 
+        /// A human-readable description, shown in `de task list`.
+        #[clap(short, long)]
+        description: Option<String>,
+
+        /// Run the command through `sh -c`, allowing pipes, `&&` and quoting.
+        #[clap(long)]
+        shell: bool,
+
         /// The name of the project to add the task to. Defaults to the current project.
         #[clap(short, long)]
         project: Option<Slug>,
 
-        /// Add the task to the workspace configuration instead of the project.
+        /// Add the task to the workspace configuration instead of the project. With `--project`,
+        /// add it to that project of the workspace instead.
         #[clap(short, long)]
         workspace: Option<Option<Slug>>,
     },
@@ -279,6 +837,36 @@ pub enum TaskCommands {
         #[clap(short, long)]
         workspace: Option<Option<Slug>>,
     },
+
+    /// Move a task to the workspace configuration or to another project.
+    Move {
+        /// The name of the task to move.
+        task: Slug,
+
+        /// The project to move the task out of. Defaults to the current project.
+        #[clap(short, long)]
+        project: Option<Slug>,
+
+        /// Move a task out of the workspace configuration instead of a project.
+        #[clap(short, long)]
+        workspace: Option<Option<Slug>>,
+
+        /// Move the task into the workspace configuration.
+        #[clap(
+            long,
+            conflicts_with = "to_project",
+            required_unless_present = "to_project"
+        )]
+        to_workspace: bool,
+
+        /// Move the task into this project of the same workspace.
+        #[clap(long)]
+        to_project: Option<Slug>,
+
+        /// Overwrite a task with the same name at the destination without prompting.
+        #[clap(short, long)]
+        yes: bool,
+    },
 }
 
 #[derive(Debug, Subcommand)]
@@ -286,13 +874,23 @@ pub enum ShimCommands {
     /// Add a shim for a specific command.
     Add {
         /// The command to shim.
-        command: Slug,
+        #[arg(required_unless_present = "all_tasks")]
+        command: Option<Slug>,
+
+        /// Add a shim for every task of the current project.
+        #[arg(long, conflicts_with = "command")]
+        all_tasks: bool,
     },
 
     /// Remove a shim for a specific command.
     Remove {
         /// The command to remove the shim for.
-        command: Slug,
+        #[arg(required_unless_present = "all")]
+        command: Option<Slug>,
+
+        /// Remove all shims managed by de.
+        #[arg(long, conflicts_with = "command")]
+        all: bool,
     },
 
     /// Update the current shims to the latest version.
@@ -311,7 +909,19 @@ pub enum ShimCommands {
 #[derive(Debug, Subcommand)]
 pub enum SelfCommands {
     /// Update the de CLI itself.
-    Update,
+    Update {
+        /// The release track to update from. It is remembered for later updates; defaults to
+        /// the remembered track, or stable.
+        #[arg(long, value_enum)]
+        channel: Option<UpdateChannel>,
+
+        /// Only report whether a newer release is available, without installing it.
+        #[arg(long)]
+        check: bool,
+    },
+
+    /// Show the version with the commit and build details, for bug reports.
+    Version,
 }
 
 #[derive(Debug, Subcommand)]
@@ -319,25 +929,38 @@ pub enum WorkspaceCommands {
     /// Run a task defined in the workspace configuration.
     Run {
         /// The name of the task to run.
-        task: Slug,
+        #[arg(required_unless_present = "list")]
+        task: Option<Slug>,
+
+        /// List the workspace tasks and their commands instead of running one.
+        #[arg(short, long, conflicts_with_all = ["task", "args"])]
+        list: bool,
+
+        /// The output format of `--list`. `json` is meant for editors building task menus.
+        #[arg(long, value_enum, default_value_t = TaskListFormat::Text, requires = "list")]
+        format: TaskListFormat,
 
         /// The name of the workspace to run the task in. Defaults to the active workspace.
         #[clap(short, long)]
         workspace: Option<Slug>,
 
-        /// Additional arguments to pass to the task command.
-        #[clap(hide = true)]
+        /// Additional arguments to pass to the task command. Flags are passed through too, and
+        /// `--` can separate them from de's own options (e.g. `de workspace run lint -- --fix`).
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
         args: Vec<String>,
     },
 
-    /// Set or get a property on the workspace (e.g., active, default-branch).
+    /// Set or get a property on the workspace (e.g., active, default-branch, root-dir,
+    /// default-task).
     Config {
         /// The name of the workspace to modify. Defaults to the active workspace.
         #[arg(short, long)]
         workspace: Option<Slug>,
 
-        /// The property key to set or get (e.g., "active", "default-branch").
-        key: String,
+        /// The property key to set or get (e.g., "active", "default-branch", "root-dir",
+        /// "default-task").
+        #[arg(required_unless_present_any = ["path", "edit"])]
+        key: Option<String>,
 
         /// The value to set for the property. If omitted, prints the current value.
         value: Option<String>,
@@ -345,6 +968,96 @@ pub enum WorkspaceCommands {
         /// Whether to unset the property instead of setting it.
         #[arg(short, long)]
         unset: bool,
+
+        /// Set the value even if it fails validation, e.g. a default branch that exists in no project.
+        #[arg(short, long, conflicts_with = "unset")]
+        force: bool,
+
+        /// Print the path of the workspace's configuration file instead.
+        #[arg(long, conflicts_with_all = ["key", "unset", "force"])]
+        path: bool,
+
+        /// Edit the whole configuration file in `$EDITOR` instead. It is only saved once it is
+        /// valid.
+        #[arg(long, conflicts_with_all = ["key", "unset", "force", "path"])]
+        edit: bool,
+    },
+
+    /// Duplicate a workspace configuration under a new name.
+    Clone {
+        /// The name of the workspace to clone.
+        from: Slug,
+
+        /// The name of the new workspace.
+        to: Slug,
+    },
+
+    /// Move the projects of one workspace into another, updating their manifests.
+    Merge {
+        /// The name of the workspace to move the projects from.
+        from: Slug,
+
+        /// The name of the workspace to move the projects into.
+        into: Slug,
+
+        /// Also copy the workspace tasks.
+        #[arg(long)]
+        tasks: bool,
+
+        /// Delete the source workspace once all of its projects were moved. Not allowed for a
+        /// repo-local workspace, whose file belongs to its repository.
+        #[arg(long)]
+        delete_source: bool,
+    },
+
+    /// List the projects registered in the workspace, including ones without a `de.toml`.
+    Projects {
+        /// The name of the workspace to list the projects of. Defaults to the active workspace.
+        #[arg(short, long)]
+        workspace: Option<Slug>,
+    },
+
+    /// Register a directory as a project of the workspace, even if it has no `de.toml` yet.
+    AddProject {
+        /// The ID to register the project under.
+        project: Slug,
+
+        /// The project directory.
+        dir: PathBuf,
+
+        /// The name of the workspace to add the project to. Defaults to the active workspace.
+        #[arg(short, long)]
+        workspace: Option<Slug>,
+    },
+
+    /// Remove a project from the workspace. The project directory is left untouched.
+    RemoveProject {
+        /// The ID of the project to remove.
+        project: Slug,
+
+        /// The name of the workspace to remove the project from. Defaults to the active workspace.
+        #[arg(short, long)]
+        workspace: Option<Slug>,
+    },
+
+    /// Skip a project in workspace-wide commands (start, stop, status, git) without removing it.
+    Disable {
+        /// The ID of the project to disable.
+        project: Slug,
+
+        /// The name of the workspace of the project. Defaults to the active workspace.
+        #[arg(short, long)]
+        workspace: Option<Slug>,
+    },
+
+    /// Include a disabled project in workspace-wide commands again.
+    Enable {
+        /// The ID of the project to enable.
+        project: Slug,
+
+        /// The name of the workspace of the project. Defaults to the active workspace.
+        #[arg(short, long)]
+        workspace: Option<Slug>,
     },
 
     /// Get information about a workspace.
@@ -352,6 +1065,11 @@ pub enum WorkspaceCommands {
         /// The name of the workspace to get information about. Defaults to the active workspace.
         #[arg(short, long)]
         workspace: Option<Slug>,
+
+        /// Also show the branch of each project and whether it has uncommitted changes. Unlike
+        /// `de status`, this doesn't need Docker.
+        #[arg(long)]
+        git: bool,
     },
 
     Snapshot {
@@ -362,5 +1080,69 @@ pub enum WorkspaceCommands {
         /// The profile to use for the snapshot. Defaults to "default".
         #[arg(short, long, default_value = "default")]
         profile: Slug,
+
+        /// Bundle each project's uncommitted changes as a patch that can be applied on setup.
+        #[arg(long)]
+        include_uncommitted: bool,
+
+        /// Where to write the zip. Defaults to `<workspace>.zip`; `-` writes it to stdout, with
+        /// all other output going to stderr.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// A previous snapshot zip. Steps whose export commands and environment are unchanged
+        /// reuse its exported files instead of running their exports again.
+        #[arg(long, value_name = "ZIP")]
+        base: Option<PathBuf>,
+
+        /// How many export commands of a step to run at once. Commands writing to the same file
+        /// still run one after another.
+        #[arg(short, long, default_value_t = 1)]
+        jobs: usize,
     },
 }
+
+/// Parses a `KEY=VALUE` environment variable assignment. The value may be empty or contain `=`.
+fn parse_env_var(s: &str) -> Result<(String, String), String> {
+    let (key, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected KEY=VALUE, got '{s}'"))?;
+
+    if key.is_empty() || key.chars().any(|c| c.is_whitespace()) {
+        return Err(format!("invalid environment variable name '{key}'"));
+    }
+
+    Ok((key.to_string(), value.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+    use std::str::FromStr;
+
+    fn parse(args: &[&str]) -> Cli {
+        Cli::try_parse_from(std::iter::once("de").chain(args.iter().copied())).unwrap()
+    }
+
+    #[test]
+    fn test_global_workspace_flag() {
+        Cli::command().debug_assert();
+
+        let api = Slug::from_str("api").unwrap();
+        assert_eq!(
+            parse(&["--workspace", "api", "info"]).workspace,
+            Some(api.clone())
+        );
+        assert_eq!(
+            parse(&["task", "list", "-w", "api"]).workspace,
+            Some(api.clone())
+        );
+
+        // A command with its own flag gets the value given after it
+        let cli = parse(&["-w", "web", "stop", "--workspace", "api"]);
+        assert!(
+            matches!(cli.command, Commands::Stop { workspace: Some(workspace), .. } if workspace == api)
+        );
+    }
+}
@@ -0,0 +1,127 @@
+use std::path::Path;
+
+use dialoguer::Confirm;
+use eyre::{Context, eyre};
+
+use crate::utils::{get_project_dirs, get_shims_dir, is_interactive, log, ui::UserInterface};
+
+/// Removes data de recreates as needed: the cache and the logs, and with `all` the shims too.
+/// The workspaces and the de configuration are never touched.
+pub fn clean(cache: bool, logs: bool, all: bool, yes: bool) -> eyre::Result<()> {
+    let dirs = get_project_dirs()?;
+    let (cache, logs) = if cache || logs {
+        (cache, logs)
+    } else {
+        (true, true)
+    };
+
+    let mut targets = Vec::new();
+    if cache || all {
+        targets.push(("cache", dirs.cache_dir().to_path_buf()));
+    }
+    if logs || all {
+        targets.push(("logs", log::logs_dir()?));
+    }
+    if all {
+        targets.push(("shims", get_shims_dir()?));
+    }
+
+    let ui = UserInterface::new();
+    ui.heading("Clean")?;
+
+    if all && !yes {
+        if !is_interactive() {
+            return Err(eyre!("Re-run with --yes to also remove the shims"));
+        }
+
+        let confirmed = Confirm::new()
+            .with_prompt(
+                "This also removes your shims, which `de shim add` has to recreate. Continue?",
+            )
+            .default(false)
+            .interact()
+            .map_err(|e| eyre!(e))
+            .wrap_err("Failed to prompt for confirmation")?;
+
+        if !confirmed {
+            println!("Aborting clean operation.");
+            return Ok(());
+        }
+    }
+
+    let mut reclaimed = 0;
+    for (name, dir) in targets {
+        if !dir.exists() {
+            ui.info_item(&ui.theme.dim(&format!("No {name} to remove")))?;
+            continue;
+        }
+
+        let size = dir_size(&dir);
+        std::fs::remove_dir_all(&dir)
+            .map_err(|e| eyre!(e))
+            .wrap_err_with(|| format!("Failed to remove {}", dir.display()))?;
+
+        reclaimed += size;
+        ui.success_item(
+            &format!("Removed {name} ({})", format_size(size)),
+            Some(&dir.display().to_string()),
+        )?;
+    }
+
+    ui.new_line()?;
+    ui.success_item(&format!("Reclaimed {}", format_size(reclaimed)), None)?;
+
+    Ok(())
+}
+
+/// The total size of the files below `dir`, skipping what can't be read.
+fn dir_size(dir: &Path) -> u64 {
+    walkdir::WalkDir::new(dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// Formats a number of bytes with a binary unit, e.g. `1.5 MiB`.
+fn format_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["KiB", "MiB", "GiB"];
+
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = UNITS[0];
+    for next in &UNITS[1..] {
+        if size < 1024.0 {
+            break;
+        }
+        size /= 1024.0;
+        unit = next;
+    }
+
+    format!("{size:.1} {unit}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sizes_are_summed_and_formatted() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(5 * 1024 * 1024), "5.0 MiB");
+        assert_eq!(format_size(3 * 1024 * 1024 * 1024), "3.0 GiB");
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("tasks")).unwrap();
+        std::fs::write(dir.path().join("tasks/a"), [0; 100]).unwrap();
+        std::fs::write(dir.path().join("b"), [0; 24]).unwrap();
+        assert_eq!(dir_size(dir.path()), 124);
+    }
+}
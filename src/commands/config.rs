@@ -1,6 +1,11 @@
+use std::path::Path;
 use std::str::FromStr;
 
-use crate::{config::Config, types::Slug, utils::theme::Theme};
+use crate::{
+    config::Config,
+    types::Slug,
+    utils::{get_project_for_cli, theme::Theme},
+};
 use eyre::{WrapErr, eyre};
 
 pub enum ConfigAction {
@@ -9,7 +14,21 @@ pub enum ConfigAction {
     Unset,
 }
 
-pub fn config(key: String, value: Option<String>, unset: bool) -> eyre::Result<()> {
+pub fn config(
+    key: String,
+    value: Option<String>,
+    unset: bool,
+    project: Option<Slug>,
+    workspace: Option<Slug>,
+    cwd: &Path,
+) -> eyre::Result<()> {
+    if key == "origin" {
+        if value.is_some() || unset {
+            return Err(eyre!("'origin' is read-only"));
+        }
+        return origin(project, workspace, cwd);
+    }
+
     let action = if unset {
         ConfigAction::Unset
     } else if let Some(value) = value {
@@ -61,7 +80,7 @@ pub fn config(key: String, value: Option<String>, unset: bool) -> eyre::Result<(
         },
         _ => {
             return Err(eyre!(
-                "Unknown configuration key: '{}'. Supported keys: active",
+                "Unknown configuration key: '{}'. Supported keys: active, origin",
                 key
             ));
         }
@@ -69,3 +88,15 @@ pub fn config(key: String, value: Option<String>, unset: bool) -> eyre::Result<(
 
     Ok(())
 }
+
+/// Prints each value of the project manifest with the source that set it, like
+/// `git config --show-origin`.
+fn origin(project: Option<Slug>, workspace: Option<Slug>, cwd: &Path) -> eyre::Result<()> {
+    let project = get_project_for_cli(project, Some(workspace), cwd)?;
+
+    for origin in project.manifest_origins()? {
+        println!("{}\t{} = {}", origin.source, origin.key, origin.value);
+    }
+
+    Ok(())
+}
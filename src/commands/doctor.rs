@@ -1,48 +1,130 @@
-use console::style;
-use eyre::eyre;
+use console::{strip_ansi_codes, style};
+use eyre::{WrapErr, eyre};
 use itertools::Itertools;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
 use std::process::Command;
 
 use crate::{
-    project::Project,
+    cli::{DoctorFormat, DoctorSection, DoctorSeverity},
+    config::Config,
+    error::DeError,
+    project::{Project, compose::compose_project_name, docker_compose_config},
     types::Slug,
-    utils::{formatter::Formatter, theme::Theme},
+    utils::{
+        formatter::Formatter,
+        get_shims_dir,
+        git::{is_ignored, is_tracked},
+        process::CommandExt,
+        shim::{
+            ShimsOnPath, executables_on_path, generate_shim_script, get_installed_shims,
+            shim_de_programs, shim_file_name, shims_on_path,
+        },
+        theme::Theme,
+    },
     workspace::{DependencyGraphError, Workspace},
 };
 
+/// The part of the environment a diagnostic belongs to.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum DiagnosticSection {
+    System,
+    Project,
+    Workspace,
+    Shims,
+}
+
+impl DiagnosticSection {
+    fn heading(self) -> &'static str {
+        match self {
+            DiagnosticSection::System => "System Dependencies:",
+            DiagnosticSection::Project => "Project Configuration:",
+            DiagnosticSection::Workspace => "Workspace Configuration:",
+            DiagnosticSection::Shims => "Shims:",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum DiagnosticLevel {
+    Success,
+    Error,
+    Warning,
+    Info,
+}
+
+impl DiagnosticLevel {
+    fn severity(self) -> DoctorSeverity {
+        match self {
+            DiagnosticLevel::Success | DiagnosticLevel::Info => DoctorSeverity::Info,
+            DiagnosticLevel::Warning => DoctorSeverity::Warning,
+            DiagnosticLevel::Error => DoctorSeverity::Error,
+        }
+    }
+}
+
+/// A single check result, recorded so it can be rendered as text or JSON.
+#[derive(Debug, Serialize)]
+struct DiagnosticEntry {
+    section: DiagnosticSection,
+    level: DiagnosticLevel,
+    message: String,
+    /// The individual findings of a grouped error.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    details: Vec<String>,
+    suggestion: Option<String>,
+}
+
 #[derive(Debug)]
 struct DiagnosticResult {
+    section: DiagnosticSection,
     errors: u32,
     warnings: u32,
+    entries: Vec<DiagnosticEntry>,
 }
 
 impl DiagnosticResult {
-    fn new() -> Self {
+    fn new(section: DiagnosticSection) -> Self {
         Self {
+            section,
             errors: 0,
             warnings: 0,
+            entries: Vec::new(),
         }
     }
 
-    fn add_success(&mut self, formatter: &Formatter, message: String) -> eyre::Result<()> {
-        formatter.success(&message)?;
-        Ok(())
-    }
-
-    fn add_error(
+    fn record(
         &mut self,
-        formatter: &Formatter,
+        level: DiagnosticLevel,
         message: String,
+        details: Vec<String>,
         suggestion: Option<String>,
-    ) -> eyre::Result<()> {
+    ) {
+        self.entries.push(DiagnosticEntry {
+            section: self.section,
+            level,
+            message,
+            details,
+            suggestion,
+        });
+    }
+
+    fn add_success(&mut self, message: String) -> eyre::Result<()> {
+        self.record(DiagnosticLevel::Success, message, Vec::new(), None);
+        Ok(())
+    }
+
+    fn add_error(&mut self, message: String, suggestion: Option<String>) -> eyre::Result<()> {
         self.errors += 1;
-        formatter.error(&message, suggestion.as_deref())?;
+        self.record(DiagnosticLevel::Error, message, Vec::new(), suggestion);
         Ok(())
     }
 
     fn add_error_group(
         &mut self,
-        formatter: &Formatter,
         heading: String,
         messages: Vec<String>,
         suggestion: Option<String>,
@@ -52,70 +134,153 @@ impl DiagnosticResult {
         }
 
         self.errors += 1;
-        formatter.error_group(&heading, &messages, suggestion.as_deref())?;
+        self.record(DiagnosticLevel::Error, heading, messages, suggestion);
         Ok(())
     }
 
-    fn add_warning(
-        &mut self,
-        formatter: &Formatter,
-        message: String,
-        suggestion: Option<String>,
-    ) -> eyre::Result<()> {
+    fn add_warning(&mut self, message: String, suggestion: Option<String>) -> eyre::Result<()> {
         self.warnings += 1;
-        formatter.warning(&message, suggestion.as_deref())?;
+        self.record(DiagnosticLevel::Warning, message, Vec::new(), suggestion);
+        Ok(())
+    }
+
+    fn add_info(&mut self, message: String) -> eyre::Result<()> {
+        self.record(DiagnosticLevel::Info, message, Vec::new(), None);
         Ok(())
     }
 
-    fn add_info(&mut self, formatter: &Formatter, message: String) -> eyre::Result<()> {
-        formatter.info(&message)?;
+    /// Prints the section heading and its entries.
+    fn render(&self, formatter: &Formatter) -> eyre::Result<()> {
+        formatter.heading(self.section.heading())?;
+
+        for entry in &self.entries {
+            let suggestion = entry.suggestion.as_deref();
+            match entry.level {
+                DiagnosticLevel::Success => formatter.success(&entry.message)?,
+                DiagnosticLevel::Error if !entry.details.is_empty() => {
+                    formatter.error_group(&entry.message, &entry.details, suggestion)?
+                }
+                DiagnosticLevel::Error => formatter.error(&entry.message, suggestion)?,
+                DiagnosticLevel::Warning => formatter.warning(&entry.message, suggestion)?,
+                DiagnosticLevel::Info => formatter.info(&entry.message)?,
+            }
+        }
+
         Ok(())
     }
 }
 
-pub fn doctor(workspace_name: Option<Slug>) -> eyre::Result<()> {
-    let formatter = Formatter::new();
+/// The `--format json` output of `de doctor`.
+#[derive(Debug, Serialize)]
+struct DoctorReport {
+    errors: u32,
+    warnings: u32,
+    entries: Vec<DiagnosticEntry>,
+}
+
+/// Runs the checks of the `only` sections, or all of them, and prints the results of at least
+/// `min_severity`. Fails with [`DeError::DoctorFailed`] if a check found an error, or with
+/// `strict` a warning, so CI can gate on it.
+pub fn doctor(
+    workspace_name: Option<Slug>,
+    format: DoctorFormat,
+    strict: bool,
+    only: &[DoctorSection],
+    min_severity: DoctorSeverity,
+    cwd: &Path,
+) -> eyre::Result<()> {
     let theme = crate::utils::theme::Theme::new();
+    let formatter = Formatter::with_theme(theme.clone());
+    let runs = |section| only.is_empty() || only.contains(&section);
+
+    let mut total_errors = 0;
+    let mut total_warnings = 0;
+    let mut entries = Vec::new();
+
+    // Text output is printed as each section finishes, so slow checks don't hold back the
+    // results of the others
+    let mut report = |mut result: DiagnosticResult| -> eyre::Result<()> {
+        total_errors += result.errors;
+        total_warnings += result.warnings;
+        result
+            .entries
+            .retain(|entry| entry.level.severity() >= min_severity);
+
+        match format {
+            DoctorFormat::Json => entries.extend(result.entries),
+            DoctorFormat::Text if !result.entries.is_empty() => {
+                result.render(&formatter)?;
+                println!();
+            }
+            DoctorFormat::Text => {}
+        }
+        Ok(())
+    };
 
     // Check system dependencies
-    formatter.heading("System Dependencies:")?;
-    let system_result = check_system_dependencies(&formatter)?;
-    println!();
+    if runs(DoctorSection::System) {
+        report(check_system_dependencies()?)?;
+    }
 
     // Check project configuration
     // We don't want to show the project in doctor if its not in the current workspace
-    let project = Project::current();
-    let project_result = if workspace_name.as_ref()
-        .map(|workspace_name| matches!(project, Ok(Some(project)) if &project.manifest().project().workspace == workspace_name))
-        .unwrap_or(true)
-    {
-        formatter.heading("Project Configuration:")?;
-        let project_result = check_project_configuration(&formatter, &theme)?;
-        println!();
-        Some(project_result)
+    if runs(DoctorSection::Project) {
+        let in_workspace = workspace_name.as_ref().is_none_or(|workspace_name| {
+            matches!(
+                Project::from_dir_recursive(cwd),
+                Ok(Some(project)) if &project.manifest().project().workspace == workspace_name
+            )
+        });
+        if in_workspace {
+            report(check_project_configuration(&theme, cwd)?)?;
+        }
+    }
+
+    // Check workspace configuration
+    if runs(DoctorSection::Workspace) {
+        report(check_workspace_configuration(workspace_name.as_ref(), cwd)?)?;
+    }
+
+    // Check installed shims, if there are any
+    if runs(DoctorSection::Shims) {
+        report(check_shims(cwd)?)?;
+    }
+
+    let outcome = if total_errors > 0 || (strict && total_warnings > 0) {
+        Err(eyre!(DeError::DoctorFailed {
+            errors: total_errors,
+            warnings: total_warnings,
+        }))
     } else {
-        None
+        Ok(())
     };
 
-    // Check workspace configuration
-    formatter.heading("Workspace Configuration:")?;
-    let workspace_result = check_workspace_configuration(&formatter, workspace_name.as_ref())?;
-
-    // Calculate totals and print status
-    let total_errors = system_result.errors
-        + project_result
-            .as_ref()
-            .map(|v| v.errors)
-            .unwrap_or_default()
-        + workspace_result.errors;
-    let total_warnings = system_result.warnings
-        + project_result
-            .as_ref()
-            .map(|v| v.warnings)
-            .unwrap_or_default()
-        + workspace_result.warnings;
-
-    println!();
+    if format == DoctorFormat::Json {
+        let strip = |text: &str| strip_ansi_codes(text).to_string();
+        let entries = entries
+            .into_iter()
+            .map(|entry| DiagnosticEntry {
+                message: strip(&entry.message),
+                details: entry.details.iter().map(|detail| strip(detail)).collect(),
+                suggestion: entry.suggestion.as_deref().map(strip),
+                ..entry
+            })
+            .collect();
+
+        let report = DoctorReport {
+            errors: total_errors,
+            warnings: total_warnings,
+            entries,
+        };
+
+        let json = serde_json::to_string_pretty(&report)
+            .map_err(|e| eyre!(e))
+            .wrap_err("Failed to serialize doctor report")?;
+        println!("{json}");
+
+        return outcome;
+    }
+
     formatter.heading("Status:")?;
     if total_errors == 0 && total_warnings == 0 {
         formatter.success(
@@ -150,17 +315,16 @@ pub fn doctor(workspace_name: Option<Slug>) -> eyre::Result<()> {
         );
     }
 
-    Ok(())
+    outcome
 }
 
-fn check_system_dependencies(formatter: &Formatter) -> eyre::Result<DiagnosticResult> {
-    let mut result = DiagnosticResult::new();
+fn check_system_dependencies() -> eyre::Result<DiagnosticResult> {
+    let mut result = DiagnosticResult::new(DiagnosticSection::System);
 
     // Check Docker
     match check_docker() {
-        Ok(version) => result.add_success(formatter, format!("Docker: {}", version.trim()))?,
+        Ok(version) => result.add_success(format!("Docker: {}", version.trim()))?,
         Err(e) => result.add_error(
-            formatter,
             format!("Docker: {e}"),
             Some("Install from https://docs.docker.com/get-docker/".to_string()),
         )?,
@@ -168,92 +332,385 @@ fn check_system_dependencies(formatter: &Formatter) -> eyre::Result<DiagnosticRe
 
     // Check Docker Compose
     match check_docker_compose() {
-        Ok(version) => {
-            result.add_success(formatter, format!("Docker Compose: {}", version.trim()))?
-        }
+        Ok(version) => result.add_success(format!("Docker Compose: {}", version.trim()))?,
         Err(e) => result.add_error(
-            formatter,
             format!("Docker Compose: {e}"),
             Some("Install from https://docs.docker.com/compose/install/".to_string()),
         )?,
     }
 
+    check_de_binaries(&mut result)?;
+
     Ok(result)
 }
 
-fn check_project_configuration(
-    formatter: &Formatter,
-    theme: &Theme,
-) -> eyre::Result<DiagnosticResult> {
-    let mut result = DiagnosticResult::new();
+/// Warns if more than one `de` is on `PATH`, e.g. after upgrading through another installer,
+/// since shims and shells run the first one, which may not be the one that was upgraded.
+fn check_de_binaries(result: &mut DiagnosticResult) -> eyre::Result<()> {
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    let binaries = executables_on_path(&path, "de");
+
+    match binaries.as_slice() {
+        [] => result.add_info("de is not on PATH, so shims can't run tasks".to_string()),
+        [binary] => result.add_success(format!("de on PATH: {}", binary.display())),
+        [first, ..] => {
+            let binaries = binaries
+                .iter()
+                .map(|binary| binary.display().to_string())
+                .collect::<Vec<_>>();
+            result.add_warning(
+                format!(
+                    "Found {} de binaries on PATH: {}. Shims and your shell run {}",
+                    binaries.len(),
+                    binaries.join(", "),
+                    first.display()
+                ),
+                Some("Remove the ones left over from earlier installs".to_string()),
+            )
+        }
+    }
+}
 
-    match Project::current() {
+fn check_project_configuration(theme: &Theme, cwd: &Path) -> eyre::Result<DiagnosticResult> {
+    let mut result = DiagnosticResult::new(DiagnosticSection::Project);
+
+    match Project::from_dir_recursive(cwd) {
         Ok(Some(project)) => {
-            result.add_success(
-                formatter,
-                format!("Project: {}", project.manifest().project().name),
-            )?;
-            check_project_details(formatter, theme, &project, &mut result)?;
+            result.add_success(format!("Project: {}", project.manifest().project().name))?;
+            check_project_name(&project, &mut result, cwd)?;
+            check_project_details(theme, &project, &mut result, cwd)?;
         }
         Ok(None) => {
             result.add_warning(
-                formatter,
                 "Not in a de project directory".to_string(),
                 Some("Run 'de init' to initialize a project here".to_string()),
             )?;
         }
         Err(e) => {
-            result.add_error(formatter, format!("Project check failed: {e}"), None)?;
+            result.add_error(format!("Project check failed: {e}"), None)?;
         }
     }
 
     Ok(result)
 }
 
+/// Warns when the project name differs from the one its directory suggests, e.g. because a
+/// manifest was copied without renaming it. A project registered under that name in its
+/// workspace is left alone, as the name is then clearly intended.
+fn check_project_name(
+    project: &Project,
+    result: &mut DiagnosticResult,
+    cwd: &Path,
+) -> eyre::Result<()> {
+    let metadata = project.manifest().project();
+    let Ok(inferred) = Project::infer_name(project.dir()) else {
+        return Ok(());
+    };
+    if inferred == metadata.name {
+        return Ok(());
+    }
+
+    let dir = project
+        .dir()
+        .canonicalize()
+        .unwrap_or_else(|_| project.dir().clone());
+    let registered = Workspace::load_from_name(&metadata.workspace, cwd)
+        .ok()
+        .flatten()
+        .and_then(|workspace| workspace.project(&metadata.name).cloned())
+        .is_some_and(|ws_project| ws_project.dir == dir);
+    if registered {
+        return Ok(());
+    }
+
+    result.add_warning(
+        format!(
+            "Project name {} doesn't match its directory ({inferred})",
+            metadata.name
+        ),
+        Some(
+            "Rename the project in de.toml if it was copied, then run `de update` to reconcile the workspace"
+                .to_string(),
+        ),
+    )
+}
+
 fn check_workspace_configuration(
-    formatter: &Formatter,
     workspace_name: Option<&Slug>,
+    cwd: &Path,
 ) -> eyre::Result<DiagnosticResult> {
-    let mut result = DiagnosticResult::new();
+    let mut result = DiagnosticResult::new(DiagnosticSection::Workspace);
+
+    check_active_workspace_exists(&mut result, Config::load(), |name| {
+        Workspace::load_from_name(name, cwd)
+    })?;
 
     let workspace = if let Some(name) = workspace_name {
-        Workspace::load_from_name(name)
+        Workspace::load_from_name(name, cwd)
     } else {
-        Workspace::active()
+        Workspace::active(cwd)
     };
 
     match workspace {
         Ok(Some(workspace)) => {
-            result.add_success(formatter, format!("Workspace: {}", workspace.config().name))?;
-            check_workspace_details(formatter, &workspace, &mut result)?;
+            result.add_success(format!("Workspace: {}", workspace.config().name))?;
+            check_workspace_details(&workspace, &mut result)?;
         }
         Ok(None) => {
             if workspace_name.is_some() {
                 result.add_error(
-                    formatter,
                     "Workspace not found".to_string(),
-                    Some("Check if the workspace name is correct or run 'de init' to create a new workspace".to_string())
+                    Some(
+                        "Check if the workspace name is correct or run 'de init' to create a \
+                         new workspace"
+                            .to_string(),
+                    ),
                 )?;
             } else {
                 result.add_warning(
-                    formatter,
                     "No active workspace found".to_string(),
                     Some("Initialize a project or set an active workspace".to_string()),
                 )?;
             }
         }
         Err(e) => {
-            result.add_error(formatter, format!("Workspace check failed: {e}"), None)?;
+            result.add_error(format!("Workspace check failed: {e}"), None)?;
         }
     }
 
     Ok(result)
 }
 
-fn check_docker() -> eyre::Result<String> {
+/// Errors if the active workspace in the application config has no configuration file, e.g.
+/// because it was deleted. Commands then report no active workspace, despite one being set.
+///
+/// Takes the loaded config and the workspace loader, so tests don't depend on the user's files.
+fn check_active_workspace_exists(
+    result: &mut DiagnosticResult,
+    config: eyre::Result<Config>,
+    load_workspace: impl Fn(&Slug) -> eyre::Result<Option<Workspace>>,
+) -> eyre::Result<()> {
+    let config = match config {
+        Ok(config) => config,
+        Err(e) => {
+            result.add_error(format!("Application config invalid: {e}"), None)?;
+            return Ok(());
+        }
+    };
+
+    let Some(name) = config.get_active_workspace() else {
+        return Ok(());
+    };
+
+    match load_workspace(name) {
+        Ok(Some(_)) => {}
+        Ok(None) => result.add_error(
+            format!("Active workspace {name} does not exist"),
+            Some(
+                "Run `de config active --unset`, or pick an existing workspace with \
+                 `de config active <name>`"
+                    .to_string(),
+            ),
+        )?,
+        Err(e) => result.add_error(format!("Active workspace {name} invalid: {e}"), None)?,
+    }
+
+    Ok(())
+}
+
+/// Errors if the shims directory isn't on `PATH`, so shims never run, and warns if it comes
+/// after a system binary directory, whose commands then win over the shims.
+fn check_shims_on_path(result: &mut DiagnosticResult) -> eyre::Result<()> {
+    let shims_dir = get_shims_dir()?;
+    let path = std::env::var_os("PATH").unwrap_or_default();
+
+    match shims_on_path(&path, &shims_dir) {
+        ShimsOnPath::Ahead => result.add_success("Shims directory is on PATH".to_string()),
+        ShimsOnPath::Behind(system_dir) => result.add_warning(
+            format!(
+                "Shims directory comes after {} on PATH, so system commands win over shims",
+                system_dir.display()
+            ),
+            Some(
+                "Move the line de added to your shell config to its end, then restart your shell"
+                    .to_string(),
+            ),
+        ),
+        ShimsOnPath::Missing => result.add_error(
+            format!("Shims directory {} is not on PATH", shims_dir.display()),
+            Some("Run 'de shim install', then restart your shell or source its config".to_string()),
+        ),
+    }
+}
+
+/// Warns about shims whose task is not defined by any known workspace or project. Such shims
+/// always fall through to the system binary.
+fn check_shims(cwd: &Path) -> eyre::Result<DiagnosticResult> {
+    let mut result = DiagnosticResult::new(DiagnosticSection::Shims);
+
+    let shims = match get_installed_shims() {
+        Ok(shims) if shims.is_empty() => return Ok(result),
+        Ok(shims) => shims,
+        Err(e) => {
+            result.add_error(format!("Shim check failed: {e}"), None)?;
+            return Ok(result);
+        }
+    };
+
+    check_shims_on_path(&mut result)?;
+    check_shim_scripts(&mut result, &shims)?;
+
+    let known_tasks = match known_task_names(cwd) {
+        Ok(tasks) => tasks,
+        Err(e) => {
+            result.add_error(format!("Shim check failed: {e}"), None)?;
+            return Ok(result);
+        }
+    };
+
+    let (live, dead): (Vec<_>, Vec<_>) = shims
+        .into_iter()
+        .partition(|shim| known_tasks.contains(shim.as_str()));
+
+    if !live.is_empty() {
+        result.add_success(format!("{} shim(s) resolve to a task", live.len()))?;
+    }
+
+    for shim in dead {
+        result.add_warning(
+            format!("Shim '{shim}' matches no task in any workspace or project"),
+            Some(format!("Run 'de shim remove {shim}'")),
+        )?;
+    }
+
+    Ok(result)
+}
+
+/// Warns about shims that call `de` by a fixed path, which keeps running that binary after an
+/// upgrade moves it, and about shims written by another version of de.
+fn check_shim_scripts(result: &mut DiagnosticResult, shims: &[String]) -> eyre::Result<()> {
+    let shims_dir = get_shims_dir()?;
+    let mut outdated = Vec::new();
+
+    for shim in shims {
+        let Ok(script) = std::fs::read_to_string(shims_dir.join(shim_file_name(shim))) else {
+            continue;
+        };
+
+        if let Some(program) = shim_de_programs(&script)
+            .into_iter()
+            .find(|program| program != "de")
+        {
+            result.add_warning(
+                format!("Shim '{shim}' runs de at {program} instead of the de on PATH"),
+                Some("Run 'de shim reinstate'".to_string()),
+            )?;
+        } else if script != generate_shim_script(shim) {
+            outdated.push(shim.as_str());
+        }
+    }
+
+    if !outdated.is_empty() {
+        result.add_warning(
+            format!(
+                "Shim(s) written by another version of de: {}",
+                outdated.join(", ")
+            ),
+            Some("Run 'de shim reinstate'".to_string()),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Reports setup steps, of the project or any of its profiles, that run in a Docker Compose
+/// service missing from `compose_services`, or without any Compose file to run it from.
+fn check_step_services(
+    project: &Project,
+    compose_services: Option<&[String]>,
+    result: &mut DiagnosticResult,
+) -> eyre::Result<()> {
+    let Some(setup) = project.manifest().setup.as_ref() else {
+        return Ok(());
+    };
+
+    let steps = setup
+        .steps
+        .iter()
+        .map(|(id, step)| (format!("Setup step '{id}'"), step))
+        .chain(setup.profiles.iter().flat_map(|(profile, settings)| {
+            settings
+                .steps
+                .iter()
+                .map(move |(id, step)| (format!("Setup step '{id}' of profile '{profile}'"), step))
+        }));
+
+    for (label, step) in steps {
+        let Some(service) = step.service.as_ref().map(|service| service.clone_value()) else {
+            continue;
+        };
+        // A step with its own Compose file isn't checked against the project's
+        if service.compose.is_some() {
+            continue;
+        }
+
+        match compose_services {
+            Some(services) if services.contains(&service.name) => {}
+            Some(_) => result.add_error(
+                format!(
+                    "{label} references missing Docker Compose service '{}'",
+                    service.name
+                ),
+                Some(
+                    "Check the setup steps in de.toml and docker-compose.yml for consistency"
+                        .to_string(),
+                ),
+            )?,
+            None => result.add_error(
+                format!(
+                    "{label} references Docker Compose service '{}' but no Docker Compose file \
+                     is configured or found",
+                    service.name
+                ),
+                Some(
+                    "Add a docker-compose.yml or configure the docker_compose path in de.toml"
+                        .to_string(),
+                ),
+            )?,
+        }
+    }
+
+    Ok(())
+}
+
+/// Names of all tasks defined by any workspace or any project registered in one, including the
+/// repo-local workspace around `cwd`.
+fn known_task_names(cwd: &Path) -> eyre::Result<BTreeSet<String>> {
+    let mut tasks = BTreeSet::new();
+
+    // A workspace that fails to resolve is reported by the workspace checks
+    let current = Workspace::current(cwd).ok().flatten();
+
+    for workspace in Workspace::all()?.workspaces.into_iter().chain(current) {
+        tasks.extend(workspace.config().tasks.keys().map(|task| task.to_string()));
+
+        for (_, project) in workspace.load_projects() {
+            // Projects that fail to load are reported by the workspace checks
+            if let Ok(project) = project
+                && let Some(project_tasks) = project.tasks()
+            {
+                tasks.extend(project_tasks.names().map(|task| task.to_string()));
+            }
+        }
+    }
+
+    Ok(tasks)
+}
+
+pub(super) fn check_docker() -> eyre::Result<String> {
     let output = Command::new("docker")
         .arg("--version")
-        .output()
+        .logged_output()
         .map_err(|e| eyre!("Failed to execute docker command: {}", e))?;
 
     if !output.status.success() {
@@ -268,7 +725,7 @@ fn check_docker() -> eyre::Result<String> {
     // Test if Docker daemon is running
     let ping_output = Command::new("docker")
         .arg("info")
-        .output()
+        .logged_output()
         .map_err(|e| eyre!("Failed to ping Docker daemon: {}", e))?;
 
     if !ping_output.status.success() {
@@ -278,9 +735,11 @@ fn check_docker() -> eyre::Result<String> {
     Ok(version)
 }
 
-fn check_docker_compose() -> eyre::Result<String> {
+pub(super) fn check_docker_compose() -> eyre::Result<String> {
     // Try docker-compose first (standalone)
-    let output = Command::new("docker-compose").arg("--version").output();
+    let output = Command::new("docker-compose")
+        .arg("--version")
+        .logged_output();
 
     if let Ok(output) = output
         && output.status.success()
@@ -296,7 +755,7 @@ fn check_docker_compose() -> eyre::Result<String> {
     let output = Command::new("docker")
         .arg("compose")
         .arg("version")
-        .output()
+        .logged_output()
         .map_err(|e| eyre!("Failed to execute docker compose command: {}", e))?;
 
     if !output.status.success() {
@@ -312,18 +771,16 @@ fn check_docker_compose() -> eyre::Result<String> {
 }
 
 fn check_project_details(
-    formatter: &Formatter,
     theme: &Theme,
     project: &Project,
     result: &mut DiagnosticResult,
+    cwd: &Path,
 ) -> eyre::Result<()> {
     use crate::project::Task;
-    use std::process::Command;
 
     // Check if project directory exists
     if !project.dir().exists() {
         result.add_error(
-            formatter,
             format!("Project directory missing: {}", project.dir().display()),
             None,
         )?;
@@ -331,11 +788,7 @@ fn check_project_details(
 
     // Check if de.toml exists and is readable
     if !project.manifest_path().exists() {
-        result.add_error(
-            formatter,
-            "Project manifest (de.toml) missing".to_string(),
-            None,
-        )?;
+        result.add_error("Project manifest (de.toml) missing".to_string(), None)?;
     }
 
     // Track Compose services for later check
@@ -344,103 +797,78 @@ fn check_project_details(
     // Check Docker Compose file if configured
     match project.docker_compose_path() {
         Ok(Some(compose_path)) => {
-            if let Err(e) = validate_docker_compose(&compose_path) {
-                result.add_error(formatter, format!("Docker Compose file invalid: {e}"), None)?;
+            if let Err(e) = docker_compose_config(&compose_path, project.env(), &["--quiet"]) {
+                result.add_error(format!("Docker Compose file invalid: {e}"), None)?;
             } else {
-                result.add_success(
-                    formatter,
-                    format!(
-                        "Docker Compose file: {}",
-                        compose_path.file_name().unwrap().to_string_lossy()
-                    ),
-                )?;
-
-                // Try to get list of services from docker-compose config --services
-                let output = Command::new("docker-compose")
-                    .arg("-f")
-                    .arg(&compose_path)
-                    .arg("config")
-                    .arg("--services")
-                    .output();
-
-                let services = if let Ok(output) = &output {
-                    if output.status.success() {
-                        let stdout = String::from_utf8_lossy(&output.stdout);
-                        let v: Vec<String> = stdout.lines().map(|s| s.trim().to_string()).collect();
-                        if !v.is_empty() { Some(v) } else { None }
-                    } else {
-                        None
-                    }
-                } else {
-                    None
-                };
-
-                // Fallback to docker compose (plugin) if standalone fails
-                let services = if services.is_none() {
-                    let output = Command::new("docker")
-                        .arg("compose")
-                        .arg("-f")
-                        .arg(&compose_path)
-                        .arg("config")
-                        .arg("--services")
-                        .output();
-                    if let Ok(output) = &output {
-                        if output.status.success() {
-                            let stdout = String::from_utf8_lossy(&output.stdout);
-                            let v: Vec<String> =
-                                stdout.lines().map(|s| s.trim().to_string()).collect();
-                            if !v.is_empty() { Some(v) } else { None }
-                        } else {
-                            None
-                        }
-                    } else {
-                        None
-                    }
+                result.add_success(format!(
+                    "Docker Compose file: {}",
+                    compose_path.file_name().unwrap().to_string_lossy()
+                ))?;
+
+                compose_services = project
+                    .docker_compose_services()
+                    .ok()
+                    .filter(|services| !services.is_empty());
+            }
+        }
+        Ok(None) => match &project.manifest().project().docker_compose {
+            Some(configured) => {
+                let configured = if configured.is_relative() {
+                    project.dir().join(configured)
                 } else {
-                    services
+                    configured.clone()
                 };
 
-                compose_services = services;
+                result.add_error(
+                    format!(
+                        "Configured Docker Compose file not found: {}",
+                        configured.display()
+                    ),
+                    Some("Fix the docker_compose path in de.toml".to_string()),
+                )?;
             }
-        }
-        Ok(None) => {
-            result.add_info(formatter, theme.dim("Docker Compose: not configured"))?;
-        }
+            None => {
+                result.add_info(theme.dim("Docker Compose: not configured"))?;
+            }
+        },
         Err(e) => {
-            result.add_error(formatter, format!("Docker Compose check failed: {e}"), None)?;
+            result.add_error(format!("Docker Compose check failed: {e}"), None)?;
         }
     }
 
     // Check project dependencies
     if let Some(depends_on) = &project.manifest().project().depends_on {
-        result.add_info(formatter, format!("Dependencies: {}", depends_on.len()))?;
+        result.add_info(format!("Dependencies: {}", depends_on.len()))?;
+
+        let name = &project.manifest().project().name;
+        if depends_on.contains(name) {
+            result.add_error(
+                format!("Project {name} cannot depend on itself"),
+                Some(format!("Remove {name} from depends_on in de.toml")),
+            )?;
+        }
 
         // If we're in a workspace context, validate dependencies
         if let Ok(Some(workspace)) =
-            Workspace::load_from_name(&project.manifest().project.workspace)
+            Workspace::load_from_name(&project.manifest().project.workspace, cwd)
         {
             let missing_dependencies: Vec<_> = depends_on
                 .iter()
-                .filter(|dep| !workspace.config().projects.contains_key(*dep))
+                .filter(|dep| workspace.project(dep).is_none())
                 .map(|dep| dep.to_string())
                 .collect();
 
             if !missing_dependencies.is_empty() {
                 result.add_error(
-                    formatter,
                     format!("Missing dependencies: {}", missing_dependencies.join(", ")),
                     Some("Ensure all required projects are added to the workspace".to_string()),
                 )?;
             } else {
                 let mut depends_on = depends_on.iter().map(|d| d.to_string());
-                result.add_success(
-                    formatter,
-                    format!("All dependencies found: {}", depends_on.join(", ")),
-                )?;
+                result.add_success(format!("All dependencies found: {}", depends_on.join(", ")))?;
             }
         } else {
             result.add_error(
-                formatter,
                 "Cannot find workspace of this project".to_string(),
                 Some(
                     "Ensure this workspace is initiated either with `de init` or `de scan`."
@@ -449,7 +877,7 @@ fn check_project_details(
             )?;
         }
     } else {
-        result.add_info(formatter, theme.dim("Dependencies: none"))?;
+        result.add_info(theme.dim("Dependencies: none"))?;
     }
 
     // Check if project has any tasks defined
@@ -461,12 +889,11 @@ fn check_project_details(
         .unwrap_or(0);
     if task_count == 0 {
         result.add_warning(
-            formatter,
             "No tasks defined".to_string(),
             Some("Add tasks to your de.toml".to_string()),
         )?;
     } else {
-        result.add_success(formatter, format!("Tasks: {task_count} defined"))?;
+        result.add_success(format!("Tasks: {task_count} defined"))?;
     }
 
     // Check if Compose tasks reference missing services or if no Compose file exists
@@ -477,9 +904,7 @@ fn check_project_details(
                 if let Task::Compose { service, .. } = task
                     && !service_set.contains(&service)
                 {
-                    result.add_error(
-                            formatter,
-                            format!(
+                    result.add_error(format!(
                                 "Task '{task_name}' references missing Docker Compose service '{service}'"
                             ),
                             Some(
@@ -493,9 +918,7 @@ fn check_project_details(
             // No Compose file found, but there are Compose tasks
             for (task_name, task) in tasks {
                 if let Task::Compose { service, .. } = task {
-                    result.add_error(
-                        formatter,
-                        format!(
+                    result.add_error(format!(
                             "Task '{task_name}' references Docker Compose service '{service}' but no Docker Compose file is configured or found"
                         ),
                         Some(
@@ -507,19 +930,44 @@ fn check_project_details(
         }
     }
 
+    // Check if setup steps run in missing services, like the Compose tasks above
+    check_step_services(project, compose_services.as_deref(), result)?;
+
     // Check .env file
     let env_file = project.dir().join(".env");
     if env_file.exists() {
-        result.add_success(formatter, "Environment file: .env".to_string())?;
+        result.add_success("Environment file: .env".to_string())?;
+
+        // The .env file usually holds secrets, so it shouldn't end up in the repository
+        if project.dir().join(".git").exists() {
+            match is_tracked(".env", project.dir()) {
+                Ok(true) => result.add_warning(
+                    ".env is committed to git and may expose secrets".to_string(),
+                    Some(
+                        "Run `git rm --cached .env`, add .env to .gitignore and rotate any \
+                         secrets it contains"
+                            .to_string(),
+                    ),
+                )?,
+                Ok(false) => match is_ignored(".env", project.dir()) {
+                    Ok(true) => result.add_success(".env is ignored by git".to_string())?,
+                    Ok(false) => result.add_warning(
+                        ".env is not ignored by git and may be committed by accident".to_string(),
+                        Some("Add .env to .gitignore".to_string()),
+                    )?,
+                    Err(e) => result.add_warning(format!("Could not check .env: {e}"), None)?,
+                },
+                Err(e) => result.add_warning(format!("Could not check .env: {e}"), None)?,
+            }
+        }
     } else {
-        result.add_info(formatter, theme.dim("Environment file: not found"))?;
+        result.add_info(theme.dim("Environment file: not found"))?;
     }
 
     Ok(())
 }
 
 fn check_workspace_details(
-    formatter: &Formatter,
     workspace: &Workspace,
     result: &mut DiagnosticResult,
 ) -> eyre::Result<()> {
@@ -528,12 +976,11 @@ fn check_workspace_details(
 
     if project_count == 0 {
         result.add_warning(
-            formatter,
             "Workspace has no projects".to_string(),
             Some("Run 'de scan' to discover projects or 'de init' to create new ones".to_string()),
         )?;
     } else {
-        result.add_success(formatter, format!("Projects: {project_count} registered"))?;
+        result.add_success(format!("Projects: {project_count} registered"))?;
 
         // Check if projects still exist
         let mut valid_projects = 0;
@@ -545,7 +992,6 @@ fn check_workspace_details(
             } else {
                 invalid_projects += 1;
                 result.add_error(
-                    formatter,
                     format!(
                         "Missing: {} ({})",
                         project_id,
@@ -558,30 +1004,31 @@ fn check_workspace_details(
 
         if invalid_projects > 0 {
             result.add_warning(
-                formatter,
                 format!("{invalid_projects} project(s) have missing directories"),
                 Some("Run 'de update' to clean up workspace configuration".to_string()),
             )?;
         }
 
         if valid_projects > 0 && invalid_projects == 0 {
-            result.add_success(formatter, "All project directories found".to_string())?;
+            result.add_success("All project directories found".to_string())?;
         }
 
         // Check for task name conflicts
-        check_for_conflicts(formatter, workspace, result)?;
+        check_for_conflicts(workspace, result)?;
 
         // Check for dependency issues
-        check_for_dependency_issues(formatter, workspace, result)?;
+        check_for_dependency_issues(workspace, result)?;
+
+        // Check for host ports published by more than one project
+        check_for_port_conflicts(workspace, result)?;
+
+        // Check for projects that share a Compose project name
+        check_for_compose_name_collisions(workspace, result)?;
     }
     Ok(())
 }
 
-fn check_for_conflicts(
-    formatter: &Formatter,
-    workspace: &Workspace,
-    result: &mut DiagnosticResult,
-) -> eyre::Result<()> {
+fn check_for_conflicts(workspace: &Workspace, result: &mut DiagnosticResult) -> eyre::Result<()> {
     let config = workspace.config();
     let project_names: std::collections::HashSet<_> = config.projects.keys().collect();
     let workspace_task_names: std::collections::HashSet<_> = config.tasks.keys().collect();
@@ -597,17 +1044,13 @@ fn check_for_conflicts(
         let project = match Project::from_dir(&workspace_project.dir) {
             Ok(project) => project,
             Err(e) => {
-                result.add_error(
-                    formatter,
-                    format!("Failed to load project {project_id}: {e}"),
-                    None,
-                )?;
+                result.add_error(format!("Failed to load project {project_id}: {e}"), None)?;
                 continue;
             }
         };
 
         if let Some(tasks) = project.tasks() {
-            for task_name in tasks.keys() {
+            for task_name in tasks.names() {
                 all_project_task_names.insert(task_name.clone());
             }
         }
@@ -617,7 +1060,6 @@ fn check_for_conflicts(
     for task_name in &all_project_task_names {
         if project_names.contains(task_name) {
             result.add_warning(
-                formatter,
                 format!("Project task '{task_name}' conflicts with a project name."),
                 Some("Consider renaming the task or project to avoid ambiguity.".to_string()),
             )?;
@@ -628,7 +1070,6 @@ fn check_for_conflicts(
     for task_name in &workspace_task_names {
         if project_names.contains(task_name) {
             result.add_warning(
-                formatter,
                 format!("Workspace task '{task_name}' conflicts with a project name."),
                 Some("Consider renaming the task or project to avoid ambiguity.".to_string()),
             )?;
@@ -638,19 +1079,15 @@ fn check_for_conflicts(
     // Highlight workspace tasks that override project tasks
     for task_name in &workspace_task_names {
         if all_project_task_names.contains(task_name) {
-            result.add_info(
-                formatter,
-                format!(
-                    "Workspace task '{task_name}' overrides a project task with the same name."
-                ),
-            )?;
+            result.add_info(format!(
+                "Workspace task '{task_name}' overrides a project task with the same name."
+            ))?;
         }
     }
     Ok(())
 }
 
 fn check_for_dependency_issues(
-    formatter: &Formatter,
     workspace: &Workspace,
     result: &mut DiagnosticResult,
 ) -> eyre::Result<()> {
@@ -658,7 +1095,6 @@ fn check_for_dependency_issues(
         Ok(graph) => graph,
         Err(e) => {
             result.add_error(
-                formatter,
                 format!("Failed to load dependency graph: {e}"),
                 Some("Ensure all projects are properly configured in the workspace".to_string()),
             )?;
@@ -670,17 +1106,19 @@ fn check_for_dependency_issues(
     // Check for circular dependencies first (more critical)
     match dependency_graph.resolve_startup_order() {
         Ok(_) => {
-            result.add_success(formatter, "Dependency order is valid".to_string())?;
+            result.add_success("Dependency order is valid".to_string())?;
         }
         Err(DependencyGraphError::CircularDependency(projects)) => {
             let projects_str = projects.iter().map(|p| p.as_str()).join(", ");
             result.add_error(
-                formatter,
                 format!("Circular dependency detected: {projects_str}"),
                 Some("Refactor your dependencies to remove circular references".to_string()),
             )?;
         }
-        Err(DependencyGraphError::MissingDependencies(_)) => {
+        Err(
+            DependencyGraphError::MissingDependencies(_)
+            | DependencyGraphError::DisabledDependencies(_),
+        ) => {
             // This will be handled in the next check
         }
     }
@@ -688,7 +1126,7 @@ fn check_for_dependency_issues(
     // Validate dependencies are available
     match dependency_graph.validate_dependencies() {
         Ok(()) => {
-            result.add_success(formatter, "All dependencies are available".to_string())?;
+            result.add_success("All dependencies are available".to_string())?;
         }
         Err(DependencyGraphError::MissingDependencies(dependencies)) => {
             let grouped = dependencies
@@ -705,16 +1143,29 @@ fn check_for_dependency_issues(
                 .collect::<Vec<_>>();
 
             result.add_error_group(
-                formatter,
                 "Missing Dependencies".to_string(),
                 grouped,
                 Some("Ensure all required projects are added to the workspace".to_string()),
             )?;
         }
+        Err(DependencyGraphError::DisabledDependencies(dependencies)) => {
+            let messages = dependencies
+                .into_iter()
+                .map(|(project, dep)| format!("{project}: {dep}"))
+                .collect::<Vec<_>>();
+
+            result.add_error_group(
+                "Dependencies on disabled projects".to_string(),
+                messages,
+                Some(
+                    "Run `de workspace enable <project>` on the dependency, or disable the dependents too"
+                        .to_string(),
+                ),
+            )?;
+        }
         Err(DependencyGraphError::CircularDependency(_)) => {
             // This should not happen here, already handled above
             result.add_error(
-                formatter,
                 "Unexpected circular dependency detected".to_string(),
                 Some("This should not happen, please report this issue".to_string()),
             )?;
@@ -724,34 +1175,287 @@ fn check_for_dependency_issues(
     Ok(())
 }
 
-fn validate_docker_compose(compose_path: &std::path::Path) -> eyre::Result<()> {
-    let output = Command::new("docker-compose")
-        .arg("-f")
-        .arg(compose_path)
-        .arg("config")
-        .arg("--quiet")
-        .output();
+/// Reports host ports that services of more than one enabled project publish, since only one
+/// of them can bind the port at `de start`.
+fn check_for_port_conflicts(
+    workspace: &Workspace,
+    result: &mut DiagnosticResult,
+) -> eyre::Result<()> {
+    let mut users: BTreeMap<(u16, String), Vec<(Slug, String)>> = BTreeMap::new();
+    let mut checked = false;
 
-    if let Ok(output) = output
-        && output.status.success()
-    {
+    for (project_id, workspace_project) in workspace.enabled_projects() {
+        let Ok(project) = Project::from_dir(&workspace_project.dir) else {
+            continue;
+        };
+        let Ok(Some(compose_path)) = project.docker_compose_path() else {
+            continue;
+        };
+        let Some(ports) =
+            docker_compose_config(&compose_path, project.env(), &["--format", "json"])
+                .ok()
+                .and_then(|stdout| parse_published_ports(&stdout))
+        else {
+            continue;
+        };
+
+        checked = true;
+        for (service, port, protocol) in ports {
+            users
+                .entry((port, protocol))
+                .or_default()
+                .push((project_id.clone(), service));
+        }
+    }
+
+    if !checked {
         return Ok(());
     }
 
-    // Try with docker compose plugin
-    let output = Command::new("docker")
-        .arg("compose")
-        .arg("-f")
-        .arg(compose_path)
-        .arg("config")
-        .arg("--quiet")
-        .output()
-        .map_err(|e| eyre!("Failed to validate compose file: {}", e))?;
+    let conflicts = users
+        .into_iter()
+        .filter(|(_, users)| users.iter().map(|(project, _)| project).unique().count() > 1)
+        .map(|((port, protocol), users)| {
+            let users = users
+                .iter()
+                .map(|(project, service)| format!("{project}/{service}"))
+                .join(", ");
+            format!("{port}/{protocol}: {users}")
+        })
+        .collect::<Vec<_>>();
+
+    if conflicts.is_empty() {
+        result.add_success("No host port conflicts between projects".to_string())?;
+    } else {
+        result.add_error_group(
+            "Host ports published by more than one project".to_string(),
+            conflicts,
+            Some(
+                "Change the published ports in the Docker Compose files so each is used once"
+                    .to_string(),
+            ),
+        )?;
+    }
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(eyre!("Compose file validation failed: {}", stderr));
+    Ok(())
+}
+
+/// Reports projects that end up with the same Docker Compose project name, whose containers
+/// would replace each other's.
+fn check_for_compose_name_collisions(
+    workspace: &Workspace,
+    result: &mut DiagnosticResult,
+) -> eyre::Result<()> {
+    let mut names: BTreeMap<String, Vec<Slug>> = BTreeMap::new();
+
+    for (project_id, workspace_project) in workspace.enabled_projects() {
+        let Ok(project) = Project::from_dir(&workspace_project.dir) else {
+            continue;
+        };
+        let Ok(Some(compose_path)) = project.docker_compose_path() else {
+            continue;
+        };
+
+        if let Some(name) = compose_project_name(&compose_path, project.env()) {
+            names.entry(name).or_default().push(project_id.clone());
+        }
+    }
+
+    if names.is_empty() {
+        return Ok(());
+    }
+
+    let collisions = names
+        .into_iter()
+        .filter(|(_, projects)| projects.len() > 1)
+        .map(|(name, projects)| format!("{name}: {}", projects.iter().join(", ")))
+        .collect::<Vec<_>>();
+
+    if collisions.is_empty() {
+        result.add_success("Docker Compose project names are unique".to_string())?;
+    } else {
+        result.add_error_group(
+            "Projects sharing a Docker Compose project name".to_string(),
+            collisions,
+            Some(
+                "Set a unique COMPOSE_PROJECT_NAME in each project's .env, or `name:` in its Docker Compose file"
+                    .to_string(),
+            ),
+        )?;
     }
 
     Ok(())
 }
+
+/// Extracts the `(service, host port, protocol)` of every published port from
+/// `docker compose config --format json`. Returns `None` if the output isn't JSON.
+fn parse_published_ports(stdout: &str) -> Option<Vec<(String, u16, String)>> {
+    let config: serde_json::Value = serde_json::from_str(stdout).ok()?;
+    let Some(services) = config
+        .get("services")
+        .and_then(|services| services.as_object())
+    else {
+        return Some(Vec::new());
+    };
+
+    let mut ports = Vec::new();
+    for (service, definition) in services {
+        let Some(service_ports) = definition.get("ports").and_then(|ports| ports.as_array()) else {
+            continue;
+        };
+
+        for port in service_ports {
+            // `published` is a string in recent Compose versions and a number in older ones
+            let published = match port.get("published") {
+                Some(serde_json::Value::String(published)) => published.parse::<u16>().ok(),
+                Some(serde_json::Value::Number(published)) => {
+                    published.as_u64().and_then(|port| u16::try_from(port).ok())
+                }
+                _ => None,
+            };
+            let Some(published) = published.filter(|port| *port != 0) else {
+                continue;
+            };
+
+            let protocol = port
+                .get("protocol")
+                .and_then(|protocol| protocol.as_str())
+                .unwrap_or("tcp")
+                .to_string();
+            ports.push((service.clone(), published, protocol));
+        }
+    }
+
+    Some(ports)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_published_ports() {
+        let stdout = r#"{
+            "name": "api",
+            "services": {
+                "db": {
+                    "image": "postgres",
+                    "ports": [
+                        {"mode": "ingress", "target": 5432, "published": "5432", "protocol": "tcp"},
+                        {"mode": "ingress", "target": 9000, "published": 9000}
+                    ]
+                },
+                "worker": {"image": "api", "ports": [{"mode": "ingress", "target": 8080}]},
+                "cron": {"image": "api"}
+            }
+        }"#;
+
+        assert_eq!(
+            parse_published_ports(stdout),
+            Some(vec![
+                ("db".to_string(), 5432, "tcp".to_string()),
+                ("db".to_string(), 9000, "tcp".to_string()),
+            ])
+        );
+        assert_eq!(parse_published_ports("services:\n  db: {}\n"), None);
+    }
+
+    #[test]
+    fn test_check_active_workspace_exists() {
+        let errors = |config: Config| {
+            let mut result = DiagnosticResult::new(DiagnosticSection::Workspace);
+            check_active_workspace_exists(&mut result, Ok(config), |name| {
+                if name.as_str() == "current" {
+                    let dir = tempfile::tempdir().unwrap();
+                    let path = dir.path().join("current.toml");
+                    std::fs::write(&path, "name = \"current\"\n\n[projects]\n").unwrap();
+                    Workspace::load_from_path(path)
+                } else {
+                    Ok(None)
+                }
+            })
+            .unwrap();
+            result
+                .entries
+                .into_iter()
+                .map(|entry| entry.message)
+                .collect::<Vec<_>>()
+        };
+
+        assert!(errors(Config::default()).is_empty());
+
+        let mut config = Config::default();
+        config.set_active_workspace(Some(Slug::from_str("current").unwrap()));
+        assert!(errors(config).is_empty());
+
+        let mut config = Config::default();
+        config.set_active_workspace(Some(Slug::from_str("deleted").unwrap()));
+        assert_eq!(errors(config), ["Active workspace deleted does not exist"]);
+    }
+
+    #[test]
+    fn test_check_step_services() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("de.toml"),
+            r#"[project]
+name = "api"
+
+[setup]
+git = { url = "https://example.com/api.git", branch = "main" }
+
+[setup.steps.seed]
+name = "Seed"
+command = "psql -f seed.sql"
+service = "db"
+
+[setup.steps.cache]
+name = "Flush cache"
+command = "redis-cli flushall"
+service = "cache"
+
+[setup.steps.other]
+name = "Other"
+command = "true"
+service = { name = "elsewhere", compose = "other.yml" }
+
+[setup.profiles.ci.steps.warm]
+name = "Warm up"
+command = "true"
+service = "queue"
+"#,
+        )
+        .unwrap();
+        let project = Project::from_dir(dir.path()).unwrap();
+
+        let errors = |services: Option<&[String]>| {
+            let mut result = DiagnosticResult::new(DiagnosticSection::Workspace);
+            check_step_services(&project, services, &mut result).unwrap();
+            result
+                .entries
+                .into_iter()
+                .map(|entry| entry.message)
+                .collect::<Vec<_>>()
+        };
+
+        let services = ["db".to_string(), "queue".to_string()];
+        assert_eq!(
+            errors(Some(&services)),
+            ["Setup step 'cache' references missing Docker Compose service 'cache'"]
+        );
+
+        let missing = errors(None);
+        assert_eq!(missing.len(), 3, "{missing:?}");
+        assert!(missing.iter().all(|e| e.contains("no Docker Compose file")));
+        assert!(
+            missing.contains(
+                &"Setup step 'warm' of profile 'ci' references Docker Compose service 'queue' but \
+                  no Docker Compose file is configured or found"
+                    .to_string()
+            ),
+            "{missing:?}"
+        );
+    }
+}
@@ -0,0 +1,69 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use eyre::{WrapErr, eyre};
+
+use crate::{cli::EnvFormat, types::Slug, utils::get_project_for_cli};
+
+/// Substrings of variable names whose values are masked unless secrets are requested.
+const SECRET_MARKERS: &[&str] = &["SECRET", "TOKEN", "PASSWORD"];
+
+const MASK: &str = "********";
+
+/// Print the environment a task or exec in the project would receive.
+pub fn env(
+    project_name: Option<Slug>,
+    workspace_name: Option<Option<Slug>>,
+    format: EnvFormat,
+    show_secrets: bool,
+    cwd: &Path,
+) -> eyre::Result<()> {
+    let project = get_project_for_cli(project_name, workspace_name, cwd)?;
+
+    // Tasks inherit the de environment with the project's `.env` layered on top.
+    let vars: BTreeMap<String, String> = std::env::vars()
+        .chain(project.env().clone())
+        .map(|(key, value)| {
+            if !show_secrets && is_secret(&key) {
+                (key, MASK.to_string())
+            } else {
+                (key, value)
+            }
+        })
+        .collect();
+
+    match format {
+        EnvFormat::Shell => {
+            for (key, value) in &vars {
+                println!("export {}={}", key, shell_quote(value));
+            }
+        }
+        EnvFormat::Dotenv => {
+            for (key, value) in &vars {
+                println!(
+                    "{}=\"{}\"",
+                    key,
+                    value.replace('\\', "\\\\").replace('"', "\\\"")
+                );
+            }
+        }
+        EnvFormat::Json => {
+            let json = serde_json::to_string_pretty(&vars)
+                .map_err(|e| eyre!(e))
+                .wrap_err("Failed to serialize environment as JSON")?;
+            println!("{json}");
+        }
+    }
+
+    Ok(())
+}
+
+fn is_secret(key: &str) -> bool {
+    let key = key.to_uppercase();
+    SECRET_MARKERS.iter().any(|marker| key.contains(marker))
+}
+
+/// Quotes a value for POSIX shells, e.g. `it's` becomes `'it'\''s'`.
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
@@ -1,43 +1,133 @@
 use eyre::{Context, Result, bail};
-use std::process::Command;
+use std::{io::IsTerminal, path::Path, process::Command};
 
-use crate::{types::Slug, workspace::Workspace};
+use crate::{
+    error::DeError, project::Project, types::Slug, utils::process::CommandExt, workspace::Workspace,
+};
 use eyre::eyre;
 
-pub fn exec(project_name: Slug, workspace_name: Option<Slug>, command: Vec<String>) -> Result<()> {
+/// Runs `de exec` in a Docker Compose service of the project instead of on the host.
+#[derive(Debug)]
+pub struct ServiceExec {
+    pub service: String,
+    /// Never allocate a TTY, even if stdin is a terminal.
+    pub no_tty: bool,
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn exec(
+    project_name: Option<Slug>,
+    workspace_root: bool,
+    workspace_name: Option<Slug>,
+    env: Vec<(String, String)>,
+    no_env: bool,
+    service: Option<ServiceExec>,
+    command: Vec<String>,
+    cwd: &Path,
+) -> Result<()> {
     let mut command = command.into_iter();
     let program = command.next().ok_or_else(|| eyre!("No command provided"))?;
     let args = command.collect::<Vec<_>>();
 
     let workspace = if let Some(workspace_name) = workspace_name {
-        Workspace::load_from_name(&workspace_name)
-            .map_err(|e| eyre!(e))
-            .wrap_err("Failed to load workspace")?
-            .ok_or_else(|| eyre!("Workspace '{}' not found", workspace_name))?
+        Workspace::require(&workspace_name, cwd)?
     } else {
-        Workspace::active()
-            .map_err(|e| eyre!(e))
-            .wrap_err("Failed to get active workspace")?
-            .ok_or_else(|| eyre!("No current workspace found"))?
+        Workspace::require_active(cwd)?
     };
 
-    let project = workspace
-        .config()
-        .projects
-        .get(&project_name)
-        .ok_or_else(|| {
+    let dir = if workspace_root {
+        workspace.config().root_dir.clone().ok_or_else(|| {
             eyre!(
-                "Project '{}' not found in workspace '{}'",
-                project_name,
+                "Workspace '{}' has no root directory, set one with `de workspace config root-dir <path>`",
                 workspace.config().name
             )
-        })?;
+        })?
+    } else {
+        let project_name = project_name.ok_or_else(|| eyre!("No project provided"))?;
+        workspace
+            .project(&project_name)
+            .ok_or_else(|| {
+                eyre!(DeError::ProjectNotFound {
+                    project: project_name.clone(),
+                    workspace: workspace.config().name.clone(),
+                })
+            })?
+            .dir
+            .clone()
+    };
 
+    if let Some(service) = service {
+        return exec_in_service(&dir, &service, &env, &program, &args);
+    }
+
+    // stdin, stdout and stderr are inherited, so interactive programs work as usual
     let mut cmd = Command::new(&program);
     cmd.args(&args);
-    cmd.current_dir(&project.dir);
+    cmd.current_dir(&dir);
+
+    // The project's `.env` is layered over the environment of de, like for tasks
+    if !no_env && dir.join("de.toml").exists() {
+        let project = Project::from_dir(&dir)
+            .map_err(|e| eyre!(e))
+            .wrap_err("Failed to load project")?;
+        cmd.envs(project.env());
+    }
+    cmd.envs(env);
+
+    let status = cmd.logged_status()?;
+    if !status.success() {
+        bail!("Command exited with non-zero status: {}", status);
+    }
+
+    Ok(())
+}
+
+/// Runs the command with `docker-compose exec` in a service of the project in `dir`.
+fn exec_in_service(
+    dir: &Path,
+    service: &ServiceExec,
+    env: &[(String, String)],
+    program: &str,
+    args: &[String],
+) -> Result<()> {
+    let project = Project::from_dir(dir)
+        .map_err(|e| eyre!(e))
+        .wrap_err("Failed to load project")?;
+
+    let compose_path = project
+        .docker_compose_path()
+        .map_err(|e| eyre!(e))
+        .wrap_err("Failed to get docker compose path")?
+        .ok_or_else(|| {
+            eyre!(
+                "Can't run in service {} because project {} has no Docker Compose file",
+                service.service,
+                project.manifest().project().name
+            )
+        })?;
+
+    let mut cmd = Command::new("docker-compose");
+    cmd.envs(project.env())
+        .current_dir(dir)
+        .arg("-f")
+        .arg(compose_path)
+        .arg("exec");
+
+    // `exec` allocates a TTY by default, which fails without a terminal on stdin
+    if service.no_tty || !std::io::stdin().is_terminal() {
+        cmd.arg("-T");
+    }
+
+    for (key, value) in env {
+        cmd.arg("-e").arg(format!("{key}={value}"));
+    }
+
+    cmd.arg(&service.service).arg(program).args(args);
 
-    let status = cmd.status()?;
+    let status = cmd
+        .logged_status()
+        .map_err(|e| eyre!(DeError::DockerUnavailable(e)))
+        .wrap_err("Failed to run docker-compose exec")?;
     if !status.success() {
         bail!("Command exited with non-zero status: {}", status);
     }
@@ -1,9 +1,34 @@
 use eyre::{Context, Result, eyre};
-use std::process::Command;
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
 
-use crate::{types::Slug, workspace::Workspace};
+use crate::{
+    cli::ExecOrder,
+    project::Project,
+    types::Slug,
+    utils::{path::has_reverse_path_traversal, process::CommandExt, ui::UserInterface},
+    workspace::Workspace,
+};
+
+pub fn exec_all(
+    workspace_name: Option<Slug>,
+    order: ExecOrder,
+    subdir: Option<PathBuf>,
+    no_env: bool,
+    command: Vec<String>,
+    cwd: &Path,
+) -> Result<()> {
+    if let Some(subdir) = &subdir
+        && (subdir.is_absolute() || has_reverse_path_traversal(subdir))
+    {
+        return Err(eyre!(
+            "--subdir must be a path inside the project, got {}",
+            subdir.display()
+        ));
+    }
 
-pub fn exec_all(workspace_name: Option<Slug>, command: Vec<String>) -> Result<()> {
     let mut command_iter = command.into_iter();
     let program = command_iter
         .next()
@@ -11,25 +36,43 @@ pub fn exec_all(workspace_name: Option<Slug>, command: Vec<String>) -> Result<()
     let args = command_iter.collect::<Vec<_>>();
 
     let workspace = if let Some(workspace_name) = workspace_name {
-        Workspace::load_from_name(&workspace_name)
-            .map_err(|e| eyre!(e))
-            .wrap_err("Failed to load workspace")?
-            .ok_or_else(|| eyre!("Workspace '{}' not found", workspace_name))?
+        Workspace::require(&workspace_name, cwd)?
     } else {
-        Workspace::active()
-            .map_err(|e| eyre!(e))
-            .wrap_err("Failed to get active workspace")?
-            .ok_or_else(|| eyre!("No current workspace found"))?
+        Workspace::require_active(cwd)?
     };
 
-    for (project_name, project) in workspace.config().projects.iter() {
+    let ui = UserInterface::new();
+    for project_name in project_order(&ui, &workspace, order) {
+        let project = &workspace.config().projects[&project_name];
+
+        let dir = match &subdir {
+            Some(subdir) => project.dir.join(subdir),
+            None => project.dir.clone(),
+        };
+        if subdir.is_some() && !dir.is_dir() {
+            println!(
+                "Skipping project '{project_name}': {} does not exist",
+                dir.display()
+            );
+            continue;
+        }
+
         println!("Executing command in project: {project_name}");
         let mut cmd = Command::new(&program);
         cmd.args(&args);
-        cmd.current_dir(&project.dir);
+        cmd.current_dir(&dir);
+
+        if !no_env && project.dir.join("de.toml").exists() {
+            match Project::from_dir(&project.dir) {
+                Ok(project) => {
+                    cmd.envs(project.env());
+                }
+                Err(e) => eprintln!("Failed to load .env for project '{project_name}': {e}"),
+            }
+        }
 
         let status = cmd
-            .status()
+            .logged_status()
             .wrap_err_with(|| format!("Failed to execute command in project '{project_name}'"))?;
         if !status.success() {
             eprintln!("Command failed in project '{project_name}' with status: {status}");
@@ -38,3 +81,133 @@ pub fn exec_all(workspace_name: Option<Slug>, command: Vec<String>) -> Result<()
 
     Ok(())
 }
+
+/// The IDs of the workspace's projects in the given order. Disabled projects, which have no
+/// place in the dependency order, run last. If the dependencies can't be loaded or form a
+/// cycle, a warning is printed and the projects are ordered by name.
+fn project_order(ui: &UserInterface, workspace: &Workspace, order: ExecOrder) -> Vec<Slug> {
+    let by_name = workspace.project_ids().cloned().collect::<Vec<_>>();
+
+    if order == ExecOrder::Name {
+        return by_name;
+    }
+
+    let resolved = workspace.load_dependency_graph().and_then(|(graph, _)| {
+        let resolved = match order {
+            ExecOrder::Shutdown => graph.resolve_shutdown_order(),
+            _ => graph.resolve_startup_order(),
+        };
+        resolved.map_err(|e| eyre!(e))
+    });
+
+    match resolved {
+        Ok(ids) => {
+            // Dependencies that aren't registered projects are part of the graph, but can't run
+            let mut ordered = ids
+                .into_iter()
+                .filter(|id| workspace.project(id).is_some())
+                .collect::<Vec<_>>();
+            let rest = by_name
+                .into_iter()
+                .filter(|id| !ordered.contains(id))
+                .collect::<Vec<_>>();
+            ordered.extend(rest);
+            ordered
+        }
+        Err(e) => {
+            let _ = ui.warning_item(&format!("{e:#}; running in name order instead"), None);
+            by_name
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{path::Path, str::FromStr};
+
+    /// A workspace with a project for each `(id, depends_on)`.
+    fn workspace_in(dir: &Path, projects: &[(&str, &str)]) -> Workspace {
+        let mut config = "name = \"test\"\n\n[projects]\n".to_string();
+        for (id, depends_on) in projects {
+            let project_dir = dir.join(id);
+            std::fs::create_dir(&project_dir).unwrap();
+            std::fs::write(
+                project_dir.join("de.toml"),
+                format!(
+                    "[project]\nname = \"{id}\"\nworkspace = \"test\"\n\
+                     depends_on = [{depends_on}]\n"
+                ),
+            )
+            .unwrap();
+            config.push_str(&format!(
+                "{id} = {{ dir = \"{}\" }}\n",
+                project_dir.display()
+            ));
+        }
+
+        let path = dir.join("test.toml");
+        std::fs::write(&path, config).unwrap();
+        Workspace::load_from_path(path).unwrap().unwrap()
+    }
+
+    fn ids(names: &[&str]) -> Vec<Slug> {
+        names
+            .iter()
+            .map(|name| Slug::from_str(name).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_project_order_follows_dependencies() {
+        let dir = tempfile::tempdir().unwrap();
+        let workspace = workspace_in(
+            dir.path(),
+            &[("api", "\"db\""), ("db", ""), ("web", "\"api\"")],
+        );
+        let ui = UserInterface::new().buffered();
+
+        assert_eq!(
+            project_order(&ui, &workspace, ExecOrder::Name),
+            ids(&["api", "db", "web"])
+        );
+        assert_eq!(
+            project_order(&ui, &workspace, ExecOrder::Startup),
+            ids(&["db", "api", "web"])
+        );
+        assert_eq!(
+            project_order(&ui, &workspace, ExecOrder::Shutdown),
+            ids(&["web", "api", "db"])
+        );
+    }
+
+    #[test]
+    fn test_project_order_runs_disabled_projects_last() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut workspace = workspace_in(dir.path(), &[("api", ""), ("cache", ""), ("db", "")]);
+        workspace
+            .config_mut()
+            .projects
+            .get_mut(&Slug::from_str("api").unwrap())
+            .unwrap()
+            .enabled = false;
+        let ui = UserInterface::new().buffered();
+
+        assert_eq!(
+            project_order(&ui, &workspace, ExecOrder::Startup),
+            ids(&["cache", "db", "api"])
+        );
+    }
+
+    #[test]
+    fn test_project_order_falls_back_to_names_on_a_cycle() {
+        let dir = tempfile::tempdir().unwrap();
+        let workspace = workspace_in(dir.path(), &[("api", "\"db\""), ("db", "\"api\"")]);
+        let ui = UserInterface::new().buffered();
+
+        assert_eq!(
+            project_order(&ui, &workspace, ExecOrder::Startup),
+            ids(&["api", "db"])
+        );
+    }
+}
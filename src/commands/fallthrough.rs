@@ -1,24 +1,29 @@
+use std::path::Path;
 use std::str::FromStr;
 
 use clap::CommandFactory;
 use eyre::{Context, bail, eyre};
 
 use crate::{
-    cli::Cli, commands::run::run_project_task, project::Project, types::Slug, utils::theme::Theme,
-    workspace::Workspace,
+    cli::Cli,
+    error::DeError,
+    project::{Project, TaskContext, run_project_task},
+    types::Slug,
+    utils::{get_workspace_for_cli, theme::Theme},
 };
 
-pub fn fallthrough(args: Vec<String>) -> eyre::Result<()> {
-    let workspace = Workspace::active()
-        .map_err(|e| eyre!(e))
-        .wrap_err("Failed to get active workspace")?
-        .ok_or_else(|| eyre!("No current workspace found"))?;
+pub fn fallthrough(
+    args: Vec<String>,
+    workspace_name: Option<Slug>,
+    cwd: &Path,
+) -> eyre::Result<()> {
+    let workspace = get_workspace_for_cli(Some(workspace_name), cwd)?;
 
     let (command, args) = split_args(args)
         .map_err(|e| eyre!(e))
         .wrap_err("Failed to parse command and arguments")?;
 
-    if let Some(ws_project) = workspace.config().projects.get(&command) {
+    if let Some(ws_project) = workspace.project(&command) {
         let project = Project::from_dir(&ws_project.dir)
             .map_err(|e| eyre!(e))
             .wrap_err("Failed to load project from directory")?;
@@ -27,21 +32,20 @@ pub fn fallthrough(args: Vec<String>) -> eyre::Result<()> {
             .map_err(|e| eyre!(e))
             .wrap_err("Failed to parse command and arguments")?;
 
-        if run_project_task(&project, &command, &args)? {
+        if run_project_task(&project, &command, &args, None, &TaskContext::default())? {
             return Ok(());
         } else {
-            bail!(
-                "Task '{}' not found in project '{}'",
-                command,
-                project.manifest().project().name
-            );
+            bail!(DeError::TaskNotFound {
+                task: command,
+                location: format!("project '{}'", project.manifest().project().name),
+            });
         }
     }
 
-    if let Some(project) = Project::current()
+    if let Some(project) = Project::from_dir_recursive(cwd)
         .map_err(|e| eyre!(e))
         .wrap_err("Failed to get current project")?
-        && run_project_task(&project, &command, &args)?
+        && run_project_task(&project, &command, &args, None, &TaskContext::default())?
     {
         return Ok(());
     }
@@ -1,46 +1,72 @@
+use std::path::Path;
+
 use crate::{
-    cli::OnDirtyAction,
+    cli::{OnDirtyAction, OnUnpushedAction},
     project::Project,
+    types::Slug,
     utils::{
         formatter::Formatter,
+        get_workspace_for_cli,
         git::{
-            branch_exists, get_current_branch, has_unpushed_commits, is_project_dirty,
+            FETCH_ATTEMPTS, auto_stash_message, branch_exists, fetch_with_retry,
+            get_current_branch, get_default_branch, has_unpushed_commits, is_project_dirty,
             run_git_command,
         },
+        is_interactive,
         theme::Theme,
     },
-    workspace::Workspace,
 };
 use dialoguer::{Select, theme::ColorfulTheme};
 use eyre::{Context, Result, eyre};
 
-pub fn base_reset(base_branch: Option<String>, on_dirty: OnDirtyAction) -> Result<()> {
+pub fn base_reset(
+    base_branch: Option<String>,
+    on_dirty: OnDirtyAction,
+    on_unpushed: Option<OnUnpushedAction>,
+    no_retry: bool,
+    stash_message: Option<String>,
+    workspace_name: Option<Slug>,
+    cwd: &Path,
+) -> Result<()> {
+    // Only ask about unpushed commits when asking about uncommitted changes too
+    let on_unpushed = on_unpushed
+        .or_else(|| (on_dirty != OnDirtyAction::Prompt).then_some(OnUnpushedAction::Abort));
+
     let theme = Theme::new();
-    let formatter = Formatter::new();
-    let workspace =
-        Workspace::active()?.ok_or_else(|| eyre::eyre!("No active workspace found."))?;
-
-    // Determine the branch to use
-    let branch = if let Some(branch) = base_branch.as_deref() {
-        branch
-    } else {
-        workspace
-            .config()
-            .default_branch
-            .as_deref()
-            .ok_or_else(|| eyre!("No default branch set in workspace config. Set default branch in workspace config or provide a base branch to command"))?
-    };
+    let formatter = Formatter::with_theme(theme.clone());
+    let workspace = get_workspace_for_cli(Some(workspace_name), cwd)?;
 
-    println!(
-        "{}",
-        theme.highlight(&format!("Resetting workspace to base branch '{branch}'..."))
-    );
+    let shared_branch = base_branch
+        .as_deref()
+        .or(workspace.config().default_branch.as_deref());
+    let stash_message = stash_message
+        .unwrap_or_else(|| auto_stash_message("de base-reset", shared_branch.unwrap_or("default")));
+
+    match shared_branch {
+        Some(branch) => println!(
+            "{}",
+            theme.highlight(&format!("Resetting workspace to base branch '{branch}'..."))
+        ),
+        None => println!(
+            "{}",
+            theme.highlight("Resetting workspace to the default branch of each project...")
+        ),
+    }
 
     let mut projects_with_issues = Vec::new();
     let mut projects_ready = Vec::new();
+    let mut git_disabled = Vec::new();
+
+    for project_name in workspace.disabled_project_ids() {
+        println!();
+        println!(
+            "{}",
+            theme.dim(&format!("Skipping project {project_name} (disabled)"))
+        );
+    }
 
     let mut aborted = false;
-    for (project_name, ws_project) in workspace.config().projects.iter() {
+    for (project_name, ws_project) in workspace.enabled_projects() {
         if aborted {
             break;
         }
@@ -61,9 +87,22 @@ pub fn base_reset(base_branch: Option<String>, on_dirty: OnDirtyAction) -> Resul
 
         if !project.manifest().git.clone().unwrap_or_default().enabled {
             println!(" Git is not enabled for this project. Skipping...");
+            git_disabled.push(project_name.to_string());
             continue;
         }
 
+        // The base branch given to the command, the project's or workspace's default branch,
+        // or the default branch of the project's remote
+        let branch = match base_branch
+            .as_deref()
+            .or_else(|| workspace.config().default_branch_for(ws_project))
+        {
+            Some(branch) => branch.to_string(),
+            None => get_default_branch(&ws_project.dir).unwrap_or_else(|_| "main".to_string()),
+        };
+        let branch = branch.as_str();
+        println!("  Base branch: {}", theme.accent(branch));
+
         if let Ok(current_branch) = get_current_branch(&ws_project.dir) {
             println!(
                 "  Current branch: {}",
@@ -74,7 +113,18 @@ pub fn base_reset(base_branch: Option<String>, on_dirty: OnDirtyAction) -> Resul
         // 1. Fetch all remotes
         println!("  Fetching remotes...");
         let mut has_issue = false;
-        if let Err(e) = run_git_command(&["fetch", "--all", "--prune"], &ws_project.dir) {
+        if let Err(e) = fetch_with_retry(
+            &["--all", "--prune"],
+            &ws_project.dir,
+            !no_retry,
+            |attempt, delay| {
+                println!(
+                    "  {} retrying in {}s (attempt {attempt}/{FETCH_ATTEMPTS})...",
+                    theme.warn("Fetch failed,"),
+                    delay.as_secs()
+                );
+            },
+        ) {
             println!(
                 "  {} {}",
                 theme.error("FETCH FAILED:"),
@@ -88,19 +138,36 @@ pub fn base_reset(base_branch: Option<String>, on_dirty: OnDirtyAction) -> Resul
             && let Ok(true) = has_unpushed_commits(&current_branch, &ws_project.dir)
         {
             println!("  {}", theme.warn("You have unpushed commits!"));
-            let choices = &[
-                "Push commits now",
-                "Skip this project",
-                "Abort all (stop processing)",
-                "Proceed anyway (dangerous!)",
-            ];
-            let selection = Select::with_theme(&ColorfulTheme::default())
-                .with_prompt("What do you want to do?")
-                .default(0)
-                .items(choices)
-                .interact()?;
-            match selection {
-                0 => {
+            let action = match on_unpushed {
+                Some(action) => action,
+                None if !is_interactive() => {
+                    return Err(eyre!(
+                        "{} has unpushed commits. Re-run with --on-unpushed <ACTION>",
+                        ws_project.dir.display()
+                    ));
+                }
+                None => {
+                    let choices = &[
+                        "Push commits now",
+                        "Skip this project",
+                        "Abort all (stop processing)",
+                        "Proceed anyway (dangerous!)",
+                    ];
+                    let selection = Select::with_theme(&ColorfulTheme::default())
+                        .with_prompt("What do you want to do?")
+                        .default(0)
+                        .items(choices)
+                        .interact()?;
+                    [
+                        OnUnpushedAction::Push,
+                        OnUnpushedAction::Skip,
+                        OnUnpushedAction::Abort,
+                        OnUnpushedAction::Proceed,
+                    ][selection]
+                }
+            };
+            match action {
+                OnUnpushedAction::Push => {
                     // Try to push
                     if let Err(e) = run_git_command(&["push"], &ws_project.dir) {
                         println!(
@@ -108,16 +175,21 @@ pub fn base_reset(base_branch: Option<String>, on_dirty: OnDirtyAction) -> Resul
                             theme.error("PUSH FAILED:"),
                             theme.highlight(&e.to_string())
                         );
-                        has_issue = true;
+                        // Resetting now would throw away the commits that failed to push
+                        projects_with_issues.push(project_name.to_string());
+                        continue;
                     }
                 }
-                1 => continue,
-                2 => {
+                OnUnpushedAction::Skip => {
+                    println!("  Skipping project with unpushed commits.");
+                    continue;
+                }
+                OnUnpushedAction::Abort => {
+                    println!("  {}", theme.error("Aborting: unpushed commits."));
                     aborted = true;
                     break;
                 }
-                3 => {} // Proceed anyway
-                _ => unreachable!(),
+                OnUnpushedAction::Proceed => {}
             }
         }
 
@@ -132,6 +204,13 @@ pub fn base_reset(base_branch: Option<String>, on_dirty: OnDirtyAction) -> Resul
                 OnDirtyAction::Prompt => {
                     println!("  {}", theme.warn("Uncommitted changes detected!"));
 
+                    if !is_interactive() {
+                        return Err(eyre!(
+                            "{} has uncommitted changes. Re-run with --on-dirty <ACTION>",
+                            ws_project.dir.display()
+                        ));
+                    }
+
                     let choices = &[
                         "Stash changes and proceed",
                         "Force reset (discard all changes)",
@@ -191,7 +270,10 @@ pub fn base_reset(base_branch: Option<String>, on_dirty: OnDirtyAction) -> Resul
             match action {
                 OnDirtyAction::Stash => {
                     println!("  Stashing changes...");
-                    if let Err(e) = run_git_command(&["stash", "push", "-u"], &ws_project.dir) {
+                    if let Err(e) = run_git_command(
+                        &["stash", "push", "-u", "-m", &stash_message],
+                        &ws_project.dir,
+                    ) {
                         println!(
                             "  {} {}",
                             theme.error("STASH FAILED:"),
@@ -328,6 +410,17 @@ pub fn base_reset(base_branch: Option<String>, on_dirty: OnDirtyAction) -> Resul
         }
     }
 
+    if !git_disabled.is_empty() {
+        println!(
+            "{}",
+            theme.dim(&format!(
+                "{} project(s) skipped (git disabled): {}",
+                git_disabled.len(),
+                git_disabled.join(", ")
+            ))
+        );
+    }
+
     if !aborted && projects_ready.is_empty() && projects_with_issues.is_empty() {
         println!("{}", theme.warn("No projects were prepared."));
     }
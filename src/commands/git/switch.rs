@@ -1,75 +1,154 @@
-use std::{collections::HashSet, path::Path, process::Command};
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    process::Command,
+    sync::Mutex,
+};
 
 use chrono::{DateTime, Utc};
 use dialoguer::{Select, theme::ColorfulTheme};
 use eyre::{Context, Result, eyre};
 use itertools::Itertools;
+use serde::Serialize;
 
 use crate::{
     cli::OnDirtyAction,
-    project::Project,
+    project::{Project, TaskContext, run_project_task},
     types::Slug,
     utils::{
-        git::{branch_exists, get_default_branch, run_git_command},
+        get_workspace_for_cli,
+        git::{
+            FETCH_ATTEMPTS, auto_stash_message, branch_exists, fetch_with_retry,
+            get_current_branch, get_default_branch, get_unmerged_paths, run_git_command,
+        },
+        is_interactive,
+        parallel::parallel_map,
+        process::CommandExt,
         ui::UserInterface,
     },
-    workspace::{Workspace, WorkspaceProject},
+    workspace::{Workspace, WorkspaceProject, config::WorkspaceConfig},
 };
 
+#[allow(clippy::too_many_arguments)]
 pub fn switch(
     query: Option<String>,
     fallback: Option<String>,
     on_dirty: Option<OnDirtyAction>,
+    keep_stash_on_failure: bool,
+    stash_message: Option<String>,
+    fetch: bool,
+    jobs: usize,
+    dry_run: bool,
+    run: Option<Slug>,
+    report: Option<PathBuf>,
+    workspace_name: Option<Slug>,
+    cwd: &Path,
 ) -> Result<()> {
     let ui = UserInterface::new();
 
     ui.heading("Switch Branch")?;
 
-    let workspace =
-        Workspace::active()?.ok_or_else(|| eyre::eyre!("No active workspace found."))?;
+    let workspace = get_workspace_for_cli(Some(workspace_name), cwd)?;
+
+    if fetch {
+        fetch_projects(&ui, &workspace)?;
+        ui.new_line()?;
+    }
 
     let target_branch = get_target_branch(&workspace, query)?;
 
     ui.info_item(&format!("Workspace: {}", workspace.config().name))?;
     ui.info_item(&format!("Target Branch: {target_branch}"))?;
 
+    if dry_run {
+        print_switch_plan(&ui, &workspace, &target_branch, fallback.as_deref())?;
+        if let Some(task_name) = &run {
+            ui.info_item(&format!(
+                "Would then run task '{task_name}' in each switched project"
+            ))?;
+        }
+        return Ok(());
+    }
+
     let dirty_projects = get_dirty_projects(&workspace)?;
     let action = on_dirty.unwrap_or(OnDirtyAction::Prompt);
 
     ui.new_line()?;
     ui.heading("Preflight")?;
 
-    if !dirty_projects.is_empty() {
-        handle_dirty_projects_preflight(&ui, &dirty_projects, &action)?;
+    let action = if !dirty_projects.is_empty() {
+        handle_dirty_projects_preflight(&ui, &dirty_projects, &action)?
     } else {
         ui.success_item("No dirty projects found. Proceeding...", None)?;
-    }
+        action
+    };
+
+    // Prompts can't be answered for several projects at once
+    let jobs = if jobs > 1 && action == OnDirtyAction::Prompt && !dirty_projects.is_empty() {
+        ui.info_item("Prompting for each dirty project, so switching one project at a time.")?;
+        1
+    } else {
+        jobs
+    };
 
     ui.new_line()?;
     ui.heading(&format!(
         "Synchronizing: {} (fallback: {})...",
         target_branch,
-        fallback.as_deref().unwrap_or("default")
+        fallback
+            .as_deref()
+            .or(workspace.config().default_branch.as_deref())
+            .unwrap_or("default")
     ))?;
 
     let mut projects_with_issues = Vec::new();
+    let mut conflicted_projects = Vec::new();
+    let mut switched_projects = HashSet::new();
+    let mut git_disabled = Vec::new();
 
-    for (project_name, ws_project) in workspace.config().projects.iter() {
-        let success = switch_project_branch(
-            &ui,
-            &workspace,
-            ws_project,
-            project_name,
-            &target_branch,
-            fallback.as_deref(),
-            &action,
-        )?;
+    for project_name in workspace.disabled_project_ids() {
+        ui.info_item(&ui.theme.dim(&format!("{project_name}: disabled, skipping")))?;
+    }
+
+    let stash_message =
+        stash_message.unwrap_or_else(|| auto_stash_message("de switch", &target_branch));
+    let projects = workspace.enabled_projects().collect::<Vec<_>>();
+    let options = SwitchOptions {
+        target_branch: &target_branch,
+        fallback: fallback.as_deref(),
+        workspace: workspace.config(),
+        on_dirty: &action,
+        stash_message: &stash_message,
+        keep_stash_on_failure,
+    };
+    let outcomes = if jobs > 1 {
+        switch_projects_concurrently(&ui, &projects, &options, jobs)?
+    } else {
+        projects
+            .iter()
+            .map(|(project_name, ws_project)| {
+                switch_project_branch(&ui, ws_project, project_name, &options)
+            })
+            .collect::<Result<Vec<_>>>()?
+    };
 
-        if !success {
-            projects_with_issues.push(project_name.to_string());
+    for ((project_name, ws_project), result) in projects.iter().zip(&outcomes) {
+        match result.outcome {
+            SwitchOutcome::Done => {
+                switched_projects.insert((*project_name).clone());
+            }
+            SwitchOutcome::Skipped => {}
+            SwitchOutcome::GitDisabled => git_disabled.push(project_name.to_string()),
+            SwitchOutcome::Failed => projects_with_issues.push(project_name.to_string()),
+            SwitchOutcome::Conflicted => conflicted_projects.push((*project_name, *ws_project)),
         }
     }
 
+    let task_failures = match &run {
+        Some(task_name) => run_post_switch_task(&ui, &workspace, task_name, &switched_projects)?,
+        None => Vec::new(),
+    };
+
     ui.new_line()?;
     ui.heading("Summary")?;
 
@@ -79,102 +158,624 @@ pub fn switch(
             &projects_with_issues,
             None,
         )?;
-    } else {
+    }
+
+    if let Some(task_name) = run.as_ref().filter(|_| !task_failures.is_empty()) {
+        ui.error_group(
+            &format!(
+                "Task '{task_name}' failed in {} project(s):",
+                task_failures.len()
+            ),
+            &task_failures,
+            None,
+        )?;
+    }
+
+    if !conflicted_projects.is_empty() {
+        ui.error_item(
+            &format!(
+                "{} project(s) left in conflict: resolve manually",
+                conflicted_projects.len()
+            ),
+            None,
+        )?;
+        ui.indented(|ui| {
+            for (project_name, ws_project) in &conflicted_projects {
+                ui.writeln(&ui.theme.bold(project_name.as_str()))?;
+                ui.indented(|ui| {
+                    for (description, command) in conflict_commands(&ws_project.dir) {
+                        ui.writeln(&format!("{description}: {}", ui.theme.accent(&command)))?;
+                    }
+                    Ok(())
+                })?;
+            }
+            Ok(())
+        })?;
+    }
+
+    if !git_disabled.is_empty() {
+        ui.info_item(&ui.theme.dim(&format!(
+            "{} project(s) skipped (git disabled): {}",
+            git_disabled.len(),
+            git_disabled.join(", ")
+        )))?;
+    }
+
+    if projects_with_issues.is_empty() && conflicted_projects.is_empty() {
         ui.success_item("All projects synchronized successfully.", None)?;
     }
 
+    if let Some(path) = report {
+        let report = SwitchReport {
+            target_branch: &target_branch,
+            projects: projects
+                .iter()
+                .zip(&outcomes)
+                .map(|((project_name, ws_project), result)| ProjectReport {
+                    project: project_name,
+                    outcome: result.outcome,
+                    branch: (result.outcome != SwitchOutcome::GitDisabled)
+                        .then(|| get_current_branch(&ws_project.dir).ok())
+                        .flatten(),
+                    stashed: result.stashed,
+                    task_failed: run
+                        .as_ref()
+                        .filter(|_| switched_projects.contains(*project_name))
+                        .map(|_| task_failures.contains(&project_name.to_string())),
+                })
+                .collect(),
+        };
+        write_report(&path, &report)?;
+        ui.info_item(&format!("Report written to {}", path.display()))?;
+    }
+
     Ok(())
 }
 
-fn switch_project_branch(
+/// The outcome of `de git switch` for each project, written with `--report`.
+#[derive(Debug, Serialize)]
+struct SwitchReport<'a> {
+    target_branch: &'a str,
+    projects: Vec<ProjectReport<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct ProjectReport<'a> {
+    project: &'a Slug,
+    outcome: SwitchOutcome,
+    /// The branch the project is on after the switch.
+    branch: Option<String>,
+    stashed: bool,
+    /// Whether the `--run` task failed, for projects it ran in.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    task_failed: Option<bool>,
+}
+
+fn write_report(path: &Path, report: &SwitchReport) -> Result<()> {
+    let json = serde_json::to_string_pretty(report)
+        .map_err(|e| eyre!(e))
+        .wrap_err("Failed to serialize the switch report")?;
+
+    std::fs::write(path, json + "\n")
+        .map_err(|e| eyre!(e))
+        .wrap_err_with(|| format!("Failed to write the switch report to {}", path.display()))
+}
+
+/// Runs the task in each switched project that defines it, in dependency order, so e.g.
+/// dependencies are reinstalled for the new branch. Returns the projects where it failed.
+fn run_post_switch_task(
+    ui: &UserInterface,
+    workspace: &Workspace,
+    task_name: &Slug,
+    switched_projects: &HashSet<Slug>,
+) -> Result<Vec<String>> {
+    ui.new_line()?;
+    ui.heading(&format!("Running task '{task_name}'"))?;
+
+    if switched_projects.is_empty() {
+        ui.info_item("No projects were switched. Skipping...")?;
+        return Ok(Vec::new());
+    }
+
+    let (dependency_graph, projects) = workspace
+        .load_dependency_graph()
+        .map_err(|e| eyre!(e))
+        .wrap_err("Failed to load dependency graph for workspace")?;
+
+    let startup_order = dependency_graph
+        .resolve_startup_order()
+        .wrap_err("Failed to resolve project order")?;
+
+    let projects_map = projects
+        .into_iter()
+        .map(|p| (p.manifest().project().name.clone(), p))
+        .collect::<HashMap<_, _>>();
+
+    let mut failed = Vec::new();
+
+    for project_id in startup_order {
+        let Some(project) = projects_map
+            .get(&project_id)
+            .filter(|_| switched_projects.contains(&project_id))
+        else {
+            continue;
+        };
+
+        ui.subheading(&format!("{project_id}:"))?;
+
+        match run_project_task(project, task_name, &[], None, &TaskContext::default()) {
+            Ok(true) => ui.indented(|ui| {
+                ui.success_item(&format!("Ran {task_name}"), None)?;
+                Ok(())
+            })?,
+            Ok(false) => ui.indented(|ui| {
+                ui.info_item(&ui.theme.dim("Task not defined. Skipping..."))?;
+                Ok(())
+            })?,
+            Err(e) => {
+                ui.indented(|ui| {
+                    ui.error_item(&format!("{e:#}"), None)?;
+                    Ok(())
+                })?;
+                failed.push(project_id.to_string());
+            }
+        }
+    }
+
+    Ok(failed)
+}
+
+/// How switching a single project went.
+#[derive(Debug)]
+struct SwitchResult {
+    outcome: SwitchOutcome,
+    /// Whether uncommitted changes were stashed before switching.
+    stashed: bool,
+}
+
+/// How switching a single project ended.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum SwitchOutcome {
+    /// The project was switched.
+    Done,
+    /// The project was left on its branch, with nothing to undo.
+    Skipped,
+    /// The project has `git.enabled = false`, so it was never looked at.
+    GitDisabled,
+    Failed,
+    /// Restoring the stashed changes conflicted and the project was left for the user to resolve.
+    Conflicted,
+}
+
+/// Fetches every git-enabled project so branches pushed by others can be found and switched to.
+/// A failed fetch is only a warning; the project can still switch to branches it already knows.
+fn fetch_projects(ui: &UserInterface, workspace: &Workspace) -> Result<()> {
+    ui.info_item("Fetching remotes...")?;
+
+    ui.indented(|ui| {
+        for (project_name, ws_project) in workspace.enabled_projects() {
+            // A project that fails to load is reported when it is switched
+            let Ok(project) = Project::from_dir(&ws_project.dir) else {
+                continue;
+            };
+
+            if !project.manifest().git.clone().unwrap_or_default().enabled {
+                continue;
+            }
+
+            let fetched = fetch_with_retry(&["--prune"], &ws_project.dir, true, |attempt, delay| {
+                let _ = ui.info_item(&format!(
+                    "{project_name}: fetch failed, retrying in {}s (attempt {attempt}/{FETCH_ATTEMPTS})...",
+                    delay.as_secs()
+                ));
+            });
+
+            match fetched {
+                Ok(()) => ui.success_item(&format!("{project_name}: fetched"), None)?,
+                Err(e) => ui.warning_item(
+                    &format!("{project_name}: {e}"),
+                    Some("Continuing with locally known branches"),
+                )?,
+            }
+        }
+        Ok(())
+    })?;
+
+    Ok(())
+}
+
+/// The branch a project would be switched to.
+#[derive(Debug, PartialEq, Eq)]
+enum BranchPlan {
+    Target,
+    /// The target branch doesn't exist in the project, but this fallback branch does.
+    Fallback(String),
+    /// Neither the target branch nor this fallback branch exist, so the project is skipped.
+    Missing {
+        fallback: String,
+    },
+}
+
+/// Decides which branch the project in `dir` would be switched to. Without a `fallback`, the
+/// default branch of the project's remote is used, or `main`.
+fn plan_branch(dir: &Path, target_branch: &str, fallback: Option<&str>) -> Result<BranchPlan> {
+    if branch_exists(target_branch, dir)? {
+        return Ok(BranchPlan::Target);
+    }
+
+    let fallback = match fallback {
+        Some(fallback) => fallback.to_string(),
+        None => get_default_branch(dir).unwrap_or_else(|_| "main".to_string()),
+    };
+
+    if branch_exists(&fallback, dir)? {
+        Ok(BranchPlan::Fallback(fallback))
+    } else {
+        Ok(BranchPlan::Missing { fallback })
+    }
+}
+
+/// Prints the branch each project would end up on, without checking anything out or
+/// stashing changes.
+fn print_switch_plan(
     ui: &UserInterface,
     workspace: &Workspace,
-    ws_project: &WorkspaceProject,
-    project_name: &Slug,
     target_branch: &str,
     fallback: Option<&str>,
-    on_dirty: &OnDirtyAction,
-) -> eyre::Result<bool> {
+) -> Result<()> {
+    ui.new_line()?;
+    ui.heading("Plan (dry run)")?;
+
+    for project_name in workspace.disabled_project_ids() {
+        ui.info_item(&ui.theme.dim(&format!("{project_name}: disabled, skipping")))?;
+    }
+
+    for (project_name, ws_project) in workspace.enabled_projects() {
+        let project = match Project::from_dir(&ws_project.dir) {
+            Ok(project) => project,
+            Err(e) => {
+                ui.error_item(
+                    &format!("{project_name}: failed to load project: {e}"),
+                    None,
+                )?;
+                continue;
+            }
+        };
+
+        if !project.manifest().git.clone().unwrap_or_default().enabled {
+            ui.info_item(
+                &ui.theme
+                    .dim(&format!("{project_name}: git not enabled, skipping")),
+            )?;
+            continue;
+        }
+
+        let dirty = if is_project_dirty(&ws_project.dir)? {
+            format!(" {}", ui.theme.warn("(uncommitted changes)"))
+        } else {
+            String::new()
+        };
+
+        let fallback = fallback.or_else(|| workspace.config().default_branch_for(ws_project));
+        match plan_branch(&ws_project.dir, target_branch, fallback)? {
+            BranchPlan::Target => {
+                ui.success_item(&format!("{project_name}: {target_branch}{dirty}"), None)?
+            }
+            BranchPlan::Fallback(fallback) => ui.warning_item(
+                &format!("{project_name}: {fallback} (fallback, target branch not found){dirty}"),
+                None,
+            )?,
+            BranchPlan::Missing { fallback } => ui.warning_item(
+                &format!(
+                    "{project_name}: stays on its branch, neither {target_branch} nor {fallback} exist"
+                ),
+                None,
+            )?,
+        }
+    }
+
+    Ok(())
+}
+
+/// What to switch each project to, and how to deal with uncommitted changes on the way.
+struct SwitchOptions<'a> {
+    target_branch: &'a str,
+    /// The fallback given on the command line, which wins over the configured default branches.
+    fallback: Option<&'a str>,
+    workspace: &'a WorkspaceConfig,
+    on_dirty: &'a OnDirtyAction,
+    /// The message of the stashes made for uncommitted changes.
+    stash_message: &'a str,
+    keep_stash_on_failure: bool,
+}
+
+/// Switches up to `jobs` projects at once. Each project's output is buffered and written out
+/// in one piece when it's done, so the output of different projects doesn't interleave.
+///
+/// Returns one result per project, in the order of `projects`.
+fn switch_projects_concurrently(
+    ui: &UserInterface,
+    projects: &[(&Slug, &WorkspaceProject)],
+    options: &SwitchOptions,
+    jobs: usize,
+) -> Result<Vec<SwitchResult>> {
+    // Written out one project at a time, so the output of two projects never interleaves
+    let flush = Mutex::new(());
+
+    parallel_map(projects, jobs, |(project_name, ws_project)| {
+        let output = ui.buffered();
+        let outcome = switch_project_branch(&output, ws_project, project_name, options);
+
+        let _flush = flush.lock().unwrap_or_else(|e| e.into_inner());
+        let _ = output.flush();
+        outcome
+    })
+    .into_iter()
+    .collect()
+}
+
+fn switch_project_branch(
+    ui: &UserInterface,
+    ws_project: &WorkspaceProject,
+    project_name: &Slug,
+    options: &SwitchOptions,
+) -> eyre::Result<SwitchResult> {
+    let SwitchOptions {
+        target_branch,
+        fallback,
+        workspace,
+        on_dirty,
+        stash_message,
+        keep_stash_on_failure,
+    } = *options;
+    let fallback = fallback.or_else(|| workspace.default_branch_for(ws_project));
+
     ui.subheading(&format!(
         "{project_name} {}",
         ui.theme.dim(&format!("({})", ws_project.dir.display()))
     ))?;
 
-    let project = Project::from_dir(&ws_project.dir)
-        .map_err(|e| eyre!(e))
-        .wrap_err_with(|| format!("Failed to load project '{project_name}'"))?;
+    let project = match Project::from_dir(&ws_project.dir) {
+        Ok(project) => project,
+        Err(e) => {
+            ui.indented(|ui| {
+                ui.error_item(&format!("Failed to load project: {e:#}"), None)?;
+                Ok(())
+            })?;
+            return Ok(SwitchResult {
+                outcome: SwitchOutcome::Failed,
+                stashed: false,
+            });
+        }
+    };
 
-    ui.indented(|ui| {
+    let mut stashed = false;
+    let outcome = ui.indented(|ui| {
         if !project.manifest().git.clone().unwrap_or_default().enabled {
             ui.info_item("Git is not enabled for this project. Skipping...")?;
-            return Ok(true);
+            return Ok(SwitchOutcome::GitDisabled);
         }
 
-        let dirty_result = handle_dirty_project(ui, &project, on_dirty)?;
+        let dirty_result = handle_dirty_project(ui, &project, on_dirty, stash_message)?;
+        stashed = matches!(dirty_result, DirtyResult::Stashed);
         match dirty_result {
             DirtyResult::Proceed | DirtyResult::Stashed => {}
             DirtyResult::Skip | DirtyResult::StashFailed => {
-                return Ok(true);
+                return Ok(SwitchOutcome::Skipped);
             }
         };
 
-        let fallback_branch = if let Some(fallback) = fallback {
-            fallback.to_string()
-        } else if let Some(default_branch) = workspace.config().default_branch.as_deref() {
-            default_branch.to_string()
-        } else {
-            get_default_branch(&ws_project.dir).unwrap_or_else(|_| "main".to_string())
-        };
-
-        let checkout_branch = if branch_exists(target_branch, &ws_project.dir)? {
-            ui.info_item("Target branch found.")?;
-            target_branch
-        } else if branch_exists(&fallback_branch, &ws_project.dir)? {
-            ui.warning_item(
-                &format!("Target branch not found. Falling back to '{fallback_branch}'."),
-                None,
-            )?;
-            fallback_branch.as_str()
-        } else {
-            ui.warning_item(
-                &format!(
-                    "Neither target branch nor fallback branch '{fallback_branch}' found. Aborting."
-                ),
-                None,
-            )?;
-            return Ok(true);
+        let plan = plan_branch(&ws_project.dir, target_branch, fallback)?;
+        let checkout_branch = match &plan {
+            BranchPlan::Target => {
+                ui.info_item("Target branch found.")?;
+                target_branch
+            }
+            BranchPlan::Fallback(fallback_branch) => {
+                ui.warning_item(
+                    &format!("Target branch not found. Falling back to '{fallback_branch}'."),
+                    None,
+                )?;
+                fallback_branch.as_str()
+            }
+            BranchPlan::Missing {
+                fallback: fallback_branch,
+            } => {
+                ui.warning_item(
+                    &format!(
+                        "Neither target branch nor fallback branch '{fallback_branch}' found. \
+                         Aborting."
+                    ),
+                    None,
+                )?;
+                if let DirtyResult::Stashed = dirty_result {
+                    report_stash_recovery(
+                        ui,
+                        recover_stash(&ws_project.dir, !keep_stash_on_failure),
+                    )?;
+                    return Ok(SwitchOutcome::Failed);
+                }
+                return Ok(SwitchOutcome::Skipped);
+            }
         };
 
         if let Err(e) = run_git_command(&["checkout", checkout_branch], &ws_project.dir) {
             ui.error_item(&format!("Failed to switch branch: {e}"), None)?;
-        } else {
-            ui.success_item("Switched to target branch.", None)?;
+            if let DirtyResult::Stashed = dirty_result {
+                report_stash_recovery(ui, recover_stash(&ws_project.dir, !keep_stash_on_failure))?;
+            }
+            return Ok(SwitchOutcome::Failed);
         }
 
+        ui.success_item("Switched to target branch.", None)?;
+
         // Restore stashed changes if it was stashed previously
         if let DirtyResult::Stashed = dirty_result {
             ui.info_item("Restoring stashed changes...")?;
-            if let Err(e) = run_git_command(&["stash", "pop"], &ws_project.dir) {
-                ui.error_item(&format!("Failed to restore stashed changes: {e}"), None)?;
-                return Ok(false);
-            } else {
-                ui.success_item("Stashed changes restored successfully.", None)?;
+            let popped = Command::new("git")
+                .arg("-C")
+                .arg(&ws_project.dir)
+                .arg("stash")
+                .arg("pop")
+                .logged_output()
+                .map_err(|e| eyre!(e))
+                .wrap_err("Failed to run git stash pop")?;
+
+            let unmerged_paths = get_unmerged_paths(&ws_project.dir)?;
+            if !unmerged_paths.is_empty() {
+                // A conflicting `git stash pop` keeps the stash entry, so nothing is lost.
+                ui.error_item(
+                    &format!(
+                        "{} while restoring stashed changes. Please resolve manually.",
+                        ui.theme.error("MERGE CONFLICT")
+                    ),
+                    Some("Your changes are still saved in stash@{0}"),
+                )?;
+                ui.indented(|ui| {
+                    for path in &unmerged_paths {
+                        ui.writeln(path)?;
+                    }
+                    Ok(())
+                })?;
+
+                if let OnDirtyAction::Prompt = on_dirty
+                    && is_interactive()
+                {
+                    return resolve_conflict_interactively(ui, &ws_project.dir, &unmerged_paths);
+                }
+                return Ok(SwitchOutcome::Conflicted);
             }
+
+            if !popped.status.success() {
+                ui.error_item(
+                    &format!(
+                        "Failed to restore stashed changes: {}",
+                        String::from_utf8_lossy(&popped.stderr).trim()
+                    ),
+                    Some("Your changes are still saved in stash@{0}"),
+                )?;
+                return Ok(SwitchOutcome::Failed);
+            }
+
+            ui.success_item("Stashed changes restored successfully.", None)?;
         }
 
-        if is_project_dirty(&ws_project.dir)? {
-            ui.error_item(
+        Ok(SwitchOutcome::Done)
+    })?;
+
+    Ok(SwitchResult { outcome, stashed })
+}
+
+/// Asks how to resolve a stash restore that conflicted with the new branch.
+fn resolve_conflict_interactively(
+    ui: &UserInterface,
+    dir: &Path,
+    unmerged_paths: &[String],
+) -> Result<SwitchOutcome> {
+    let selections = &[
+        "Undo restoring the changes (they stay in stash@{0})",
+        "Use the stashed version of the conflicted files",
+        "Leave the conflict and resolve it manually",
+    ];
+
+    let selection = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("How do you want to resolve the conflict?")
+        .default(0)
+        .items(&selections[..])
+        .interact()?;
+
+    match selection {
+        0 => {
+            undo_stash_restore(dir)?;
+            ui.warning_item(
                 &format!(
-                    "{} detected. Please resolve manually.",
-                    ui.theme.error("MERGE CONFLICT")
+                    "Restore undone. Your changes are still stashed in {}",
+                    ui.theme.accent("stash@{0}")
                 ),
-                None,
+                Some(&format!(
+                    "Run `git -C {} stash pop` to restore them later",
+                    dir.display()
+                )),
             )?;
-            Ok(false)
-        } else {
-            Ok(true)
+            Ok(SwitchOutcome::Done)
         }
-    })
+        1 => {
+            use_stashed_version(dir, unmerged_paths)?;
+            ui.success_item("Conflicts resolved with the stashed changes.", None)?;
+            Ok(SwitchOutcome::Done)
+        }
+        _ => Ok(SwitchOutcome::Conflicted),
+    }
+}
+
+/// Puts the working tree back to the checked out branch. The stash entry is kept, as a
+/// conflicting `git stash pop` doesn't drop it.
+fn undo_stash_restore(dir: &Path) -> Result<()> {
+    run_git_command(&["reset", "--merge"], dir)
+}
+
+/// Resolves the conflicted paths with their stashed contents, leaves all restored changes
+/// unstaged like a clean `git stash pop`, and drops the stash entry.
+fn use_stashed_version(dir: &Path, unmerged_paths: &[String]) -> Result<()> {
+    let mut checkout = vec!["checkout", "--theirs", "--"];
+    checkout.extend(unmerged_paths.iter().map(String::as_str));
+    run_git_command(&checkout, dir)?;
+    run_git_command(&["reset", "-q"], dir)?;
+    run_git_command(&["stash", "drop", "-q"], dir)
+}
+
+/// The ways to resolve a project left in conflict, with the exact command for each.
+fn conflict_commands(dir: &Path) -> Vec<(&'static str, String)> {
+    let git = format!("git -C {}", dir.display());
+    vec![
+        ("Undo the restore", format!("{git} reset --merge")),
+        (
+            "Or keep the stashed version",
+            format!("{git} checkout --theirs -- . && {git} reset -q && {git} stash drop"),
+        ),
+        (
+            "Or fix the files by hand, then",
+            format!("{git} reset -q && {git} stash drop"),
+        ),
+    ]
+}
+
+/// What happened to stashed changes after switching a project failed.
+#[derive(Debug, PartialEq, Eq)]
+enum StashRecovery {
+    /// The changes were popped back onto the original branch.
+    Restored,
+    /// The changes are still in the stash; the hint tells the user how to get them back.
+    Kept { hint: String },
+}
+
+/// Tries to undo the stash taken before a failed switch, so the user's changes don't stay hidden.
+fn recover_stash(dir: &Path, restore: bool) -> StashRecovery {
+    if restore && run_git_command(&["stash", "pop"], dir).is_ok() {
+        return StashRecovery::Restored;
+    }
+
+    StashRecovery::Kept {
+        hint: format!(
+            "Run `git -C {} stash pop` to restore stash@{{0}}",
+            dir.display()
+        ),
+    }
+}
+
+fn report_stash_recovery(ui: &UserInterface, recovery: StashRecovery) -> Result<()> {
+    match recovery {
+        StashRecovery::Restored => ui.success_item("Stashed changes restored.", None)?,
+        StashRecovery::Kept { hint } => ui.warning_item(
+            &format!(
+                "Your changes are still stashed in {}",
+                ui.theme.accent("stash@{0}")
+            ),
+            Some(&hint),
+        )?,
+    }
+    Ok(())
 }
 
 fn get_target_branch(workspace: &Workspace, query: Option<String>) -> Result<String> {
@@ -219,6 +820,14 @@ fn get_target_branch_from_query(workspace: &Workspace, query: String) -> Result<
     } else {
         let branch_names: Vec<_> = matches.iter().map(|b| b.name.clone()).collect();
 
+        if !is_interactive() {
+            return Err(eyre!(
+                "Multiple branches match '{}': {}. Re-run with the full branch name",
+                query,
+                branch_names.join(", ")
+            ));
+        }
+
         // Prompt user to select from matches
         let selection = Select::with_theme(&ColorfulTheme::default())
             .with_prompt("Multiple branches match your query. Please select one:")
@@ -237,15 +846,31 @@ pub struct Branch {
 }
 
 fn get_workspace_branches(workspace: &Workspace) -> Result<Vec<Branch>> {
-    let mut branches = HashSet::new();
-    for project in workspace.config().projects.values() {
-        let project_branches = get_project_branches(&project.dir)?;
-        for branch in project_branches {
-            branches.insert(branch);
+    let mut branches = Vec::new();
+    for (project_name, project) in workspace.enabled_projects() {
+        // A project whose branches can't be listed is reported when it is switched
+        match get_project_branches(&project.dir) {
+            Ok(project_branches) => branches.extend(project_branches),
+            Err(e) => tracing::warn!("Failed to list branches of {project_name}: {e:#}"),
         }
     }
 
-    let mut branches: Vec<_> = branches.into_iter().collect();
+    Ok(merge_branches(branches))
+}
+
+/// Keeps one entry per branch name, with the most recent commit date of any project, sorted
+/// from the most recently committed branch.
+fn merge_branches(branches: impl IntoIterator<Item = Branch>) -> Vec<Branch> {
+    let mut latest = HashMap::<String, Option<DateTime<Utc>>>::new();
+    for branch in branches {
+        let date = latest.entry(branch.name).or_default();
+        *date = (*date).max(branch.date);
+    }
+
+    let mut branches: Vec<_> = latest
+        .into_iter()
+        .map(|(name, date)| Branch { name, date })
+        .collect();
     branches.sort_by(|a, b| {
         // Sort by date if available, otherwise by name
         match (a.date, b.date) {
@@ -255,22 +880,19 @@ fn get_workspace_branches(workspace: &Workspace) -> Result<Vec<Branch>> {
             (None, None) => a.name.cmp(&b.name),
         }
     });
-    branches.dedup_by(|a, b| a.name == b.name);
 
-    Ok(branches)
+    branches
 }
 
 fn get_project_branches(dir: &Path) -> Result<Vec<Branch>, eyre::Error> {
-    use chrono::{DateTime, Utc};
-
     let output = Command::new("git")
         .current_dir(dir)
         .arg("for-each-ref")
         .arg("--sort=-committerdate")
         .arg("refs/heads/")
         .arg("refs/remotes/")
-        .arg("--format=%(committerdate:iso8601) %(refname:short)")
-        .output()?;
+        .arg("--format=%(committerdate:iso8601-strict) %(refname:short)")
+        .logged_output()?;
 
     if !output.status.success() {
         return Err(eyre::eyre!(
@@ -288,49 +910,72 @@ fn get_project_branches(dir: &Path) -> Result<Vec<Branch>, eyre::Error> {
             continue;
         }
 
-        // Split into date and branch name
-        if let Some((date_str, branch_name)) = line.split_at_checked(25) {
-            let branch_name = branch_name.trim();
-
-            // Remove "origin/HEAD" and similar symbolic refs
-            if branch_name.ends_with("HEAD") {
+        // A line that can't be parsed only loses that branch, not the whole listing
+        let (date, branch_name) = match parse_branch_line(line) {
+            Ok(parsed) => parsed,
+            Err(err) => {
+                tracing::warn!("Skipping branch in {}: {err}", dir.display());
                 continue;
             }
+        };
 
-            // Remove duplicate branches (local and remote with same name)
-            let branch_name = if let Some(idx) = branch_name.find('/') {
-                branch_name[idx + 1..].to_string()
-            } else {
-                branch_name.to_string()
-            };
+        // Remove "origin/HEAD" and similar symbolic refs
+        if branch_name.ends_with("HEAD") {
+            continue;
+        }
 
-            if seen.contains(&branch_name) {
-                continue;
-            }
+        // Remove duplicate branches (local and remote with same name)
+        let branch_name = if let Some(idx) = branch_name.find('/') {
+            branch_name[idx + 1..].to_string()
+        } else {
+            branch_name.to_string()
+        };
 
-            seen.insert(branch_name.clone());
+        if seen.contains(&branch_name) {
+            continue;
+        }
 
-            // Parse date using carbon, convert to UTC chrono::DateTime
-            let dt = DateTime::parse_from_str(date_str, "%Y-%m-%d %H:%M:%S %z")
-                .map_err(|e| eyre::eyre!("Failed to parse date '{}': {}", date_str, e))?
-                .with_timezone(&Utc);
+        seen.insert(branch_name.clone());
 
-            branches.push(Branch {
-                name: branch_name,
-                date: Some(dt),
-            });
-        }
+        branches.push(Branch {
+            name: branch_name,
+            date: Some(date),
+        });
     }
 
     Ok(branches)
 }
 
+/// Splits a `for-each-ref` line into the commit date and the ref name. The date is expected in
+/// git's `iso8601-strict` format, but the looser `iso8601` format is accepted too.
+fn parse_branch_line(line: &str) -> Result<(DateTime<Utc>, &str)> {
+    let parse_error = || eyre!("Failed to parse date in line '{}'", line);
+
+    let (date_str, rest) = line.split_once(' ').ok_or_else(parse_error)?;
+    if let Ok(date) = DateTime::parse_from_rfc3339(date_str) {
+        return Ok((date.with_timezone(&Utc), rest.trim()));
+    }
+
+    // `iso8601` separates the date, time and offset with spaces
+    let [time, offset, branch_name] = rest.splitn(3, ' ').collect::<Vec<_>>()[..] else {
+        return Err(parse_error());
+    };
+    let date = DateTime::parse_from_str(
+        &format!("{date_str} {time} {offset}"),
+        "%Y-%m-%d %H:%M:%S %z",
+    )
+    .map_err(|_| parse_error())?;
+
+    Ok((date.with_timezone(&Utc), branch_name.trim()))
+}
+
 fn get_dirty_projects(workspace: &Workspace) -> Result<Vec<String>> {
     let mut dirty_projects = Vec::new();
-    for (project_name, ws_project) in workspace.config().projects.iter() {
-        let project = Project::from_dir(&ws_project.dir)
-            .map_err(|e| eyre!(e))
-            .wrap_err_with(|| format!("Failed to load project '{project_name}'"))?;
+    for (project_name, ws_project) in workspace.enabled_projects() {
+        // A project that fails to load is reported when it is switched
+        let Ok(project) = Project::from_dir(&ws_project.dir) else {
+            continue;
+        };
 
         if !project.manifest().git.clone().unwrap_or_default().enabled {
             continue;
@@ -349,7 +994,7 @@ fn is_project_dirty(dir: &std::path::Path) -> Result<bool> {
         .arg(dir)
         .arg("status")
         .arg("--porcelain")
-        .output()?;
+        .logged_output()?;
     Ok(!output.stdout.is_empty())
 }
 
@@ -362,10 +1007,13 @@ fn handle_dirty_projects_preflight(
 
     ui.indented(|ui| {
         for project_name in dirty_projects {
-            ui.info_item(&project_name.to_string())?;
+            ui.info_item(project_name)?;
         }
 
         match on_dirty {
+            OnDirtyAction::Prompt if !is_interactive() => Err(eyre!(
+                "Uncommitted changes found. Re-run with --on-dirty stash, force or abort"
+            )),
             OnDirtyAction::Prompt => {
                 let selections = &[
                     "Prompt individually",
@@ -411,14 +1059,21 @@ fn handle_dirty_project(
     ui: &UserInterface,
     project: &Project,
     on_dirty: &OnDirtyAction,
+    stash_message: &str,
 ) -> eyre::Result<DirtyResult> {
     if !is_project_dirty(project.dir())? {
         return Ok(DirtyResult::Proceed);
     };
 
-    fn stash_changes(ui: &UserInterface, project: &Project) -> eyre::Result<DirtyResult> {
+    fn stash_changes(
+        ui: &UserInterface,
+        project: &Project,
+        stash_message: &str,
+    ) -> eyre::Result<DirtyResult> {
         ui.info_item("Stashing changes...")?;
-        if let Err(e) = run_git_command(&["stash", "push", "-u"], project.dir()) {
+        if let Err(e) =
+            run_git_command(&["stash", "push", "-u", "-m", stash_message], project.dir())
+        {
             ui.error_item(&format!("Failed to stash changes: {e}"), None)?;
             return Ok(DirtyResult::StashFailed);
         }
@@ -452,10 +1107,7 @@ fn handle_dirty_project(
                 .interact()?;
 
             match selection {
-                0 => {
-                    stash_changes(ui, project)?;
-                    Ok(DirtyResult::Proceed)
-                }
+                0 => stash_changes(ui, project, stash_message),
                 1 => {
                     force_checkout(ui, project)?;
                     Ok(DirtyResult::Proceed)
@@ -464,10 +1116,7 @@ fn handle_dirty_project(
                 _ => Err(eyre::eyre!("Operation aborted by user.")),
             }
         }
-        OnDirtyAction::Stash => {
-            stash_changes(ui, project)?;
-            Ok(DirtyResult::Proceed)
-        }
+        OnDirtyAction::Stash => stash_changes(ui, project, stash_message),
         OnDirtyAction::Force => {
             force_checkout(ui, project)?;
             Ok(DirtyResult::Proceed)
@@ -475,3 +1124,167 @@ fn handle_dirty_project(
         OnDirtyAction::Abort => Err(eyre::eyre!("Operation aborted by user.")),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["-c", "user.email=de@example.com", "-c", "user.name=de"])
+            .args(args)
+            .logged_output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {args:?} failed");
+    }
+
+    /// Creates a repository with a stashed change and a failed checkout, like a failed switch.
+    fn stashed_repo_after_failed_checkout() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        git(dir.path(), &["init", "-q"]);
+        std::fs::write(dir.path().join("file.txt"), "committed").unwrap();
+        git(dir.path(), &["add", "."]);
+        git(dir.path(), &["commit", "-q", "-m", "init"]);
+
+        std::fs::write(dir.path().join("file.txt"), "uncommitted").unwrap();
+        git(dir.path(), &["stash", "push", "-u"]);
+        assert!(run_git_command(&["checkout", "does-not-exist"], dir.path()).is_err());
+
+        dir
+    }
+
+    #[test]
+    fn test_failed_switch_keeps_stash_and_explains_recovery() {
+        let dir = stashed_repo_after_failed_checkout();
+
+        let StashRecovery::Kept { hint } = recover_stash(dir.path(), false) else {
+            panic!("stash should be kept");
+        };
+        assert!(hint.contains("stash pop"));
+        assert!(hint.contains("stash@{0}"));
+
+        let file = std::fs::read_to_string(dir.path().join("file.txt")).unwrap();
+        assert_eq!(file, "committed");
+    }
+
+    /// Creates a repository on a branch whose change conflicts with a popped stash.
+    fn repo_with_conflicting_stash_pop() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        git(dir.path(), &["init", "-q", "-b", "main"]);
+        std::fs::write(dir.path().join("file.txt"), "committed").unwrap();
+        git(dir.path(), &["add", "."]);
+        git(dir.path(), &["commit", "-q", "-m", "init"]);
+
+        git(dir.path(), &["checkout", "-q", "-b", "feature"]);
+        std::fs::write(dir.path().join("file.txt"), "feature").unwrap();
+        git(dir.path(), &["commit", "-q", "-am", "feature"]);
+        git(dir.path(), &["checkout", "-q", "main"]);
+
+        std::fs::write(dir.path().join("file.txt"), "stashed").unwrap();
+        git(dir.path(), &["stash", "push", "-u"]);
+        git(dir.path(), &["checkout", "-q", "feature"]);
+        assert!(run_git_command(&["stash", "pop"], dir.path()).is_err());
+        assert_eq!(get_unmerged_paths(dir.path()).unwrap(), ["file.txt"]);
+
+        dir
+    }
+
+    #[test]
+    fn test_conflict_resolution_undo_and_use_stashed() {
+        let dir = repo_with_conflicting_stash_pop();
+        undo_stash_restore(dir.path()).unwrap();
+        assert!(get_unmerged_paths(dir.path()).unwrap().is_empty());
+        let file = std::fs::read_to_string(dir.path().join("file.txt")).unwrap();
+        assert_eq!(file, "feature");
+        assert!(run_git_command(&["rev-parse", "-q", "--verify", "stash@{0}"], dir.path()).is_ok());
+
+        let dir = repo_with_conflicting_stash_pop();
+        use_stashed_version(dir.path(), &["file.txt".to_string()]).unwrap();
+        assert!(get_unmerged_paths(dir.path()).unwrap().is_empty());
+        let file = std::fs::read_to_string(dir.path().join("file.txt")).unwrap();
+        assert_eq!(file, "stashed");
+        assert!(
+            run_git_command(&["rev-parse", "-q", "--verify", "stash@{0}"], dir.path()).is_err()
+        );
+    }
+
+    #[test]
+    fn test_merge_branches_keeps_latest_date_per_name() {
+        let date = |s: &str| Some(DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc));
+        let branch = |name: &str, d: &str| Branch {
+            name: name.to_string(),
+            date: date(d),
+        };
+
+        let api = [
+            branch("main", "2024-01-01T00:00:00Z"),
+            branch("feature", "2024-03-01T00:00:00Z"),
+        ];
+        let web = [
+            branch("main", "2024-04-01T00:00:00Z"),
+            branch("feature", "2024-02-01T00:00:00Z"),
+        ];
+
+        assert_eq!(
+            merge_branches(api.into_iter().chain(web)),
+            [
+                branch("main", "2024-04-01T00:00:00Z"),
+                branch("feature", "2024-03-01T00:00:00Z"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_branch_line_accepts_both_iso_formats() {
+        let (date, name) = parse_branch_line("2024-03-01T12:30:00+05:30 feature/login").unwrap();
+        assert_eq!(date.to_rfc3339(), "2024-03-01T07:00:00+00:00");
+        assert_eq!(name, "feature/login");
+
+        let (date, name) = parse_branch_line("2024-03-01 12:30:00 -0800 origin/main").unwrap();
+        assert_eq!(date.to_rfc3339(), "2024-03-01T20:30:00+00:00");
+        assert_eq!(name, "origin/main");
+
+        assert!(parse_branch_line("yesterday main").is_err());
+        assert!(parse_branch_line("main").is_err());
+    }
+
+    #[test]
+    fn test_failed_switch_restores_stash_when_requested() {
+        let dir = stashed_repo_after_failed_checkout();
+
+        assert_eq!(recover_stash(dir.path(), true), StashRecovery::Restored);
+
+        let file = std::fs::read_to_string(dir.path().join("file.txt")).unwrap();
+        assert_eq!(file, "uncommitted");
+    }
+
+    #[test]
+    fn test_switch_report_serializes_outcomes() {
+        use std::str::FromStr;
+
+        let api = Slug::from_str("api").unwrap();
+        let report = SwitchReport {
+            target_branch: "feature/login",
+            projects: vec![ProjectReport {
+                project: &api,
+                outcome: SwitchOutcome::GitDisabled,
+                branch: None,
+                stashed: false,
+                task_failed: None,
+            }],
+        };
+
+        assert_eq!(
+            serde_json::to_value(&report).unwrap(),
+            serde_json::json!({
+                "target_branch": "feature/login",
+                "projects": [
+                    {"project": "api", "outcome": "git_disabled", "branch": null, "stashed": false}
+                ]
+            })
+        );
+    }
+}
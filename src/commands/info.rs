@@ -0,0 +1,89 @@
+use std::path::Path;
+
+use crate::{
+    commands::{
+        doctor::{check_docker, check_docker_compose},
+        status::{StatusFilter, workspace_status},
+    },
+    config::Config,
+    types::Slug,
+    utils::{
+        shim::{get_installed_shims, get_shims_dir},
+        ui::UserInterface,
+    },
+    workspace::Workspace,
+};
+
+/// Shows an overview of the de environment: version, config, Docker, shims and the active
+/// workspace with the status of its projects.
+pub fn info(workspace_name: Option<Slug>, cwd: &Path) -> eyre::Result<()> {
+    let ui = UserInterface::new();
+
+    ui.heading("Environment")?;
+    ui.info_item(&format!(
+        "de version: {}",
+        ui.theme.accent(env!("CARGO_PKG_VERSION"))
+    ))?;
+
+    match Config::config_path() {
+        Ok(path) if path.exists() => {
+            ui.info_item(&format!("Config file: {}", path.display()))?;
+        }
+        Ok(path) => ui.info_item(&format!(
+            "Config file: {} {}",
+            path.display(),
+            ui.theme.dim("(not created yet)")
+        ))?,
+        Err(err) => ui.error_item(&format!("Config file: {err}"), None)?,
+    }
+
+    match check_docker() {
+        Ok(version) => ui.success_item(&format!("Docker: {version}"), None)?,
+        Err(err) => ui.error_item(
+            &format!("Docker: {err}"),
+            Some("Run `de doctor` for details"),
+        )?,
+    }
+
+    match check_docker_compose() {
+        Ok(version) => ui.success_item(&format!("Docker Compose: {version}"), None)?,
+        Err(err) => ui.error_item(&format!("Docker Compose: {err}"), None)?,
+    }
+
+    ui.new_line()?;
+    ui.heading("Shims")?;
+    match get_installed_shims() {
+        Ok(shims) if shims.is_empty() => ui.info_item("No shims installed")?,
+        Ok(shims) => {
+            if let Ok(dir) = get_shims_dir() {
+                ui.info_item(&format!("Directory: {}", dir.display()))?;
+            }
+            ui.info_item(&format!("Installed: {}", shims.join(", ")))?;
+        }
+        Err(err) => ui.error_item(&format!("Failed to list shims: {err}"), None)?,
+    }
+
+    ui.new_line()?;
+    match Workspace::named_or_active(workspace_name.as_ref(), cwd) {
+        Ok(Some(workspace)) => {
+            // The status has its own headings and reports per-project problems itself
+            if let Err(err) = workspace_status(&ui, &workspace, StatusFilter::default(), false, cwd)
+            {
+                ui.error_item(&format!("Failed to get workspace status: {err}"), None)?;
+            }
+        }
+        Ok(None) => {
+            ui.heading("Workspace")?;
+            ui.warning_item(
+                "No active workspace",
+                Some("Run `de start` in a project or set DE_WORKSPACE"),
+            )?;
+        }
+        Err(err) => {
+            ui.heading("Workspace")?;
+            ui.error_item(&format!("Failed to get active workspace: {err:#}"), None)?;
+        }
+    }
+
+    Ok(())
+}
@@ -1,25 +1,76 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use crate::{
+    cli::ListSort,
     project::Project,
     types::Slug,
-    utils::{theme::Theme, ui::UserInterface},
-    workspace::Workspace,
+    utils::{is_interactive, table::Table, ui::UserInterface},
+    workspace::{DependencyGraph, DependencyGraphError, Workspace},
 };
 use console::style;
+use dialoguer::Confirm;
 use eyre::{Context, eyre};
 
-pub fn list(workspace: Workspace) -> eyre::Result<()> {
-    let ui = UserInterface::new();
-    let theme = Theme::new();
+pub fn list(
+    workspace_name: Option<Slug>,
+    sort: ListSort,
+    porcelain: bool,
+    cwd: &Path,
+) -> eyre::Result<()> {
+    let workspace = require_workspace(workspace_name, cwd)?;
+
+    // Keep stdout to the porcelain lines, so warnings go to stderr
+    let ui = if porcelain {
+        UserInterface::new().stderr()
+    } else {
+        UserInterface::new()
+    };
     let name = &workspace.config().name;
 
     if workspace.config().projects.is_empty() {
-        ui.warning_item(&format!("No projects found in workspace '{name}'"), None)?;
+        if !porcelain {
+            ui.warning_item(&format!("No projects found in workspace '{name}'"), None)?;
+        }
+        return Ok(());
+    }
+
+    let mut projects_to_display = gather_projects(&workspace, cwd)?;
+
+    if let Err(err) = sort_projects(&mut projects_to_display, sort) {
+        ui.warning_item(
+            &format!("Can't sort by dependencies: {err}"),
+            Some("Falling back to sorting by name"),
+        )?;
+    }
+
+    if porcelain {
+        for project in &projects_to_display {
+            println!("{}", porcelain_line(project));
+        }
         return Ok(());
     }
 
-    let current_project = Project::current()
+    ui.heading(&format!("Projects in workspace {name}:"))?;
+    let mut table = Table::new();
+    for project in &projects_to_display {
+        table.add_row(project_row(project, &ui));
+    }
+    table.print(&ui)?;
+
+    Ok(())
+}
+
+/// The named workspace, or the active one when no name is given.
+fn require_workspace(workspace_name: Option<Slug>, cwd: &Path) -> eyre::Result<Workspace> {
+    match workspace_name {
+        Some(workspace_name) => Workspace::require(&workspace_name, cwd),
+        None => Workspace::require_active(cwd),
+    }
+}
+
+/// Loads what `de list` shows about each project of the workspace.
+fn gather_projects(workspace: &Workspace, cwd: &Path) -> eyre::Result<Vec<ProjectDisplay>> {
+    let current_project = Project::from_dir_recursive(cwd)
         .map_err(|e| eyre!(e))
         .wrap_err("Failed to get current project")?;
 
@@ -29,13 +80,21 @@ pub fn list(workspace: Workspace) -> eyre::Result<()> {
         let project_dir = &wp.dir;
         let present = project_dir.exists();
 
-        let project_name = if present {
+        let (project_name, depends_on) = if present {
             match Project::from_dir(project_dir) {
-                Ok(project) => project.manifest().project().name.clone(),
-                Err(_) => id.clone(), // Fallback if project manifest can't be loaded
+                Ok(project) => (
+                    project.manifest().project().name.clone(),
+                    project
+                        .manifest()
+                        .project()
+                        .depends_on
+                        .clone()
+                        .unwrap_or_default(),
+                ),
+                Err(_) => (id.clone(), Vec::new()), // Fallback if project manifest can't be loaded
             }
         } else {
-            id.clone()
+            (id.clone(), Vec::new())
         };
 
         let current = if let Some(cp) = &current_project {
@@ -50,16 +109,78 @@ pub fn list(workspace: Workspace) -> eyre::Result<()> {
             dir: project_dir.clone(),
             present,
             current,
+            depends_on,
         });
     }
 
-    projects_to_display.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(projects_to_display)
+}
 
-    ui.heading(&format!("Projects in workspace {name}:"))?;
-    for project in &projects_to_display {
-        print_project_display(project, &ui, &theme)?;
+/// Prints only the projects whose directory is missing, optionally removing them from the workspace.
+pub fn list_missing(
+    workspace_name: Option<Slug>,
+    prune: bool,
+    yes: bool,
+    cwd: &Path,
+) -> eyre::Result<()> {
+    let mut workspace = require_workspace(workspace_name, cwd)?;
+
+    let missing: Vec<(Slug, PathBuf)> = workspace
+        .config()
+        .projects
+        .iter()
+        .filter(|(_, wp)| !wp.dir.exists())
+        .map(|(id, wp)| (id.clone(), wp.dir.clone()))
+        .collect();
+
+    for (id, dir) in &missing {
+        println!("{id}\t{}", dir.display());
+    }
+
+    if !prune || missing.is_empty() {
+        return Ok(());
+    }
+
+    if !yes {
+        if !is_interactive() {
+            return Err(eyre!(
+                "Re-run with --yes to remove {} missing project(s)",
+                missing.len()
+            ));
+        }
+
+        let confirmed = Confirm::new()
+            .with_prompt(format!(
+                "Remove {} missing project(s) from workspace '{}'?",
+                missing.len(),
+                workspace.config().name
+            ))
+            .default(false)
+            .interact()
+            .map_err(|e| eyre!(e))
+            .wrap_err("Failed to prompt for confirmation")?;
+
+        if !confirmed {
+            eprintln!("Aborting prune operation.");
+            return Ok(());
+        }
     }
 
+    workspace
+        .edit(|workspace| {
+            for (id, _) in &missing {
+                workspace.remove_project(id);
+            }
+            Ok(())
+        })
+        .wrap_err("Failed to save workspace configuration")?;
+
+    eprintln!(
+        "Removed {} missing project(s) from workspace '{}'.",
+        missing.len(),
+        workspace.config().name
+    );
+
     Ok(())
 }
 
@@ -69,30 +190,122 @@ struct ProjectDisplay {
     dir: PathBuf,
     present: bool,
     current: bool,
+    depends_on: Vec<Slug>,
 }
 
-fn print_project_display(
-    project: &ProjectDisplay,
-    ui: &UserInterface,
-    theme: &Theme,
-) -> eyre::Result<()> {
+/// Orders projects by `sort`. The projects are left sorted by ID if the dependencies can't be
+/// resolved.
+fn sort_projects(
+    projects: &mut [ProjectDisplay],
+    sort: ListSort,
+) -> Result<(), DependencyGraphError> {
+    projects.sort_by(|a, b| a.id.cmp(&b.id));
+
+    match sort {
+        ListSort::Name => {}
+        ListSort::Status => projects.sort_by_key(|project| !project.present),
+        ListSort::Deps => {
+            let mut graph = DependencyGraph::new();
+            for project in projects.iter() {
+                graph.add_project(project.id.clone(), project.depends_on.clone());
+            }
+
+            let startup_order = graph.resolve_startup_order()?;
+            projects.sort_by_key(|project| startup_order.iter().position(|id| id == &project.id));
+        }
+    }
+
+    Ok(())
+}
+
+/// A project's line in `de list --porcelain`: its ID, directory and whether the directory
+/// exists, separated by tabs.
+fn porcelain_line(project: &ProjectDisplay) -> String {
+    format!(
+        "{}\t{}\t{}",
+        project.id,
+        project.dir.display(),
+        project.present
+    )
+}
+
+/// The name and directory columns for a project in `de list`.
+fn project_row(project: &ProjectDisplay, ui: &UserInterface) -> [String; 2] {
     let current_indicator = if project.current {
-        format!(" {}", style("(current)").fg(theme.accent_color))
+        format!(" {}", style("(current)").fg(ui.theme.accent_color))
     } else {
         "".to_string()
     };
 
     if project.present {
-        ui.success_item(&format!("{}{}", project.name, current_indicator), None)?;
+        [
+            format!(
+                "{} {}{}",
+                ui.symbols.success, project.name, current_indicator
+            ),
+            ui.theme.dim(&project.dir.display().to_string()),
+        ]
     } else {
-        ui.error_item(
-            project.name.as_str(),
-            Some(&format!(
+        [
+            format!("{} {}", ui.symbols.error, project.name),
+            ui.theme.error(&format!(
                 "Project directory '{}' does not exist",
                 project.dir.display()
             )),
-        )?;
+        ]
     }
+}
 
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn project(id: &str, present: bool, depends_on: &[&str]) -> ProjectDisplay {
+        let id = Slug::from_str(id).unwrap();
+        ProjectDisplay {
+            name: id.clone(),
+            id,
+            dir: PathBuf::new(),
+            present,
+            current: false,
+            depends_on: depends_on
+                .iter()
+                .map(|dep| Slug::from_str(dep).unwrap())
+                .collect(),
+        }
+    }
+
+    fn ids(projects: &[ProjectDisplay]) -> Vec<&str> {
+        projects.iter().map(|project| project.id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_sort_projects() {
+        let mut projects = vec![
+            project("web", true, &["api"]),
+            project("api", false, &["db"]),
+            project("db", true, &[]),
+        ];
+
+        sort_projects(&mut projects, ListSort::Deps).unwrap();
+        assert_eq!(ids(&projects), ["db", "api", "web"]);
+
+        sort_projects(&mut projects, ListSort::Status).unwrap();
+        assert_eq!(ids(&projects), ["db", "web", "api"]);
+
+        projects.push(project("cron", true, &["web"]));
+        projects[0].depends_on.push(Slug::from_str("cron").unwrap());
+        assert!(sort_projects(&mut projects, ListSort::Deps).is_err());
+        assert_eq!(ids(&projects), ["api", "cron", "db", "web"]);
+    }
+
+    #[test]
+    fn test_porcelain_line() {
+        let mut api = project("api", false, &[]);
+        api.dir = PathBuf::from("/src/api");
+        api.current = true;
+
+        assert_eq!(porcelain_line(&api), "api\t/src/api\tfalse");
+    }
 }
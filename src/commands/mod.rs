@@ -1,35 +1,47 @@
+mod clean;
 mod config;
 mod doctor;
+mod env;
 mod exec;
 mod exec_all;
 mod fallthrough;
 pub mod git;
+mod info;
 mod init;
 mod list;
+mod prune;
 mod run;
 mod scan;
+mod schema;
 pub mod self_;
 pub mod setup;
 mod start;
 mod status;
 mod stop;
 mod update;
+mod validate;
 pub mod workspace;
 
 pub mod shim;
 pub mod task;
 
+pub use clean::clean;
 pub use config::config;
 pub use doctor::doctor;
-pub use exec::exec;
+pub use env::env;
+pub use exec::{ServiceExec, exec};
 pub use exec_all::exec_all;
 pub use fallthrough::fallthrough;
+pub use info::info;
 pub use init::init;
-pub use list::list;
-pub use run::run;
+pub use list::{list, list_missing};
+pub use prune::prune;
+pub use run::{run, run_watch};
 pub use scan::scan;
+pub use schema::schema;
 pub use setup::setup;
 pub use start::start;
-pub use status::status;
+pub use status::{StatusFilter, status};
 pub use stop::stop;
 pub use update::update;
+pub use validate::validate;
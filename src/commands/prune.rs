@@ -0,0 +1,188 @@
+use crate::{
+    error::DeError,
+    project::{DownOptions, Project, compose::compose_project_name, config::ProjectHook},
+    types::Slug,
+    utils::{is_interactive, process::CommandExt, ui::UserInterface},
+    workspace::{Workspace, run_project_hooks},
+};
+use dialoguer::Confirm;
+use eyre::{Context, eyre};
+use std::{path::Path, process::Command};
+
+/// Label Docker Compose puts on containers, networks and built images with the project name.
+const COMPOSE_PROJECT_LABEL: &str = "com.docker.compose.project";
+
+pub fn prune(
+    workspace_name: Option<Slug>,
+    volumes: bool,
+    images: bool,
+    yes: bool,
+    cwd: &Path,
+) -> eyre::Result<()> {
+    if !volumes && !images {
+        return Err(eyre!(
+            "Nothing to prune. Pass --volumes, --images or both (use `de stop` to only stop services)"
+        ));
+    }
+
+    let workspace = if let Some(workspace_name) = workspace_name {
+        Workspace::require(&workspace_name, cwd)?
+    } else {
+        Workspace::require_active(cwd)?
+    };
+
+    let (dependency_graph, projects) = workspace
+        .load_dependency_graph()
+        .map_err(|e| eyre!(e))
+        .wrap_err("Failed to load dependency graph for workspace")?;
+
+    let shutdown_order = dependency_graph
+        .resolve_shutdown_order()
+        .wrap_err("Failed to resolve project shutdown order")?;
+
+    let mut projects = projects
+        .into_iter()
+        .filter_map(|project| match project.docker_compose_path() {
+            Ok(Some(path)) => Some((project, path)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    projects.sort_by_key(|(project, _)| {
+        shutdown_order
+            .iter()
+            .position(|id| id == &project.manifest().project().name)
+    });
+
+    let ui = UserInterface::new();
+
+    if projects.is_empty() {
+        ui.info_item(&format!(
+            "No projects with a Docker Compose file in workspace {}",
+            workspace.config().name
+        ))?;
+        return Ok(());
+    }
+
+    let scope = match (volumes, images) {
+        (true, true) => "containers, networks, volumes and images",
+        (true, false) => "containers, networks and volumes",
+        (false, _) => "containers, networks and images",
+    };
+
+    ui.heading(&format!("Prune {}", workspace.config().name))?;
+    ui.warning_item(&format!("This removes the {scope} of:"), None)?;
+    ui.indented(|ui| {
+        for (project, _) in &projects {
+            ui.writeln(project.manifest().project().name.as_str())?;
+        }
+        Ok(())
+    })?;
+    ui.new_line()?;
+
+    if !yes {
+        if !is_interactive() {
+            return Err(eyre!("Re-run with --yes to prune without confirmation"));
+        }
+
+        let confirmed = Confirm::new()
+            .with_prompt("This cannot be undone. Continue?")
+            .default(false)
+            .interact()
+            .map_err(|e| eyre!(e))
+            .wrap_err("Failed to prompt for confirmation")?;
+
+        if !confirmed {
+            println!("Aborting prune operation.");
+            return Ok(());
+        }
+    }
+
+    let mut results = Vec::new();
+    for (project, compose_path) in &projects {
+        let name = project.manifest().project().name.clone();
+        ui.subheading(name.as_str())?;
+        let result = prune_project(&ui, project, compose_path, volumes, images);
+        ui.new_line()?;
+        results.push((name, result));
+    }
+
+    ui.heading("Summary")?;
+
+    let mut failed = 0;
+    for (name, result) in results {
+        match result {
+            Ok(Some(reclaimed)) => {
+                ui.success_item(&format!("Pruned {name}"), Some(&reclaimed))?;
+            }
+            Ok(None) => ui.success_item(&format!("Pruned {name}"), None)?,
+            Err(err) => {
+                failed += 1;
+                ui.error_item(&format!("Failed to prune {name}: {err:#}"), None)?;
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(eyre!("Failed to prune {} projects", failed));
+    }
+
+    Ok(())
+}
+
+/// Takes down the project and removes the requested resources. Returns the space Docker reports
+/// as reclaimed by the image prune, if any.
+fn prune_project(
+    ui: &UserInterface,
+    project: &Project,
+    compose_path: &Path,
+    volumes: bool,
+    images: bool,
+) -> eyre::Result<Option<String>> {
+    // Like `de stop`, a failing before_stop hook doesn't block taking the project down.
+    if let Err(err) = run_project_hooks(project, ProjectHook::BeforeStop) {
+        ui.error_item(&format!("{err:#}"), None)?;
+    }
+
+    project.docker_compose_down(DownOptions {
+        timeout: None,
+        volumes,
+        images,
+    })?;
+
+    if !images {
+        return Ok(None);
+    }
+
+    let compose_project = compose_project_name(compose_path, project.env()).ok_or_else(|| {
+        eyre!(
+            "Couldn't tell the Docker Compose project name of {}, so its images were not pruned",
+            compose_path.display()
+        )
+    })?;
+    let output = Command::new("docker")
+        .arg("image")
+        .arg("prune")
+        .arg("-f")
+        .arg("--filter")
+        .arg(format!("label={COMPOSE_PROJECT_LABEL}={compose_project}"))
+        .logged_output()
+        .map_err(|e| eyre!(DeError::DockerUnavailable(e)))
+        .wrap_err("Failed to prune images")?;
+
+    if !output.status.success() {
+        return Err(eyre!(
+            "docker image prune failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(reclaimed_space(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Extracts the size from the `Total reclaimed space: 1.2GB` line of `docker image prune`.
+fn reclaimed_space(output: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        line.strip_prefix("Total reclaimed space:")
+            .map(|size| format!("Reclaimed {}", size.trim()))
+    })
+}
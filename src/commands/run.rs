@@ -1,63 +1,99 @@
 use eyre::{Context, eyre};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::File,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
 
-use crate::{project::Project, types::Slug, workspace::Workspace};
+use crate::{
+    error::DeError,
+    project::{Project, TaskContext, project_task_command, run_project_task, task_prerequisites},
+    types::{ProjectArg, Slug},
+    utils::{
+        process::{interrupted, print_dry_run, run_interruptible},
+        ui::UserInterface,
+        watch::{PathFilter, Watcher},
+    },
+    workspace::Workspace,
+};
 
+/// The `--project` value that dispatches a task to every project in the workspace.
+const ALL_PROJECTS: &str = "all";
+
+/// The task `de run` runs when none is given and no `default_task` is configured.
+const DEFAULT_TASK: &str = "default";
+
+#[allow(clippy::too_many_arguments)]
 pub fn run(
-    task_name: Slug,
+    task_name: Option<Slug>,
     args: Vec<String>,
-    project_name: Option<Slug>,
+    project_arg: Option<ProjectArg>,
     workspace_name: Option<Slug>,
+    timeout: Option<Duration>,
+    dry_run: bool,
+    context: TaskContext,
+    cwd: &Path,
 ) -> eyre::Result<()> {
     let workspace = match workspace_name.as_ref() {
-        Some(workspace_name) => Workspace::load_from_name(workspace_name)
+        Some(workspace_name) => Workspace::load_from_name(workspace_name, cwd)
             .map_err(|e| eyre!(e))
             .wrap_err("Failed to load workspace")?,
-        None => Workspace::active()
+        None => Workspace::active(cwd)
             .map_err(|e| eyre!(e))
             .wrap_err("Failed to get active workspace")?,
     };
 
-    if let Some(project_name) = project_name {
-        let workspace = workspace.as_ref().ok_or_else(|| {
-            if let Some(workspace_name) = workspace_name.as_ref() {
-                eyre!("Workspace '{}' not found", workspace_name)
-            } else {
-                eyre!("No active workspace found")
-            }
-        })?;
-
-        // If a project is specified, check if it exists in the workspace
-        let ws_project = workspace
-            .config()
-            .projects
-            .get(&project_name)
-            .ok_or_else(|| {
-                eyre!(
-                    "Project '{}' not found in workspace '{}'",
-                    project_name,
-                    workspace.config().name
-                )
-            })?;
-
-        let project = Project::from_dir(&ws_project.dir)
+    let task_name = match task_name {
+        Some(task_name) => task_name,
+        None => resolve_default_task(
+            project_arg.as_ref(),
+            workspace.as_ref(),
+            workspace_name.as_ref(),
+            cwd,
+        )?,
+    };
+
+    // Start with an empty file; each project task run appends to it
+    if let Some(output) = context.output.as_ref().filter(|_| !dry_run) {
+        File::create(&output.path)
             .map_err(|e| eyre!(e))
-            .wrap_err("Failed to load project from directory")?;
+            .wrap_err_with(|| format!("Failed to create {}", output.path.display()))?;
+    }
 
-        if !run_project_task(&project, &task_name, &args)? {
-            return Err(eyre!(
-                "Task '{}' not found in project '{}'",
-                task_name,
-                project.manifest().project().name
-            ));
+    if let Some(project_arg) = project_arg {
+        // `--project all` fans the task out, unless a project is literally named "all"
+        if let ProjectArg::Id(id) = &project_arg
+            && id.as_str() == ALL_PROJECTS
+        {
+            let workspace = require_workspace(workspace.as_ref(), workspace_name.as_ref())?;
+            if workspace.project(id).is_none() {
+                return run_all_projects_task(
+                    workspace, &task_name, &args, timeout, dry_run, &context,
+                );
+            }
         }
+
+        let project = load_project_arg(&project_arg, workspace.as_ref(), workspace_name.as_ref())?;
+
+        if !run_or_print_project_task(&project, &task_name, &args, timeout, dry_run, &context)? {
+            return Err(eyre!(DeError::TaskNotFound {
+                task: task_name,
+                location: format!("project '{}'", project.manifest().project().name),
+            }));
+        }
+        return Ok(());
     } else if let Some(workspace_name) = workspace_name.as_ref() {
         // If a workspace is specified, check if the current project is part of that workspace
-        if let Some(project) = Project::current()
+        if let Some(project) = Project::from_dir_recursive(cwd)
             .map_err(|e| eyre!(e))
             .wrap_err("Failed to get current project")?
         {
             if &project.manifest().project().workspace == workspace_name {
-                if run_project_task(&project, &task_name, &args)? {
+                if run_or_print_project_task(
+                    &project, &task_name, &args, timeout, dry_run, &context,
+                )? {
                     return Ok(());
                 }
             } else {
@@ -70,10 +106,10 @@ pub fn run(
         }
     } else {
         // If no project specified, try to run the task in the current project
-        if let Some(project) = Project::current()
+        if let Some(project) = Project::from_dir_recursive(cwd)
             .map_err(|e| eyre!(e))
             .wrap_err("Failed to get current project")?
-            && run_project_task(&project, &task_name, &args)?
+            && run_or_print_project_task(&project, &task_name, &args, timeout, dry_run, &context)?
         {
             return Ok(());
         }
@@ -83,47 +119,434 @@ pub fn run(
     if let Some(workspace) = workspace
         && workspace.config().tasks.contains_key(&task_name)
     {
-        println!("Running workspace task '{task_name}'...");
-        return super::workspace::run(None, task_name, args);
+        if context.cwd.is_some() {
+            return Err(eyre!(
+                "--cwd only applies to project tasks, but '{}' is a workspace task",
+                task_name
+            ));
+        }
+
+        if context.output.is_some() {
+            return Err(eyre!(
+                "--tee and --capture only apply to project tasks, but '{}' is a workspace task",
+                task_name
+            ));
+        }
+
+        if !dry_run {
+            println!("Running workspace task '{task_name}'...");
+        }
+        return super::workspace::run(None, task_name, args, timeout, dry_run, &context.env, cwd);
+    }
+
+    Err(eyre!(DeError::TaskNotFound {
+        task: task_name,
+        location: "project or active workspace".to_string(),
+    }))
+}
+
+/// Runs a project task, then reruns it whenever watched files in the project change.
+/// Ctrl-C stops the current run and exits the loop.
+pub fn run_watch(
+    task_name: Option<Slug>,
+    args: Vec<String>,
+    project_arg: Option<ProjectArg>,
+    workspace_name: Option<Slug>,
+    context: TaskContext,
+    watch_paths: Vec<String>,
+    cwd: &Path,
+) -> eyre::Result<()> {
+    let project = if let Some(project_arg) = project_arg {
+        if matches!(&project_arg, ProjectArg::Id(id) if id.as_str() == ALL_PROJECTS) {
+            return Err(eyre!("--watch runs a task in a single project"));
+        }
+
+        let workspace = match workspace_name.as_ref() {
+            Some(workspace_name) => Workspace::load_from_name(workspace_name, cwd)
+                .map_err(|e| eyre!(e))
+                .wrap_err("Failed to load workspace")?,
+            None => Workspace::active(cwd)
+                .map_err(|e| eyre!(e))
+                .wrap_err("Failed to get active workspace")?,
+        };
+
+        load_project_arg(&project_arg, workspace.as_ref(), workspace_name.as_ref())?
+    } else {
+        Project::from_dir_recursive(cwd)
+            .map_err(|e| eyre!(e))
+            .wrap_err("Failed to get current project")?
+            .ok_or_else(|| eyre!(DeError::NoCurrentProject))?
+    };
+
+    let task_name = match task_name {
+        Some(task_name) => task_name,
+        None => default_task(Some(&project), None)?,
+    };
+
+    // Fail before watching if the task is missing or `--cwd` is invalid
+    if project_task_command(&project, &task_name, &args, &context)?.is_none() {
+        return Err(eyre!(DeError::TaskNotFound {
+            task: task_name,
+            location: format!(
+                "project '{}' (--watch only runs project tasks)",
+                project.manifest().project().name
+            ),
+        }));
+    }
+
+    let mut watcher = Watcher::new(project.dir(), PathFilter::new(&watch_paths)?)?;
+    let term = console::Term::stdout();
+    let ui = UserInterface::new();
+
+    let (result, _) = run_interruptible(|| -> eyre::Result<()> {
+        loop {
+            let _ = term.clear_screen();
+            ui.heading(&format!("Running task '{task_name}'"))?;
+            ui.writeln(&ui.theme.dim("Watching for changes, press Ctrl-C to exit"))?;
+            ui.new_line()?;
+
+            let result = run_project_task(&project, &task_name, &args, None, &context);
+            if interrupted() {
+                return Ok(());
+            }
+
+            ui.new_line()?;
+            match result {
+                Ok(_) => ui.success_item(&format!("Task '{task_name}' finished"), None)?,
+                Err(e) => ui.error_item(&format!("{e:#}"), None)?,
+            }
+
+            watcher.reset();
+            if !watcher.wait(interrupted) {
+                return Ok(());
+            }
+        }
+    });
+
+    result
+}
+
+/// Finds the task to run when `de run` is given none, in the project the task would run in and,
+/// unless a project was given, the workspace.
+fn resolve_default_task(
+    project_arg: Option<&ProjectArg>,
+    workspace: Option<&Workspace>,
+    workspace_name: Option<&Slug>,
+    cwd: &Path,
+) -> eyre::Result<Slug> {
+    let Some(project_arg) = project_arg else {
+        let project = Project::from_dir_recursive(cwd)
+            .map_err(|e| eyre!(e))
+            .wrap_err("Failed to get current project")?
+            .filter(|project| {
+                workspace_name.is_none_or(|name| &project.manifest().project().workspace == name)
+            });
+        return default_task(project.as_ref(), workspace);
+    };
+
+    if let ProjectArg::Id(id) = project_arg
+        && id.as_str() == ALL_PROJECTS
+        && workspace.is_some_and(|workspace| workspace.project(id).is_none())
+    {
+        return Err(eyre!("--project all needs the name of the task to run"));
+    }
+
+    let project = load_project_arg(project_arg, workspace, workspace_name)?;
+    default_task(Some(&project), None)
+}
+
+/// The project's `default_task` or task named `default`, or else the workspace's. Fails with
+/// the tasks that could be run instead if neither has a default.
+fn default_task(project: Option<&Project>, workspace: Option<&Workspace>) -> eyre::Result<Slug> {
+    let default = Slug::from_str(DEFAULT_TASK).expect("default task name should be valid");
+
+    if let Some(project) = project {
+        if let Some(task) = &project.manifest().project().default_task {
+            return Ok(task.clone());
+        }
+        if project.task(&default)?.is_some() {
+            return Ok(default);
+        }
     }
 
+    if let Some(workspace) = workspace {
+        if let Some(task) = &workspace.config().default_task {
+            return Ok(task.clone());
+        }
+        if workspace.config().tasks.contains_key(&default) {
+            return Ok(default);
+        }
+    }
+
+    let mut available = BTreeSet::new();
+    if let Some(tasks) = project.and_then(Project::tasks) {
+        available.extend(tasks.tasks.keys().chain(tasks.aliases.keys()));
+    }
+    if let Some(workspace) = workspace {
+        available.extend(workspace.config().tasks.keys());
+    }
+
+    if available.is_empty() {
+        return Err(eyre!("No task given, and there are no tasks to run here"));
+    }
+
+    let available = available
+        .iter()
+        .map(|task| task.as_str())
+        .collect::<Vec<_>>();
     Err(eyre!(
-        "Task '{}' not found in project or active workspace",
-        task_name
+        "No task given and no default task is set. Available tasks: {}. Set `default_task` in the manifest or add a task named '{DEFAULT_TASK}' to run one by default",
+        available.join(", ")
     ))
 }
 
-pub fn run_project_task(
+/// Runs the named task in every workspace project that defines it, in dependency order.
+fn run_all_projects_task(
+    workspace: &Workspace,
+    task_name: &Slug,
+    args: &[String],
+    timeout: Option<Duration>,
+    dry_run: bool,
+    context: &TaskContext,
+) -> eyre::Result<()> {
+    let ui = UserInterface::new();
+
+    let (dependency_graph, projects) = workspace
+        .load_dependency_graph()
+        .map_err(|e| eyre!(e))
+        .wrap_err("Failed to load dependency graph for workspace")?;
+
+    dependency_graph
+        .validate_dependencies()
+        .wrap_err("Failed to validate project dependencies")?;
+
+    let startup_order = dependency_graph
+        .resolve_startup_order()
+        .wrap_err("Failed to resolve project order")?;
+
+    let projects_map: BTreeMap<_, _> = projects
+        .into_iter()
+        .map(|p| (p.manifest().project().name.clone(), p))
+        .collect();
+
+    ui.heading(&format!(
+        "Running task '{task_name}' in workspace {}",
+        workspace.config().name
+    ))?;
+
+    let mut ran = Vec::new();
+    let mut skipped = Vec::new();
+    let mut failed = Vec::new();
+
+    for project_id in startup_order {
+        let Some(project) = projects_map.get(&project_id) else {
+            continue;
+        };
+
+        // Alias errors are reported with the project like other failures
+        if matches!(project.task(task_name), Ok(None)) {
+            skipped.push(project_id.to_string());
+            continue;
+        }
+
+        ui.new_line()?;
+        ui.subheading(&format!("{project_id}:"))?;
+
+        match run_or_print_project_task(project, task_name, args, timeout, dry_run, context) {
+            Ok(_) => ran.push(project_id.to_string()),
+            Err(e) => {
+                ui.error_item(&format!("{e}"), None)?;
+                failed.push(project_id.to_string());
+            }
+        }
+    }
+
+    ui.new_line()?;
+    ui.heading("Summary")?;
+
+    if !ran.is_empty() {
+        ui.success_item(&format!("Ran in: {}", ran.join(", ")), None)?;
+    }
+    if !skipped.is_empty() {
+        ui.info_item(&format!(
+            "Skipped (task not defined): {}",
+            ui.theme.dim(&skipped.join(", "))
+        ))?;
+    }
+    if !failed.is_empty() {
+        ui.error_item(&format!("Failed in: {}", failed.join(", ")), None)?;
+        return Err(eyre!(
+            "Task '{}' failed in {} project(s)",
+            task_name,
+            failed.len()
+        ));
+    }
+    if ran.is_empty() {
+        return Err(eyre!(DeError::TaskNotFound {
+            task: task_name.clone(),
+            location: format!("any project of workspace '{}'", workspace.config().name),
+        }));
+    }
+
+    Ok(())
+}
+
+/// The workspace `--project` IDs are looked up in, failing if it could not be loaded.
+fn require_workspace<'a>(
+    workspace: Option<&'a Workspace>,
+    workspace_name: Option<&Slug>,
+) -> eyre::Result<&'a Workspace> {
+    workspace.ok_or_else(|| match workspace_name {
+        Some(workspace_name) => eyre!(DeError::WorkspaceNotFound(workspace_name.clone())),
+        None => eyre!(DeError::NoActiveWorkspace),
+    })
+}
+
+/// Loads the project `--project` refers to. An ID is looked up in the workspace, unless it
+/// isn't registered and names a directory. A path loads the project in that directory, whether
+/// or not it is registered, and fails if it has no de.toml.
+fn load_project_arg(
+    project_arg: &ProjectArg,
+    workspace: Option<&Workspace>,
+    workspace_name: Option<&Slug>,
+) -> eyre::Result<Project> {
+    let dir = match project_arg {
+        ProjectArg::Path(dir) => dir.clone(),
+        ProjectArg::Id(id) => match workspace.and_then(|workspace| workspace.project(id)) {
+            Some(ws_project) => {
+                return Project::from_dir(&ws_project.dir)
+                    .map_err(|e| eyre!(e))
+                    .wrap_err("Failed to load project from directory");
+            }
+            None if Path::new(id.as_str()).is_dir() => PathBuf::from(id.as_str()),
+            None => {
+                let workspace = require_workspace(workspace, workspace_name)?;
+                return Err(eyre!(DeError::ProjectNotFound {
+                    project: id.clone(),
+                    workspace: workspace.config().name.clone(),
+                }));
+            }
+        },
+    };
+
+    let dir = dir
+        .canonicalize()
+        .map_err(|e| eyre!(e))
+        .wrap_err_with(|| format!("Project directory {} does not exist", dir.display()))?;
+
+    // Falling back to a parent project would run a task the user didn't name
+    if !dir.join("de.toml").is_file() {
+        return Err(eyre!("No de.toml found in {}", dir.display()));
+    }
+
+    Project::from_dir(&dir)
+        .map_err(|e| eyre!(e))
+        .wrap_err("Failed to load project from directory")
+}
+
+/// Runs the project task, or only prints its command line when `dry_run` is set.
+fn run_or_print_project_task(
     project: &Project,
     task_name: &Slug,
-    args: &Vec<String>,
+    args: &[String],
+    timeout: Option<Duration>,
+    dry_run: bool,
+    context: &TaskContext,
 ) -> eyre::Result<bool> {
-    if let Some(task) = project
-        .manifest()
-        .tasks
-        .as_ref()
-        .and_then(|tasks| tasks.get(task_name))
-    {
-        let mut command = task
-            .command(project)
-            .map_err(|e| eyre!(e))
-            .wrap_err("Failed to build command for task")?;
+    if !dry_run {
+        return run_project_task(project, task_name, args, timeout, context);
+    }
 
-        if !args.is_empty() {
-            command.args(args);
+    for prerequisite in task_prerequisites(project, task_name, context)? {
+        if let Some(command) = project_task_command(project, &prerequisite, &[], context)? {
+            print_dry_run(&command);
         }
+    }
 
-        let status = command
-            .status()
-            .map_err(|e| eyre!(e))
-            .wrap_err("Failed to execute task command")?;
-
-        if !status.success() {
-            return Err(eyre!("Task '{}' failed with status: {}", task_name, status));
+    match project_task_command(project, task_name, args, context)? {
+        Some(command) => {
+            print_dry_run(&command);
+            Ok(true)
         }
+        None => Ok(false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        return Ok(true);
+    #[test]
+    fn test_project_path_needs_its_own_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("services/api");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(dir.path().join("de.toml"), "[project]\nname = \"parent\"\n").unwrap();
+
+        let Err(error) = load_project_arg(&ProjectArg::Path(nested.clone()), None, None) else {
+            panic!("loaded the parent project");
+        };
+        assert!(
+            error.to_string().starts_with("No de.toml found in"),
+            "{error}"
+        );
+
+        std::fs::write(nested.join("de.toml"), "[project]\nname = \"api\"\n").unwrap();
+        let project = load_project_arg(&ProjectArg::Path(nested), None, None).unwrap();
+        assert_eq!(project.manifest().project.name.as_str(), "api");
     }
 
-    Ok(false)
+    #[test]
+    fn test_default_task_prefers_the_project_then_the_workspace() {
+        let slug = |name: &str| Slug::from_str(name).unwrap();
+        // Each manifest gets its own directory, as loaded projects are cached by directory
+        let project_with = |manifest: &str| {
+            let dir = tempfile::tempdir().unwrap();
+            std::fs::write(dir.path().join("de.toml"), manifest).unwrap();
+            let project = Project::from_dir(dir.path()).unwrap();
+            (dir, project)
+        };
+        let mut workspace = Workspace::new(slug("ws")).unwrap();
+        workspace
+            .config_mut()
+            .tasks
+            .insert(slug("deploy"), "echo deploy".to_string());
+
+        // Without a default anywhere, the error lists what could be run
+        let (_dir, project) =
+            project_with("[project]\nname = \"api\"\n\n[tasks]\nbuild = \"make\"\n");
+        let error = default_task(Some(&project), Some(&workspace)).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("Available tasks: build, deploy."),
+            "{error}"
+        );
+
+        workspace.config_mut().default_task = Some(slug("deploy"));
+        assert_eq!(
+            default_task(Some(&project), Some(&workspace)).unwrap(),
+            slug("deploy")
+        );
+
+        // A project task named `default` wins over the workspace
+        let (_dir, project) = project_with(
+            "[project]\nname = \"api\"\n\n[tasks]\nbuild = \"make\"\ndefault = \"make all\"\n",
+        );
+        assert_eq!(
+            default_task(Some(&project), Some(&workspace)).unwrap(),
+            slug("default")
+        );
+
+        // And the project's `default_task` wins over its task named `default`
+        let (_dir, project) = project_with(
+            "[project]\nname = \"api\"\ndefault_task = \"build\"\n\n[tasks]\nbuild = \"make\"\ndefault = \"make all\"\n",
+        );
+        assert_eq!(
+            default_task(Some(&project), Some(&workspace)).unwrap(),
+            slug("build")
+        );
+
+        assert!(default_task(None, None).is_err());
+    }
 }
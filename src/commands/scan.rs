@@ -1,28 +1,86 @@
 use eyre::{WrapErr, eyre};
-use std::path::{Path, PathBuf};
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::{Path, PathBuf},
+};
 use walkdir::WalkDir;
 
+use super::init::prompt_workspace_name;
 use crate::{
-    project::Project,
+    project::{
+        Project,
+        config::{ProjectManifest, ProjectMetadata},
+    },
     types::Slug,
-    workspace::{self},
+    utils::parallel::parallel_map,
+    workspace::{self, OnConflict, Workspace},
 };
 
+/// Whether scanning a manifest changed its workspace registration.
+enum ScanOutcome {
+    Added,
+    Unchanged,
+    Skipped,
+}
+
+/// Docker Compose files that mark a directory as a project for `--init-missing`.
+const COMPOSE_FILE_NAMES: &[&str] = &[
+    "docker-compose.yml",
+    "docker-compose.yaml",
+    "compose.yml",
+    "compose.yaml",
+];
+
 /// Scans the specified directory for `de.toml` files and updates the workspace configuration.
 ///
+/// With `init_missing`, directories with a Docker Compose file but no `de.toml` get a minimal
+/// manifest in `workspace`, which is prompted for if not given, and are registered too.
+///
+/// With `prune`, registrations whose directory no longer contains a `de.toml` are removed
+/// afterwards, from `workspace` or the active workspace, or from every workspace with `all`.
+///
+/// With `follow_symlinks`, symlinked directories are scanned too. Each directory is only scanned
+/// once, by its canonical path, which also breaks link cycles.
+///
 /// FIXME: We can improve this by only checking for config files and not all files.
-pub fn scan(target_dir: Option<PathBuf>, workspace: Option<Slug>) -> eyre::Result<()> {
+pub fn scan(
+    target_dir: Option<PathBuf>,
+    workspace: Option<Slug>,
+    prune: bool,
+    all: bool,
+    init_missing: bool,
+    follow_symlinks: bool,
+    cwd: &Path,
+) -> eyre::Result<()> {
     let dir = match target_dir {
         Some(d) => d,
-        None => std::env::current_dir()
-            .map_err(|e| eyre!(e))
-            .wrap_err("Failed to get current directory")?,
+        None => cwd.to_path_buf(),
     };
 
-    let walkdir = WalkDir::new(&dir);
+    let mut added_count = 0;
+    let mut unchanged_count = 0;
+    let mut manifest_paths = Vec::new();
+    let mut compose_files = Vec::new();
+
+    let mut visited_dirs = HashSet::new();
+    let walkdir = WalkDir::new(&dir)
+        .follow_links(follow_symlinks)
+        .into_iter()
+        .filter_entry(|entry| {
+            if !follow_symlinks || !entry.file_type().is_dir() {
+                return true;
+            }
+            // A directory linked from several places would otherwise be scanned once per link
+            match entry.path().canonicalize() {
+                Ok(canonical) => visited_dirs.insert(canonical),
+                Err(_) => true,
+            }
+        });
     for entry in walkdir {
         let entry = match entry {
             Ok(e) => e,
+            // A link back to a directory being scanned, which is skipped like any revisit
+            Err(e) if e.loop_ancestor().is_some() => continue,
             Err(e) => {
                 eprintln!("Error reading entry: {e}");
                 continue;
@@ -30,48 +88,335 @@ pub fn scan(target_dir: Option<PathBuf>, workspace: Option<Slug>) -> eyre::Resul
         };
 
         if entry.file_type().is_file() {
-            if entry.file_name() != "de.toml" {
+            if init_missing
+                && COMPOSE_FILE_NAMES
+                    .iter()
+                    .any(|name| entry.file_name() == *name)
+            {
+                compose_files.push(entry.path().to_path_buf());
                 continue;
             }
 
-            let result = update_workspace(entry.path(), workspace.as_ref());
-            if let Err(e) = result {
-                eprintln!(
-                    "Failed to update workspace for {}: {}",
-                    entry.path().display(),
-                    e
+            if entry.file_name() == "de.toml" {
+                manifest_paths.push(entry.path().to_path_buf());
+            }
+        }
+    }
+
+    // Loading is side-effect free, so it runs concurrently; registering stays on this thread
+    for (manifest_path, project) in load_projects(&manifest_paths) {
+        match project.and_then(|project| update_workspace(&project, workspace.as_ref(), cwd)) {
+            Ok(ScanOutcome::Added) => {
+                added_count += 1;
+                println!("Updated workspace for {}", manifest_path.display());
+            }
+            Ok(ScanOutcome::Unchanged) => unchanged_count += 1,
+            Ok(ScanOutcome::Skipped) => {}
+            Err(e) => eprintln!(
+                "Failed to update workspace for {}: {}",
+                manifest_path.display(),
+                e
+            ),
+        }
+    }
+
+    // Checked after the walk, as a directory's compose file may be visited before its de.toml
+    let uninitialized = uninitialized_compose_files(compose_files);
+
+    if !uninitialized.is_empty() {
+        let workspace_name = match workspace.clone() {
+            Some(workspace_name) => workspace_name,
+            None => {
+                println!(
+                    "Found {} Docker Compose projects without a de.toml",
+                    uninitialized.len()
                 );
-            } else {
-                println!("Updated workspace for {}", entry.path().display());
+                prompt_workspace_name(cwd)?
+            }
+        };
+
+        for compose_file in &uninitialized {
+            match init_project(compose_file, &workspace_name, cwd) {
+                Ok(Some(name)) => {
+                    added_count += 1;
+                    println!(
+                        "Initialized {} in workspace {} ({})",
+                        name,
+                        workspace_name,
+                        compose_file.display()
+                    );
+                }
+                Ok(None) => {}
+                Err(e) => eprintln!(
+                    "Failed to initialize project for {}: {:#}",
+                    compose_file.display(),
+                    e
+                ),
             }
         }
     }
 
+    let removed_count = if prune {
+        prune_workspaces(workspace.as_ref(), all, cwd)?
+    } else {
+        0
+    };
+
+    println!("{added_count} added, {removed_count} removed, {unchanged_count} unchanged");
+
     Ok(())
 }
 
-fn update_workspace(manifest_path: &Path, workspace: Option<&Slug>) -> eyre::Result<()> {
-    let project_path = manifest_path
-        .parent()
-        .ok_or_else(|| eyre!("Manifest path has no parent directory"))?;
+/// Loads the project of each manifest on a pool of worker threads, one per CPU. Returns the
+/// results in the order of `manifest_paths`.
+fn load_projects(manifest_paths: &[PathBuf]) -> Vec<(&PathBuf, eyre::Result<Project>)> {
+    let jobs = std::thread::available_parallelism()
+        .map(usize::from)
+        .unwrap_or(1);
 
-    let project = Project::from_dir(project_path)
-        .map_err(|e| eyre!(e))
-        .wrap_err("Failed to load project from directory")?;
+    parallel_map(manifest_paths, jobs, |manifest_path| {
+        let project = manifest_path
+            .parent()
+            .ok_or_else(|| eyre!("Manifest path has no parent directory"))
+            .and_then(|project_path| {
+                Project::from_dir(project_path)
+                    .map_err(|e| eyre!(e))
+                    .wrap_err("Failed to load project from directory")
+            });
+        (manifest_path, project)
+    })
+}
+
+fn update_workspace(
+    project: &Project,
+    workspace: Option<&Slug>,
+    cwd: &Path,
+) -> eyre::Result<ScanOutcome> {
+    let project_path = project.dir().as_path();
+
+    let workspace_name = &project.manifest().project().workspace;
+    let project_name = &project.manifest().project().name;
 
     if let Some(ws) = workspace
-        && project.manifest().project().workspace != *ws
+        && workspace_name != ws
     {
-        return Ok(());
+        return Ok(ScanOutcome::Skipped);
+    }
+
+    let already_registered = Workspace::load_from_name(workspace_name, cwd)
+        .map_err(|e| eyre!(e))?
+        .and_then(|ws| ws.project(project_name).cloned())
+        .is_some_and(|existing| existing.dir == project_path);
+
+    if already_registered {
+        return Ok(ScanOutcome::Unchanged);
     }
 
     workspace::add_project_to_workspace(
-        project.manifest().project().workspace.clone(),
-        project.manifest().project.name.clone(),
+        workspace_name.clone(),
+        project_name.clone(),
         project_path.to_path_buf(),
+        OnConflict::Update,
+        cwd,
     )
     .wrap_err("Failed to add project to workspace")
     .map_err(|e| eyre!(e))?;
 
-    Ok(())
+    Ok(ScanOutcome::Added)
+}
+
+/// Picks the compose file of each directory that has no `de.toml` of its own. A directory nested
+/// in a project still counts, as it is a project of its own. A directory with several compose
+/// files uses the first one in `COMPOSE_FILE_NAMES`.
+fn uninitialized_compose_files(compose_files: Vec<PathBuf>) -> Vec<PathBuf> {
+    let mut by_dir = BTreeMap::<PathBuf, PathBuf>::new();
+    for compose_file in compose_files {
+        let Some(project_dir) = compose_file.parent() else {
+            continue;
+        };
+        if project_dir.join("de.toml").exists() {
+            continue;
+        }
+
+        let rank = |path: &Path| {
+            COMPOSE_FILE_NAMES
+                .iter()
+                .position(|name| path.file_name().is_some_and(|file| file == *name))
+        };
+        let preferred = by_dir
+            .get(project_dir)
+            .is_none_or(|existing| rank(&compose_file) < rank(existing));
+        if preferred {
+            by_dir.insert(project_dir.to_path_buf(), compose_file);
+        }
+    }
+    by_dir.into_values().collect()
+}
+
+/// Writes a minimal `de.toml` next to the compose file and registers the project in the
+/// workspace. Returns the project name, or `None` if the name is taken by another directory.
+fn init_project(
+    compose_file: &Path,
+    workspace_name: &Slug,
+    cwd: &Path,
+) -> eyre::Result<Option<Slug>> {
+    let project_dir = compose_file
+        .parent()
+        .ok_or_else(|| eyre!("Compose file has no parent directory"))?
+        .canonicalize()
+        .map_err(|e| eyre!(e))
+        .wrap_err("Failed to canonicalize project directory")?;
+
+    let name = Project::infer_name(&project_dir)?;
+
+    let taken_by = Workspace::load_from_name(workspace_name, cwd)
+        .map_err(|e| eyre!(e))?
+        .and_then(|ws| ws.project(&name).cloned())
+        .filter(|existing| existing.dir != project_dir);
+
+    if let Some(existing) = taken_by {
+        eprintln!(
+            "Skipping {}: project {} already exists in workspace {} ({})",
+            project_dir.display(),
+            name,
+            workspace_name,
+            existing.dir.display()
+        );
+        return Ok(None);
+    }
+
+    let manifest = ProjectManifest {
+        project: ProjectMetadata {
+            name: name.clone(),
+            workspace: workspace_name.clone(),
+            docker_compose: compose_file.file_name().map(PathBuf::from),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let manifest_path = project_dir.join("de.toml");
+    manifest
+        .save(&manifest_path)
+        .map_err(|e| eyre!(e))
+        .wrap_err_with(|| format!("Failed to save manifest to {}", manifest_path.display()))?;
+
+    workspace::add_project_to_workspace(
+        workspace_name.clone(),
+        name.clone(),
+        project_dir,
+        OnConflict::Error,
+        cwd,
+    )
+    .wrap_err("Failed to add project to workspace")?;
+
+    Ok(Some(name))
+}
+
+/// Removes registrations whose directory no longer contains a `de.toml`, returning how many were removed.
+///
+/// Only the given workspace is pruned, or the active one if none is given. With `all`, every
+/// workspace is.
+fn prune_workspaces(workspace: Option<&Slug>, all: bool, cwd: &Path) -> eyre::Result<usize> {
+    let workspace_names = match workspace {
+        _ if all => Workspace::all_names()?,
+        Some(name) => vec![name.clone()],
+        None => vec![
+            Workspace::require_active(cwd)
+                .wrap_err("Pass --workspace or --all to choose the workspaces to prune")?
+                .config()
+                .name
+                .clone(),
+        ],
+    };
+
+    let mut removed_count = 0;
+
+    for workspace_name in workspace_names {
+        let _lock = Workspace::lock(&workspace_name, cwd)?;
+
+        let Some(mut workspace) = Workspace::load_from_name(&workspace_name, cwd)
+            .map_err(|e| eyre!(e))
+            .wrap_err_with(|| format!("Failed to load workspace '{workspace_name}'"))?
+        else {
+            continue;
+        };
+
+        let stale = workspace
+            .config()
+            .projects
+            .iter()
+            .filter(|(_, project)| !project.dir.join("de.toml").exists())
+            .map(|(id, project)| (id.clone(), project.dir.clone()))
+            .collect::<Vec<_>>();
+
+        if stale.is_empty() {
+            continue;
+        }
+
+        for (id, dir) in &stale {
+            workspace.remove_project(id);
+            println!(
+                "Removed {} from workspace {} ({})",
+                id,
+                workspace_name,
+                dir.display()
+            );
+        }
+
+        workspace
+            .save()
+            .map_err(|e| eyre!(e))
+            .wrap_err_with(|| format!("Failed to save workspace '{workspace_name}'"))?;
+
+        removed_count += stale.len();
+    }
+
+    Ok(removed_count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn touch(path: &Path) -> PathBuf {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, "").unwrap();
+        path.to_path_buf()
+    }
+
+    #[test]
+    fn test_uninitialized_compose_files_skips_only_dirs_with_a_manifest() {
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path();
+
+        // A top-level directory without a manifest, with two compose files
+        let top_level = touch(&root.join("compose.yaml"));
+        let preferred = touch(&root.join("docker-compose.yml"));
+        // A directory nested in it, which has no manifest of its own either
+        let nested = touch(&root.join("nested/compose.yml"));
+        // A directory that is already a project
+        touch(&root.join("project/de.toml"));
+        let initialized = touch(&root.join("project/compose.yml"));
+
+        let uninitialized = uninitialized_compose_files(vec![
+            top_level,
+            nested.clone(),
+            preferred.clone(),
+            initialized,
+        ]);
+        assert_eq!(uninitialized, vec![preferred, nested]);
+    }
+
+    #[test]
+    fn test_uninitialized_compose_files_includes_dirs_nested_in_a_project() {
+        let root = tempfile::tempdir().unwrap();
+        touch(&root.path().join("de.toml"));
+        let nested = touch(&root.path().join("services/api/compose.yaml"));
+
+        assert_eq!(
+            uninitialized_compose_files(vec![nested.clone()]),
+            vec![nested]
+        );
+    }
 }
@@ -0,0 +1,187 @@
+use eyre::{Context, eyre};
+use schemars::{JsonSchema, Schema, generate::SchemaSettings, transform::transform_subschemas};
+use serde_json::Value;
+
+use crate::{
+    cli::SchemaKind, project::config::ProjectManifest, workspace::config::WorkspaceConfig,
+};
+
+/// Prints the JSON Schema of `de.toml` or of a workspace configuration, for editors and TOML
+/// language servers.
+pub fn schema(kind: SchemaKind) -> eyre::Result<()> {
+    let schema = match kind {
+        SchemaKind::Manifest => schema_for::<ProjectManifest>("de project manifest"),
+        SchemaKind::Workspace => schema_for::<WorkspaceConfig>("de workspace configuration"),
+    };
+
+    let json = serde_json::to_string_pretty(&schema)
+        .map_err(|e| eyre!(e))
+        .wrap_err("Failed to serialize schema")?;
+    println!("{json}");
+
+    Ok(())
+}
+
+/// The schema derived from the type `de` parses the file into, so it can't drift from it.
+/// Draft 7 is the newest draft most TOML language servers support.
+fn schema_for<T: JsonSchema>(title: &str) -> Schema {
+    let mut schema = SchemaSettings::draft07()
+        .into_generator()
+        .into_root_schema_for::<T>();
+    schema.insert("title".to_string(), title.into());
+    remove_null(&mut schema);
+    schema
+}
+
+/// Drops the `null` schemars allows for `Option` fields. TOML has no null, so an optional field
+/// is simply left out.
+fn remove_null(schema: &mut Schema) {
+    if let Some(object) = schema.as_object_mut() {
+        if let Some(types) = object.get_mut("type").and_then(Value::as_array_mut) {
+            types.retain(|kind| kind != "null");
+            if let [kind] = types.as_slice() {
+                let kind = kind.clone();
+                object.insert("type".to_string(), kind);
+            }
+        }
+
+        if let Some(variants) = object.get_mut("anyOf").and_then(Value::as_array_mut) {
+            variants.retain(|variant| variant.get("type").is_none_or(|kind| kind != "null"));
+            if let [Value::Object(variant)] = variants.as_slice() {
+                let variant = variant.clone();
+                object.remove("anyOf");
+                object.extend(variant);
+            }
+        }
+
+        if let Some(default) = object.get_mut("default") {
+            remove_null_fields(default);
+            if default.is_null() {
+                object.remove("default");
+            }
+        }
+    }
+
+    transform_subschemas(&mut remove_null, schema);
+}
+
+fn remove_null_fields(value: &mut Value) {
+    if let Value::Object(fields) = value {
+        fields.retain(|_, field| !field.is_null());
+        fields.values_mut().for_each(remove_null_fields);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest_schema() -> Value {
+        serde_json::to_value(schema_for::<ProjectManifest>("manifest")).unwrap()
+    }
+
+    fn workspace_schema() -> Value {
+        serde_json::to_value(schema_for::<WorkspaceConfig>("workspace")).unwrap()
+    }
+
+    /// Follows a `$ref`, also when schemars wraps it in an `allOf` to add a description.
+    fn resolve<'a>(root: &'a Value, schema: &'a Value) -> &'a Value {
+        let reference = schema["$ref"]
+            .as_str()
+            .or_else(|| schema["allOf"][0]["$ref"].as_str());
+        match reference.and_then(|reference| reference.strip_prefix("#/definitions/")) {
+            Some(name) => resolve(root, &root["definitions"][name]),
+            None => schema,
+        }
+    }
+
+    fn required(schema: &Value) -> Vec<&str> {
+        schema["required"]
+            .as_array()
+            .map(|fields| fields.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn test_manifest_schema_describes_the_task_variants() {
+        let root = manifest_schema();
+        let tasks = resolve(&root, &root["properties"]["tasks"]);
+
+        assert_eq!(
+            resolve(&root, &tasks["properties"]["aliases"])["type"],
+            "object"
+        );
+        assert_eq!(
+            resolve(&root, &tasks["propertyNames"])["pattern"],
+            "^[a-z][a-z0-9_-]*$"
+        );
+
+        let task = resolve(&root, &tasks["additionalProperties"]);
+        let variants = task["anyOf"].as_array().unwrap();
+        let compose = &variants[0];
+        assert_eq!(required(compose), ["service", "command"]);
+        assert!(compose["properties"]["shell"].is_object());
+        assert_eq!(
+            resolve(&root, &compose["properties"]["timeout"])["type"],
+            "string"
+        );
+
+        let script = &variants[1];
+        assert_eq!(required(script), ["script"]);
+        assert!(script["properties"]["shell"].is_null());
+
+        let raw = resolve(&root, &variants[2])["anyOf"].as_array().unwrap();
+        assert_eq!(raw[0]["type"], "string");
+        assert_eq!(required(&raw[1]), ["command"]);
+    }
+
+    #[test]
+    fn test_manifest_schema_describes_project_and_setup() {
+        let root = manifest_schema();
+        assert_eq!(root["$schema"], "http://json-schema.org/draft-07/schema#");
+        assert_eq!(root["title"], "manifest");
+
+        let project = resolve(&root, &root["properties"]["project"]);
+        assert_eq!(project["additionalProperties"], false);
+        assert_eq!(project["properties"]["name"]["default"], "default");
+        let hooks = resolve(&root, &project["properties"]["hooks"]);
+        assert!(hooks["properties"]["before_start"]["items"].is_object());
+
+        let setup = resolve(&root, &root["properties"]["setup"]);
+        assert_eq!(required(setup), ["git"]);
+
+        let step = resolve(
+            &root,
+            &setup["properties"]["steps"]["patternProperties"]["^[a-z][a-z0-9_-]*$"],
+        );
+        assert_eq!(required(step), ["name"]);
+        let kinds = step["anyOf"].as_array().unwrap();
+        let copy_files = &resolve(&root, &kinds[0])["oneOf"][0];
+        assert_eq!(copy_files["properties"]["type"]["const"], "copy_files");
+        assert_eq!(copy_files["properties"]["recursive"]["default"], true);
+        assert_eq!(required(&kinds[1]), ["apply", "export"]);
+        assert_eq!(required(&kinds[2]), ["command"]);
+    }
+
+    #[test]
+    fn test_workspace_schema_describes_projects() {
+        let root = workspace_schema();
+        assert_eq!(required(&root), ["name", "projects"]);
+
+        let project = resolve(
+            &root,
+            &root["properties"]["projects"]["patternProperties"]["^[a-z][a-z0-9_-]*$"],
+        );
+        assert_eq!(required(project), ["dir"]);
+        assert_eq!(project["properties"]["enabled"]["type"], "boolean");
+        assert_eq!(project["properties"]["default_branch"]["type"], "string");
+    }
+
+    #[test]
+    fn test_schemas_leave_out_null() {
+        for schema in [manifest_schema(), workspace_schema()] {
+            let json = serde_json::to_string(&schema).unwrap();
+            assert!(!json.contains("null"), "{json}");
+        }
+    }
+}
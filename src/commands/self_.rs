@@ -1,15 +1,60 @@
 use crate::commands;
-use axoupdater::{AxoUpdater, UpdateResult};
+use axoupdater::{
+    AxoUpdater, ReleaseSource, ReleaseSourceType, UpdateRequest, UpdateResult, Version,
+};
 use eyre::{WrapErr, eyre};
 use indicatif::ProgressBar;
+use serde::Deserialize;
 
-use crate::{constants::PROJECT_NAME, utils::ui::UserInterface};
+use crate::{
+    config::{Config, UpdateChannel},
+    constants::{
+        BUILD_DATE, BUILD_TARGET, GIT_SHA, GITHUB_TOKEN_ENV_VAR, PROJECT_NAME, RUSTC_VERSION,
+        VERSION,
+    },
+    utils::{download, ui::UserInterface},
+};
+
+pub fn version() -> eyre::Result<()> {
+    let ui = UserInterface::new();
+    ui.writeln(&format!("{PROJECT_NAME} {VERSION}"))?;
+    ui.indented(|ui| {
+        for (label, value) in [
+            ("commit", GIT_SHA),
+            ("built", BUILD_DATE),
+            ("target", BUILD_TARGET),
+            ("rustc", RUSTC_VERSION),
+        ] {
+            ui.writeln(&format!("{label}: {}", ui.theme.dim(value)))?;
+        }
+        Ok(())
+    })?;
+
+    Ok(())
+}
+
+/// Updates de from the release track `channel`, remembering it for later updates once it
+/// worked, or from the remembered track. With `check`, only reports whether a newer release is
+/// available, without remembering `channel`.
+pub fn update(channel: Option<UpdateChannel>, check: bool) -> eyre::Result<()> {
+    let new_channel = channel.filter(|_| !check);
+    let channel = match channel {
+        Some(channel) => channel,
+        None => Config::load()?.update_channel.unwrap_or_default(),
+    };
 
-pub fn update() -> eyre::Result<()> {
     let ui = UserInterface::new();
     let mut loading_bar = ui.loading_bar("Checking for updates...")?;
 
-    if let Some(update_result) = update_binary(&ui, &mut loading_bar)? {
+    let update_result = update_binary(&ui, &mut loading_bar, channel, check)?;
+
+    // Only a channel that could be updated from is remembered, so a bad one doesn't break
+    // every later `de self update`
+    if let Some(channel) = new_channel {
+        Config::mutate_persisted(|config| config.update_channel = Some(channel))?;
+    }
+
+    if let Some(update_result) = update_result {
         commands::shim::reinstate()?;
 
         ui.new_line()?;
@@ -27,6 +72,8 @@ pub fn update() -> eyre::Result<()> {
 fn update_binary(
     ui: &UserInterface,
     loading_bar: &mut ProgressBar,
+    channel: UpdateChannel,
+    check: bool,
 ) -> eyre::Result<Option<UpdateResult>> {
     let mut updater = AxoUpdater::new_for(PROJECT_NAME);
     updater
@@ -34,19 +81,57 @@ fn update_binary(
         .map_err(|e| eyre!(e))
         .wrap_err("Failed to initialize updater.")?;
 
-    let is_update_needed = updater
-        .is_update_needed_sync()
-        .map_err(|e| eyre!(e))
-        .wrap_err("Failed to check for updates")?;
+    let token = std::env::var(GITHUB_TOKEN_ENV_VAR)
+        .ok()
+        .filter(|token| !token.is_empty());
+    if let Some(token) = &token {
+        updater.set_github_token(token);
+    }
 
-    if is_update_needed {
-        loading_bar.set_message("Update available, downloading...");
+    // The updater only tells full releases from prereleases, so other tracks pick their tag
+    let available = if channel == UpdateChannel::Stable {
+        let is_update_needed = updater
+            .is_update_needed_sync()
+            .map_err(|e| eyre!(e))
+            .wrap_err("Failed to check for updates")?;
+        is_update_needed.then(|| "a newer stable release".to_string())
     } else {
+        let current = Version::parse(VERSION)
+            .map_err(|e| eyre!(e))
+            .wrap_err("Failed to parse the current version")?;
+        let source = updater
+            .source
+            .as_ref()
+            .ok_or_else(|| eyre!("The install receipt doesn't say where de was installed from"))?;
+        let tags = release_tags(GITHUB_API, source, token.as_deref())?;
+        newest_release(&tags, channel)
+            .filter(|(_, version)| *version > current)
+            .map(|(tag, _)| {
+                updater.configure_version_specifier(UpdateRequest::SpecificTag(tag.clone()));
+                tag
+            })
+    };
+
+    let Some(available) = available else {
         loading_bar.finish_and_clear();
-        ui.writeln("You are already on the latest version.")?;
+        ui.writeln(&format!(
+            "You are already on the latest {} version.",
+            channel.as_str()
+        ))?;
+        return Ok(None);
+    };
+
+    if check {
+        loading_bar.finish_and_clear();
+        ui.writeln(&format!(
+            "Update available: {available} ({} channel). Run `de self update` to install it.",
+            channel.as_str()
+        ))?;
         return Ok(None);
     }
 
+    loading_bar.set_message("Update available, downloading...");
+
     let updated = updater
         .disable_installer_output()
         .run_sync()
@@ -60,3 +145,195 @@ fn update_binary(
         Ok(None)
     }
 }
+
+const GITHUB_API: &str = "https://api.github.com";
+
+#[derive(Debug, Deserialize)]
+struct GithubRelease {
+    tag_name: String,
+}
+
+/// Lists the tags of all GitHub releases of the repository de was installed from, prereleases
+/// included, authenticated with `token` if there is one.
+fn release_tags(
+    api: &str,
+    source: &ReleaseSource,
+    token: Option<&str>,
+) -> eyre::Result<Vec<String>> {
+    if source.release_type != ReleaseSourceType::GitHub {
+        return Err(eyre!(
+            "Release channels are only supported for de installed from GitHub releases"
+        ));
+    }
+
+    let client = download::client()?;
+    let mut tags = Vec::new();
+    let mut url = Some(format!(
+        "{api}/repos/{}/{}/releases?per_page=100",
+        source.owner, source.name
+    ));
+
+    while let Some(page) = url.take() {
+        let mut request = client
+            .get(&page)
+            .header(reqwest::header::ACCEPT, "application/vnd.github+json");
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+        let response = download::send(request).wrap_err("Failed to list releases")?;
+
+        url = response
+            .headers()
+            .get(reqwest::header::LINK)
+            .and_then(|link| link.to_str().ok())
+            .and_then(next_page_url);
+
+        let body = response
+            .text()
+            .map_err(|e| eyre!(e))
+            .wrap_err("Failed to read release list")?;
+        let releases = serde_json::from_str::<Vec<GithubRelease>>(&body)
+            .map_err(|e| eyre!(e))
+            .wrap_err("Failed to parse release list")?;
+        tags.extend(releases.into_iter().map(|release| release.tag_name));
+    }
+
+    Ok(tags)
+}
+
+/// The URL of the next page in a GitHub `Link` header, like
+/// `<https://api.github.com/...?page=2>; rel="next", <...>; rel="last"`.
+fn next_page_url(link: &str) -> Option<String> {
+    link.split(',').find_map(|entry| {
+        let (url, params) = entry.split_once(';')?;
+        params
+            .split(';')
+            .any(|param| param.trim() == "rel=\"next\"")
+            .then(|| {
+                url.trim()
+                    .trim_start_matches('<')
+                    .trim_end_matches('>')
+                    .to_string()
+            })
+    })
+}
+
+/// The newest of the release tags on the track, with its version. Tags that aren't versions
+/// are ignored.
+fn newest_release(tags: &[String], channel: UpdateChannel) -> Option<(String, Version)> {
+    tags.iter()
+        .filter_map(|tag| {
+            let version = Version::parse(tag.trim_start_matches('v')).ok()?;
+            channel
+                .includes(version.pre.as_str())
+                .then(|| (tag.clone(), version))
+        })
+        .max_by(|(_, a), (_, b)| a.cmp(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_newest_release_follows_channel() {
+        let tags = [
+            "v0.6.0-nightly.20260101",
+            "v0.6.0-beta.2",
+            "v0.5.1",
+            "latest",
+        ]
+        .map(String::from);
+
+        let newest = |channel| newest_release(&tags, channel).map(|(tag, _)| tag);
+        assert_eq!(newest(UpdateChannel::Stable).as_deref(), Some("v0.5.1"));
+        assert_eq!(
+            newest(UpdateChannel::Beta).as_deref(),
+            Some("v0.6.0-beta.2")
+        );
+        assert_eq!(
+            newest(UpdateChannel::Nightly).as_deref(),
+            Some("v0.6.0-nightly.20260101")
+        );
+    }
+
+    #[test]
+    fn test_next_page_url() {
+        let link = "<https://api.github.com/repositories/1/releases?page=1>; rel=\"prev\", \
+                    <https://api.github.com/repositories/1/releases?page=3>; rel=\"next\", \
+                    <https://api.github.com/repositories/1/releases?page=5>; rel=\"last\"";
+        assert_eq!(
+            next_page_url(link).as_deref(),
+            Some("https://api.github.com/repositories/1/releases?page=3")
+        );
+        assert_eq!(
+            next_page_url("<https://api.github.com/x?page=1>; rel=\"first\""),
+            None
+        );
+    }
+
+    #[test]
+    fn test_release_tags_follows_pages_with_the_token() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let api = format!("http://{}", listener.local_addr().unwrap());
+        let next = format!("{api}/repositories/1/releases?page=2");
+        let (requests_tx, requests) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let pages = [
+                (
+                    format!("Link: <{next}>; rel=\"next\"\r\n"),
+                    r#"[{"tag_name": "v0.6.0-beta.1"}]"#,
+                ),
+                (String::new(), r#"[{"tag_name": "v0.5.0"}]"#),
+            ];
+            for (headers, body) in pages {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = [0; 4096];
+                let len = std::io::Read::read(&mut stream, &mut request).unwrap();
+                requests_tx
+                    .send(String::from_utf8_lossy(&request[..len]).to_string())
+                    .unwrap();
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nConnection: close\r\n{headers}\
+                     Content-Length: {}\r\n\r\n{body}",
+                    body.len()
+                );
+                std::io::Write::write_all(&mut stream, response.as_bytes()).unwrap();
+            }
+        });
+
+        let source = ReleaseSource {
+            release_type: ReleaseSourceType::GitHub,
+            owner: "umbra-ecosystem".to_string(),
+            name: "de".to_string(),
+            app_name: "de".to_string(),
+        };
+        assert_eq!(
+            release_tags(&api, &source, Some("secret")).unwrap(),
+            ["v0.6.0-beta.1", "v0.5.0"]
+        );
+
+        let requests = requests.try_iter().collect::<Vec<_>>();
+        assert!(requests[0].starts_with("GET /repos/umbra-ecosystem/de/releases?per_page=100 "));
+        assert!(requests[1].starts_with("GET /repositories/1/releases?page=2 "));
+        for request in &requests {
+            assert!(
+                request
+                    .to_lowercase()
+                    .contains("authorization: bearer secret"),
+                "{request}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_release_tags_needs_github_releases() {
+        let source = ReleaseSource {
+            release_type: ReleaseSourceType::Axo,
+            owner: "umbra-ecosystem".to_string(),
+            name: "de".to_string(),
+            app_name: "de".to_string(),
+        };
+        assert!(release_tags(GITHUB_API, &source, None).is_err());
+    }
+}
@@ -2,9 +2,25 @@ use std::path::{Path, PathBuf};
 
 use eyre::Context;
 
-use crate::{setup::snapshot::apply_snapshot, utils::ui::UserInterface};
+use crate::{
+    cli::SnapshotInspectFormat,
+    setup::snapshot::{apply_snapshot, inspect_snapshot},
+    types::Slug,
+    utils::{
+        download::{download_to_tempfile, is_url},
+        ui::UserInterface,
+    },
+};
 
-pub fn setup(snapshot: PathBuf, target_dir: Option<PathBuf>) -> eyre::Result<()> {
+pub fn setup(
+    snapshot: PathBuf,
+    target_dir: Option<PathBuf>,
+    latest: bool,
+    jobs: Option<usize>,
+    only: Vec<Slug>,
+    no_input: bool,
+    cwd: &Path,
+) -> eyre::Result<()> {
     let target_dir = if let Some(dir) = target_dir {
         // Create the directory if it doesn't exist
         if !dir.exists() {
@@ -21,22 +37,58 @@ pub fn setup(snapshot: PathBuf, target_dir: Option<PathBuf>) -> eyre::Result<()>
                 format!("Failed to canonicalize target directory: {}", dir.display())
             })?
     } else {
-        std::env::current_dir()
-            .map_err(|e| eyre::eyre!(e))
-            .wrap_err("Failed to get current directory")?
+        cwd.to_path_buf()
     };
 
     verify_target_dir(&target_dir)?;
 
+    let jobs = jobs.unwrap_or_else(|| {
+        std::thread::available_parallelism()
+            .map(usize::from)
+            .unwrap_or(1)
+    });
+
     let ui = UserInterface::new();
 
-    apply_snapshot(&ui, &snapshot, &target_dir)
-        .map_err(|e| eyre::eyre!(e))
-        .wrap_err_with(|| format!("Failed to apply snapshot from: {}", snapshot.display()))?;
+    // Keep the download around until the snapshot is applied
+    let download = if is_url(&snapshot) {
+        let url = snapshot.to_string_lossy();
+        ui.heading("Download")?;
+        let file = download_to_tempfile(&ui, &url)?;
+        ui.success_item(&format!("Downloaded {url}"), None)?;
+        ui.new_line()?;
+        Some(file)
+    } else {
+        None
+    };
+    let snapshot_path = download
+        .as_ref()
+        .map_or(snapshot.as_path(), |file| file.path());
+
+    apply_snapshot(
+        &ui,
+        snapshot_path,
+        &target_dir,
+        latest,
+        jobs,
+        &only,
+        no_input,
+    )
+    .map_err(|e| eyre::eyre!(e))
+    .wrap_err_with(|| format!("Failed to apply snapshot from: {}", snapshot.display()))?;
 
     Ok(())
 }
 
+/// Prints the contents of a snapshot without applying it.
+pub fn inspect(snapshot: PathBuf, format: SnapshotInspectFormat) -> eyre::Result<()> {
+    let ui = UserInterface::new();
+
+    inspect_snapshot(&ui, &snapshot, format)
+        .map_err(|e| eyre::eyre!(e))
+        .wrap_err_with(|| format!("Failed to inspect snapshot: {}", snapshot.display()))
+}
+
 fn verify_target_dir(dir: &Path) -> eyre::Result<()> {
     // If path is a file, return error
     if dir.exists() && !dir.is_dir() {
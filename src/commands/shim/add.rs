@@ -1,5 +1,55 @@
-use crate::{types::Slug, utils::shim::write_shim_to_file};
+use std::path::Path;
+
+use eyre::{Context, eyre};
+
+use crate::{
+    error::DeError,
+    project::Project,
+    types::Slug,
+    utils::{
+        shim::{is_protected_command, write_shim_to_file},
+        ui::UserInterface,
+    },
+};
 
 pub fn add(command: Slug) -> eyre::Result<()> {
     write_shim_to_file(&command)
 }
+
+/// Adds a shim for every task of the current project, except for protected system commands.
+pub fn add_all_tasks(cwd: &Path) -> eyre::Result<()> {
+    let ui = UserInterface::new();
+
+    let project = Project::from_dir_recursive(cwd)
+        .map_err(|e| eyre!(e))
+        .wrap_err("Failed to get current project")?
+        .ok_or_else(|| eyre!(DeError::NoCurrentProject))?;
+
+    let tasks = project
+        .tasks()
+        .map(|tasks| tasks.names().cloned().collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    if tasks.is_empty() {
+        ui.info_item("The current project has no tasks to shim")?;
+        return Ok(());
+    }
+
+    ui.heading("Adding shims")?;
+    for task in tasks {
+        if is_protected_command(task.as_str()) {
+            ui.warning_item(
+                &format!("Skipped {}", ui.theme.accent(task.as_str())),
+                Some("Shimming it would shadow a critical system command"),
+            )?;
+            continue;
+        }
+
+        write_shim_to_file(&task)
+            .map_err(|e| eyre!(e))
+            .wrap_err_with(|| format!("Failed to write shim for task: {task}"))?;
+        ui.success_item(task.as_str(), None)?;
+    }
+
+    Ok(())
+}
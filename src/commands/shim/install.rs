@@ -1,9 +1,10 @@
 use eyre::{Context, eyre};
 
-use crate::utils::{
-    check_shim_installation_in_shell_config, get_shims_dir, shim_export_line,
-    unix::{get_shell_config_paths, primary_shell_config_path},
-};
+#[cfg(target_family = "unix")]
+use crate::utils::unix::{get_shell_config_paths, primary_shell_config_path};
+#[cfg(target_family = "windows")]
+use crate::utils::windows::{get_shell_config_paths, primary_shell_config_path};
+use crate::utils::{check_shim_installation_in_shell_config, get_shims_dir, shim_export_line};
 use std::{
     fs,
     io::Write,
@@ -74,6 +75,11 @@ fn check_shim_installation(config_files: &[PathBuf], install_dir: &Path) -> eyre
 fn add_to_shell_config(config_file_path: &Path, install_dir: &Path) -> eyre::Result<()> {
     let shim_export = shim_export_line(install_dir)?;
 
+    // The PowerShell profile directory doesn't exist until something is put in it
+    if let Some(parent) = config_file_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
     let mut file = fs::OpenOptions::new()
         .create(true)
         .append(true)
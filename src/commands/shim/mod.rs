@@ -5,9 +5,9 @@ mod reinstate;
 mod remove;
 mod uninstall;
 
-pub use add::add;
+pub use add::{add, add_all_tasks};
 pub use install::install;
 pub use list::list;
 pub use reinstate::reinstate;
-pub use remove::remove;
+pub use remove::{remove, remove_all};
 pub use uninstall::uninstall;
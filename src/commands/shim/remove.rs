@@ -1,9 +1,16 @@
-use crate::{types::Slug, utils::get_shims_dir};
+use crate::{
+    types::Slug,
+    utils::{
+        get_shims_dir,
+        shim::{get_installed_shims, shim_file_name},
+        ui::UserInterface,
+    },
+};
 use eyre::{WrapErr, eyre};
 
 pub fn remove(command: Slug) -> eyre::Result<()> {
     let shims_dir = get_shims_dir()?;
-    let shim_path = shims_dir.join(format!("{command}"));
+    let shim_path = shims_dir.join(shim_file_name(command.as_str()));
 
     if shim_path.exists() {
         std::fs::remove_file(&shim_path)
@@ -16,3 +23,26 @@ pub fn remove(command: Slug) -> eyre::Result<()> {
 
     Ok(())
 }
+
+/// Removes every shim in the de shims directory.
+pub fn remove_all() -> eyre::Result<()> {
+    let ui = UserInterface::new();
+    let shims_dir = get_shims_dir()?;
+
+    let shims = get_installed_shims()?;
+    if shims.is_empty() {
+        ui.info_item("No shims found to remove.")?;
+        return Ok(());
+    }
+
+    ui.heading("Removing shims")?;
+    for shim in shims {
+        let shim_path = shims_dir.join(shim_file_name(&shim));
+        std::fs::remove_file(&shim_path)
+            .map_err(|e| eyre!(e))
+            .wrap_err_with(|| format!("Failed to remove shim at {}", shim_path.display()))?;
+        ui.success_item(&shim, None)?;
+    }
+
+    Ok(())
+}
@@ -2,10 +2,11 @@ use std::path::Path;
 
 use eyre::{WrapErr, eyre};
 
-use crate::utils::{
-    check_shim_installation_in_shell_config, get_shims_dir, shim_export_line,
-    unix::get_shell_config_paths,
-};
+#[cfg(target_family = "unix")]
+use crate::utils::unix::get_shell_config_paths;
+#[cfg(target_family = "windows")]
+use crate::utils::windows::get_shell_config_paths;
+use crate::utils::{check_shim_installation_in_shell_config, get_shims_dir, shim_export_line};
 
 pub fn uninstall() -> eyre::Result<()> {
     let shims_dir = get_shims_dir()
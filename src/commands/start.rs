@@ -1,28 +1,39 @@
 use dialoguer::{Select, theme::ColorfulTheme};
 use eyre::{WrapErr, eyre};
 use std::collections::BTreeSet;
+use std::path::Path;
 
 use crate::{
-    commands::{status::workspace_status, stop::stop_workspace},
+    commands::{
+        status::{StatusFilter, workspace_status},
+        stop::stop_workspace,
+    },
     config::Config,
-    project::Project,
+    error::DeError,
+    project::{DownOptions, Project, UpOptions, config::ProjectHook},
     types::Slug,
-    utils::{get_workspace_for_cli, ui::UserInterface},
-    workspace::{Workspace, spin_up_workspace},
+    utils::{get_workspace_for_cli, is_interactive, ui::UserInterface},
+    workspace::{Workspace, hook_failures_result, run_project_hooks, spin_up_workspace},
 };
 
-pub fn start(workspace_name: Option<Option<Slug>>, yes: bool) -> eyre::Result<()> {
+pub fn start(
+    workspace_name: Option<Option<Slug>>,
+    yes: bool,
+    attach: bool,
+    down_on_exit: bool,
+    cwd: &Path,
+) -> eyre::Result<()> {
     let ui = UserInterface::new();
 
-    check_for_active_workspace(&ui, yes)?;
+    check_for_active_workspace(&ui, yes, cwd)?;
 
     if let Some(workspace_name) = workspace_name {
         // Start entire workspace
-        let workspace = get_workspace_for_cli(Some(workspace_name))
+        let workspace = get_workspace_for_cli(Some(workspace_name), cwd)
             .map_err(|e| eyre!(e))
             .wrap_err("Failed to get workspace for CLI")?;
 
-        spin_up_workspace(&workspace)
+        spin_up_workspace(&ui, &workspace)
             .map_err(|e| eyre!(e))
             .wrap_err("Failed to spin up workspace")?;
 
@@ -32,39 +43,45 @@ pub fn start(workspace_name: Option<Option<Slug>>, yes: bool) -> eyre::Result<()
 
         // We ignore the error here because we want to proceed even if the status check fails
         ui.new_line()?;
-        let _ = workspace_status(&ui, &workspace);
+        let _ = workspace_status(&ui, &workspace, StatusFilter::default(), false, cwd);
     } else {
         // Start current project and its dependencies
-        let project = Project::current()
+        let project = Project::from_dir_recursive(cwd)
             .map_err(|e| eyre!(e))
             .wrap_err("Failed to get current project")?
-            .ok_or_else(|| eyre!("No current project found"))?;
+            .ok_or_else(|| eyre!(DeError::NoCurrentProject))?;
 
         let workspace_name = project.manifest().project().workspace.clone();
 
-        let workspace = Workspace::load_from_name(&workspace_name)
-            .map_err(|e| eyre!(e))
-            .wrap_err("Failed to load workspace")?
-            .ok_or_else(|| eyre!("Workspace {} not found", workspace_name))?;
+        let workspace = Workspace::require(&workspace_name, cwd)?;
 
-        spin_up_project_and_dependencies(&ui, &workspace, &project.manifest().project().name)
-            .map_err(|e| eyre!(e))
-            .wrap_err("Failed to spin up project and dependencies")?;
+        spin_up_project_and_dependencies(
+            &ui,
+            &workspace,
+            &project.manifest().project().name,
+            !attach,
+        )
+        .map_err(|e| eyre!(e))
+        .wrap_err("Failed to spin up project and dependencies")?;
 
         Config::mutate_persisted(|config| {
             config.set_active_workspace(Some(workspace_name));
         })?;
 
+        if attach {
+            return run_attached(&ui, &project, down_on_exit);
+        }
+
         // We ignore the error here because we want to proceed even if the status check fails
         ui.new_line()?;
-        let _ = workspace_status(&ui, &workspace);
+        let _ = workspace_status(&ui, &workspace, StatusFilter::default(), false, cwd);
     }
 
     Ok(())
 }
 
-fn check_for_active_workspace(ui: &UserInterface, yes: bool) -> eyre::Result<()> {
-    let working_workspace = Workspace::working()
+fn check_for_active_workspace(ui: &UserInterface, yes: bool, cwd: &Path) -> eyre::Result<()> {
+    let working_workspace = Workspace::working(cwd)
         .map_err(|e| eyre!(e))
         .wrap_err("Failed to get working workspace")?;
 
@@ -77,6 +94,11 @@ fn check_for_active_workspace(ui: &UserInterface, yes: bool) -> eyre::Result<()>
     let choice = if yes {
         // When --yes is used, default to option 1 (deactivate current and start new)
         1
+    } else if !is_interactive() {
+        return Err(eyre!(
+            "Workspace {} is already active. Re-run with --yes to stop it and start the new one",
+            working_workspace.config().name
+        ));
     } else {
         Select::with_theme(&ColorfulTheme::default())
             .with_prompt(format!(
@@ -101,7 +123,7 @@ fn check_for_active_workspace(ui: &UserInterface, yes: bool) -> eyre::Result<()>
         1 => {
             ui.new_line()?;
 
-            let stopped = stop_workspace(ui, working_workspace, yes)
+            let stopped = stop_workspace(ui, working_workspace, yes, None, cwd)
                 .map_err(|e| eyre!(e))
                 .wrap_err("Failed to stop current workspace")?;
 
@@ -118,10 +140,56 @@ fn check_for_active_workspace(ui: &UserInterface, yes: bool) -> eyre::Result<()>
     Ok(())
 }
 
+/// Runs the project's Docker Compose services in the foreground until they exit or Ctrl-C
+/// stops them, optionally bringing them down afterwards.
+fn run_attached(ui: &UserInterface, project: &Project, down_on_exit: bool) -> eyre::Result<()> {
+    let project_name = &project.manifest().project().name;
+
+    if project.manifest().project().start_command.is_some() {
+        return Err(eyre!(
+            "Project {} uses a start_command and cannot be attached to",
+            project_name
+        ));
+    }
+
+    ui.writeln(
+        &ui.theme
+            .bold(&format!("Attaching to project {project_name}:")),
+    )?;
+
+    run_project_hooks(project, ProjectHook::BeforeStart)?;
+
+    let started = project
+        .docker_compose_up(UpOptions { detach: false })
+        .map_err(|e| eyre!(e))
+        .wrap_err_with(|| format!("Failed to run project {project_name} in the foreground"))?;
+
+    if !started {
+        return Err(eyre!(
+            "Project {} has no Docker Compose file to attach to",
+            project_name
+        ));
+    }
+
+    if down_on_exit {
+        ui.new_line()?;
+        project
+            .docker_compose_down(DownOptions::default())
+            .map_err(|e| eyre!(e))
+            .wrap_err_with(|| format!("Failed to spin down project {project_name}"))?;
+        ui.success_item(&format!("Spun down project {project_name}"), None)?;
+    }
+
+    Ok(())
+}
+
+/// Starts the project and its dependencies in dependency order. With `include_project`
+/// unset, only the dependencies are started.
 fn spin_up_project_and_dependencies(
     ui: &UserInterface,
     workspace: &Workspace,
     project_name: &Slug,
+    include_project: bool,
 ) -> eyre::Result<()> {
     let (dependency_graph, projects) = workspace
         .load_dependency_graph()
@@ -141,6 +209,9 @@ fn spin_up_project_and_dependencies(
     // Get all projects that need to be started (current project and its dependencies)
     let mut projects_to_start = BTreeSet::new();
     collect_dependencies(&dependency_graph, project_name, &mut projects_to_start);
+    if !include_project {
+        projects_to_start.remove(project_name);
+    }
 
     // Get startup order for all projects
     let startup_order = dependency_graph
@@ -148,6 +219,7 @@ fn spin_up_project_and_dependencies(
         .wrap_err("Failed to resolve project startup order")?;
 
     let mut applied_projects = Vec::new();
+    let mut failed_projects = Vec::new();
 
     // Start only the projects we need, in dependency order
     for project_id in startup_order {
@@ -156,28 +228,36 @@ fn spin_up_project_and_dependencies(
         {
             ui.writeln(&ui.theme.bold(&format!("Spinning up project {project_id}:")))?;
 
-            let applied = project
-                .docker_compose_up()
-                .map_err(|e| eyre!(e))
-                .wrap_err_with(|| {
-                    format!(
-                        "Failed to spin up project {} in workspace {}",
-                        project_id,
-                        workspace.config().name
-                    )
-                })?;
+            if let Err(err) = run_project_hooks(project, ProjectHook::BeforeStart) {
+                ui.error_item(&format!("{err:#}"), None)?;
+                failed_projects.push(project_id);
+                continue;
+            }
+
+            let applied = project.start().map_err(|e| eyre!(e)).wrap_err_with(|| {
+                format!(
+                    "Failed to spin up project {} in workspace {}",
+                    project_id,
+                    workspace.config().name
+                )
+            })?;
 
             if applied {
                 applied_projects.push(project);
+
+                if let Err(err) = run_project_hooks(project, ProjectHook::AfterStart) {
+                    ui.error_item(&format!("{err:#}"), None)?;
+                    failed_projects.push(project_id);
+                }
             }
         }
     }
 
-    if applied_projects.is_empty() {
+    if include_project && applied_projects.is_empty() && failed_projects.is_empty() {
         ui.warning_item("No projects to spin up", None)?;
     }
 
-    Ok(())
+    hook_failures_result(&failed_projects)
 }
 
 fn collect_dependencies(
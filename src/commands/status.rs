@@ -1,27 +1,87 @@
 use crate::{
-    project::Project,
+    cli::StatusCheck,
+    error::DeError,
+    project::{
+        Project,
+        compose::{DockerServiceStatus, get_docker_services},
+    },
     types::Slug,
-    utils::{theme::Theme, ui::UserInterface},
-    workspace::{Workspace, WorkspaceProject},
+    utils::{
+        fs::write_atomic,
+        get_project_dirs,
+        git::{fetch_with_retry, get_default_branch},
+        process::CommandExt,
+        table::Table,
+        theme::Theme,
+        ui::UserInterface,
+    },
+    workspace::{Workspace, WorkspaceProject, config::WorkspaceConfig},
 };
 use console::style;
 use eyre::{WrapErr, eyre};
-use std::path::Path;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Restricts which projects `de status` prints. Projects matching any enabled condition are shown.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StatusFilter {
+    /// Projects with uncommitted changes.
+    pub dirty: bool,
+    /// Projects with Docker Compose services that are not running.
+    pub downed: bool,
+    /// Projects whose branch is behind its remote.
+    pub behind: bool,
+    /// Projects that are not on the default branch.
+    pub off_branch: bool,
+}
+
+impl StatusFilter {
+    pub fn is_empty(&self) -> bool {
+        !(self.dirty || self.downed || self.behind || self.off_branch)
+    }
+
+    fn matches(&self, status: &ProjectStatus) -> bool {
+        if self.is_empty() {
+            return true;
+        }
+
+        (self.dirty && status.git.is_repo && status.git.dirty)
+            || (self.downed
+                && status
+                    .downed_services
+                    .as_ref()
+                    .is_some_and(|downed| !downed.is_empty()))
+            || (self.behind && status.git.is_repo && status.git.behind.unwrap_or(0) > 0)
+            || (self.off_branch && status.git.off_default_branch().is_some())
+    }
+}
 
 /// Show the status of the active workspace and its projects.
-pub fn status(workspace_name: Option<Slug>) -> eyre::Result<()> {
+///
+/// With `fetch`, the remotes of all projects are fetched first, so ahead/behind counts are current.
+/// Unless `no_cache` or `fetch` is set, git results gathered within the last
+/// [`GIT_STATUS_TTL`] are reused. Fails with [`DeError::StatusCheckFailed`] if a project matches
+/// one of `fail_on`.
+pub fn status(
+    workspace_name: Option<Slug>,
+    filter: StatusFilter,
+    fetch: bool,
+    no_cache: bool,
+    fail_on: &[StatusCheck],
+    cwd: &Path,
+) -> eyre::Result<()> {
     tracing::info!("Starting status command");
     let ui = UserInterface::new();
 
     let workspace = if let Some(workspace_name) = workspace_name {
         tracing::info!("Loading workspace '{}'", workspace_name);
-        Workspace::load_from_name(&workspace_name)
-            .map_err(|e| eyre!(e))
-            .wrap_err_with(|| format!("Failed to load workspace {workspace_name}"))?
-            .ok_or_else(|| eyre!("Workspace {} not found", workspace_name))?
+        Workspace::require(&workspace_name, cwd)?
     } else {
-        match Workspace::active()? {
+        match Workspace::active(cwd)? {
             Some(ws) => ws,
             None => {
                 ui.warning_item("No active workspace found.", None)?;
@@ -30,15 +90,70 @@ pub fn status(workspace_name: Option<Slug>) -> eyre::Result<()> {
         }
     };
 
-    workspace_status(&ui, &workspace)?;
+    if fetch {
+        let loading_bar = ui.loading_bar("Fetching remotes...")?;
+        let failures = fetch_projects(&workspace);
+        loading_bar.finish_and_clear();
+
+        for (project_name, err) in &failures {
+            ui.warning_item(
+                &format!("{project_name}: {err}"),
+                Some("Ahead/behind counts for this project may be out of date"),
+            )?;
+        }
+        if !failures.is_empty() {
+            ui.new_line()?;
+        }
+    }
+
+    let workspace_status = workspace_status(&ui, &workspace, filter, !(no_cache || fetch), cwd)?;
 
     tracing::info!("Finished status command");
+
+    let failures = workspace_status.failed_checks(fail_on);
+    if !failures.is_empty() {
+        return Err(eyre!(DeError::StatusCheckFailed(failures.join("; "))));
+    }
+
     Ok(())
 }
 
+/// Fetches all git-enabled projects concurrently. Returns the projects whose fetch failed.
+fn fetch_projects(workspace: &Workspace) -> Vec<(Slug, eyre::Report)> {
+    std::thread::scope(|scope| {
+        let fetches = workspace
+            .enabled_projects()
+            .filter(|(_, ws_project)| {
+                Project::from_dir(&ws_project.dir)
+                    .is_ok_and(|project| project.manifest().git.clone().unwrap_or_default().enabled)
+            })
+            .map(|(project_name, ws_project)| {
+                let fetch = scope
+                    .spawn(|| fetch_with_retry(&["--quiet"], &ws_project.dir, false, |_, _| {}));
+                (project_name, fetch)
+            })
+            .collect::<Vec<_>>();
+
+        fetches
+            .into_iter()
+            .filter_map(|(project_name, fetch)| {
+                let result = fetch
+                    .join()
+                    .unwrap_or_else(|_| Err(eyre!("git fetch panicked")));
+                result.err().map(|err| (project_name.clone(), err))
+            })
+            .collect()
+    })
+}
+
+/// Prints the status of the enabled projects of the workspace. With `use_cache`, recently
+/// gathered git results of unchanged repositories are reused.
 pub fn workspace_status(
     ui: &UserInterface,
     workspace: &Workspace,
+    filter: StatusFilter,
+    use_cache: bool,
+    cwd: &Path,
 ) -> eyre::Result<WorkspaceStatus> {
     let ws_config = workspace.config();
     tracing::info!("Loaded workspace '{}'", ws_config.name);
@@ -47,30 +162,41 @@ pub fn workspace_status(
     ui.heading("Projects:")?;
     let loading_bar = ui.loading_bar("Gathering project statuses...")?;
 
-    let current_project = Project::current()
+    let current_project = Project::from_dir_recursive(cwd)
         .map_err(|e| eyre!(e))
         .wrap_err("Failed to load current project")?;
 
-    let statuses: Vec<ProjectStatus> = ws_config
-        .projects
-        .iter()
+    let statuses: Vec<ProjectStatus> = workspace
+        .enabled_projects()
         .map(|(project_name, ws_project)| {
             tracing::info!("Processing project '{}'", project_name);
             ProjectStatus::gather(
-                &ws_config.name,
+                ws_config,
                 project_name,
                 ws_project,
                 current_project.as_ref(),
+                use_cache,
             )
         })
         .collect();
 
     loading_bar.finish_and_clear();
 
-    for status in &statuses {
+    let mut shown = 0;
+    for status in statuses.iter().filter(|s| filter.matches(s)) {
         status.print(ui)?;
+        shown += 1;
+    }
+
+    if shown == 0 && !filter.is_empty() {
+        ui.info_item("No projects match the given filters.")?;
+    }
+
+    for project_name in workspace.disabled_project_ids() {
+        ui.writeln(&ui.theme.dim(&format!("{project_name} [disabled]")))?;
     }
 
+    // The summary always covers the whole workspace, regardless of the filter.
     print_status_summary(ui, &statuses)?;
 
     Ok(WorkspaceStatus { statuses })
@@ -86,6 +212,37 @@ impl WorkspaceStatus {
             .iter()
             .any(|s| s.git.is_repo && (s.git.dirty || s.git.ahead.unwrap_or(0) > 0))
     }
+
+    /// Describes each of the `checks` some projects fail, e.g. "dirty: api, web".
+    pub fn failed_checks(&self, checks: &[StatusCheck]) -> Vec<String> {
+        let mut failures = Vec::new();
+
+        for check in checks.iter().unique() {
+            let (label, failing): (_, fn(&ProjectStatus) -> bool) = match check {
+                StatusCheck::Dirty => ("dirty", |s| s.git.is_repo && s.git.dirty),
+                StatusCheck::Unpushed => ("unpushed", |s| {
+                    s.git.is_repo && s.git.ahead.unwrap_or(0) > 0
+                }),
+                StatusCheck::Downed => ("downed", |s| {
+                    s.downed_services
+                        .as_ref()
+                        .is_some_and(|downed| !downed.is_empty())
+                }),
+            };
+
+            let projects = self
+                .statuses
+                .iter()
+                .filter(|s| failing(s))
+                .map(|s| s.slug.as_str())
+                .collect::<Vec<_>>();
+            if !projects.is_empty() {
+                failures.push(format!("{label}: {}", projects.join(", ")));
+            }
+        }
+
+        failures
+    }
 }
 
 /// Holds all dynamic status info for a project in the workspace.
@@ -98,20 +255,14 @@ struct ProjectStatus {
     git: GitStatus,
 }
 
-/// Status for a single Docker Compose service.
-struct DockerServiceStatus {
-    name: String,
-    status: String,
-    ports: Option<String>,
-}
-
 impl ProjectStatus {
     /// Gather dynamic status for a project, including Git and Docker Compose state.
     fn gather(
-        workspace_name: &Slug,
+        ws_config: &WorkspaceConfig,
         project_name: &Slug,
         ws_project: &WorkspaceProject,
         current_project: Option<&Project>,
+        use_cache: bool,
     ) -> Self {
         let dir = &ws_project.dir;
         let present = dir.exists();
@@ -137,21 +288,25 @@ impl ProjectStatus {
                 tracing::debug!("Loaded project manifest for '{}'", project_name);
 
                 let current = current_project.as_ref().is_some_and(|p| {
-                    &p.manifest().project().workspace == workspace_name
+                    p.manifest().project().workspace == ws_config.name
                         && &p.manifest().project().name == project_name
                 });
 
                 let dc_path = project.docker_compose_path().unwrap_or(None);
                 let docker_services = dc_path.as_ref().and_then(|compose_path| {
                     tracing::debug!("Checking Docker Compose services for '{}'", project_name);
-                    get_docker_services(compose_path)
+                    get_docker_services(compose_path, project.env())
                 });
-                let downed_services = dc_path
+                let downed_services = docker_services
                     .as_ref()
-                    .and_then(|compose_path| get_downed_services(compose_path));
+                    .map(|containers| get_downed_services(&project, containers));
 
                 let git = if project.manifest().git.clone().unwrap_or_default().enabled {
-                    GitStatus::gather(dir)
+                    GitStatus::gather_cached(
+                        dir,
+                        ws_config.default_branch_for(ws_project),
+                        use_cache,
+                    )
                 } else {
                     GitStatus::disabled()
                 };
@@ -181,7 +336,7 @@ impl ProjectStatus {
 
     /// Print the status for this project.
     fn print(&self, ui: &UserInterface) -> eyre::Result<()> {
-        let theme = Theme::new();
+        let theme = &ui.theme;
         ui.writeln(&format!(
             "{} [{}] {}",
             style(&self.slug).bold(),
@@ -198,7 +353,7 @@ impl ProjectStatus {
         ))?;
 
         ui.indented(|ui| {
-            ui.writeln(&format!("Git: {}", self.git.format(ui)))?;
+            ui.writeln(&format!("Git: {}", self.git.format(&ui.theme)))?;
 
             let Some(docker_services) = self.docker_services.as_ref() else {
                 return Ok(());
@@ -210,25 +365,33 @@ impl ProjectStatus {
 
             ui.writeln("Docker Compose services:")?;
             ui.indented(|ui| {
+                let mut table = Table::with_header(["Service", "Status", "Ports"]);
                 for svc in docker_services {
-                    let status_style = if svc.status.contains("Up") {
-                        style(&svc.status).fg(theme.success_color)
+                    let mut status_style = if svc.running {
+                        theme.success(&svc.status)
                     } else {
-                        style(&svc.status).fg(theme.error_color)
+                        theme.error(&svc.status)
                     };
 
-                    if let Some(ref ports) = svc.ports {
-                        ui.writeln(&format!(
-                            "{}: {} {}",
-                            style(&svc.name).bold(),
-                            status_style,
-                            theme.dim(ports),
-                        ))?;
-                    } else {
-                        ui.writeln(&format!("{}: {}", style(&svc.name).bold(), status_style))?;
+                    if let Some(ref health) = svc.health {
+                        let health_label = format!("({health})");
+                        let health_style = match health.as_str() {
+                            "healthy" => theme.success(&health_label),
+                            "unhealthy" => theme.error(&health_label),
+                            _ => theme.warn(&health_label),
+                        };
+                        status_style = format!("{status_style} {health_style}");
                     }
+
+                    let ports = svc.ports.as_deref().map(|ports| theme.dim(ports));
+                    table.add_row(
+                        [theme.bold(&svc.name), status_style]
+                            .into_iter()
+                            .chain(ports),
+                    );
                 }
 
+                table.print(ui)?;
                 Ok(())
             })?;
 
@@ -239,11 +402,18 @@ impl ProjectStatus {
     }
 }
 
+/// How long `de status` reuses the git status of a repository whose `HEAD` and index are
+/// unchanged. Edits to files that aren't staged show up once it expires.
+const GIT_STATUS_TTL: Duration = Duration::from_secs(2);
+
 /// Git status for a project.
-struct GitStatus {
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct GitStatus {
     is_repo: bool,
     git_disabled: bool,
     branch: Option<String>,
+    /// The workspace's default branch, or else the default branch of the project's remote.
+    default_branch: Option<String>,
     ahead: Option<u32>,
     behind: Option<u32>,
     dirty: bool,
@@ -251,7 +421,7 @@ struct GitStatus {
 
 /// Print a concise, actionable summary of project and service status.
 fn print_status_summary(ui: &UserInterface, statuses: &[ProjectStatus]) -> eyre::Result<()> {
-    let theme = Theme::new();
+    let theme = &ui.theme;
     let dirty = statuses
         .iter()
         .filter(|s| s.git.is_repo && s.git.dirty)
@@ -264,18 +434,33 @@ fn print_status_summary(ui: &UserInterface, statuses: &[ProjectStatus]) -> eyre:
         .iter()
         .filter(|s| s.git.is_repo && s.git.behind.unwrap_or(0) > 0)
         .count();
+    let off_branch = statuses
+        .iter()
+        .filter_map(|s| {
+            s.git
+                .off_default_branch()
+                .map(|(branch, _)| format!("{} on {branch}", s.slug))
+        })
+        .collect::<Vec<_>>();
     let downed_services_total: usize = statuses
         .iter()
         .filter_map(|s| s.downed_services.as_ref())
         .map(|downed| downed.len())
         .sum();
+    let unhealthy_services_total = statuses
+        .iter()
+        .filter_map(|s| s.docker_services.as_ref())
+        .flatten()
+        .filter(|svc| svc.is_unhealthy())
+        .count();
 
     tracing::info!(
-        "Summary: dirty={}, ahead={}, behind={}, downed_services={}",
+        "Summary: dirty={}, ahead={}, behind={}, downed_services={}, unhealthy_services={}",
         dirty,
         ahead,
         behind,
-        downed_services_total
+        downed_services_total,
+        unhealthy_services_total
     );
 
     println!();
@@ -308,6 +493,14 @@ fn print_status_summary(ui: &UserInterface, statuses: &[ProjectStatus]) -> eyre:
         ))?;
         any = true;
     }
+    if !off_branch.is_empty() {
+        ui.info_item(&format!(
+            "Off the default branch: {} ({})",
+            off_branch.len(),
+            off_branch.join(", ")
+        ))?;
+        any = true;
+    }
     if downed_services_total > 0 {
         ui.error_item(
             &format!(
@@ -319,6 +512,17 @@ fn print_status_summary(ui: &UserInterface, statuses: &[ProjectStatus]) -> eyre:
         )?;
         any = true;
     }
+    if unhealthy_services_total > 0 {
+        ui.warning_item(
+            &format!(
+                "Unhealthy services: {} (run: {})",
+                unhealthy_services_total,
+                style("docker-compose logs").fg(theme.accent_color)
+            ),
+            None,
+        )?;
+        any = true;
+    }
 
     if !any {
         ui.success_item(
@@ -338,24 +542,26 @@ impl GitStatus {
             is_repo: false,
             git_disabled: false,
             branch: None,
+            default_branch: None,
             ahead: None,
             behind: None,
             dirty: false,
         }
     }
 
-    fn disabled() -> Self {
+    pub(crate) fn disabled() -> Self {
         GitStatus {
             is_repo: false,
             git_disabled: true,
             branch: None,
+            default_branch: None,
             ahead: None,
             behind: None,
             dirty: false,
         }
     }
 
-    fn gather(dir: &Path) -> Self {
+    pub(crate) fn gather(dir: &Path, default_branch: Option<&str>) -> Self {
         let git_dir = dir.join(".git");
         if !(git_dir.exists() && git_dir.is_dir()) {
             return GitStatus::not_repo();
@@ -367,7 +573,7 @@ impl GitStatus {
             .arg("rev-parse")
             .arg("--abbrev-ref")
             .arg("HEAD")
-            .output()
+            .logged_output()
             .ok()
             .and_then(|o| {
                 if o.status.success() {
@@ -382,7 +588,7 @@ impl GitStatus {
             .arg(dir)
             .arg("status")
             .arg("--porcelain")
-            .output()
+            .logged_output()
             .ok()
             .map(|o| !o.stdout.is_empty())
             .unwrap_or(false);
@@ -392,7 +598,7 @@ impl GitStatus {
             .arg(dir)
             .arg("status")
             .arg("-sb")
-            .output()
+            .logged_output()
             .ok()
             .and_then(|o| {
                 if o.status.success() {
@@ -431,19 +637,58 @@ impl GitStatus {
             }
         }
 
+        let default_branch = default_branch
+            .map(str::to_string)
+            .or_else(|| get_default_branch(dir).ok());
+
         GitStatus {
             is_repo: true,
             git_disabled: false,
             branch,
+            default_branch,
             ahead,
             behind,
             dirty,
         }
     }
 
-    fn format(&self, ui: &UserInterface) -> String {
-        let theme = &ui.theme;
+    /// Like [`GitStatus::gather`], but with `use_cache`, returns the status cached for `dir` if
+    /// it was gathered less than [`GIT_STATUS_TTL`] ago and the repository hasn't changed since.
+    /// Freshly gathered statuses are always cached.
+    fn gather_cached(dir: &Path, default_branch: Option<&str>, use_cache: bool) -> Self {
+        let cache = git_fingerprint(dir, default_branch)
+            .and_then(|fingerprint| Some((git_status_cache_path(dir).ok()?, fingerprint)));
+
+        if use_cache
+            && let Some((path, fingerprint)) = &cache
+            && let Some(status) = read_cached_git_status(path, fingerprint)
+        {
+            tracing::debug!("Using cached git status for {}", dir.display());
+            return status;
+        }
+
+        let status = Self::gather(dir, default_branch);
+
+        if let Some((path, fingerprint)) = cache
+            && let Err(e) = write_cached_git_status(&path, fingerprint, &status)
+        {
+            tracing::debug!("Failed to cache git status for {}: {e}", dir.display());
+        }
+
+        status
+    }
+
+    /// The current and the default branch, if the project is on another branch than the default.
+    fn off_default_branch(&self) -> Option<(&str, &str)> {
+        match (&self.branch, &self.default_branch) {
+            (Some(branch), Some(default_branch)) if self.is_repo && branch != default_branch => {
+                Some((branch, default_branch))
+            }
+            _ => None,
+        }
+    }
 
+    pub(crate) fn format(&self, theme: &Theme) -> String {
         if self.git_disabled {
             return theme.dim("git disabled");
         }
@@ -455,11 +700,15 @@ impl GitStatus {
         let mut out = String::new();
 
         if let Some(branch) = &self.branch {
-            out.push_str(&ui.theme.highlight(branch));
+            out.push_str(&theme.highlight(branch));
         } else {
             out.push('?');
         }
 
+        if let Some((_, default_branch)) = self.off_default_branch() {
+            out.push_str(&theme.dim(&format!(" (default: {default_branch})")));
+        }
+
         if let Some(a) = self.ahead {
             out.push_str(&format!(" (ahead {a})"));
         }
@@ -478,90 +727,136 @@ impl GitStatus {
     }
 }
 
-/// Get the status of all Docker Compose services for a project.
-/// Returns a vector of DockerServiceStatus, or None if docker-compose fails.
-fn get_docker_services(compose_path: &Path) -> Option<Vec<DockerServiceStatus>> {
-    use std::process::Command;
-    tracing::debug!("Running docker-compose ps -a for {:?}", compose_path);
-    let output = Command::new("docker-compose")
-        .arg("-f")
-        .arg(compose_path)
-        .arg("ps")
-        .arg("-a")
-        .output()
-        .ok()?;
-
-    if !output.status.success() {
+/// A [`GitStatus`] as cached in the cache directory.
+#[derive(Serialize, Deserialize)]
+struct CachedGitStatus {
+    /// See [`git_fingerprint`].
+    fingerprint: String,
+    /// When the status was gathered, in milliseconds since the Unix epoch.
+    gathered_at: u128,
+    status: GitStatus,
+}
+
+/// Identifies the state of the repository in `dir` a git status was gathered in: the
+/// modification times of `.git/HEAD` and `.git/index`, which change with commits, checkouts and
+/// staging, and the default branch it was compared against. `None` if `dir` is no repository.
+fn git_fingerprint(dir: &Path, default_branch: Option<&str>) -> Option<String> {
+    let git_dir = dir.join(".git");
+    if !git_dir.is_dir() {
         return None;
     }
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let mut services = Vec::new();
-
-    let mut lines = stdout.lines();
-    let header_line = lines.next().unwrap_or("");
-    let header_cols: Vec<&str> = header_line.split_whitespace().collect();
-    let service_idx = header_cols.iter().position(|&h| h == "SERVICE");
-    let status_idx = header_cols.iter().position(|&h| h == "STATUS");
-    let ports_idx = header_cols.iter().position(|&h| h == "PORTS");
-
-    if let (Some(service_idx), Some(status_idx)) = (service_idx, status_idx) {
-        for line in lines {
-            // Split line into columns by whitespace, but preserve spaces in STATUS and PORTS
-            // We'll do this by splitting the line into fields based on header column positions
-            let mut start_indices = Vec::new();
-            let mut idx = 0;
-            for col in &header_cols {
-                // Find the start index of each column in the header line
-                if let Some(pos) = header_line[idx..].find(col) {
-                    start_indices.push(idx + pos);
-                    idx += pos + col.len();
-                }
-            }
-            // Now, for each column, extract the substring from the line
-            let mut fields = Vec::new();
-            for i in 0..start_indices.len() {
-                let start = start_indices[i];
-                let end = if i + 1 < start_indices.len() {
-                    start_indices[i + 1]
-                } else {
-                    line.len()
-                };
-                let field = line.get(start..end).unwrap_or("").trim();
-                fields.push(field);
-            }
-            // Now extract by header index
-            if fields.len() <= status_idx {
-                continue;
-            }
-            let name = fields[service_idx].to_string();
-            let status = fields[status_idx].to_string();
-            let ports = ports_idx
-                .and_then(|idx| fields.get(idx).map(|s| s.to_string()))
-                .filter(|s| !s.is_empty());
-            tracing::debug!(
-                "Service '{}' status: '{}', ports: {:?}",
-                name,
-                status,
-                ports
-            );
-            services.push(DockerServiceStatus {
-                name,
-                status,
-                ports,
-            });
-        }
+    let mtime = |name: &str| {
+        std::fs::metadata(git_dir.join(name))
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |modified| modified.as_nanos())
+    };
+
+    Some(format!(
+        "{}:{}:{}",
+        mtime("HEAD"),
+        mtime("index"),
+        default_branch.unwrap_or_default()
+    ))
+}
+
+/// Where the git status of the project in `dir` is cached.
+fn git_status_cache_path(dir: &Path) -> eyre::Result<PathBuf> {
+    let key = format!("{:x}", Sha256::digest(dir.to_string_lossy().as_bytes()));
+
+    Ok(get_project_dirs()?
+        .cache_dir()
+        .join("status")
+        .join(format!("{}.json", &key[..16])))
+}
+
+fn read_cached_git_status(path: &Path, fingerprint: &str) -> Option<GitStatus> {
+    let contents = std::fs::read_to_string(path).ok()?;
+    let cached = serde_json::from_str::<CachedGitStatus>(&contents).ok()?;
+
+    let age = now_millis().checked_sub(cached.gathered_at)?;
+    (cached.fingerprint == fingerprint && age < GIT_STATUS_TTL.as_millis()).then_some(cached.status)
+}
+
+fn write_cached_git_status(
+    path: &Path,
+    fingerprint: String,
+    status: &GitStatus,
+) -> eyre::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| eyre!(e))
+            .wrap_err_with(|| format!("Failed to create directory {}", parent.display()))?;
     }
 
-    Some(services)
+    let cached = CachedGitStatus {
+        fingerprint,
+        gathered_at: now_millis(),
+        status: status.clone(),
+    };
+    let contents = serde_json::to_string(&cached).map_err(|e| eyre!(e))?;
+    write_atomic(path, contents)
+        .map_err(|e| eyre!(e))
+        .wrap_err_with(|| format!("Failed to write {}", path.display()))
+}
+
+fn now_millis() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.as_millis())
 }
 
-fn get_downed_services(compose_path: &Path) -> Option<Vec<String>> {
-    get_docker_services(compose_path).map(|services| {
-        services
+/// The services of the project without a running container. Services defined in the Compose
+/// file count even without any container, as after `de stop`; if the services can't be listed,
+/// only those with a stopped container do.
+fn get_downed_services(project: &Project, containers: &[DockerServiceStatus]) -> Vec<String> {
+    let is_running = |service: &str| {
+        containers
+            .iter()
+            .any(|svc| svc.name == service && svc.running)
+    };
+
+    match project.docker_compose_services() {
+        Ok(services) if !services.is_empty() => services
             .into_iter()
-            .filter(|svc| !svc.status.contains("Up"))
-            .map(|svc| svc.name)
-            .collect()
-    })
+            .filter(|service| !is_running(service))
+            .collect(),
+        _ => containers
+            .iter()
+            .filter(|svc| !svc.running)
+            .map(|svc| svc.name.clone())
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cached_git_status_expires_when_the_repo_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join(".git")).unwrap();
+        std::fs::write(dir.path().join(".git/HEAD"), "ref: refs/heads/main\n").unwrap();
+        let cache = dir.path().join("cache/status.json");
+
+        let fingerprint = git_fingerprint(dir.path(), Some("main")).unwrap();
+        write_cached_git_status(&cache, fingerprint.clone(), &GitStatus::not_repo()).unwrap();
+        assert!(read_cached_git_status(&cache, &fingerprint).is_some());
+        assert_ne!(git_fingerprint(dir.path(), None).unwrap(), fingerprint);
+
+        let head = std::fs::File::options()
+            .write(true)
+            .open(dir.path().join(".git/HEAD"))
+            .unwrap();
+        head.set_modified(SystemTime::now() + Duration::from_secs(5))
+            .unwrap();
+        let changed = git_fingerprint(dir.path(), Some("main")).unwrap();
+        assert_ne!(changed, fingerprint);
+        assert!(read_cached_git_status(&cache, &changed).is_none());
+
+        assert!(git_fingerprint(&dir.path().join("cache"), None).is_none());
+    }
 }
@@ -1,40 +1,65 @@
 use crate::{
-    commands::status::workspace_status,
+    commands::status::{StatusFilter, workspace_status},
     config::Config,
+    error::DeError,
     types::Slug,
-    utils::ui::UserInterface,
+    utils::{is_interactive, process::CommandExt, ui::UserInterface},
     workspace::{Workspace, spin_down_workspace},
 };
 use dialoguer::Confirm;
 use eyre::{Context, eyre};
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Label Docker Compose puts on containers with the directory of the compose file.
+const COMPOSE_WORKING_DIR_LABEL: &str = "com.docker.compose.project.working_dir";
+
+pub fn stop(
+    workspace_name: Option<Slug>,
+    all: bool,
+    yes: bool,
+    timeout: Option<u64>,
+    cwd: &Path,
+) -> eyre::Result<()> {
+    if all {
+        return stop_all_workspaces(yes, timeout, cwd);
+    }
 
-pub fn stop(workspace_name: Option<Slug>, yes: bool) -> eyre::Result<()> {
     let workspace = if let Some(workspace_name) = workspace_name {
-        Workspace::load_from_name(&workspace_name)
-            .map_err(|e| eyre!(e))
-            .wrap_err("Failed to load workspace")?
-            .ok_or_else(|| eyre!("Workspace {} not found", workspace_name))?
+        Workspace::require(&workspace_name, cwd)?
     } else {
-        Workspace::active()
-            .map_err(|e| eyre!(e))
-            .wrap_err("Failed to get current workspace")?
-            .ok_or_else(|| eyre!("No workspace is currently active"))?
+        Workspace::require_active(cwd)?
     };
 
     let ui = UserInterface::new();
-    stop_workspace(&ui, workspace, yes)?;
+    stop_workspace(&ui, workspace, yes, timeout, cwd)?;
 
     Ok(())
 }
 
-pub fn stop_workspace(ui: &UserInterface, workspace: Workspace, yes: bool) -> eyre::Result<bool> {
-    let workspace_status = workspace_status(ui, &workspace)
+pub fn stop_workspace(
+    ui: &UserInterface,
+    workspace: Workspace,
+    yes: bool,
+    timeout: Option<u64>,
+    cwd: &Path,
+) -> eyre::Result<bool> {
+    let workspace_status = workspace_status(ui, &workspace, StatusFilter::default(), false, cwd)
         .map_err(|e| eyre!(e))
         .wrap_err("Failed to get workspace status")?;
 
     ui.new_line()?;
 
     if workspace_status.has_uncommited_or_unpushed_changes() && !yes {
+        if !is_interactive() {
+            return Err(eyre!(
+                "Uncommitted or unpushed changes detected. Re-run with --yes to stop anyway"
+            ));
+        }
+
         let prompt = Confirm::new()
             .with_prompt("Uncommitted or unpushed changes detected. Stop anyway?")
             .default(false)
@@ -48,7 +73,7 @@ pub fn stop_workspace(ui: &UserInterface, workspace: Workspace, yes: bool) -> ey
         }
     }
 
-    spin_down_workspace(&workspace)
+    spin_down_workspace(ui, &workspace, timeout)
         .map_err(|e| eyre!(e))
         .wrap_err("Failed to spin down workspace")?;
 
@@ -59,6 +84,140 @@ pub fn stop_workspace(ui: &UserInterface, workspace: Workspace, yes: bool) -> ey
     Ok(true)
 }
 
+/// Stops every workspace with running services, reporting the result for each one.
+fn stop_all_workspaces(yes: bool, timeout: Option<u64>, cwd: &Path) -> eyre::Result<()> {
+    let ui = UserInterface::new();
+
+    let running_dirs = running_compose_dirs()?;
+
+    let listing = Workspace::all()?;
+
+    for skipped in &listing.skipped {
+        ui.warning_item(
+            &format!(
+                "Skipping invalid workspace file: {} ({})",
+                skipped.path.display(),
+                skipped.reason
+            ),
+            None,
+        )?;
+    }
+
+    let workspaces = listing
+        .workspaces
+        .into_iter()
+        .filter(|workspace| has_running_services(workspace, &running_dirs))
+        .collect::<Vec<_>>();
+
+    if workspaces.is_empty() {
+        ui.info_item("No workspaces with running services")?;
+        return Ok(());
+    }
+
+    if workspaces.len() > 1 && !yes {
+        let names = workspaces
+            .iter()
+            .map(|workspace| workspace.config().name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        if !is_interactive() {
+            return Err(eyre!(
+                "Re-run with --yes to stop {} workspaces ({names})",
+                workspaces.len()
+            ));
+        }
+
+        let confirmed = Confirm::new()
+            .with_prompt(format!("Stop {} workspaces ({names})?", workspaces.len()))
+            .default(false)
+            .interact()
+            .map_err(|e| eyre!(e))
+            .wrap_err("Failed to prompt for confirmation")?;
+
+        if !confirmed {
+            println!("Aborting stop operation.");
+            return Ok(());
+        }
+    }
+
+    let mut results = Vec::new();
+    for workspace in workspaces {
+        let name = workspace.config().name.clone();
+
+        ui.heading(name.as_str())?;
+        let result = stop_workspace(&ui, workspace, yes, timeout, cwd);
+        ui.new_line()?;
+
+        results.push((name, result));
+    }
+
+    ui.heading("Summary")?;
+
+    let mut failed = 0;
+    for (name, result) in results {
+        match result {
+            Ok(true) => ui.success_item(&format!("Stopped {name}"), None)?,
+            Ok(false) => ui.warning_item(&format!("Skipped {name}"), None)?,
+            Err(err) => {
+                failed += 1;
+                ui.error_item(&format!("Failed to stop {name}: {err:#}"), None)?;
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(eyre!("Failed to stop {} workspaces", failed));
+    }
+
+    Ok(())
+}
+
+/// Returns the compose directories of all running containers, using a single `docker ps` call.
+fn running_compose_dirs() -> eyre::Result<HashSet<PathBuf>> {
+    let output = Command::new("docker")
+        .arg("ps")
+        .arg("--filter")
+        .arg(format!("label={COMPOSE_WORKING_DIR_LABEL}"))
+        .arg("--format")
+        .arg(format!("{{{{.Label \"{COMPOSE_WORKING_DIR_LABEL}\"}}}}"))
+        .logged_output()
+        .map_err(|e| eyre!(DeError::DockerUnavailable(e)))
+        .wrap_err("Failed to list running containers")?;
+
+    if !output.status.success() {
+        return Err(eyre!(
+            "docker ps failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(PathBuf::from)
+        .collect())
+}
+
+/// Whether any project in the workspace may have running services. Projects with a
+/// `stop_command` can't be checked through Docker, so they always count as running.
+fn has_running_services(workspace: &Workspace, running_dirs: &HashSet<PathBuf>) -> bool {
+    let Ok((_, projects)) = workspace.load_dependency_graph() else {
+        return false;
+    };
+
+    projects.iter().any(|project| {
+        if project.manifest().project().stop_command.is_some() {
+            return true;
+        }
+
+        matches!(
+            project.docker_compose_path(),
+            Ok(Some(path)) if path.parent().is_some_and(|dir| running_dirs.contains(dir))
+        )
+    })
+}
+
 fn deactivate_workspace_if_active(workspace_name: Slug) -> eyre::Result<()> {
     let mut config = Config::load()
         .map_err(|e| eyre!(e))
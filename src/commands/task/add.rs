@@ -1,34 +1,73 @@
+use std::path::Path;
+
 use eyre::{Context, eyre};
 
 use crate::{
-    project::{RawTask, Task},
+    project::{Project, RawTask, Task},
     types::Slug,
     utils::{get_project_for_cli, get_workspace_for_cli},
+    workspace::Workspace,
 };
 
+/// Where `de task add` puts the task.
+#[derive(Debug, PartialEq, Eq)]
+enum TaskTarget {
+    Workspace,
+    Project,
+}
+
+/// Checks the combination of options. With `--workspace` alone the task goes to the workspace
+/// configuration, which has no project to run a Docker Compose service of, nor room for a
+/// description or the shell flag. Adding `--project` adds it to that project of the workspace.
+fn task_target(
+    project_name: Option<&Slug>,
+    workspace_name: Option<&Option<Slug>>,
+    service: Option<&str>,
+    description: Option<&str>,
+    shell: bool,
+) -> eyre::Result<TaskTarget> {
+    if workspace_name.is_none() || project_name.is_some() {
+        return Ok(TaskTarget::Project);
+    }
+
+    if service.is_some() {
+        return Err(eyre!(
+            "Workspace tasks can't run in a Docker Compose service, as they belong to no project. Pass --project to add the task to a project of the workspace."
+        ));
+    }
+
+    if description.is_some() || shell {
+        return Err(eyre!(
+            "Workspace tasks do not support a description or the shell flag."
+        ));
+    }
+
+    Ok(TaskTarget::Workspace)
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn add(
     task_name: Slug,
     command: String,
     service: Option<String>,
+    description: Option<String>,
+    shell: bool,
     project_name: Option<Slug>,
     workspace_name: Option<Option<Slug>>,
+    cwd: &Path,
 ) -> eyre::Result<()> {
-    if workspace_name.is_some() {
-        let mut workspace = get_workspace_for_cli(workspace_name)?;
-
-        if service.is_some() {
-            return Err(eyre!(
-                "Workspace tasks do not support specifying a service."
-            ));
-        }
-
-        workspace
-            .config_mut()
-            .tasks
-            .insert(task_name.clone(), command);
-        workspace
-            .save()
-            .wrap_err("Failed to save workspace configuration")?;
+    let target = task_target(
+        project_name.as_ref(),
+        workspace_name.as_ref(),
+        service.as_deref(),
+        description.as_deref(),
+        shell,
+    )?;
+
+    if target == TaskTarget::Workspace {
+        let mut workspace = get_workspace_for_cli(workspace_name, cwd)?;
+
+        insert_workspace_task(&mut workspace, task_name.clone(), command)?;
 
         println!(
             "Task '{}' added to workspace '{}'.",
@@ -36,23 +75,36 @@ pub fn add(
             workspace.config().name
         );
     } else {
-        let mut project = get_project_for_cli(project_name, workspace_name)?;
+        let mut project = get_project_for_cli(project_name, workspace_name, cwd)?;
 
         let task = if let Some(service) = service {
-            Task::Compose { service, command }
+            Task::Compose {
+                service,
+                command,
+                description,
+                shell,
+                inputs: Vec::new(),
+                outputs: Vec::new(),
+                retries: 0,
+                timeout: None,
+                depends_on: Vec::new(),
+            }
+        } else if description.is_some() || shell {
+            Task::Raw(RawTask::Complex {
+                command,
+                description,
+                shell,
+                inputs: Vec::new(),
+                outputs: Vec::new(),
+                retries: 0,
+                timeout: None,
+                depends_on: Vec::new(),
+            })
         } else {
             Task::Raw(RawTask::Flat(command))
         };
 
-        project
-            .manifest_mut()
-            .tasks
-            .get_or_insert_with(Default::default)
-            .insert(task_name.clone(), task);
-        project
-            .manifest()
-            .save(project.manifest_path())
-            .wrap_err("Failed to save project configuration")?;
+        insert_project_task(&mut project, task_name.clone(), task)?;
 
         println!(
             "Task '{}' added to project '{}'.",
@@ -63,3 +115,62 @@ pub fn add(
 
     Ok(())
 }
+
+/// Adds (or replaces) a workspace task and saves the workspace configuration.
+pub(super) fn insert_workspace_task(
+    workspace: &mut Workspace,
+    task_name: Slug,
+    command: String,
+) -> eyre::Result<()> {
+    workspace
+        .edit(|workspace| {
+            workspace.config_mut().tasks.insert(task_name, command);
+            Ok(())
+        })
+        .wrap_err("Failed to save workspace configuration")
+}
+
+/// Adds (or replaces) a project task and saves the project manifest.
+pub(super) fn insert_project_task(
+    project: &mut Project,
+    task_name: Slug,
+    task: Task,
+) -> eyre::Result<()> {
+    project
+        .edit_manifest(|manifest| {
+            manifest
+                .tasks
+                .get_or_insert_with(Default::default)
+                .insert(task_name, task)
+        })
+        .wrap_err("Failed to save project configuration")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_task_target_checks_workspace_combinations() {
+        let project = Slug::from_str("api").unwrap();
+        let workspace = Some(None);
+
+        assert_eq!(
+            task_target(None, None, Some("app"), None, false).unwrap(),
+            TaskTarget::Project
+        );
+        assert_eq!(
+            task_target(None, workspace.as_ref(), None, None, false).unwrap(),
+            TaskTarget::Workspace
+        );
+        assert!(task_target(None, workspace.as_ref(), Some("app"), None, false).is_err());
+        assert!(task_target(None, workspace.as_ref(), None, None, true).is_err());
+        assert_eq!(
+            task_target(Some(&project), workspace.as_ref(), Some("app"), None, false).unwrap(),
+            TaskTarget::Project
+        );
+    }
+}
@@ -1,21 +1,250 @@
+use std::{io::Write, path::Path};
+
 use eyre::{Context, eyre};
 
-use crate::types::Slug;
+use crate::{error::DeError, project::Project, types::Slug, workspace::Workspace};
+
+/// Checks whether `de run` would find a task, in the current project or the active workspace.
+/// With `workspace_name`, the task is looked up like `de run --workspace` does: in the current
+/// project only if it belongs to that workspace, then in that workspace.
+///
+/// The shims rely on this contract: exit status 0 if the task exists and non-zero otherwise,
+/// with nothing written to stdout unless `verbose` is set. With `quiet`, failures are not
+/// reported on stderr either.
+pub fn check(
+    task: Slug,
+    workspace_name: Option<Slug>,
+    verbose: bool,
+    quiet: bool,
+    cwd: &Path,
+) -> eyre::Result<()> {
+    let workspace = match workspace_name.as_ref() {
+        Some(workspace_name) => Workspace::require(workspace_name, cwd).map(Some),
+        None => Workspace::active(cwd)
+            .map_err(|e| eyre!(e))
+            .wrap_err("Failed to get active workspace"),
+    };
+
+    let result = check_with_workspace(
+        cwd,
+        workspace,
+        workspace_name.is_some(),
+        &task,
+        verbose,
+        &mut std::io::stdout(),
+    );
+
+    match result {
+        Ok(true) => Ok(()),
+        _ if quiet => std::process::exit(1),
+        Ok(false) => Err(eyre!(DeError::TaskNotFound {
+            task,
+            location: match workspace_name {
+                Some(workspace_name) => format!("project or workspace '{workspace_name}'"),
+                None => "project or active workspace".to_string(),
+            },
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+/// Where `de run` finds a task.
+pub(super) enum TaskSource {
+    /// The project containing the directory the task was looked up from.
+    Project(Box<Project>),
+    /// The workspace configuration.
+    Workspace,
+}
 
-pub fn check(task: Slug) -> eyre::Result<()> {
-    let current_project = crate::project::Project::current()
+/// Looks the task up in the project containing `dir`, then in `workspace`, in the same order as
+/// `de run`. With `pinned`, as for `de run --workspace`, the project is skipped unless it
+/// belongs to `workspace`.
+pub(super) fn resolve_task(
+    dir: &Path,
+    workspace: Option<&Workspace>,
+    pinned: bool,
+    task: &Slug,
+) -> eyre::Result<Option<TaskSource>> {
+    let current_project = Project::from_dir_recursive(dir)
         .map_err(|e| eyre!(e))
         .wrap_err("Failed to get current project")?
-        .ok_or_else(|| eyre!("No current project found"))?;
+        .filter(|project| {
+            !pinned
+                || workspace.is_some_and(|workspace| {
+                    project.manifest().project().workspace == workspace.config().name
+                })
+        });
+
+    if let Some(project) = current_project
+        && project.task(task)?.is_some()
+    {
+        return Ok(Some(TaskSource::Project(Box::new(project))));
+    }
+
+    if workspace.is_some_and(|workspace| workspace.config().tasks.contains_key(task)) {
+        return Ok(Some(TaskSource::Workspace));
+    }
+
+    Ok(None)
+}
+
+/// Checks the task with the workspace it was looked up in, or the error loading it. Unless
+/// `pinned`, a broken active workspace only matters when the current project doesn't define
+/// the task, as `de run` finds project tasks without it.
+fn check_with_workspace(
+    dir: &Path,
+    workspace: eyre::Result<Option<Workspace>>,
+    pinned: bool,
+    task: &Slug,
+    verbose: bool,
+    out: &mut impl Write,
+) -> eyre::Result<bool> {
+    match workspace {
+        Ok(workspace) => check_in_dir(dir, workspace.as_ref(), pinned, task, verbose, out),
+        Err(e) if !pinned => {
+            if check_in_dir(dir, None, false, task, verbose, out)? {
+                Ok(true)
+            } else {
+                Err(e)
+            }
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Resolves the task like [`resolve_task`]. The result is written to `out` only when `verbose`
+/// is set.
+fn check_in_dir(
+    dir: &Path,
+    workspace: Option<&Workspace>,
+    pinned: bool,
+    task: &Slug,
+    verbose: bool,
+    out: &mut impl Write,
+) -> eyre::Result<bool> {
+    let source = resolve_task(dir, workspace, pinned, task)?;
+
+    if verbose {
+        match (&source, workspace) {
+            (Some(TaskSource::Project(_)), _) => {
+                writeln!(out, "Task '{task}' exists in the current project.")?;
+            }
+            (Some(TaskSource::Workspace), Some(workspace)) => writeln!(
+                out,
+                "Task '{task}' exists in workspace '{}'.",
+                workspace.config().name
+            )?,
+            _ => {}
+        }
+    }
+
+    Ok(source.is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn project_with_task(task: &str) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("de.toml"),
+            format!("[project]\nname = \"check\"\n\n[tasks]\n{task} = \"echo {task}\"\n"),
+        )
+        .unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_check_writes_nothing_when_task_exists() {
+        let dir = project_with_task("build");
+        let mut out = Vec::new();
+
+        let found = check_in_dir(
+            dir.path(),
+            None,
+            false,
+            &Slug::from_str("build").unwrap(),
+            false,
+            &mut out,
+        );
+
+        assert!(found.unwrap());
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn test_check_writes_nothing_when_task_is_missing() {
+        let dir = project_with_task("build");
+        let mut out = Vec::new();
+
+        let found = check_in_dir(
+            dir.path(),
+            None,
+            false,
+            &Slug::from_str("test").unwrap(),
+            false,
+            &mut out,
+        );
+
+        assert!(!found.unwrap());
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn test_pinned_check_skips_project_outside_workspace() {
+        let dir = project_with_task("build");
+        let task = Slug::from_str("build").unwrap();
+
+        let found = check_in_dir(dir.path(), None, true, &task, false, &mut Vec::new());
+
+        assert!(!found.unwrap());
+    }
+
+    #[test]
+    fn test_check_finds_workspace_task_outside_the_project() {
+        let dir = project_with_task("build");
+        let task = Slug::from_str("deploy").unwrap();
+        let mut workspace = Workspace::new(Slug::from_str("ws").unwrap()).unwrap();
+        workspace
+            .config_mut()
+            .tasks
+            .insert(task.clone(), "echo deploy".to_string());
+        let mut out = Vec::new();
+
+        let found = check_in_dir(dir.path(), Some(&workspace), false, &task, true, &mut out);
+
+        assert!(found.unwrap());
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "Task 'deploy' exists in workspace 'ws'.\n"
+        );
+    }
 
-    current_project
-        .manifest()
-        .tasks
-        .as_ref()
-        .and_then(|tasks| tasks.get(&task))
-        .ok_or_else(|| eyre!("Task '{}' not found in project", task))?;
+    #[test]
+    fn test_check_ignores_broken_workspace_for_project_tasks() {
+        let dir = project_with_task("build");
+        let broken = || Err(eyre!("invalid workspace config"));
 
-    println!("Task '{task}' exists in the current project.");
+        let found = check_with_workspace(
+            dir.path(),
+            broken(),
+            false,
+            &Slug::from_str("build").unwrap(),
+            false,
+            &mut Vec::new(),
+        );
+        assert!(found.unwrap());
 
-    Ok(())
+        let missing = check_with_workspace(
+            dir.path(),
+            broken(),
+            false,
+            &Slug::from_str("deploy").unwrap(),
+            false,
+            &mut Vec::new(),
+        );
+        assert!(missing.is_err());
+    }
 }
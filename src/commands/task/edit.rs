@@ -0,0 +1,106 @@
+use std::path::Path;
+
+use eyre::{Context, eyre};
+
+use super::check::{TaskSource, resolve_task};
+use crate::{error::DeError, types::Slug, utils::editor::open_in_editor, workspace::Workspace};
+
+/// Opens the file that defines the task, as `de run` resolves it, in `$VISUAL` or `$EDITOR`.
+/// A project task is opened in `.de/config.toml` if that is where it is defined.
+pub fn edit(task: Slug, workspace_name: Option<Slug>, cwd: &Path) -> eyre::Result<()> {
+    let workspace = Workspace::named_or_active(workspace_name.as_ref(), cwd)
+        .map_err(|e| eyre!(e))
+        .wrap_err("Failed to get active workspace")?;
+
+    let mut task = task;
+    let path = match resolve_task(cwd, workspace.as_ref(), false, &task)? {
+        Some(TaskSource::Project(project)) => {
+            // Open the task an alias refers to, rather than the alias
+            if let Some((name, _)) = project
+                .tasks()
+                .map(|tasks| tasks.resolve(&task))
+                .transpose()?
+                .flatten()
+            {
+                task = name.clone();
+            }
+            project
+                .task_source_file(&task)?
+                .unwrap_or_else(|| project.manifest_path().clone())
+        }
+        Some(TaskSource::Workspace) => workspace
+            .as_ref()
+            .map(|workspace| workspace.config_path.clone())
+            .ok_or_else(|| eyre!(DeError::NoActiveWorkspace))?,
+        None => {
+            return Err(eyre!(DeError::TaskNotFound {
+                task,
+                location: "project or active workspace".to_string(),
+            }));
+        }
+    };
+
+    let contents = std::fs::read_to_string(&path)
+        .map_err(|e| eyre!(e))
+        .wrap_err_with(|| format!("Failed to read {}", path.display()))?;
+
+    open_in_editor(&path, task_line(&contents, &task))
+}
+
+/// Finds the 1-based line defining the task, either as a key in `[tasks]` or as a
+/// `[tasks.<task>]` table.
+fn task_line(contents: &str, task: &Slug) -> Option<usize> {
+    let task = task.as_str();
+    let quoted = format!("\"{task}\"");
+    let mut in_tasks = false;
+
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+
+        if let Some(header) = line.strip_prefix('[') {
+            let header = header.trim_end_matches(']').trim();
+            if header
+                .strip_prefix("tasks.")
+                .is_some_and(|name| name == task || name == quoted)
+            {
+                return Some(index + 1);
+            }
+            in_tasks = header == "tasks";
+            continue;
+        }
+
+        if in_tasks
+            && let Some((key, _)) = line.split_once('=')
+            && (key.trim() == task || key.trim() == quoted)
+        {
+            return Some(index + 1);
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_task_line_finds_keys_and_tables() {
+        let contents = "[project]\nname = \"api\"\n\n[tasks]\nbuild = \"cargo build\"\n\"test\" = \"cargo test\"\n\n[tasks.lint]\ncommand = \"cargo clippy\"\n";
+
+        assert_eq!(
+            task_line(contents, &Slug::from_str("build").unwrap()),
+            Some(5)
+        );
+        assert_eq!(
+            task_line(contents, &Slug::from_str("test").unwrap()),
+            Some(6)
+        );
+        assert_eq!(
+            task_line(contents, &Slug::from_str("lint").unwrap()),
+            Some(8)
+        );
+        assert_eq!(task_line(contents, &Slug::from_str("name").unwrap()), None);
+    }
+}
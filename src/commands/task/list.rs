@@ -1,13 +1,83 @@
+use std::path::Path;
+
 use eyre::{WrapErr, eyre};
+use serde::Serialize;
+
+use crate::{
+    cli::TaskListFormat,
+    project::{Project, Task},
+    types::Slug,
+    utils::theme::{Symbols, Theme},
+    workspace::Workspace,
+};
+
+/// A task as `--format json` lists it, for editors to build "run task" menus from.
+#[derive(Debug, Serialize)]
+pub struct TaskListing<'a> {
+    name: &'a Slug,
+    command: String,
+    service: Option<&'a str>,
+    description: Option<&'a str>,
+    /// `compose` or `command` for project tasks, `alias` for aliases and `workspace` for
+    /// workspace tasks.
+    kind: &'static str,
+    /// The task an alias refers to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    alias_of: Option<&'a Slug>,
+}
+
+impl<'a> TaskListing<'a> {
+    pub fn project(name: &'a Slug, task: &'a Task) -> Self {
+        Self {
+            name,
+            command: task.command_str(),
+            service: task.service(),
+            description: task.description(),
+            kind: task.kind(),
+            alias_of: None,
+        }
+    }
 
-use crate::{project::Project, utils::theme::Theme, workspace::Workspace};
+    pub fn alias(name: &'a Slug, target: &'a Slug, task: &'a Task) -> Self {
+        Self {
+            kind: "alias",
+            alias_of: Some(target),
+            ..Self::project(name, task)
+        }
+    }
+
+    pub fn workspace(name: &'a Slug, command: &str) -> Self {
+        Self {
+            name,
+            command: command.to_string(),
+            service: None,
+            description: None,
+            kind: "workspace",
+            alias_of: None,
+        }
+    }
+}
+
+/// Prints the listings as a pretty JSON array.
+pub fn print_task_listings(listings: &[TaskListing]) -> eyre::Result<()> {
+    let json = serde_json::to_string_pretty(listings)
+        .map_err(|e| eyre!(e))
+        .wrap_err("Failed to serialize tasks")?;
+    println!("{json}");
+    Ok(())
+}
+
+pub fn list(format: TaskListFormat, workspace_name: Option<Slug>, cwd: &Path) -> eyre::Result<()> {
+    if format == TaskListFormat::Json {
+        return list_json(workspace_name, cwd);
+    }
 
-pub fn list() -> eyre::Result<()> {
     let mut found_tasks = false;
     let theme = Theme::new();
+    let symbols = Symbols::new(&theme);
 
     // List project tasks
-    if let Some(project) = Project::current()
+    if let Some(project) = Project::from_dir_recursive(cwd)
         .map_err(|e| eyre!(e))
         .wrap_err("Failed to get current project")?
         && let Some(tasks) = project.manifest().tasks.as_ref()
@@ -19,13 +89,24 @@ pub fn list() -> eyre::Result<()> {
             theme.highlight(project.manifest().project().name.as_str())
         );
         for (name, task) in tasks {
-            println!("- {}: {}", name.as_str(), theme.dim(&task.command_str()));
+            match task.description() {
+                Some(description) => println!(
+                    "- {} {}: {}",
+                    name.as_str(),
+                    theme.dim(&format!("({description})")),
+                    theme.dim(&task.command_str())
+                ),
+                None => println!("- {}: {}", name.as_str(), theme.dim(&task.command_str())),
+            }
+        }
+        for (alias, target) in &tasks.aliases {
+            println!("- {} {} {}", alias.as_str(), symbols.arrow, target.as_str());
         }
         found_tasks = true;
     }
 
     // List workspace tasks
-    if let Some(workspace) = Workspace::active()
+    if let Some(workspace) = Workspace::named_or_active(workspace_name.as_ref(), cwd)
         .map_err(|e| eyre!(e))
         .wrap_err("Failed to get active workspace")?
         && !workspace.config().tasks.is_empty()
@@ -46,3 +127,112 @@ pub fn list() -> eyre::Result<()> {
 
     Ok(())
 }
+
+/// Lists the tasks of the current project, then those of the workspace, as JSON.
+fn list_json(workspace_name: Option<Slug>, cwd: &Path) -> eyre::Result<()> {
+    let project = Project::from_dir_recursive(cwd)
+        .map_err(|e| eyre!(e))
+        .wrap_err("Failed to get current project")?;
+    let workspace = Workspace::named_or_active(workspace_name.as_ref(), cwd)
+        .map_err(|e| eyre!(e))
+        .wrap_err("Failed to get active workspace")?;
+
+    print_task_listings(&task_listings(project.as_ref(), workspace.as_ref())?)
+}
+
+/// The listings of the project's tasks and aliases, followed by the workspace's tasks.
+fn task_listings<'a>(
+    project: Option<&'a Project>,
+    workspace: Option<&'a Workspace>,
+) -> eyre::Result<Vec<TaskListing<'a>>> {
+    let mut listings = Vec::new();
+
+    if let Some(tasks) = project.and_then(|p| p.manifest().tasks.as_ref()) {
+        for (name, task) in tasks {
+            listings.push(TaskListing::project(name, task));
+        }
+        for alias in tasks.aliases.keys() {
+            if let Some((target, task)) = tasks.resolve(alias)? {
+                listings.push(TaskListing::alias(alias, target, task));
+            }
+        }
+    }
+
+    if let Some(workspace) = workspace {
+        for (name, command) in &workspace.config().tasks {
+            listings.push(TaskListing::workspace(name, command));
+        }
+    }
+
+    Ok(listings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_task_listings_as_json() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("de.toml"),
+            r#"[project]
+name = "api"
+
+[tasks]
+test = "cargo test"
+logs = { service = "web", command = "tail -f log", description = "Follow the logs" }
+
+[tasks.aliases]
+t = "test"
+"#,
+        )
+        .unwrap();
+        let workspace_path = dir.path().join("ws.toml");
+        std::fs::write(
+            &workspace_path,
+            "name = \"ws\"\n\n[projects]\n\n[tasks]\ndeploy = \"./deploy.sh\"\n",
+        )
+        .unwrap();
+
+        let project = Project::from_dir(dir.path()).unwrap();
+        let workspace = Workspace::load_from_path(workspace_path).unwrap().unwrap();
+        let listings = task_listings(Some(&project), Some(&workspace)).unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&listings).unwrap(),
+            serde_json::json!([
+                {
+                    "name": "logs",
+                    "command": "docker-compose exec web tail -f log",
+                    "service": "web",
+                    "description": "Follow the logs",
+                    "kind": "compose"
+                },
+                {
+                    "name": "test",
+                    "command": "cargo test",
+                    "service": null,
+                    "description": null,
+                    "kind": "command"
+                },
+                {
+                    "name": "t",
+                    "command": "cargo test",
+                    "service": null,
+                    "description": null,
+                    "kind": "alias",
+                    "alias_of": "test"
+                },
+                {
+                    "name": "deploy",
+                    "command": "./deploy.sh",
+                    "service": null,
+                    "description": null,
+                    "kind": "workspace"
+                }
+            ])
+        );
+        assert!(task_listings(None, None).unwrap().is_empty());
+    }
+}
@@ -1,9 +1,13 @@
 mod add;
 mod check;
+mod edit;
 mod list;
+mod move_;
 mod remove;
 
 pub use add::add;
 pub use check::check;
-pub use list::list;
+pub use edit::edit;
+pub use list::{TaskListing, list, print_task_listings};
+pub use move_::move_task;
 pub use remove::remove;
@@ -0,0 +1,208 @@
+use std::path::Path;
+
+use dialoguer::Confirm;
+use eyre::{WrapErr, eyre};
+
+use crate::{
+    error::DeError,
+    project::{Project, RawTask, Task},
+    types::Slug,
+    utils::{get_project_for_cli, get_workspace_for_cli, is_interactive},
+    workspace::Workspace,
+};
+
+use super::{
+    add::{insert_project_task, insert_workspace_task},
+    remove::{take_project_task, take_workspace_task},
+};
+
+/// Where a task is defined: the workspace configuration or a project manifest.
+enum TaskLocation {
+    Workspace(Workspace),
+    Project(Box<Project>),
+}
+
+impl TaskLocation {
+    fn describe(&self) -> String {
+        match self {
+            TaskLocation::Workspace(workspace) => {
+                format!("workspace '{}'", workspace.config().name)
+            }
+            TaskLocation::Project(project) => {
+                format!("project '{}'", project.manifest().project().name)
+            }
+        }
+    }
+
+    fn get(&self, task_name: &Slug) -> Option<Task> {
+        match self {
+            TaskLocation::Workspace(workspace) => workspace
+                .config()
+                .tasks
+                .get(task_name)
+                .map(|command| Task::Raw(RawTask::Flat(command.clone()))),
+            TaskLocation::Project(project) => project
+                .tasks()
+                .and_then(|tasks| tasks.get(task_name))
+                .cloned(),
+        }
+    }
+
+    fn insert(&mut self, task_name: Slug, task: Task) -> eyre::Result<()> {
+        match self {
+            TaskLocation::Workspace(workspace) => {
+                let command = workspace_command(&task)?;
+                insert_workspace_task(workspace, task_name, command)
+            }
+            TaskLocation::Project(project) => insert_project_task(project, task_name, task),
+        }
+    }
+
+    fn take(&mut self, task_name: &Slug) -> eyre::Result<Option<Task>> {
+        match self {
+            TaskLocation::Workspace(workspace) => Ok(take_workspace_task(workspace, task_name)?
+                .map(|command| Task::Raw(RawTask::Flat(command)))),
+            TaskLocation::Project(project) => take_project_task(project, task_name),
+        }
+    }
+
+    /// The workspace the location belongs to, used to resolve the destination.
+    fn workspace(&self, cwd: &Path) -> eyre::Result<Workspace> {
+        let workspace_name = match self {
+            TaskLocation::Workspace(workspace) => &workspace.config().name,
+            TaskLocation::Project(project) => &project.manifest().project().workspace,
+        };
+
+        Workspace::require(workspace_name, cwd)
+    }
+}
+
+/// Workspace tasks are plain commands, so only simple raw tasks can become one.
+fn workspace_command(task: &Task) -> eyre::Result<String> {
+    match task {
+        Task::Compose { .. } => Err(eyre!(
+            "Docker Compose tasks can't be moved to the workspace."
+        )),
+        Task::Script { .. } => Err(eyre!("Script tasks can't be moved to the workspace.")),
+        _ if task.shell() => Err(eyre!("Shell tasks can't be moved to the workspace.")),
+        Task::Raw(raw) => {
+            if task.description().is_some() {
+                println!("Note: workspace tasks have no description, it will be dropped.");
+            }
+            Ok(raw.command_str().to_string())
+        }
+    }
+}
+
+/// Moves a task from its current location to the workspace or another project.
+///
+/// The task is written to the destination first and only then removed from the source;
+/// if the removal fails, the destination is restored.
+pub fn move_task(
+    task_name: Slug,
+    project_name: Option<Slug>,
+    workspace_name: Option<Option<Slug>>,
+    to_workspace: bool,
+    to_project: Option<Slug>,
+    yes: bool,
+    cwd: &Path,
+) -> eyre::Result<()> {
+    let mut source = if workspace_name.is_some() {
+        TaskLocation::Workspace(get_workspace_for_cli(workspace_name, cwd)?)
+    } else {
+        TaskLocation::Project(Box::new(get_project_for_cli(project_name, None, cwd)?))
+    };
+
+    let mut destination = if to_workspace {
+        if matches!(source, TaskLocation::Workspace(_)) {
+            return Err(eyre!("Task '{}' is already a workspace task.", task_name));
+        }
+        TaskLocation::Workspace(source.workspace(cwd)?)
+    } else {
+        let to_project = to_project.ok_or_else(|| eyre!("No destination given"))?;
+        let workspace = source.workspace(cwd)?;
+        let ws_project = workspace.project(&to_project).ok_or_else(|| {
+            eyre!(DeError::ProjectNotFound {
+                project: to_project.clone(),
+                workspace: workspace.config().name.clone(),
+            })
+        })?;
+
+        let project = Project::from_dir(&ws_project.dir)
+            .map_err(|e| eyre!(e))
+            .wrap_err("Failed to load project from directory")?;
+
+        if let TaskLocation::Project(source_project) = &source
+            && source_project.manifest_path() == project.manifest_path()
+        {
+            return Err(eyre!(
+                "Task '{}' is already in project '{}'.",
+                task_name,
+                to_project
+            ));
+        }
+
+        TaskLocation::Project(Box::new(project))
+    };
+
+    let task = source.get(&task_name).ok_or_else(|| {
+        eyre!(DeError::TaskNotFound {
+            task: task_name.clone(),
+            location: source.describe(),
+        })
+    })?;
+
+    let previous = destination.get(&task_name);
+    if previous.is_some() && !yes {
+        if !is_interactive() {
+            return Err(eyre!(
+                "Task '{}' already exists in {}. Re-run with --yes to overwrite it",
+                task_name,
+                destination.describe()
+            ));
+        }
+
+        let overwrite = Confirm::new()
+            .with_prompt(format!(
+                "Task '{}' already exists in {}. Overwrite it?",
+                task_name,
+                destination.describe()
+            ))
+            .default(false)
+            .interact()
+            .map_err(|e| eyre!(e))
+            .wrap_err("Failed to read confirmation")?;
+
+        if !overwrite {
+            println!("Move aborted.");
+            return Ok(());
+        }
+    }
+
+    destination.insert(task_name.clone(), task)?;
+
+    if let Err(err) = source.take(&task_name) {
+        let restored = match previous {
+            Some(previous) => destination.insert(task_name.clone(), previous),
+            None => destination.take(&task_name).map(|_| ()),
+        };
+
+        return Err(err.wrap_err(match restored {
+            Ok(()) => format!("Failed to remove task from {}", source.describe()),
+            Err(_) => format!(
+                "Failed to remove task from {} and to restore {}",
+                source.describe(),
+                destination.describe()
+            ),
+        }));
+    }
+
+    println!(
+        "Task '{}' moved from {} to {}.",
+        task_name,
+        source.describe(),
+        destination.describe()
+    );
+
+    Ok(())
+}
@@ -1,23 +1,24 @@
-use eyre::{Context, eyre};
+use std::path::Path;
+
+use eyre::Context;
 
 use crate::{
+    project::{Project, Task, config::ProjectManifest},
     types::Slug,
     utils::{get_project_for_cli, get_workspace_for_cli},
+    workspace::Workspace,
 };
 
 pub fn remove(
     task_name: Slug,
     project_name: Option<Slug>,
     workspace_name: Option<Option<Slug>>,
+    cwd: &Path,
 ) -> eyre::Result<()> {
     if workspace_name.is_some() {
-        let mut workspace = get_workspace_for_cli(workspace_name)?;
+        let mut workspace = get_workspace_for_cli(workspace_name, cwd)?;
 
-        if workspace.config_mut().tasks.remove(&task_name).is_some() {
-            workspace
-                .save()
-                .map_err(|e| eyre!(e))
-                .wrap_err("Failed to save workspace configuration")?;
+        if take_workspace_task(&mut workspace, &task_name)?.is_some() {
             println!(
                 "Task '{}' removed from workspace '{}'.",
                 task_name,
@@ -31,20 +32,9 @@ pub fn remove(
             );
         }
     } else {
-        let mut project = get_project_for_cli(project_name, workspace_name)?;
-
-        if project
-            .manifest_mut()
-            .tasks
-            .get_or_insert_with(Default::default)
-            .remove(&task_name)
-            .is_some()
-        {
-            project
-                .manifest()
-                .save(project.manifest_path())
-                .map_err(|e| eyre!(e))
-                .wrap_err("Failed to save project configuration")?;
+        let mut project = get_project_for_cli(project_name, workspace_name, cwd)?;
+
+        if take_project_task(&mut project, &task_name)?.is_some() {
             println!(
                 "Task '{}' removed from project '{}'.",
                 task_name,
@@ -61,3 +51,77 @@ pub fn remove(
 
     Ok(())
 }
+
+/// Removes a workspace task, saving the workspace configuration if it existed.
+pub(super) fn take_workspace_task(
+    workspace: &mut Workspace,
+    task_name: &Slug,
+) -> eyre::Result<Option<String>> {
+    if !workspace.config().tasks.contains_key(task_name) {
+        return Ok(None);
+    }
+
+    workspace
+        .edit(|workspace| Ok(workspace.config_mut().tasks.remove(task_name)))
+        .wrap_err("Failed to save workspace configuration")
+}
+
+/// Removes a project task, saving the project manifest if it existed.
+pub(super) fn take_project_task(
+    project: &mut Project,
+    task_name: &Slug,
+) -> eyre::Result<Option<Task>> {
+    // Tasks only defined in `.de/config.toml` can't be removed from `de.toml`
+    let defined = ProjectManifest::load(project.manifest_path())?
+        .tasks
+        .is_some_and(|tasks| tasks.contains(task_name));
+    if !defined {
+        return Ok(None);
+    }
+
+    project
+        .edit_manifest(|manifest| {
+            Ok(manifest
+                .tasks
+                .as_mut()
+                .and_then(|tasks| tasks.remove(task_name)))
+        })
+        .wrap_err("Failed to save project configuration")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_take_project_task_leaves_the_overlay_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("de.toml"),
+            "[project]\nname = \"api\"\n\n[tasks]\nbuild = \"make\"\n",
+        )
+        .unwrap();
+        std::fs::create_dir(dir.path().join(".de")).unwrap();
+        std::fs::write(
+            dir.path().join(".de/config.toml"),
+            "[tasks]\nlocal = \"make local\"\n",
+        )
+        .unwrap();
+        let mut project = Project::from_dir(dir.path()).unwrap();
+
+        let local = Slug::from_str("local").unwrap();
+        assert!(take_project_task(&mut project, &local).unwrap().is_none());
+        assert!(project.task(&local).unwrap().is_some());
+
+        let build = Slug::from_str("build").unwrap();
+        assert!(take_project_task(&mut project, &build).unwrap().is_some());
+        assert!(project.task(&build).unwrap().is_none());
+        assert!(
+            !std::fs::read_to_string(dir.path().join("de.toml"))
+                .unwrap()
+                .contains("local")
+        );
+        assert!(project.task(&local).unwrap().is_some());
+    }
+}
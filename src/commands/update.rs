@@ -1,14 +1,17 @@
+use std::path::Path;
+
 use eyre::{Context, eyre};
 
 use crate::{
+    error::DeError,
     project::Project,
     types::Slug,
     utils::ui::UserInterface,
-    workspace::{self, Workspace, WorkspaceProject},
+    workspace::{self, OnConflict, Workspace, WorkspaceProject},
 };
 
 /// Updates workspace registrations and project configurations.
-pub fn update(all: bool, workspace: Option<Option<Slug>>) -> eyre::Result<()> {
+pub fn update(all: bool, workspace: Option<Option<Slug>>, cwd: &Path) -> eyre::Result<()> {
     let ui = UserInterface::new();
 
     ui.heading("Update Summary:")?;
@@ -17,80 +20,53 @@ pub fn update(all: bool, workspace: Option<Option<Slug>>) -> eyre::Result<()> {
         update_all_workspaces(&ui)
     } else if let Some(workspace_name) = workspace {
         let workspace = if let Some(name) = workspace_name {
-            Workspace::load_from_name(&name)
-                .map_err(|e| eyre!(e))
-                .wrap_err("Failed to load workspace")?
-                .ok_or_else(|| eyre!("Workspace '{}' not found", name))?
+            Workspace::require(&name, cwd)?
         } else {
-            Workspace::active()
-                .map_err(|e| eyre!(e))
-                .wrap_err("Failed to get active workspace")?
-                .ok_or_else(|| eyre!("No active workspace found"))?
+            Workspace::require_active(cwd)?
         };
 
         update_workspace(&ui, workspace)
     } else {
-        update_current_project(&ui)
+        update_current_project(&ui, cwd)
     }
 }
 
 /// Updates all workspaces by scanning for projects and validating existing registrations.
 fn update_all_workspaces(ui: &UserInterface) -> eyre::Result<()> {
-    let project_dirs = crate::utils::get_project_dirs()?;
-    let workspaces_dir = project_dirs.config_local_dir().join("workspaces");
+    let listing = Workspace::all()?;
 
-    if !workspaces_dir.exists() {
-        ui.error_item("No workspaces directory found. Nothing to update.", None)?;
+    if listing.workspaces.is_empty() && listing.skipped.is_empty() {
+        ui.error_item("No workspaces found. Nothing to update.", None)?;
         return Ok(());
     }
 
     let mut updated_count = 0;
     let mut removed_count = 0;
-    let mut skipped_count = 0;
+    let skipped_count = listing.skipped.len();
 
     // Collect per-workspace results for summary
     let mut workspace_summaries = Vec::new();
 
-    for entry in std::fs::read_dir(&workspaces_dir)
-        .map_err(|e| eyre!(e))
-        .wrap_err("Failed to read workspaces directory")?
-    {
-        let entry = entry
-            .map_err(|e| eyre!(e))
-            .wrap_err("Failed to read workspace directory entry")?;
-
-        let path = entry.path();
-        if path.extension().and_then(|s| s.to_str()) != Some("toml") {
-            continue;
-        }
-
-        let workspace_name = path
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .and_then(|s| s.parse::<Slug>().ok());
-
-        if let Some(workspace_name) = workspace_name {
-            let workspace = Workspace::load_from_name(&workspace_name)
-                .map_err(|e| eyre!(e))
-                .wrap_err_with(|| format!("Failed to load workspace '{workspace_name}'"))?
-                .ok_or_else(|| eyre!("Workspace '{}' not found.", workspace_name))?;
+    for skipped in &listing.skipped {
+        ui.error_item(
+            &format!(
+                "Skipping invalid workspace file: {} ({})",
+                skipped.path.display(),
+                skipped.reason
+            ),
+            None,
+        )?;
+    }
 
-            ui.subheading(workspace_name.as_str())?;
+    for workspace in listing.workspaces {
+        ui.subheading(workspace.config().name.as_str())?;
 
-            let (updated, removed, summary) =
-                ui.indented(|ui| update_workspace_internal_verbose(ui, workspace))?;
+        let (updated, removed, summary) =
+            ui.indented(|ui| update_workspace_internal_verbose(ui, workspace))?;
 
-            updated_count += updated;
-            removed_count += removed;
-            workspace_summaries.push(summary);
-        } else {
-            ui.error_item(
-                &format!("Skipping invalid workspace file: {}", path.display()),
-                None,
-            )?;
-            skipped_count += 1;
-            continue;
-        }
+        updated_count += updated;
+        removed_count += removed;
+        workspace_summaries.push(summary);
     }
 
     // Print summary for all workspaces
@@ -145,7 +121,7 @@ fn update_workspace(ui: &UserInterface, workspace: Workspace) -> eyre::Result<()
 /// Internal function that handles the actual workspace update logic, with verbose UI output.
 fn update_workspace_internal_verbose(
     ui: &UserInterface,
-    mut workspace: Workspace,
+    workspace: Workspace,
 ) -> eyre::Result<(usize, usize, String)> {
     let workspace_name = &workspace.config().name;
 
@@ -191,8 +167,9 @@ fn update_workspace_internal_verbose(
                 // Check if project name has changed, and update if necessary
                 if current_manifest.project().name != project_name {
                     ui.info_item(&format!(
-                        "Renamed: {} → {}",
+                        "Renamed: {} {} {}",
                         ui.theme.highlight(project_name.as_str()),
+                        ui.symbols.arrow,
                         ui.theme.accent(current_manifest.project().name.as_str()),
                     ))?;
                     remove_projects.push(project_name.clone());
@@ -212,6 +189,13 @@ fn update_workspace_internal_verbose(
         }
     }
 
+    // Apply the changes to the latest version of the workspace, in case another `de`
+    // process changed it while the projects were being checked
+    let _lock = workspace.lock_config()?;
+    let mut workspace = workspace
+        .reload()?
+        .ok_or_else(|| eyre!(DeError::WorkspaceNotFound(workspace_name.clone())))?;
+
     // Apply changes: remove stale projects and add new/updated projects
     for project_name in remove_projects {
         workspace.remove_project(&project_name);
@@ -235,9 +219,9 @@ fn update_workspace_internal_verbose(
 }
 
 /// Updates the current project's workspace registration.
-fn update_current_project(ui: &UserInterface) -> eyre::Result<()> {
-    let project =
-        Project::current()?.ok_or_else(|| eyre!("No de.toml found in current directory"))?;
+fn update_current_project(ui: &UserInterface, cwd: &Path) -> eyre::Result<()> {
+    let project = Project::from_dir_recursive(cwd)?
+        .ok_or_else(|| eyre!("No de.toml found in current directory"))?;
 
     let project_name = &project.manifest().project().name;
     let workspace_name = &project.manifest().project().workspace;
@@ -248,6 +232,8 @@ fn update_current_project(ui: &UserInterface) -> eyre::Result<()> {
         workspace_name.clone(),
         project_name.clone(),
         project_path.clone(),
+        OnConflict::Update,
+        cwd,
     )
     .wrap_err("Failed to update project registration")?;
 
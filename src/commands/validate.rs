@@ -0,0 +1,145 @@
+use std::path::{Path, PathBuf};
+
+use eyre::eyre;
+
+use crate::{
+    error::DeError,
+    project::{
+        Project,
+        config::{ProjectHook, ProjectManifest},
+    },
+    types::Slug,
+    utils::{get_workspace_for_cli, ui::UserInterface},
+};
+
+/// Checks a project manifest for syntax errors, unknown fields and hooks that refer to
+/// undefined tasks. Without a project name, the manifest of the current project is checked.
+pub fn validate(
+    project_name: Option<Slug>,
+    workspace_name: Option<Slug>,
+    cwd: &Path,
+) -> eyre::Result<()> {
+    let dir = match project_name {
+        Some(project_name) => {
+            let workspace = get_workspace_for_cli(Some(workspace_name), cwd)?;
+
+            workspace
+                .project(&project_name)
+                .map(|project| project.dir.clone())
+                .ok_or_else(|| {
+                    eyre!(DeError::ProjectNotFound {
+                        project: project_name.clone(),
+                        workspace: workspace.config().name.clone(),
+                    })
+                })?
+        }
+        None => Project::find_dir(cwd).ok_or_else(|| eyre!(DeError::NoCurrentProject))?,
+    };
+
+    let ui = UserInterface::new();
+    ui.heading(&format!("Validating {}", dir.display()))?;
+
+    let mut problems = 0;
+
+    // Parse each file on its own, so errors point at the line in the file that has them
+    for path in manifest_files(&dir) {
+        if let Err(message) = parse_manifest_file(&path) {
+            problems += 1;
+            ui.error_item(&path.display().to_string(), None)?;
+            ui.indented(|ui| {
+                for line in message.lines() {
+                    ui.writeln(line)?;
+                }
+                Ok(())
+            })?;
+        } else {
+            ui.success_item(&path.display().to_string(), None)?;
+        }
+    }
+
+    // Check the merged manifest too, which includes the environment overrides
+    if problems == 0 {
+        match Project::from_dir(&dir) {
+            Ok(project) => {
+                problems += check_hooks(&ui, &project)?;
+                problems += check_aliases(&ui, &project)?;
+            }
+            Err(err) => {
+                problems += 1;
+                ui.error_item(&format!("{err:#}"), None)?;
+            }
+        }
+    }
+
+    ui.new_line()?;
+
+    if problems > 0 {
+        return Err(eyre!(
+            "Found {} problem(s) in the project manifest",
+            problems
+        ));
+    }
+
+    ui.success_item("Manifest is valid", None)?;
+    Ok(())
+}
+
+/// The manifest files of the project in `dir` that exist: `de.toml` and the optional `.de/config.toml`.
+fn manifest_files(dir: &Path) -> Vec<PathBuf> {
+    [dir.join("de.toml"), dir.join(".de").join("config.toml")]
+        .into_iter()
+        .filter(|path| path.exists())
+        .collect()
+}
+
+/// Parses a manifest file, returning the error message with its location on failure.
+fn parse_manifest_file(path: &Path) -> Result<(), String> {
+    let contents = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    toml::from_str::<ProjectManifest>(&contents)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+/// Reports task aliases that refer to undefined tasks or form a cycle. Returns the number of
+/// problems.
+fn check_aliases(ui: &UserInterface, project: &Project) -> eyre::Result<usize> {
+    let Some(tasks) = project.tasks() else {
+        return Ok(0);
+    };
+
+    let mut problems = 0;
+    for alias in tasks.aliases.keys() {
+        if let Err(err) = tasks.resolve(alias) {
+            problems += 1;
+            ui.error_item(&err.to_string(), None)?;
+        }
+    }
+
+    Ok(problems)
+}
+
+/// Reports hooks that refer to tasks the project doesn't define. Returns the number of problems.
+fn check_hooks(ui: &UserInterface, project: &Project) -> eyre::Result<usize> {
+    let Some(hooks) = project.manifest().project().hooks.as_ref() else {
+        return Ok(0);
+    };
+
+    let mut problems = 0;
+    for hook in [
+        ProjectHook::BeforeStart,
+        ProjectHook::AfterStart,
+        ProjectHook::BeforeStop,
+    ] {
+        for task in hooks.tasks(hook) {
+            if !project.tasks().is_some_and(|tasks| tasks.contains(task)) {
+                problems += 1;
+                ui.error_item(
+                    &format!("Hook {} refers to undefined task '{task}'", hook.as_str()),
+                    None,
+                )?;
+            }
+        }
+    }
+
+    Ok(problems)
+}
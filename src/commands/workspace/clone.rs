@@ -0,0 +1,49 @@
+use std::path::Path;
+
+use eyre::{Context, eyre};
+
+use crate::{types::Slug, utils::ui::UserInterface, workspace::Workspace};
+
+/// Duplicate a workspace configuration under a new name, sharing the same project directories.
+pub fn clone(from: Slug, to: Slug, cwd: &Path) -> eyre::Result<()> {
+    let ui = UserInterface::new();
+
+    let source = Workspace::require(&from, cwd)?;
+
+    // Another `de` creating the same workspace at once must not be overwritten
+    let _lock = Workspace::lock(&to, cwd)?;
+
+    let target_path = Workspace::path_from_name(&to)
+        .map_err(|e| eyre!(e))
+        .wrap_err_with(|| format!("Failed to get path for workspace '{to}'"))?;
+
+    if target_path.exists() {
+        return Err(eyre!("Workspace '{}' already exists", to));
+    }
+
+    let mut target = Workspace::new(to.clone())
+        .map_err(|e| eyre!(e))
+        .wrap_err_with(|| format!("Failed to create workspace '{to}'"))?;
+
+    *target.config_mut() = source.config().clone();
+    target.config_mut().name = to.clone();
+
+    target
+        .save()
+        .map_err(|e| eyre!(e))
+        .wrap_err_with(|| format!("Failed to save workspace '{to}'"))?;
+
+    ui.success_item(
+        &format!(
+            "Cloned workspace {} into {}",
+            ui.theme.highlight(from.as_str()),
+            ui.theme.highlight(to.as_str())
+        ),
+        Some(&format!(
+            "{} project(s) are shared with '{from}'; their manifests were not changed",
+            target.config().projects.len()
+        )),
+    )?;
+
+    Ok(())
+}
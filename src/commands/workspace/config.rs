@@ -1,24 +1,45 @@
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use dialoguer::Confirm;
 use eyre::{Context, eyre};
 
-use crate::{commands::config::ConfigAction, types::Slug, workspace::Workspace};
+use crate::{
+    commands::config::ConfigAction,
+    error::DeError,
+    types::Slug,
+    utils::{
+        editor::open_in_editor, fs::write_atomic, git::branch_exists, is_interactive,
+        ui::UserInterface,
+    },
+    workspace::{Workspace, config::WorkspaceConfig},
+};
 
-/// Set or get a property on the workspace (e.g., active, default-branch).
+/// Set or get a property on the workspace (e.g., active, default-branch, root-dir), or on one of
+/// its projects with `project.<id>.dir`, `project.<id>.enabled` and `project.<id>.default-branch`.
 pub fn config(
     workspace_name: Option<Slug>,
     key: String,
     value: Option<String>,
     unset: bool,
+    force: bool,
+    cwd: &Path,
 ) -> eyre::Result<()> {
-    let mut workspace = if let Some(name) = workspace_name {
-        Workspace::load_from_name(&name)
-            .wrap_err("Failed to load workspace")?
-            .ok_or_else(|| eyre!("Workspace '{}' not found", name))?
+    let workspace = if let Some(name) = workspace_name {
+        Workspace::require(&name, cwd)?
     } else {
-        Workspace::active()
-            .wrap_err("Failed to get active workspace")?
-            .ok_or_else(|| eyre!("No active workspace found"))?
+        Workspace::require_active(cwd)?
     };
 
+    // Hold the lock from loading to saving, so no other `de` process changes the workspace in
+    // between
+    let _lock = workspace.lock_config()?;
+    let mut workspace = workspace
+        .reload()?
+        .ok_or_else(|| eyre!(DeError::WorkspaceNotFound(workspace.config().name.clone())))?;
+
     let action = if unset {
         ConfigAction::Unset
     } else if let Some(value) = value {
@@ -27,6 +48,10 @@ pub fn config(
         ConfigAction::Show
     };
 
+    if let Some(project_key) = key.strip_prefix("project.") {
+        return project_config(&mut workspace, project_key, action, force);
+    }
+
     match key.as_str() {
         "default-branch" | "default_branch" => match action {
             ConfigAction::Show => match &workspace.config().default_branch {
@@ -37,6 +62,14 @@ pub fn config(
                 ),
             },
             ConfigAction::Set(branch) => {
+                if !force && !branch_exists_in_any_project(&workspace, &branch) {
+                    return Err(eyre!(
+                        "Branch '{}' does not exist in any project of workspace '{}'. Use --force to set it anyway.",
+                        branch,
+                        workspace.config().name
+                    ));
+                }
+
                 workspace.config_mut().default_branch = Some(branch.clone());
                 workspace
                     .save()
@@ -58,6 +91,85 @@ pub fn config(
                 );
             }
         },
+        "root-dir" | "root_dir" => match action {
+            ConfigAction::Show => match &workspace.config().root_dir {
+                Some(dir) => println!("{}", dir.display()),
+                None => println!(
+                    "No root directory set for workspace '{}'.",
+                    workspace.config().name
+                ),
+            },
+            ConfigAction::Set(dir) => {
+                let dir = PathBuf::from(dir)
+                    .canonicalize()
+                    .wrap_err("Failed to resolve root directory")?;
+                if !dir.is_dir() {
+                    return Err(eyre!(
+                        "Root directory is not a directory: {}",
+                        dir.display()
+                    ));
+                }
+
+                workspace.config_mut().root_dir = Some(dir.clone());
+                workspace
+                    .save()
+                    .wrap_err("Failed to save workspace configuration")?;
+                println!(
+                    "Root directory for workspace '{}' set to '{}'.",
+                    workspace.config().name,
+                    dir.display()
+                );
+            }
+            ConfigAction::Unset => {
+                workspace.config_mut().root_dir = None;
+                workspace
+                    .save()
+                    .wrap_err("Failed to save workspace configuration")?;
+                println!(
+                    "Root directory removed from workspace '{}'",
+                    workspace.config().name,
+                );
+            }
+        },
+        "default-task" | "default_task" => match action {
+            ConfigAction::Show => match &workspace.config().default_task {
+                Some(task) => println!("{task}"),
+                None => println!(
+                    "No default task set for workspace '{}'.",
+                    workspace.config().name
+                ),
+            },
+            ConfigAction::Set(task) => {
+                let task = Slug::from_str(&task).map_err(|e| eyre!(e))?;
+                if !force && !workspace.config().tasks.contains_key(&task) {
+                    return Err(eyre!(
+                        "Workspace '{}' has no task '{}'. Use --force to set it anyway.",
+                        workspace.config().name,
+                        task
+                    ));
+                }
+
+                workspace.config_mut().default_task = Some(task.clone());
+                workspace
+                    .save()
+                    .wrap_err("Failed to save workspace configuration")?;
+                println!(
+                    "Default task for workspace '{}' set to '{}'.",
+                    workspace.config().name,
+                    task
+                );
+            }
+            ConfigAction::Unset => {
+                workspace.config_mut().default_task = None;
+                workspace
+                    .save()
+                    .wrap_err("Failed to save workspace configuration")?;
+                println!(
+                    "Default task removed from workspace '{}'",
+                    workspace.config().name,
+                );
+            }
+        },
         _ => {
             return Err(eyre!("Unknown property key '{}'", key));
         }
@@ -65,3 +177,273 @@ pub fn config(
 
     Ok(())
 }
+
+/// Sets or gets a field of a project's registration, from a key like `api.dir` or
+/// `api.enabled` (the part after `project.`).
+fn project_config(
+    workspace: &mut Workspace,
+    project_key: &str,
+    action: ConfigAction,
+    force: bool,
+) -> eyre::Result<()> {
+    let (id, field) = project_key
+        .rsplit_once('.')
+        .ok_or_else(|| {
+            eyre!(
+                "Expected a key like 'project.<id>.dir', 'project.<id>.enabled' or 'project.<id>.default-branch'"
+            )
+        })?;
+    let id = Slug::from_str(id).map_err(|e| eyre!(e))?;
+    let workspace_name = workspace.config().name.clone();
+
+    let project = workspace
+        .config_mut()
+        .projects
+        .get_mut(&id)
+        .ok_or_else(|| {
+            eyre!(DeError::ProjectNotFound {
+                project: id.clone(),
+                workspace: workspace_name.clone(),
+            })
+        })?;
+
+    match (field, action) {
+        ("dir", ConfigAction::Show) => println!("{}", project.dir.display()),
+        ("dir", ConfigAction::Set(dir)) => {
+            let dir = PathBuf::from(dir)
+                .canonicalize()
+                .wrap_err("Failed to resolve project directory")?;
+            if !dir.is_dir() {
+                return Err(eyre!(
+                    "Project directory is not a directory: {}",
+                    dir.display()
+                ));
+            }
+            if !force && !dir.join("de.toml").exists() {
+                return Err(eyre!(
+                    "{} has no de.toml. Use --force to set it anyway.",
+                    dir.display()
+                ));
+            }
+
+            project.dir = dir.clone();
+            if let Some((other, _)) = workspace
+                .config()
+                .projects
+                .iter()
+                .find(|(other, project)| **other != id && project.dir == dir)
+            {
+                return Err(eyre!(
+                    "{} is already registered as project '{}' in workspace '{}'",
+                    dir.display(),
+                    other,
+                    workspace_name
+                ));
+            }
+
+            workspace
+                .save()
+                .wrap_err("Failed to save workspace configuration")?;
+            println!(
+                "Directory of project '{}' in workspace '{}' set to '{}'.",
+                id,
+                workspace_name,
+                dir.display()
+            );
+        }
+        ("dir", ConfigAction::Unset) => {
+            return Err(eyre!(
+                "A project can't be registered without a directory; use `de workspace remove-project {}` instead",
+                id
+            ));
+        }
+        ("enabled", ConfigAction::Show) => println!("{}", project.enabled),
+        ("enabled", ConfigAction::Set(value)) => {
+            let enabled = value.parse::<bool>().map_err(|_| {
+                eyre!("Expected 'true' or 'false' for project.{id}.enabled, got '{value}'")
+            })?;
+
+            project.enabled = enabled;
+            workspace
+                .save()
+                .wrap_err("Failed to save workspace configuration")?;
+            println!(
+                "Project '{}' in workspace '{}' {}.",
+                id,
+                workspace_name,
+                if enabled { "enabled" } else { "disabled" }
+            );
+        }
+        ("enabled", ConfigAction::Unset) => {
+            project.enabled = true;
+            workspace
+                .save()
+                .wrap_err("Failed to save workspace configuration")?;
+            println!("Project '{id}' in workspace '{workspace_name}' enabled.");
+        }
+        ("default-branch" | "default_branch", ConfigAction::Show) => {
+            match &project.default_branch {
+                Some(branch) => println!("{branch}"),
+                None => println!(
+                    "No default branch set for project '{id}'; it uses the workspace default."
+                ),
+            }
+        }
+        ("default-branch" | "default_branch", ConfigAction::Set(branch)) => {
+            if !force && !branch_exists(&branch, &project.dir).unwrap_or(false) {
+                return Err(eyre!(
+                    "Branch '{}' does not exist in project '{}'. Use --force to set it anyway.",
+                    branch,
+                    id
+                ));
+            }
+
+            project.default_branch = Some(branch.clone());
+            workspace
+                .save()
+                .wrap_err("Failed to save workspace configuration")?;
+            println!(
+                "Default branch for project '{id}' in workspace '{workspace_name}' set to '{branch}'."
+            );
+        }
+        ("default-branch" | "default_branch", ConfigAction::Unset) => {
+            project.default_branch = None;
+            workspace
+                .save()
+                .wrap_err("Failed to save workspace configuration")?;
+            println!(
+                "Default branch removed from project '{id}'; it uses the workspace default again."
+            );
+        }
+        (field, _) => {
+            return Err(eyre!(
+                "Unknown project property '{}'; expected 'dir', 'enabled' or 'default-branch'",
+                field
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Prints the path of the configuration file of the workspace, or of the active workspace.
+pub fn config_path(workspace_name: Option<Slug>, cwd: &Path) -> eyre::Result<()> {
+    let (_, path) = config_file(workspace_name, cwd)?;
+    println!("{}", path.display());
+    Ok(())
+}
+
+/// The name and configuration file of the workspace, or of the active workspace. A named
+/// workspace is found without loading it, unless it is repo-local, so the file of a broken one
+/// can still be fixed.
+fn config_file(workspace_name: Option<Slug>, cwd: &Path) -> eyre::Result<(Slug, PathBuf)> {
+    if let Some(name) = workspace_name {
+        if let Ok(Some(workspace)) = Workspace::load_from_dir(cwd)
+            && workspace.config().name == name
+        {
+            return Ok((name, workspace.config_path));
+        }
+
+        let path = Workspace::path_from_name(&name)?;
+        if !path.exists() {
+            return Err(eyre!(DeError::WorkspaceNotFound(name)));
+        }
+        return Ok((name, path));
+    }
+
+    let workspace = Workspace::require_active(cwd)?;
+    Ok((workspace.config().name.clone(), workspace.config_path))
+}
+
+/// Edits a copy of the workspace configuration file in `$EDITOR` and only saves it once it
+/// parses, reopening the editor on request when it doesn't.
+pub fn edit(workspace_name: Option<Slug>, cwd: &Path) -> eyre::Result<()> {
+    let (name, path) = config_file(workspace_name, cwd)?;
+
+    let original = std::fs::read_to_string(&path)
+        .map_err(|e| eyre!(e))
+        .wrap_err_with(|| format!("Failed to read workspace config from {}", path.display()))?;
+
+    let draft = tempfile::Builder::new()
+        .prefix(&format!("{name}-"))
+        .suffix(".toml")
+        .tempfile()
+        .map_err(|e| eyre!(e))
+        .wrap_err("Failed to create a temporary file to edit")?;
+    std::fs::write(draft.path(), &original)
+        .map_err(|e| eyre!(e))
+        .wrap_err("Failed to write the temporary file to edit")?;
+
+    let ui = UserInterface::new();
+
+    let edited = loop {
+        open_in_editor(draft.path(), None)?;
+
+        let edited = std::fs::read_to_string(draft.path())
+            .map_err(|e| eyre!(e))
+            .wrap_err("Failed to read the edited configuration")?;
+
+        let Err(message) = check_workspace_config(&edited, &name) else {
+            break edited;
+        };
+
+        ui.error_item("The workspace configuration is invalid:", None)?;
+        ui.indented(|ui| {
+            for line in message.lines() {
+                ui.writeln(line)?;
+            }
+            Ok(())
+        })?;
+
+        let reopen = is_interactive()
+            && Confirm::new()
+                .with_prompt("Reopen the editor?")
+                .default(true)
+                .interact()
+                .map_err(|e| eyre!(e))
+                .wrap_err("Failed to prompt for confirmation")?;
+
+        if !reopen {
+            return Err(eyre!(
+                "Discarded the changes; {} was left unchanged",
+                path.display()
+            ));
+        }
+    };
+
+    if edited == original {
+        ui.info_item("No changes made.")?;
+        return Ok(());
+    }
+
+    write_atomic(&path, edited)
+        .map_err(|e| eyre!(e))
+        .wrap_err_with(|| format!("Failed to write workspace config to {}", path.display()))?;
+
+    ui.success_item(&format!("Saved {}", path.display()), None)?;
+    Ok(())
+}
+
+/// Parses an edited workspace configuration, which must keep the workspace's name.
+fn check_workspace_config(contents: &str, name: &Slug) -> Result<(), String> {
+    let config = toml::from_str::<WorkspaceConfig>(contents).map_err(|e| e.to_string())?;
+
+    if &config.name != name {
+        return Err(format!(
+            "The name must stay '{name}'; use `de workspace clone` to copy a workspace under a new name"
+        ));
+    }
+
+    Ok(())
+}
+
+/// Whether the branch exists locally or on `origin` in at least one project of the workspace.
+/// Workspaces without projects have nothing to check against, so any branch is accepted.
+fn branch_exists_in_any_project(workspace: &Workspace, branch: &str) -> bool {
+    let projects = &workspace.config().projects;
+
+    projects.is_empty()
+        || projects
+            .values()
+            .any(|project| branch_exists(branch, &project.dir).unwrap_or(false))
+}
@@ -1,6 +1,7 @@
-use eyre::{Context, eyre};
+use std::path::Path;
 
 use crate::{
+    commands::status::GitStatus,
     project::Project,
     utils::{formatter::Formatter, theme::Theme},
     workspace::Workspace,
@@ -8,19 +9,17 @@ use crate::{
 
 use crate::types::Slug;
 
-pub fn info(workspace_name: Option<Slug>) -> eyre::Result<()> {
+/// Prints the configuration of the workspace. With `git`, each project's branch and whether it
+/// has uncommitted changes are shown too, without asking Docker about its services.
+pub fn info(workspace_name: Option<Slug>, git: bool, cwd: &Path) -> eyre::Result<()> {
     let workspace = if let Some(workspace_name) = workspace_name {
-        Workspace::load_from_name(&workspace_name)
-            .wrap_err("Failed to load workspace")?
-            .ok_or_else(|| eyre!("Workspace '{}' not found", workspace_name))?
+        Workspace::require(&workspace_name, cwd)?
     } else {
-        Workspace::active()
-            .wrap_err("Failed to get active workspace")?
-            .ok_or_else(|| eyre!("No active workspace found"))?
+        Workspace::require_active(cwd)?
     };
 
     let theme = Theme::new();
-    let formatter = Formatter::new();
+    let formatter = Formatter::with_theme(theme.clone());
 
     formatter.heading(&format!(
         "Workspace: {}",
@@ -28,15 +27,28 @@ pub fn info(workspace_name: Option<Slug>) -> eyre::Result<()> {
     ))?;
 
     if let Some(path) = workspace.config_path.to_str() {
-        formatter.line(&format!("Path: {path}"), 2)?;
+        if workspace.is_local() {
+            formatter.line(&format!("Path: {path} (repo-local)"), 2)?;
+        } else {
+            formatter.line(&format!("Path: {path}"), 2)?;
+        }
     }
 
     formatter.new_line()?;
     formatter.heading(&format!("Projects: {}", workspace.config().projects.len()))?;
     for (name, project) in workspace.config().projects.iter() {
-        let is_valid = Project::from_dir(&project.dir).is_ok();
-        if is_valid {
+        let loaded = Project::from_dir(&project.dir);
+        if let Ok(loaded) = loaded {
             formatter.success(&format!("{}: {}", name, project.dir.display()))?;
+
+            if git {
+                let status = if loaded.manifest().git.clone().unwrap_or_default().enabled {
+                    GitStatus::gather(&project.dir, workspace.config().default_branch_for(project))
+                } else {
+                    GitStatus::disabled()
+                };
+                formatter.line(&format!("Git: {}", status.format(&theme)), 4)?;
+            }
         } else {
             formatter.error(
                 &format!("{}: {}", name, project.dir.display()),
@@ -0,0 +1,492 @@
+use std::path::Path;
+
+use dialoguer::Confirm;
+use eyre::{Context, eyre};
+
+use crate::{
+    config::Config,
+    project::config::ProjectManifest,
+    types::Slug,
+    utils::{is_interactive, ui::UserInterface},
+    workspace::{Workspace, WorkspaceProject},
+};
+
+/// Moves the projects of workspace `from` into workspace `into`, pointing each project's
+/// manifest at its new workspace. With `tasks`, the workspace tasks are copied too.
+///
+/// A project whose ID or directory is already registered differently in `into` is only moved
+/// if the user agrees when asked. With `delete_source`, `from` is deleted afterwards, unless
+/// some of its projects were left behind or it is a repo-local workspace.
+pub fn merge(
+    from: Slug,
+    into: Slug,
+    tasks: bool,
+    delete_source: bool,
+    cwd: &Path,
+) -> eyre::Result<()> {
+    if from == into {
+        return Err(eyre!("Can't merge workspace '{}' into itself", from));
+    }
+
+    let ui = UserInterface::new();
+
+    // Always lock in the same order, so two merges in opposite directions can't deadlock
+    let (first, second) = if from < into {
+        (&from, &into)
+    } else {
+        (&into, &from)
+    };
+    let _first_lock = Workspace::lock(first, cwd)?;
+    let _second_lock = Workspace::lock(second, cwd)?;
+
+    let mut source = Workspace::require(&from, cwd)?;
+
+    let mut target = Workspace::require(&into, cwd)?;
+
+    ui.heading(&format!("Merging workspace {from} into {into}"))?;
+
+    let merged = merge_workspaces(
+        &ui,
+        &mut source,
+        &mut target,
+        tasks,
+        delete_source,
+        &mut confirm,
+    )?;
+
+    if merged.source_deleted {
+        let mut config = Config::load()
+            .map_err(|e| eyre!(e))
+            .wrap_err("Failed to load application config")?;
+        if config.get_active_workspace() == Some(&from) {
+            config.set_active_workspace(Some(into.clone()));
+            config
+                .save()
+                .map_err(|e| eyre!(e))
+                .wrap_err("Failed to save application config")?;
+        }
+    }
+
+    ui.success_item(
+        &format!(
+            "Moved {} project(s) from {} into {}",
+            merged.moved.len(),
+            ui.theme.highlight(from.as_str()),
+            ui.theme.highlight(into.as_str())
+        ),
+        None,
+    )?;
+
+    if !merged.stale_manifests.is_empty() {
+        let ids = merged
+            .stale_manifests
+            .iter()
+            .map(|id| id.to_string())
+            .collect::<Vec<_>>();
+        return Err(eyre!(
+            "Failed to point the manifests of {} at workspace '{}'; \
+             set `workspace = \"{}\"` in their de.toml",
+            ids.join(", "),
+            into,
+            into
+        ));
+    }
+
+    Ok(())
+}
+
+/// The result of [`merge_workspaces`].
+#[derive(Debug, Default)]
+struct Merged {
+    moved: Vec<Slug>,
+    left_behind: Vec<Slug>,
+    source_deleted: bool,
+    /// Moved projects whose `de.toml` couldn't be updated, so it still names the source.
+    stale_manifests: Vec<Slug>,
+}
+
+/// Moves the projects, and with `tasks` the tasks, of `source` into `target`, asking `confirm`
+/// about conflicts.
+///
+/// Both workspaces are saved, or the source deleted, before any manifest is rewritten, so a
+/// failed save leaves every file as it was.
+fn merge_workspaces(
+    ui: &UserInterface,
+    source: &mut Workspace,
+    target: &mut Workspace,
+    tasks: bool,
+    delete_source: bool,
+    confirm: &mut dyn FnMut(&str) -> eyre::Result<bool>,
+) -> eyre::Result<Merged> {
+    let from = source.config().name.clone();
+    let into = target.config().name.clone();
+
+    // The file of a repo-local workspace is part of its repository, so it is not de's to delete
+    if delete_source && source.is_local() {
+        return Err(eyre!(
+            "Workspace '{}' is defined in {}, which --delete-source won't delete; merge without it \
+             and remove the file from its repository",
+            from,
+            source.config_path.display()
+        ));
+    }
+
+    let mut merged = Merged::default();
+    let mut moved_projects = Vec::new();
+
+    for (id, project) in source.config().projects.clone() {
+        if !resolve_project_conflict(ui, target, &id, &project, confirm)? {
+            merged.left_behind.push(id);
+            continue;
+        }
+
+        // Drop a registration of the same directory under another ID
+        let same_dir = target
+            .config()
+            .projects
+            .iter()
+            .filter(|(other, existing)| **other != id && existing.dir == project.dir)
+            .map(|(other, _)| other.clone())
+            .collect::<Vec<_>>();
+        for other in &same_dir {
+            target.remove_project(other);
+        }
+
+        target.add_project(id.clone(), project.clone());
+        source.remove_project(&id);
+        moved_projects.push((id, project));
+    }
+
+    let mut copied_tasks = Vec::new();
+    if tasks {
+        for (name, command) in source.config().tasks.clone() {
+            let copy = match target.config().tasks.get(&name) {
+                Some(existing) if *existing == command => false,
+                Some(existing) => confirm(&format!(
+                    "Task {name} is `{existing}` in {into} and `{command}` in {from}. Replace it?"
+                ))?,
+                None => true,
+            };
+
+            if copy {
+                target.config_mut().tasks.insert(name.clone(), command);
+                copied_tasks.push(name);
+            }
+        }
+    }
+
+    target
+        .save()
+        .map_err(|e| eyre!(e))
+        .wrap_err_with(|| format!("Failed to save workspace '{into}'"))?;
+
+    merged.source_deleted = delete_source && merged.left_behind.is_empty();
+    if merged.source_deleted {
+        std::fs::remove_file(&source.config_path)
+            .map_err(|e| eyre!(e))
+            .wrap_err_with(|| format!("Failed to delete workspace '{from}'"))?;
+    } else {
+        source
+            .save()
+            .map_err(|e| eyre!(e))
+            .wrap_err_with(|| format!("Failed to save workspace '{from}'"))?;
+    }
+
+    for (id, project) in moved_projects {
+        if let Err(e) = update_manifest_workspace(ui, &id, &project, &into) {
+            ui.error_item(&format!("{e:#}"), None)?;
+            merged.stale_manifests.push(id.clone());
+        } else {
+            ui.success_item(&format!("Moved {id}"), None)?;
+        }
+        merged.moved.push(id);
+    }
+
+    for name in copied_tasks {
+        ui.success_item(&format!("Copied task {name}"), None)?;
+    }
+
+    ui.new_line()?;
+
+    if !merged.left_behind.is_empty() {
+        let left_behind = merged
+            .left_behind
+            .iter()
+            .map(|id| id.to_string())
+            .collect::<Vec<_>>();
+        ui.warning_item(
+            &format!("Left in {from}: {}", left_behind.join(", ")),
+            delete_source.then_some("The source workspace was kept, as it still has projects"),
+        )?;
+    }
+
+    if merged.source_deleted {
+        ui.success_item(&format!("Deleted workspace {from}"), None)?;
+    }
+
+    Ok(merged)
+}
+
+/// Checks the project against the registrations of the target workspace. Returns whether it
+/// should be moved, asking the user if its ID or directory is taken.
+fn resolve_project_conflict(
+    ui: &UserInterface,
+    target: &Workspace,
+    id: &Slug,
+    project: &WorkspaceProject,
+    confirm: &mut dyn FnMut(&str) -> eyre::Result<bool>,
+) -> eyre::Result<bool> {
+    let into = &target.config().name;
+
+    if let Some(existing) = target.project(id)
+        && existing.dir != project.dir
+    {
+        let replace = confirm(&format!(
+            "Project {id} is registered in {into} with {}. Replace it with {}?",
+            existing.dir.display(),
+            project.dir.display()
+        ))?;
+        if !replace {
+            ui.warning_item(&format!("Skipped {id}: the ID is taken in {into}"), None)?;
+        }
+        return Ok(replace);
+    }
+
+    if let Some((other, _)) = target
+        .config()
+        .projects
+        .iter()
+        .find(|(other, existing)| *other != id && existing.dir == project.dir)
+    {
+        let replace = confirm(&format!(
+            "{} is registered in {into} as {other}. Register it as {id} instead?",
+            project.dir.display()
+        ))?;
+        if !replace {
+            ui.warning_item(
+                &format!("Skipped {id}: its directory is registered in {into} as {other}"),
+                None,
+            )?;
+        }
+        return Ok(replace);
+    }
+
+    Ok(true)
+}
+
+/// Points the `workspace` field of the project's `de.toml` at `workspace`, so the project is
+/// found in its new workspace.
+fn update_manifest_workspace(
+    ui: &UserInterface,
+    id: &Slug,
+    project: &WorkspaceProject,
+    workspace: &Slug,
+) -> eyre::Result<()> {
+    let manifest_path = project.dir.join("de.toml");
+    if !manifest_path.exists() {
+        ui.warning_item(
+            &format!("{id} has no de.toml, so only its registration was moved"),
+            None,
+        )?;
+        return Ok(());
+    }
+
+    let mut manifest = ProjectManifest::load(&manifest_path)
+        .map_err(|e| eyre!(e))
+        .wrap_err_with(|| format!("Failed to load manifest of project '{id}'"))?;
+
+    if manifest.project.workspace == *workspace {
+        return Ok(());
+    }
+
+    manifest.project.workspace = workspace.clone();
+    manifest
+        .save(&manifest_path)
+        .map_err(|e| eyre!(e))
+        .wrap_err_with(|| format!("Failed to update manifest of project '{id}'"))
+}
+
+/// Asks the user to confirm, declining when there is no terminal to ask on.
+fn confirm(prompt: &str) -> eyre::Result<bool> {
+    if !is_interactive() {
+        return Ok(false);
+    }
+
+    Confirm::new()
+        .with_prompt(prompt)
+        .default(false)
+        .interact()
+        .map_err(|e| eyre!(e))
+        .wrap_err("Failed to prompt for confirmation")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::Path, str::FromStr};
+
+    use super::*;
+
+    fn slug(name: &str) -> Slug {
+        Slug::from_str(name).unwrap()
+    }
+
+    /// A saved workspace named `name` in `dir`, with a project for each of `projects`.
+    fn workspace_in(dir: &Path, name: &str, projects: &[&str]) -> Workspace {
+        let path = dir.join(format!("{name}.toml"));
+        std::fs::write(&path, format!("name = \"{name}\"\n\n[projects]\n")).unwrap();
+        let mut workspace = Workspace::load_from_path(path).unwrap().unwrap();
+        for id in projects {
+            let project_dir = dir.join(id);
+            std::fs::create_dir_all(&project_dir).unwrap();
+            std::fs::write(
+                project_dir.join("de.toml"),
+                format!("[project]\nname = \"{id}\"\nworkspace = \"{name}\"\n"),
+            )
+            .unwrap();
+            workspace.add_project(slug(id), WorkspaceProject::new(project_dir).unwrap());
+        }
+        workspace.save().unwrap();
+        workspace
+    }
+
+    fn reload(workspace: &Workspace) -> Workspace {
+        Workspace::load_from_path(workspace.config_path.clone())
+            .unwrap()
+            .unwrap()
+    }
+
+    fn manifest_workspace(dir: &Path) -> Slug {
+        ProjectManifest::load(&dir.join("de.toml"))
+            .unwrap()
+            .project
+            .workspace
+    }
+
+    #[test]
+    fn test_merge_moves_projects_and_tasks() {
+        let dir = tempfile::tempdir().unwrap();
+        let ui = UserInterface::new().buffered();
+        let mut source = workspace_in(dir.path(), "old", &["api", "web"]);
+        source
+            .config_mut()
+            .tasks
+            .insert(slug("deploy"), "make deploy".to_string());
+        let mut target = workspace_in(dir.path(), "new", &[]);
+
+        let merged = merge_workspaces(&ui, &mut source, &mut target, true, false, &mut |_| {
+            panic!("nothing conflicts")
+        })
+        .unwrap();
+
+        assert_eq!(merged.moved, [slug("api"), slug("web")]);
+        assert!(merged.left_behind.is_empty());
+        assert!(merged.stale_manifests.is_empty());
+        assert!(!merged.source_deleted);
+
+        let target = reload(&target);
+        assert_eq!(
+            target.config().projects.keys().collect::<Vec<_>>(),
+            [&slug("api"), &slug("web")]
+        );
+        assert_eq!(target.config().tasks[&slug("deploy")], "make deploy");
+        assert!(reload(&source).config().projects.is_empty());
+        assert_eq!(manifest_workspace(&dir.path().join("api")), slug("new"));
+        assert_eq!(manifest_workspace(&dir.path().join("web")), slug("new"));
+    }
+
+    #[test]
+    fn test_merge_leaves_declined_conflicts_behind() {
+        let dir = tempfile::tempdir().unwrap();
+        let ui = UserInterface::new().buffered();
+        let mut source = workspace_in(dir.path(), "old", &["api", "web"]);
+        let other = tempfile::tempdir().unwrap();
+        let mut target = workspace_in(other.path(), "new", &["api"]);
+
+        let mut prompts = Vec::new();
+        let merged = merge_workspaces(&ui, &mut source, &mut target, false, true, &mut |prompt| {
+            prompts.push(prompt.to_string());
+            Ok(false)
+        })
+        .unwrap();
+
+        assert_eq!(prompts.len(), 1);
+        assert!(prompts[0].starts_with("Project api is registered in new"));
+        assert_eq!(merged.moved, [slug("web")]);
+        assert_eq!(merged.left_behind, [slug("api")]);
+
+        // The source still has a project, so it survives --delete-source
+        assert!(!merged.source_deleted);
+        let source = reload(&source);
+        assert_eq!(
+            source.config().projects.keys().collect::<Vec<_>>(),
+            [&slug("api")]
+        );
+        assert_eq!(manifest_workspace(&dir.path().join("api")), slug("old"));
+        assert_eq!(
+            reload(&target).project(&slug("api")).unwrap().dir,
+            other.path().join("api")
+        );
+    }
+
+    #[test]
+    fn test_merge_deletes_the_emptied_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let ui = UserInterface::new().buffered();
+        let mut source = workspace_in(dir.path(), "old", &["api"]);
+        let mut target = workspace_in(dir.path(), "new", &[]);
+
+        let merged = merge_workspaces(&ui, &mut source, &mut target, false, true, &mut |_| {
+            Ok(true)
+        })
+        .unwrap();
+
+        assert!(merged.source_deleted);
+        assert!(!source.config_path.exists());
+        assert!(reload(&target).project(&slug("api")).is_some());
+        assert_eq!(manifest_workspace(&dir.path().join("api")), slug("new"));
+    }
+
+    #[test]
+    fn test_merge_keeps_the_file_of_a_local_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let ui = UserInterface::new().buffered();
+        std::fs::create_dir(dir.path().join(".de")).unwrap();
+        std::fs::write(
+            dir.path().join(".de/workspace.toml"),
+            "name = \"old\"\n\n[projects]\n",
+        )
+        .unwrap();
+        let mut source = Workspace::load_from_dir(dir.path()).unwrap().unwrap();
+        let other = tempfile::tempdir().unwrap();
+        let mut target = workspace_in(other.path(), "new", &[]);
+
+        assert!(
+            merge_workspaces(&ui, &mut source, &mut target, false, true, &mut |_| Ok(
+                true
+            ))
+            .is_err()
+        );
+        assert!(source.config_path.exists());
+    }
+
+    #[test]
+    fn test_merge_saves_before_rewriting_manifests() {
+        let dir = tempfile::tempdir().unwrap();
+        let ui = UserInterface::new().buffered();
+        let mut source = workspace_in(dir.path(), "old", &["api"]);
+        let mut target = workspace_in(dir.path(), "new", &[]);
+
+        // Saving the target fails, so no manifest may point at it
+        std::fs::remove_file(&target.config_path).unwrap();
+        std::fs::create_dir(&target.config_path).unwrap();
+
+        assert!(
+            merge_workspaces(&ui, &mut source, &mut target, false, false, &mut |_| Ok(
+                true
+            ))
+            .is_err()
+        );
+        assert_eq!(manifest_workspace(&dir.path().join("api")), slug("old"));
+        assert!(reload(&source).project(&slug("api")).is_some());
+    }
+}
@@ -1,12 +1,22 @@
+mod clone;
 mod config;
 mod info;
+mod merge;
+mod projects;
 mod run;
 
-use std::{fs::File, path::PathBuf};
+use std::{
+    fs::File,
+    io::{Cursor, Write},
+    path::{Path, PathBuf},
+};
 
-pub use config::config;
+pub use clone::clone;
+pub use config::{config, config_path, edit as config_edit};
 pub use info::info;
-pub use run::run;
+pub use merge::merge;
+pub use projects::{add_project, list_projects, remove_project, set_project_enabled};
+pub use run::{list_tasks, run};
 use tempfile::TempDir;
 
 use crate::{
@@ -16,18 +26,41 @@ use crate::{
 };
 use eyre::{WrapErr, eyre};
 
-pub fn snapshot(workspace_name: Option<Slug>, profile: Slug) -> eyre::Result<()> {
-    let workspace = get_workspace_for_cli(Some(workspace_name))?;
+pub fn snapshot(
+    workspace_name: Option<Slug>,
+    profile: Slug,
+    include_uncommitted: bool,
+    output: Option<PathBuf>,
+    base: Option<PathBuf>,
+    jobs: usize,
+    cwd: &Path,
+) -> eyre::Result<()> {
+    let workspace = get_workspace_for_cli(Some(workspace_name), cwd)?;
     let workspace_name = workspace.config().name.clone();
 
-    let ui = UserInterface::new();
-
-    let (snapshot_dir, snapshot) = create_snapshot(&ui, workspace, profile)
-        .map_err(|e| eyre!(e))
-        .wrap_err_with(|| format!("Failed to create snapshot for workspace: {workspace_name}"))?;
+    let output = output.unwrap_or_else(|| PathBuf::from(format!("{workspace_name}.zip")));
+    let to_stdout = output == Path::new("-");
+
+    // Keep stdout free for the zip when streaming it
+    let ui = if to_stdout {
+        UserInterface::new().stderr()
+    } else {
+        UserInterface::new()
+    };
+
+    let (snapshot_dir, snapshot) = create_snapshot(
+        &ui,
+        workspace,
+        profile,
+        include_uncommitted,
+        base.as_deref(),
+        jobs,
+    )
+    .map_err(|e| eyre!(e))
+    .wrap_err_with(|| format!("Failed to create snapshot for workspace: {workspace_name}"))?;
 
     ui.new_line()?;
-    zip_snapshot(&ui, &workspace_name, &snapshot_dir, &snapshot)?;
+    zip_snapshot(&ui, &workspace_name, &snapshot_dir, &snapshot, &output)?;
 
     Ok(())
 }
@@ -37,6 +70,7 @@ fn zip_snapshot(
     workspace_name: &Slug,
     snapshot_dir: &TempDir,
     snapshot: &Snapshot,
+    output: &Path,
 ) -> eyre::Result<()> {
     ui.heading("Bundle")?;
 
@@ -66,24 +100,36 @@ fn zip_snapshot(
         None,
     )?;
 
-    let zip_path = PathBuf::from(format!("{workspace_name}.zip"));
-    let zip_file = File::create(&zip_path)
-        .map_err(|e| eyre!(e))
-        .wrap_err_with(|| format!("Failed to create zip file: {}", zip_path.display()))?;
-
-    zip_dir(zip_file, snapshot_dir.path())
-        .map_err(|e| eyre!(e))
-        .wrap_err_with(|| format!("Failed to zip snapshot directory for: {workspace_name}"))?;
-
-    let zip_size = std::fs::metadata(&zip_path)
-        .map_err(|e| eyre!(e))
-        .wrap_err_with(|| {
-            format!(
-                "Failed to get metadata for zip file: {}",
-                zip_path.display()
-            )
-        })?
-        .len();
+    let zip_size = if output == Path::new("-") {
+        // Stdout can't seek, so the zip is assembled in memory first
+        let mut buffer = Cursor::new(Vec::new());
+        zip_dir(&mut buffer, snapshot_dir.path())
+            .map_err(|e| eyre!(e))
+            .wrap_err_with(|| format!("Failed to zip snapshot directory for: {workspace_name}"))?;
+
+        let buffer = buffer.into_inner();
+        let mut stdout = std::io::stdout().lock();
+        stdout
+            .write_all(&buffer)
+            .and_then(|_| stdout.flush())
+            .map_err(|e| eyre!(e))
+            .wrap_err("Failed to write zip to stdout")?;
+
+        buffer.len() as u64
+    } else {
+        let zip_file = File::create(output)
+            .map_err(|e| eyre!(e))
+            .wrap_err_with(|| format!("Failed to create zip file: {}", output.display()))?;
+
+        zip_dir(zip_file, snapshot_dir.path())
+            .map_err(|e| eyre!(e))
+            .wrap_err_with(|| format!("Failed to zip snapshot directory for: {workspace_name}"))?;
+
+        std::fs::metadata(output)
+            .map_err(|e| eyre!(e))
+            .wrap_err_with(|| format!("Failed to get metadata for zip file: {}", output.display()))?
+            .len()
+    };
 
     if let Some(checksum) = &snapshot.checksum {
         ui.success_item(&format!("Checksum: {}", checksum), None)?;
@@ -92,7 +138,11 @@ fn zip_snapshot(
     ui.success_item(
         &format!(
             "Output: {} {}",
-            zip_path.display(),
+            if output == Path::new("-") {
+                "stdout".into()
+            } else {
+                output.display().to_string()
+            },
             ui.theme.dim(&format!("({zip_size}b)"))
         ),
         None,
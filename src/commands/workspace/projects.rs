@@ -0,0 +1,362 @@
+use std::path::{Path, PathBuf};
+
+use eyre::{Context, eyre};
+
+use crate::{
+    error::DeError,
+    project::Project,
+    types::Slug,
+    utils::{table::Table, ui::UserInterface},
+    workspace::{Workspace, WorkspaceProject},
+};
+
+/// Registers a directory as a project of the workspace, without requiring a `de.toml` in it.
+pub fn add_project(
+    workspace_name: Option<Slug>,
+    id: Slug,
+    dir: PathBuf,
+    cwd: &Path,
+) -> eyre::Result<()> {
+    let ui = UserInterface::new();
+    let workspace_name = resolve_workspace_name(workspace_name, cwd)?;
+
+    if !dir.is_dir() {
+        return Err(eyre!("{} is not a directory", dir.display()));
+    }
+
+    let dir = dir
+        .canonicalize()
+        .map_err(|e| eyre!(e))
+        .wrap_err_with(|| format!("Failed to canonicalize {}", dir.display()))?;
+
+    let _lock = Workspace::lock(&workspace_name, cwd)?;
+
+    let mut workspace = match Workspace::load_from_name(&workspace_name, cwd)
+        .map_err(|e| eyre!(e))
+        .wrap_err_with(|| format!("Failed to load workspace '{workspace_name}'"))?
+    {
+        Some(workspace) => workspace,
+        None => Workspace::new(workspace_name.clone())?,
+    };
+
+    register(&ui, &mut workspace, id, dir)
+}
+
+/// Adds the canonical `dir` to the workspace under `id` and saves it.
+fn register(
+    ui: &UserInterface,
+    workspace: &mut Workspace,
+    id: Slug,
+    dir: PathBuf,
+) -> eyre::Result<()> {
+    let workspace_name = workspace.config().name.clone();
+
+    if let Some(existing) = workspace.project(&id) {
+        if existing.dir == dir {
+            ui.info_item(&format!(
+                "Project {id} is already registered in workspace {workspace_name}"
+            ))?;
+            return Ok(());
+        }
+
+        return Err(eyre!(
+            "Project '{}' is already registered with {} (run `de workspace remove-project {}` first)",
+            id,
+            existing.dir.display(),
+            id
+        ));
+    }
+
+    workspace.add_project(id.clone(), WorkspaceProject::new_unchecked(dir.clone()));
+    workspace
+        .save()
+        .map_err(|e| eyre!(e))
+        .wrap_err_with(|| format!("Failed to save workspace '{workspace_name}'"))?;
+
+    ui.success_item(
+        &format!(
+            "Added {} to workspace {} ({})",
+            ui.theme.highlight(id.as_str()),
+            ui.theme.highlight(workspace_name.as_str()),
+            dir.display()
+        ),
+        None,
+    )?;
+
+    if !dir.join("de.toml").exists() {
+        ui.warning_item(
+            "The directory has no de.toml, so commands that load the project will fail",
+            Some(&format!(
+                "Run `de init --workspace {workspace_name} --name {id}` in the directory to create one. `de scan --prune` removes projects without one"
+            )),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Removes a project from the workspace. Its directory and manifest are left untouched.
+pub fn remove_project(workspace_name: Option<Slug>, id: Slug, cwd: &Path) -> eyre::Result<()> {
+    let ui = UserInterface::new();
+    let workspace_name = resolve_workspace_name(workspace_name, cwd)?;
+
+    let _lock = Workspace::lock(&workspace_name, cwd)?;
+
+    let mut workspace = Workspace::require(&workspace_name, cwd)?;
+
+    unregister(&ui, &mut workspace, id)
+}
+
+/// Removes `id` from the workspace and saves it.
+fn unregister(ui: &UserInterface, workspace: &mut Workspace, id: Slug) -> eyre::Result<()> {
+    let workspace_name = workspace.config().name.clone();
+
+    let Some(project) = workspace.project(&id).cloned() else {
+        return Err(eyre!(DeError::ProjectNotFound {
+            project: id,
+            workspace: workspace_name,
+        }));
+    };
+
+    workspace.remove_project(&id);
+    workspace
+        .save()
+        .map_err(|e| eyre!(e))
+        .wrap_err_with(|| format!("Failed to save workspace '{workspace_name}'"))?;
+
+    ui.success_item(
+        &format!(
+            "Removed {} from workspace {}",
+            ui.theme.highlight(id.as_str()),
+            ui.theme.highlight(workspace_name.as_str())
+        ),
+        Some(&format!(
+            "{} and its files were not changed",
+            project.dir.display()
+        )),
+    )?;
+
+    Ok(())
+}
+
+/// Lists the projects registered in the workspace, with their directory and whether they are
+/// disabled or have no `de.toml`.
+pub fn list_projects(workspace_name: Option<Slug>, cwd: &Path) -> eyre::Result<()> {
+    let ui = UserInterface::new();
+    let workspace_name = resolve_workspace_name(workspace_name, cwd)?;
+    let workspace = Workspace::require(&workspace_name, cwd)?;
+
+    if workspace.config().projects.is_empty() {
+        ui.info_item(&format!(
+            "No projects registered in workspace {workspace_name}"
+        ))?;
+        return Ok(());
+    }
+
+    ui.heading(&format!(
+        "Projects registered in workspace {workspace_name}:"
+    ))?;
+    let mut table = Table::new();
+    for [id, dir, notes] in registration_rows(&workspace) {
+        let mut row = vec![ui.theme.highlight(&id), dir];
+        if !notes.is_empty() {
+            row.push(ui.theme.dim(&notes));
+        }
+        table.add_row(row);
+    }
+    table.print(&ui)?;
+
+    Ok(())
+}
+
+/// The ID, directory and notes of each registration, sorted by ID.
+fn registration_rows(workspace: &Workspace) -> Vec<[String; 3]> {
+    workspace
+        .config()
+        .projects
+        .iter()
+        .map(|(id, project)| {
+            let mut notes = Vec::new();
+            if !project.enabled {
+                notes.push("disabled");
+            }
+            if !project.dir.exists() {
+                notes.push("missing directory");
+            } else if !project.dir.join("de.toml").exists() {
+                notes.push("no de.toml");
+            }
+
+            [
+                id.to_string(),
+                project.dir.display().to_string(),
+                notes.join(", "),
+            ]
+        })
+        .collect()
+}
+
+/// Enables or disables a project. Disabled projects stay registered, but workspace-wide commands
+/// like `de start`, `de status` and `de git switch` skip them.
+pub fn set_project_enabled(
+    workspace_name: Option<Slug>,
+    id: Slug,
+    enabled: bool,
+    cwd: &Path,
+) -> eyre::Result<()> {
+    let ui = UserInterface::new();
+    let workspace_name = resolve_workspace_name(workspace_name, cwd)?;
+
+    let _lock = Workspace::lock(&workspace_name, cwd)?;
+
+    let mut workspace = Workspace::require(&workspace_name, cwd)?;
+
+    let state = if enabled { "enabled" } else { "disabled" };
+
+    let Some(project) = workspace.config_mut().projects.get_mut(&id) else {
+        return Err(eyre!(DeError::ProjectNotFound {
+            project: id,
+            workspace: workspace_name,
+        }));
+    };
+
+    if project.enabled == enabled {
+        ui.info_item(&format!("Project {id} is already {state}"))?;
+        return Ok(());
+    }
+
+    project.enabled = enabled;
+    workspace
+        .save()
+        .map_err(|e| eyre!(e))
+        .wrap_err_with(|| format!("Failed to save workspace '{workspace_name}'"))?;
+
+    ui.success_item(
+        &format!(
+            "Project {} is now {state} in workspace {}",
+            ui.theme.highlight(id.as_str()),
+            ui.theme.highlight(workspace_name.as_str())
+        ),
+        None,
+    )?;
+
+    // Dependents of a disabled project can't start until they're disabled too
+    if !enabled {
+        let dependents = workspace
+            .enabled_projects()
+            .filter_map(|(dependent, ws_project)| {
+                let project = Project::from_dir(&ws_project.dir).ok()?;
+                project
+                    .manifest()
+                    .project()
+                    .depends_on
+                    .as_ref()?
+                    .contains(&id)
+                    .then(|| dependent.to_string())
+            })
+            .collect::<Vec<_>>();
+
+        if !dependents.is_empty() {
+            ui.warning_item(
+                &format!("Enabled projects depend on {id}: {}", dependents.join(", ")),
+                Some("Disable them too, or workspace commands will fail to resolve dependencies"),
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The given workspace name, or the name of the active workspace.
+fn resolve_workspace_name(workspace_name: Option<Slug>, cwd: &Path) -> eyre::Result<Slug> {
+    if let Some(workspace_name) = workspace_name {
+        return Ok(workspace_name);
+    }
+
+    Ok(Workspace::require_active(cwd)?.config().name.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn slug(name: &str) -> Slug {
+        Slug::from_str(name).unwrap()
+    }
+
+    /// A saved, empty workspace in a temporary directory.
+    fn workspace_in(dir: &std::path::Path) -> Workspace {
+        let path = dir.join("test.toml");
+        std::fs::write(&path, "name = \"test\"\n\n[projects]\n").unwrap();
+        Workspace::load_from_path(path).unwrap().unwrap()
+    }
+
+    fn reload(workspace: &Workspace) -> Workspace {
+        Workspace::load_from_path(workspace.config_path.clone())
+            .unwrap()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_register_saves_a_directory_without_a_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let project_dir = dir.path().join("legacy-api");
+        std::fs::create_dir(&project_dir).unwrap();
+        let ui = UserInterface::new().buffered();
+
+        let mut workspace = workspace_in(dir.path());
+        register(&ui, &mut workspace, slug("legacy-api"), project_dir.clone()).unwrap();
+        assert_eq!(
+            reload(&workspace).project(&slug("legacy-api")).unwrap().dir,
+            project_dir
+        );
+        assert_eq!(
+            registration_rows(&workspace),
+            [[
+                "legacy-api".to_string(),
+                project_dir.display().to_string(),
+                "no de.toml".to_string()
+            ]]
+        );
+
+        // Registering the same directory again is a no-op, another one is refused
+        register(&ui, &mut workspace, slug("legacy-api"), project_dir.clone()).unwrap();
+        assert!(
+            register(
+                &ui,
+                &mut workspace,
+                slug("legacy-api"),
+                dir.path().to_path_buf()
+            )
+            .is_err()
+        );
+        assert_eq!(
+            reload(&workspace).project(&slug("legacy-api")).unwrap().dir,
+            project_dir
+        );
+    }
+
+    #[test]
+    fn test_unregister_removes_only_the_registration() {
+        let dir = tempfile::tempdir().unwrap();
+        let project_dir = dir.path().join("api");
+        std::fs::create_dir(&project_dir).unwrap();
+        std::fs::write(project_dir.join("de.toml"), "").unwrap();
+        let ui = UserInterface::new().buffered();
+
+        let mut workspace = workspace_in(dir.path());
+        register(&ui, &mut workspace, slug("api"), project_dir.clone()).unwrap();
+        assert_eq!(registration_rows(&workspace)[0][2], "");
+
+        unregister(&ui, &mut workspace, slug("api")).unwrap();
+        assert!(reload(&workspace).project(&slug("api")).is_none());
+        assert!(project_dir.join("de.toml").exists());
+
+        let error = unregister(&ui, &mut workspace, slug("api")).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<DeError>(),
+            Some(DeError::ProjectNotFound { .. })
+        ));
+    }
+}
@@ -1,41 +1,177 @@
 use eyre::{Context, Result, bail, eyre};
-use std::process::Command;
+use std::{path::Path, process::Command, time::Duration};
 
-use crate::{types::Slug, workspace::Workspace};
+use crate::{
+    cli::TaskListFormat,
+    commands::task::{TaskListing, print_task_listings},
+    error::DeError,
+    project::expand_task_command,
+    types::Slug,
+    utils::{
+        process::{ProcessOutcome, print_dry_run, run_with_timeout},
+        table::Table,
+        ui::UserInterface,
+    },
+    workspace::Workspace,
+};
 
-pub fn run(workspace_name: Option<Slug>, task_name: Slug, args: Vec<String>) -> Result<()> {
-    let workspace = if let Some(workspace_name) = workspace_name {
-        Workspace::load_from_name(&workspace_name)
-            .map_err(|e| eyre!(e))
-            .wrap_err("Failed to load workspace")?
-            .ok_or_else(|| eyre!("Workspace '{}' not found", workspace_name))?
+/// Prints the tasks of the workspace with their commands, marking tasks named like a project of
+/// the workspace, as `de <name>` runs the project's task instead.
+pub fn list_tasks(workspace_name: Option<Slug>, format: TaskListFormat, cwd: &Path) -> Result<()> {
+    let workspace = load_workspace(workspace_name, cwd)?;
+    let ui = UserInterface::new();
+    let name = &workspace.config().name;
+
+    if format == TaskListFormat::Json {
+        let listings = workspace
+            .config()
+            .tasks
+            .iter()
+            .map(|(task_name, command)| TaskListing::workspace(task_name, command))
+            .collect::<Vec<_>>();
+        return print_task_listings(&listings);
+    }
+
+    if workspace.config().tasks.is_empty() {
+        ui.info_item(&format!("No tasks defined in workspace '{name}'"))?;
+        return Ok(());
+    }
+
+    ui.heading(&format!("Tasks in workspace {name}:"))?;
+    let mut table = Table::new();
+    for (task_name, command) in &workspace.config().tasks {
+        let task_name = if workspace.project(task_name).is_some() {
+            format!(
+                "{task_name} {}",
+                ui.theme.warn("(conflicts with a project name)")
+            )
+        } else {
+            task_name.to_string()
+        };
+        table.add_row([task_name, ui.theme.dim(command)]);
+    }
+    table.print(&ui)?;
+
+    Ok(())
+}
+
+fn load_workspace(workspace_name: Option<Slug>, cwd: &Path) -> Result<Workspace> {
+    if let Some(workspace_name) = workspace_name {
+        Workspace::require(&workspace_name, cwd)
     } else {
-        Workspace::active()
-            .map_err(|e| eyre!(e))
-            .wrap_err("Failed to get active workspace")?
-            .ok_or_else(|| eyre!("No current workspace found"))?
-    };
+        Workspace::require_active(cwd)
+    }
+}
+
+pub fn run(
+    workspace_name: Option<Slug>,
+    task_name: Slug,
+    args: Vec<String>,
+    timeout: Option<Duration>,
+    dry_run: bool,
+    env: &[(String, String)],
+    cwd: &Path,
+) -> Result<()> {
+    let workspace = load_workspace(workspace_name, cwd)?;
 
     let task_command = workspace.config().tasks.get(&task_name).ok_or_else(|| {
-        eyre!(
-            "Task '{}' not found in workspace '{}'",
-            task_name,
-            workspace.config().name
-        )
+        eyre!(DeError::TaskNotFound {
+            task: task_name.clone(),
+            location: format!("workspace '{}'", workspace.config().name),
+        })
     })?;
 
-    let mut parts = task_command.split_whitespace();
-    let program = parts.next().ok_or_else(|| eyre!("Empty command"))?;
-    let task_args = parts.collect::<Vec<_>>();
+    let mut cmd = task_command_with_args(task_command, &task_name, &args)?;
+    cmd.envs(env.iter().cloned());
 
-    let mut cmd = Command::new(program);
-    cmd.args(&task_args);
-    cmd.args(&args);
+    if dry_run {
+        print_dry_run(&cmd);
+        return Ok(());
+    }
 
-    let status = cmd.status()?;
+    let status = match run_with_timeout(&mut cmd, timeout)? {
+        ProcessOutcome::Exited(status) => status,
+        ProcessOutcome::TimedOut => bail!(
+            "Task '{}' timed out after {}s",
+            task_name,
+            timeout.unwrap_or_default().as_secs()
+        ),
+    };
     if !status.success() {
-        bail!("Command exited with non-zero status: {}", status);
+        bail!(DeError::TaskFailed {
+            task: task_name,
+            status
+        });
     }
 
     Ok(())
 }
+
+/// Builds the command for a workspace task, with `args` appended to it.
+fn task_command_with_args(
+    task_command: &str,
+    task_name: &Slug,
+    args: &[String],
+) -> Result<Command> {
+    let argv = expand_task_command(task_command, args)
+        .wrap_err_with(|| format!("Failed to build command for task '{task_name}'"))?;
+    let (program, task_args) = argv.split_first().ok_or_else(|| eyre!("Empty command"))?;
+
+    let mut cmd = Command::new(program);
+    cmd.args(task_args);
+    Ok(cmd)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cli::{Cli, Commands, WorkspaceCommands},
+        utils::process::CommandExt,
+    };
+    use clap::Parser;
+
+    fn parse_args(argv: &[&str]) -> (Slug, Option<Slug>, Vec<String>) {
+        match Cli::try_parse_from(argv).unwrap().command {
+            Commands::Workspace {
+                command:
+                    WorkspaceCommands::Run {
+                        task,
+                        workspace,
+                        args,
+                        ..
+                    },
+            } => (task.unwrap(), workspace, args),
+            command => panic!("unexpected command: {command:?}"),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_task_flags_reach_the_command() {
+        let (task, workspace, args) = parse_args(&[
+            "de",
+            "workspace",
+            "run",
+            "-w",
+            "ws",
+            "greet",
+            "--loud",
+            "-n",
+            "2",
+        ]);
+        assert_eq!(workspace.unwrap().as_str(), "ws");
+        assert_eq!(args, ["--loud", "-n", "2"]);
+
+        let (_, workspace, separated) =
+            parse_args(&["de", "workspace", "run", "greet", "--", "-w", "other"]);
+        assert!(workspace.is_none());
+        assert_eq!(separated, ["-w", "other"]);
+
+        let output = task_command_with_args("echo", &task, &args)
+            .unwrap()
+            .logged_output()
+            .unwrap();
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), "--loud -n 2\n");
+    }
+}
@@ -1,13 +1,106 @@
+use std::path::Path;
+
 use eyre::{Context, eyre};
 use serde::{Deserialize, Serialize};
 
-use crate::{types::Slug, utils::get_project_dirs};
+use crate::{
+    types::Slug,
+    utils::{fs::write_atomic, get_project_dirs, ui::UserInterface},
+};
+
+/// The version of the application config format this build of de writes.
+pub const CONFIG_VERSION: u32 = 1;
+
+/// Upgrades the `version` of the config file at `path`, written by an older de, to `current`.
+/// Version 0 predates the field; every setting added since has a default.
+///
+/// A file written by a newer de is left unchanged, with a warning, since this build may not
+/// understand all of its settings.
+pub fn migrate_version(path: &Path, version: &mut u32, current: u32) {
+    if *version > current {
+        let _ = UserInterface::new().stderr().warning_item(
+            &format!(
+                "{} was written by a newer version of de (config version {}, this one supports \
+                 {}); unknown settings are ignored",
+                path.display(),
+                version,
+                current
+            ),
+            Some("Upgrade de to change it"),
+        );
+    } else if *version == 0 {
+        *version = current;
+    }
+}
+
+/// Fails if the config file at `path` has a `version` newer than `current`, so saving it doesn't
+/// drop the settings this build doesn't know.
+pub fn ensure_writable_version(path: &Path, version: u32, current: u32) -> eyre::Result<()> {
+    if version > current {
+        return Err(eyre!(
+            "{} was written by a newer version of de (config version {version}); upgrade de to \
+             change it",
+            path.display()
+        ));
+    }
+
+    Ok(())
+}
 
 /// Global configuration for the application.
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Config {
+    /// The format version the file was written with. Files from before it existed are version 0.
+    #[serde(default)]
+    pub version: u32,
     /// The active workspace configuration.
     pub active: Option<ActiveConfig>,
+    /// The release track `de self update` installs from, set with `--channel`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub update_channel: Option<UpdateChannel>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            version: CONFIG_VERSION,
+            active: None,
+            update_channel: None,
+        }
+    }
+}
+
+/// A release track of de.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateChannel {
+    /// Only full releases.
+    #[default]
+    Stable,
+    /// Full releases and `-beta` prereleases.
+    Beta,
+    /// Every release, including nightly builds.
+    Nightly,
+}
+
+impl UpdateChannel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UpdateChannel::Stable => "stable",
+            UpdateChannel::Beta => "beta",
+            UpdateChannel::Nightly => "nightly",
+        }
+    }
+
+    /// Whether a release with the prerelease identifier `pre`, empty for full releases, is on
+    /// this track.
+    pub fn includes(&self, pre: &str) -> bool {
+        match self {
+            UpdateChannel::Stable => pre.is_empty(),
+            UpdateChannel::Beta => pre.is_empty() || pre.starts_with("beta"),
+            UpdateChannel::Nightly => true,
+        }
+    }
 }
 
 /// Configuration for the active workspace.
@@ -24,12 +117,13 @@ impl Config {
 
     pub fn save(&self) -> eyre::Result<()> {
         let config_path = Self::config_path()?;
+        ensure_writable_version(&config_path, self.version, CONFIG_VERSION)?;
 
         let config_str = toml::to_string_pretty(self)
             .map_err(|e| eyre!(e))
             .wrap_err("Failed to format config as string")?;
 
-        std::fs::write(&config_path, config_str)
+        write_atomic(&config_path, config_str)
             .map_err(|e| eyre!(e))
             .wrap_err_with(|| format!("Failed to write config to {}", config_path.display()))?;
 
@@ -45,9 +139,13 @@ impl Config {
                 .wrap_err_with(|| {
                     format!("Failed to read config file at {}", config_path.display())
                 })?;
-            toml::from_str(&config_str)
+            let mut config: Self = toml::from_str(&config_str)
                 .map_err(|e| eyre!(e))
-                .wrap_err("Failed to parse config file")
+                .wrap_err("Failed to parse config file")?;
+
+            migrate_version(&config_path, &mut config.version, CONFIG_VERSION);
+
+            Ok(config)
         } else {
             Ok(Self::default())
         }
@@ -1,2 +1,38 @@
 pub const PROJECT_NAME: &str = env!("CARGO_PKG_NAME");
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Commit, build date, target and compiler captured by `build.rs`.
+pub const GIT_SHA: &str = env!("DE_BUILD_GIT_SHA");
+pub const BUILD_DATE: &str = env!("DE_BUILD_DATE");
+pub const BUILD_TARGET: &str = env!("DE_BUILD_TARGET");
+pub const RUSTC_VERSION: &str = env!("DE_BUILD_RUSTC_VERSION");
+
+/// The output of `de --version` and `de self version`.
+pub const LONG_VERSION: &str = concat!(
+    env!("CARGO_PKG_VERSION"),
+    "\ncommit: ",
+    env!("DE_BUILD_GIT_SHA"),
+    "\nbuilt: ",
+    env!("DE_BUILD_DATE"),
+    "\ntarget: ",
+    env!("DE_BUILD_TARGET"),
+    "\nrustc: ",
+    env!("DE_BUILD_RUSTC_VERSION"),
+);
 pub const ORGANIZATION_NAME: &str = "Umbra";
+
+/// Environment variable with a GitHub token for `de self update`, e.g. to avoid rate limits.
+pub const GITHUB_TOKEN_ENV_VAR: &str = "GITHUB_TOKEN";
+
+/// Environment variable that turns on the log file, like `de --log-file`.
+pub const LOG_ENV_VAR: &str = "DE_LOG";
+
+/// A workspace definition inside a repository, relative to a directory at or above the cwd.
+pub const LOCAL_WORKSPACE_FILE: &str = ".de/workspace.toml";
+
+/// Environment variable naming the workspace to use when not inside a project.
+pub const WORKSPACE_ENV_VAR: &str = "DE_WORKSPACE";
+
+/// Environment variable that `de run` sets for task commands, listing the tasks in flight as
+/// `<project>:<task>`, comma-separated, so a task that calls itself through `de run` is caught.
+pub const TASK_STACK_ENV_VAR: &str = "DE_TASK_STACK";
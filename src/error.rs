@@ -0,0 +1,147 @@
+use std::process::ExitStatus;
+
+use crate::types::Slug;
+
+/// Exit codes of the de process, by error category.
+pub mod exit_code {
+    /// Any error without a more specific category.
+    pub const FAILURE: i32 = 1;
+    /// Not inside a project, or no workspace is active.
+    pub const NO_CONTEXT: i32 = 2;
+    /// Docker Compose is missing or failed.
+    pub const DOCKER: i32 = 3;
+    /// A task ran and failed.
+    pub const TASK_FAILED: i32 = 4;
+    /// A named workspace, project or task does not exist.
+    pub const NOT_FOUND: i32 = 5;
+    /// A check of `de status --exit-code` or `--fail-on` found a problem.
+    pub const STATUS_CHECK: i32 = 6;
+    /// `de doctor` found errors, or warnings with `--strict`.
+    pub const DOCTOR: i32 = 7;
+    /// Ctrl-C stopped a running task, the code a shell uses for SIGINT.
+    pub const INTERRUPTED: i32 = 130;
+}
+
+/// Errors that callers may want to tell apart, rather than only display.
+///
+/// They are raised through eyre like any other error and can be found again in the chain of the
+/// resulting report.
+#[derive(Debug, thiserror::Error)]
+pub enum DeError {
+    #[error("Workspace '{0}' not found{hint}", hint = workspace_path_hint(.0))]
+    WorkspaceNotFound(Slug),
+    #[error("Project '{project}' not found in workspace '{workspace}'")]
+    ProjectNotFound { project: Slug, workspace: Slug },
+    #[error("No active workspace found{}", active_workspace_hint())]
+    NoActiveWorkspace,
+    #[error("No current project found")]
+    NoCurrentProject,
+    #[error("Task '{task}' not found in {location}")]
+    TaskNotFound { task: Slug, location: String },
+    #[error("Task '{task}' failed with status: {status}")]
+    TaskFailed { task: Slug, status: ExitStatus },
+    #[error("Task '{task}' would run itself recursively: {cycle}")]
+    RecursiveTask { task: Slug, cycle: String },
+    #[error("docker-compose is not available: {0}")]
+    DockerUnavailable(std::io::Error),
+    #[error("docker-compose {command} failed with status code: {code}")]
+    DockerComposeFailed { command: &'static str, code: i32 },
+    #[error("Status check failed: {0}")]
+    StatusCheckFailed(String),
+    #[error("de doctor found {errors} error(s) and {warnings} warning(s)")]
+    DoctorFailed { errors: u32, warnings: u32 },
+    #[error("Interrupted")]
+    Interrupted,
+}
+
+impl DeError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            DeError::NoActiveWorkspace | DeError::NoCurrentProject => exit_code::NO_CONTEXT,
+            DeError::DockerUnavailable(_) | DeError::DockerComposeFailed { .. } => {
+                exit_code::DOCKER
+            }
+            DeError::TaskFailed { .. } | DeError::RecursiveTask { .. } => exit_code::TASK_FAILED,
+            DeError::WorkspaceNotFound(_)
+            | DeError::ProjectNotFound { .. }
+            | DeError::TaskNotFound { .. } => exit_code::NOT_FOUND,
+            DeError::StatusCheckFailed(_) => exit_code::STATUS_CHECK,
+            DeError::DoctorFailed { .. } => exit_code::DOCTOR,
+            DeError::Interrupted => exit_code::INTERRUPTED,
+        }
+    }
+}
+
+/// Where the configuration file of the workspace would be, to tell where it was looked for.
+fn workspace_path_hint(name: &Slug) -> String {
+    crate::workspace::Workspace::path_from_name(name)
+        .map(|path| format!(" (expected {})", path.display()))
+        .unwrap_or_default()
+}
+
+/// Where the active workspace is recorded, to tell where it was looked for.
+fn active_workspace_hint() -> String {
+    crate::config::Config::config_path()
+        .map(|path| {
+            format!(
+                " (`de start` records the active workspace in {})",
+                path.display()
+            )
+        })
+        .unwrap_or_default()
+}
+
+/// The exit code for `report`, taken from the first `DeError` in its chain.
+pub fn exit_code_for(report: &eyre::Report) -> i32 {
+    report
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<DeError>())
+        .map_or(exit_code::FAILURE, DeError::exit_code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eyre::{WrapErr, eyre};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_exit_code_for_wrapped_error() {
+        let result: eyre::Result<()> = Err(eyre!(DeError::NoCurrentProject));
+        let report = result
+            .map_err(|e| eyre!(e))
+            .wrap_err("Failed to get current project")
+            .unwrap_err();
+
+        assert_eq!(exit_code_for(&report), exit_code::NO_CONTEXT);
+    }
+
+    #[test]
+    fn test_exit_code_for_categories() {
+        let slug = Slug::from_str("app").unwrap();
+
+        assert_eq!(
+            exit_code_for(&eyre!(DeError::WorkspaceNotFound(slug.clone()))),
+            exit_code::NOT_FOUND
+        );
+        assert_eq!(
+            exit_code_for(&eyre!(DeError::DockerComposeFailed {
+                command: "up",
+                code: 1
+            })),
+            exit_code::DOCKER
+        );
+        assert_eq!(
+            exit_code_for(&eyre!(DeError::StatusCheckFailed("dirty: app".to_string()))),
+            exit_code::STATUS_CHECK
+        );
+        assert_eq!(
+            exit_code_for(&eyre!(DeError::DoctorFailed {
+                errors: 0,
+                warnings: 2
+            })),
+            exit_code::DOCTOR
+        );
+        assert_eq!(exit_code_for(&eyre!("Something else")), exit_code::FAILURE);
+    }
+}
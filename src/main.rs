@@ -8,6 +8,7 @@ mod cli;
 mod commands;
 mod config;
 mod constants;
+mod error;
 mod project;
 mod setup;
 mod types;
@@ -15,144 +16,572 @@ mod utils;
 mod workspace;
 
 use clap::Parser;
-use eyre::{Context, eyre};
-use tracing_subscriber::EnvFilter;
+use tracing_subscriber::{EnvFilter, Layer, layer::SubscriberExt};
 
 use crate::{
     cli::{
-        Cli, Commands, GitCommands, SelfCommands, ShimCommands, TaskCommands, WorkspaceCommands,
+        Cli, Commands, GitCommands, SelfCommands, SetupCommands, ShimCommands, StatusCheck,
+        TaskCommands, WorkspaceCommands,
     },
+    types::Slug,
     utils::theme::Theme,
-    workspace::Workspace,
 };
 
 fn main() -> eyre::Result<()> {
-    tracing_subscriber::fmt()
-        .with_env_filter(EnvFilter::from_default_env())
-        .try_init()
-        .expect("Failed to initialize tracing subscriber");
+    let cli = Cli::parse();
+
+    init_tracing(cli.log_file);
 
     color_eyre::config::HookBuilder::default()
         .display_env_section(false)
         .install()?;
 
-    let cli = Cli::parse();
+    let theme = Theme::from_cli(&cli);
+    theme.clone().install();
+
+    utils::process::install_interrupt_handler();
+
+    // The workspace given to `de --workspace`, for commands not given one of their own
+    let default_workspace = cli.workspace.clone();
+    let or_default = |workspace: Option<Slug>| workspace.or_else(|| default_workspace.clone());
+    let or_default_or_active =
+        |workspace: Option<Option<Slug>>| workspace.or_else(|| default_workspace.clone().map(Some));
+
+    // The directory commands work from, which `de --dir` can point elsewhere
+    let working_dir = match utils::resolve_working_dir(cli.dir.as_deref()) {
+        Ok(dir) => dir,
+        Err(err) => exit_with_error(&theme, &err),
+    };
 
     let result = match cli.command {
         Commands::Init {
             path,
             name,
             workspace,
-        } => commands::init(path, name, workspace),
-        Commands::Start { workspace, yes } => commands::start(workspace, yes),
-        Commands::Stop { workspace, yes } => commands::stop(workspace, yes),
+            on_conflict,
+            git,
+        } => commands::init(
+            path,
+            name,
+            or_default(workspace),
+            on_conflict,
+            git,
+            &working_dir,
+        ),
+        Commands::Start {
+            workspace,
+            yes,
+            attach,
+            down_on_exit,
+        } => commands::start(
+            or_default_or_active(workspace),
+            yes,
+            attach,
+            down_on_exit,
+            &working_dir,
+        ),
+        Commands::Stop {
+            workspace,
+            all,
+            yes,
+            timeout,
+        } => commands::stop(
+            or_default(workspace),
+            all,
+            yes,
+            timeout.map(|t| t.as_whole_secs()),
+            &working_dir,
+        ),
+        Commands::Prune {
+            workspace,
+            volumes,
+            images,
+            yes,
+        } => commands::prune(or_default(workspace), volumes, images, yes, &working_dir),
+        Commands::Clean {
+            cache,
+            logs,
+            all,
+            yes,
+        } => commands::clean(cache, logs, all, yes),
         Commands::Run {
             command,
             project,
             workspace,
+            timeout,
+            dry_run,
+            cwd,
+            env,
+            watch,
+            watch_path,
+            auto_start,
+            if_changed,
+            no_deps,
+            jobs,
+            tee,
+            capture,
             args,
-        } => commands::run(command, args, project, workspace),
+        } => {
+            let output = match (tee, capture) {
+                (Some(path), _) => Some(project::TaskOutput { path, echo: true }),
+                (None, Some(path)) => Some(project::TaskOutput { path, echo: false }),
+                (None, None) => None,
+            };
+            let workspace = or_default(workspace);
+            let context = project::TaskContext {
+                cwd,
+                env,
+                auto_start,
+                if_changed,
+                output,
+                no_deps,
+                jobs,
+                skip_service_check: false,
+            };
+            if watch {
+                commands::run_watch(
+                    command,
+                    args,
+                    project,
+                    workspace,
+                    context,
+                    watch_path,
+                    &working_dir,
+                )
+            } else {
+                commands::run(
+                    command,
+                    args,
+                    project,
+                    workspace,
+                    timeout.map(|t| t.as_duration()),
+                    dry_run,
+                    context,
+                    &working_dir,
+                )
+            }
+        }
         Commands::Exec {
             project,
+            workspace_root,
+            workspace,
+            env,
+            no_env,
+            service,
+            no_tty,
+            command,
+        } => commands::exec(
+            project,
+            workspace_root,
+            or_default(workspace),
+            env,
+            no_env,
+            service.map(|service| commands::ServiceExec { service, no_tty }),
+            command,
+            &working_dir,
+        ),
+        Commands::ExecAll {
             workspace,
+            order,
+            subdir,
+            no_env,
             command,
-        } => commands::exec(project, workspace, command),
-        Commands::ExecAll { workspace, command } => commands::exec_all(workspace, command),
-        Commands::List { workspace } => {
-            if let Some(workspace_name) = workspace {
-                let workspace = Workspace::load_from_name(&workspace_name)
-                    .map_err(|e| eyre!(e))
-                    .wrap_err("Failed to load workspace")?
-                    .ok_or_else(|| eyre!("Workspace {} not found", workspace_name))?;
-
-                commands::list(workspace)
+        } => commands::exec_all(
+            or_default(workspace),
+            order,
+            subdir,
+            no_env,
+            command,
+            &working_dir,
+        ),
+        Commands::Env {
+            project,
+            workspace,
+            format,
+            show_secrets,
+        } => commands::env(
+            project,
+            or_default_or_active(workspace),
+            format,
+            show_secrets,
+            &working_dir,
+        ),
+        Commands::List {
+            workspace,
+            missing,
+            prune,
+            yes,
+            sort,
+            porcelain,
+        } => {
+            if missing {
+                commands::list_missing(or_default(workspace), prune, yes, &working_dir)
             } else {
-                let current_workspace =
-                    Workspace::active()?.ok_or_else(|| eyre!("No active workspace found"))?;
-                commands::list(current_workspace)
+                commands::list(or_default(workspace), sort, porcelain, &working_dir)
             }
         }
-        Commands::Scan { dir, workspace } => commands::scan(dir, workspace),
-        Commands::Update { all, workspace } => commands::update(all, workspace),
+        Commands::Scan {
+            dir,
+            workspace,
+            prune,
+            all,
+            init_missing,
+            follow_symlinks,
+        } => commands::scan(
+            dir,
+            or_default(workspace),
+            prune,
+            all,
+            init_missing,
+            follow_symlinks,
+            &working_dir,
+        ),
+        Commands::Update { all, workspace } => {
+            commands::update(all, or_default_or_active(workspace), &working_dir)
+        }
+        Commands::Setup {
+            command: Some(SetupCommands::Inspect { snapshot, format }),
+            ..
+        } => commands::setup::inspect(snapshot, format),
         Commands::Setup {
+            command: None,
             snapshot,
             target_dir: dir,
-        } => commands::setup(snapshot, dir),
+            latest,
+            jobs,
+            only,
+            no_input,
+        } => match snapshot {
+            Some(snapshot) => {
+                commands::setup(snapshot, dir, latest, jobs, only, no_input, &working_dir)
+            }
+            None => Err(eyre::eyre!("No snapshot file given")),
+        },
         Commands::Task { command } => match command {
-            TaskCommands::Check { task } => commands::task::check(task),
-            TaskCommands::List => commands::task::list(),
+            TaskCommands::Check {
+                task,
+                workspace,
+                verbose,
+                quiet,
+            } => commands::task::check(task, or_default(workspace), verbose, quiet, &working_dir),
+            TaskCommands::List { format } => {
+                commands::task::list(format, default_workspace.clone(), &working_dir)
+            }
+            TaskCommands::Edit { task } => {
+                commands::task::edit(task, default_workspace.clone(), &working_dir)
+            }
             TaskCommands::Add {
                 task,
                 task_command,
                 service,
+                description,
+                shell,
                 project,
                 workspace,
-            } => commands::task::add(task, task_command, service, project, workspace),
+            } => commands::task::add(
+                task,
+                task_command,
+                service,
+                description,
+                shell,
+                project,
+                or_default_or_active(workspace),
+                &working_dir,
+            ),
             TaskCommands::Remove {
                 task,
                 project,
                 workspace,
-            } => commands::task::remove(task, project, workspace),
+            } => {
+                commands::task::remove(task, project, or_default_or_active(workspace), &working_dir)
+            }
+            TaskCommands::Move {
+                task,
+                project,
+                workspace,
+                to_workspace,
+                to_project,
+                yes,
+            } => commands::task::move_task(
+                task,
+                project,
+                or_default_or_active(workspace),
+                to_workspace,
+                to_project,
+                yes,
+                &working_dir,
+            ),
         },
-        #[cfg(target_family = "unix")]
         Commands::Shim { command } => match command {
-            ShimCommands::Add { command } => commands::shim::add(command),
-            ShimCommands::Remove { command } => commands::shim::remove(command),
+            ShimCommands::Add { command, all_tasks } => match command {
+                Some(command) if !all_tasks => commands::shim::add(command),
+                _ => commands::shim::add_all_tasks(&working_dir),
+            },
+            ShimCommands::Remove { command, all } => match command {
+                Some(command) if !all => commands::shim::remove(command),
+                _ => commands::shim::remove_all(),
+            },
             ShimCommands::Reinstate => commands::shim::reinstate(),
             ShimCommands::List => commands::shim::list(),
             ShimCommands::Install => commands::shim::install(),
             ShimCommands::Uninstall => commands::shim::uninstall(),
         },
         Commands::Self_ { command } => match command {
-            SelfCommands::Update => commands::self_::update(),
+            SelfCommands::Update { channel, check } => commands::self_::update(channel, check),
+            SelfCommands::Version => commands::self_::version(),
         },
         Commands::Workspace { command } => match command {
             WorkspaceCommands::Run {
                 task,
+                list: _,
+                format,
                 workspace,
                 args,
-            } => commands::workspace::run(workspace, task, args),
+            } => match (task, or_default(workspace)) {
+                (Some(task), workspace) => {
+                    commands::workspace::run(workspace, task, args, None, false, &[], &working_dir)
+                }
+                (None, workspace) => {
+                    commands::workspace::list_tasks(workspace, format, &working_dir)
+                }
+            },
             WorkspaceCommands::Config {
                 workspace,
                 key,
                 value,
                 unset,
-            } => commands::workspace::config(workspace, key, value, unset),
-            WorkspaceCommands::Info { workspace } => commands::workspace::info(workspace),
-            WorkspaceCommands::Snapshot { workspace, profile } => {
-                commands::workspace::snapshot(workspace, profile)
+                force,
+                path,
+                edit,
+            } => {
+                let workspace = or_default(workspace);
+                if path {
+                    commands::workspace::config_path(workspace, &working_dir)
+                } else if edit {
+                    commands::workspace::config_edit(workspace, &working_dir)
+                } else {
+                    commands::workspace::config(
+                        workspace,
+                        key.expect("clap requires a key without --path or --edit"),
+                        value,
+                        unset,
+                        force,
+                        &working_dir,
+                    )
+                }
+            }
+            WorkspaceCommands::Clone { from, to } => {
+                commands::workspace::clone(from, to, &working_dir)
+            }
+            WorkspaceCommands::Merge {
+                from,
+                into,
+                tasks,
+                delete_source,
+            } => commands::workspace::merge(from, into, tasks, delete_source, &working_dir),
+            WorkspaceCommands::Projects { workspace } => {
+                commands::workspace::list_projects(or_default(workspace), &working_dir)
+            }
+            WorkspaceCommands::AddProject {
+                project,
+                dir,
+                workspace,
+            } => {
+                commands::workspace::add_project(or_default(workspace), project, dir, &working_dir)
+            }
+            WorkspaceCommands::RemoveProject { project, workspace } => {
+                commands::workspace::remove_project(or_default(workspace), project, &working_dir)
             }
+            WorkspaceCommands::Disable { project, workspace } => {
+                commands::workspace::set_project_enabled(
+                    or_default(workspace),
+                    project,
+                    false,
+                    &working_dir,
+                )
+            }
+            WorkspaceCommands::Enable { project, workspace } => {
+                commands::workspace::set_project_enabled(
+                    or_default(workspace),
+                    project,
+                    true,
+                    &working_dir,
+                )
+            }
+            WorkspaceCommands::Info { workspace, git } => {
+                commands::workspace::info(or_default(workspace), git, &working_dir)
+            }
+            WorkspaceCommands::Snapshot {
+                workspace,
+                profile,
+                include_uncommitted,
+                output,
+                base,
+                jobs,
+            } => commands::workspace::snapshot(
+                or_default(workspace),
+                profile,
+                include_uncommitted,
+                output,
+                base,
+                jobs,
+                &working_dir,
+            ),
         },
-        Commands::Doctor { workspace } => commands::doctor(workspace),
-        Commands::Status { workspace } => commands::status(workspace),
+        Commands::Doctor {
+            workspace,
+            format,
+            strict,
+            only,
+            min_severity,
+        } => commands::doctor(
+            or_default(workspace),
+            format,
+            strict,
+            &only,
+            min_severity,
+            &working_dir,
+        ),
+        Commands::Info => commands::info(default_workspace.clone(), &working_dir),
+        Commands::Validate { project } => {
+            commands::validate(project, default_workspace.clone(), &working_dir)
+        }
+        Commands::Schema { kind } => commands::schema(kind),
+        Commands::Status {
+            workspace,
+            dirty,
+            downed,
+            behind,
+            off_branch,
+            fetch,
+            no_cache,
+            exit_code,
+            mut fail_on,
+        } => {
+            if exit_code {
+                fail_on.extend([StatusCheck::Dirty, StatusCheck::Unpushed]);
+            }
+            commands::status(
+                or_default(workspace),
+                commands::StatusFilter {
+                    dirty,
+                    downed,
+                    behind,
+                    off_branch,
+                },
+                fetch,
+                no_cache,
+                &fail_on,
+                &working_dir,
+            )
+        }
         Commands::Git { command } => match command {
             GitCommands::Switch {
                 target_branch,
                 fallback,
                 on_dirty,
-            } => commands::git::switch::switch(Some(target_branch), fallback, on_dirty),
+                keep_stash_on_failure,
+                stash_message,
+                fetch: _,
+                no_fetch,
+                jobs,
+                dry_run,
+                run,
+                report,
+            } => commands::git::switch::switch(
+                Some(target_branch),
+                fallback,
+                on_dirty,
+                keep_stash_on_failure,
+                stash_message,
+                !no_fetch,
+                jobs,
+                dry_run,
+                run,
+                report,
+                default_workspace.clone(),
+                &working_dir,
+            ),
             GitCommands::BaseReset {
                 base_branch,
                 on_dirty,
-            } => commands::git::base_reset(base_branch, on_dirty),
+                on_unpushed,
+                no_retry,
+                stash_message,
+            } => commands::git::base_reset(
+                base_branch,
+                on_dirty,
+                on_unpushed,
+                no_retry,
+                stash_message,
+                default_workspace.clone(),
+                &working_dir,
+            ),
         },
-        Commands::Config { key, value, unset } => commands::config(key, value, unset),
-        Commands::Fallthrough(args) => commands::fallthrough(args),
+        Commands::Config {
+            key,
+            value,
+            unset,
+            project,
+        } => commands::config(
+            key,
+            value,
+            unset,
+            project,
+            default_workspace.clone(),
+            &working_dir,
+        ),
+        Commands::Fallthrough(args) => {
+            commands::fallthrough(args, default_workspace.clone(), &working_dir)
+        }
     };
 
     if let Err(err) = result {
-        let theme = Theme::new();
+        exit_with_error(&theme, &err);
+    }
 
-        let error_prefix = theme.error("Error:");
-        let cause_prefix = theme.dim("Caused by:");
+    Ok(())
+}
 
-        eprintln!("{error_prefix} {err}");
-        for cause in err.chain().skip(1) {
-            eprintln!("{cause_prefix} {cause}");
-        }
+/// Prints the error with its causes and exits with the code for its category.
+fn exit_with_error(theme: &Theme, err: &eyre::Report) -> ! {
+    let error_prefix = theme.error("Error:");
+    let cause_prefix = theme.dim("Caused by:");
 
-        std::process::exit(1);
+    eprintln!("{error_prefix} {err}");
+    for cause in err.chain().skip(1) {
+        eprintln!("{cause_prefix} {cause}");
     }
 
-    Ok(())
+    std::process::exit(error::exit_code_for(err));
+}
+
+/// Logs to stdout as `RUST_LOG` asks, and everything de does to the log file if it is enabled.
+fn init_tracing(log_file: bool) {
+    let file_layer = if utils::log::log_file_enabled(log_file) {
+        match utils::log::logs_dir().and_then(|dir| utils::log::file_layer(&dir)) {
+            Ok(layer) => Some(layer),
+            Err(err) => {
+                eprintln!("Warning: {err:#}");
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    let subscriber = tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer().with_filter(EnvFilter::from_default_env()))
+        .with(file_layer);
+    tracing::subscriber::set_global_default(subscriber)
+        .expect("Failed to initialize tracing subscriber");
+
+    tracing::info!(
+        target: "de",
+        "Invoked as: {}",
+        std::env::args()
+            .map(|arg| utils::process::quote_arg(&arg))
+            .collect::<Vec<_>>()
+            .join(" ")
+    );
 }
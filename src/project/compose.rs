@@ -0,0 +1,363 @@
+use std::{collections::BTreeMap, path::Path, process::Command};
+
+use crate::{project::docker_compose_config, utils::process::CommandExt};
+
+/// Status for a single Docker Compose service.
+pub struct DockerServiceStatus {
+    pub name: String,
+    pub running: bool,
+    pub status: String,
+    /// Healthcheck state (`healthy`, `unhealthy` or `starting`), if the service defines one.
+    pub health: Option<String>,
+    pub ports: Option<String>,
+}
+
+impl DockerServiceStatus {
+    pub fn is_unhealthy(&self) -> bool {
+        self.running && self.health.as_deref() == Some("unhealthy")
+    }
+}
+
+/// Get the status of all Docker Compose services for a project.
+/// Returns a vector of DockerServiceStatus, or None if docker-compose fails.
+pub fn get_docker_services(
+    compose_path: &Path,
+    env: &BTreeMap<String, String>,
+) -> Option<Vec<DockerServiceStatus>> {
+    tracing::debug!(
+        "Running docker-compose ps -a --format json for {:?}",
+        compose_path
+    );
+    let output = Command::new("docker-compose")
+        .envs(env)
+        .arg("-f")
+        .arg(compose_path)
+        .arg("ps")
+        .arg("-a")
+        .arg("--format")
+        .arg("json")
+        .logged_output()
+        .ok()?;
+
+    if output.status.success()
+        && let Some(services) = parse_docker_services_json(&String::from_utf8_lossy(&output.stdout))
+    {
+        return Some(services);
+    }
+
+    // Older Compose versions don't support `--format json`, so fall back to the table output.
+    tracing::debug!(
+        "Falling back to docker-compose ps -a for {:?}",
+        compose_path
+    );
+    let output = Command::new("docker-compose")
+        .envs(env)
+        .arg("-f")
+        .arg(compose_path)
+        .arg("ps")
+        .arg("-a")
+        .logged_output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    Some(parse_docker_services_table(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+/// A container entry from `docker compose ps --format json`.
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ComposePsEntry {
+    service: String,
+    #[serde(default)]
+    state: String,
+    #[serde(default)]
+    status: String,
+    #[serde(default)]
+    health: String,
+    #[serde(default)]
+    publishers: Option<Vec<ComposePsPublisher>>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ComposePsPublisher {
+    #[serde(rename = "URL", default)]
+    url: String,
+    #[serde(default)]
+    target_port: u16,
+    #[serde(default)]
+    published_port: u16,
+    #[serde(default)]
+    protocol: String,
+}
+
+/// Parses `docker compose ps --format json`, which is either a JSON array or one object per line
+/// depending on the Compose version. Returns `None` if the output isn't JSON.
+fn parse_docker_services_json(stdout: &str) -> Option<Vec<DockerServiceStatus>> {
+    let trimmed = stdout.trim();
+
+    let entries: Vec<ComposePsEntry> = if trimmed.is_empty() {
+        Vec::new()
+    } else if trimmed.starts_with('[') {
+        serde_json::from_str(trimmed).ok()?
+    } else {
+        trimmed
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()
+            .ok()?
+    };
+
+    let services = entries
+        .into_iter()
+        .map(|entry| {
+            let ports = entry
+                .publishers
+                .unwrap_or_default()
+                .iter()
+                .filter(|publisher| publisher.published_port != 0)
+                .map(|publisher| {
+                    let host = if publisher.url.is_empty() {
+                        "0.0.0.0"
+                    } else {
+                        &publisher.url
+                    };
+                    format!(
+                        "{}:{}->{}/{}",
+                        host, publisher.published_port, publisher.target_port, publisher.protocol
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            let status = if entry.status.is_empty() {
+                entry.state.clone()
+            } else {
+                entry.status
+            };
+
+            let (status, parsed_health) = split_health(&status);
+            let health = Some(entry.health)
+                .filter(|health| !health.is_empty())
+                .or(parsed_health);
+
+            tracing::debug!(
+                "Service '{}' state: '{}', status: '{}', ports: '{}'",
+                entry.service,
+                entry.state,
+                status,
+                ports
+            );
+
+            DockerServiceStatus {
+                name: entry.service,
+                running: entry.state == "running",
+                status,
+                health,
+                ports: Some(ports).filter(|s| !s.is_empty()),
+            }
+        })
+        .collect();
+
+    Some(services)
+}
+
+/// Splits a trailing healthcheck marker like `(healthy)` or `(health: starting)` off a status.
+fn split_health(status: &str) -> (String, Option<String>) {
+    for (marker, health) in [
+        ("(healthy)", "healthy"),
+        ("(unhealthy)", "unhealthy"),
+        ("(health: starting)", "starting"),
+    ] {
+        if let Some(stripped) = status.trim_end().strip_suffix(marker) {
+            return (stripped.trim_end().to_string(), Some(health.to_string()));
+        }
+    }
+
+    (status.to_string(), None)
+}
+
+/// Parses the fixed-width table printed by `docker-compose ps -a`.
+fn parse_docker_services_table(stdout: &str) -> Vec<DockerServiceStatus> {
+    let mut services = Vec::new();
+
+    let mut lines = stdout.lines();
+    let header_line = lines.next().unwrap_or("");
+    let header_cols: Vec<&str> = header_line.split_whitespace().collect();
+    let service_idx = header_cols.iter().position(|&h| h == "SERVICE");
+    let status_idx = header_cols.iter().position(|&h| h == "STATUS");
+    let ports_idx = header_cols.iter().position(|&h| h == "PORTS");
+
+    if let (Some(service_idx), Some(status_idx)) = (service_idx, status_idx) {
+        for line in lines {
+            // Split line into columns by whitespace, but preserve spaces in STATUS and PORTS
+            // We'll do this by splitting the line into fields based on header column positions
+            let mut start_indices = Vec::new();
+            let mut idx = 0;
+            for col in &header_cols {
+                // Find the start index of each column in the header line
+                if let Some(pos) = header_line[idx..].find(col) {
+                    start_indices.push(idx + pos);
+                    idx += pos + col.len();
+                }
+            }
+            // Now, for each column, extract the substring from the line
+            let mut fields = Vec::new();
+            for i in 0..start_indices.len() {
+                let start = start_indices[i];
+                let end = if i + 1 < start_indices.len() {
+                    start_indices[i + 1]
+                } else {
+                    line.len()
+                };
+                let field = line.get(start..end).unwrap_or("").trim();
+                fields.push(field);
+            }
+            // Now extract by header index
+            if fields.len() <= status_idx {
+                continue;
+            }
+            let name = fields[service_idx].to_string();
+            let status = fields[status_idx].to_string();
+            let ports = ports_idx
+                .and_then(|idx| fields.get(idx).map(|s| s.to_string()))
+                .filter(|s| !s.is_empty());
+            tracing::debug!(
+                "Service '{}' status: '{}', ports: {:?}",
+                name,
+                status,
+                ports
+            );
+            let (status, health) = split_health(&status);
+            services.push(DockerServiceStatus {
+                name,
+                running: status.contains("Up"),
+                status,
+                health,
+                ports,
+            });
+        }
+    }
+
+    services
+}
+
+/// Whether the service of the Compose project is running. Services that were never created count
+/// as not running. Returns `None` if docker-compose fails.
+pub fn is_service_running(
+    compose_path: &Path,
+    env: &BTreeMap<String, String>,
+    service: &str,
+) -> Option<bool> {
+    get_docker_services(compose_path, env).map(|services| {
+        services
+            .iter()
+            .any(|svc| svc.name == service && svc.running)
+    })
+}
+
+/// The name Docker Compose gives the project, as `docker compose config` resolves it, so a
+/// `name:` in the file counts. Falls back to the name Compose derives without one if the
+/// config can't be read.
+pub fn compose_project_name(compose_path: &Path, env: &BTreeMap<String, String>) -> Option<String> {
+    docker_compose_config(compose_path, env, &["--format", "json"])
+        .ok()
+        .and_then(|stdout| parse_compose_name(&stdout))
+        .or_else(|| derived_compose_project_name(env, compose_path))
+}
+
+/// Reads the project name from `docker compose config --format json`.
+fn parse_compose_name(stdout: &str) -> Option<String> {
+    let config: serde_json::Value = serde_json::from_str(stdout).ok()?;
+    config.get("name")?.as_str().map(str::to_string)
+}
+
+/// The project name Docker Compose derives without a `name:` in the file: `COMPOSE_PROJECT_NAME`
+/// from the project or process environment, or else the compose file's directory name, both
+/// normalized like Compose does.
+fn derived_compose_project_name(
+    env: &BTreeMap<String, String>,
+    compose_path: &Path,
+) -> Option<String> {
+    let name = env
+        .get("COMPOSE_PROJECT_NAME")
+        .cloned()
+        .or_else(|| std::env::var("COMPOSE_PROJECT_NAME").ok())
+        .filter(|name| !name.is_empty())
+        .or_else(|| {
+            compose_path
+                .parent()?
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+        })?;
+
+    let name = name
+        .to_lowercase()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '_' || *c == '-')
+        .collect::<String>();
+
+    (!name.is_empty()).then_some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_derived_compose_project_name() {
+        let compose_path = Path::new("/src/My.API/docker-compose.yml");
+
+        assert_eq!(
+            derived_compose_project_name(&BTreeMap::new(), compose_path).as_deref(),
+            Some("myapi")
+        );
+
+        let env = BTreeMap::from([("COMPOSE_PROJECT_NAME".to_string(), "Backend_1".to_string())]);
+        assert_eq!(
+            derived_compose_project_name(&env, compose_path).as_deref(),
+            Some("backend_1")
+        );
+
+        assert_eq!(
+            parse_compose_name(r#"{"name":"shop","services":{}}"#).as_deref(),
+            Some("shop")
+        );
+    }
+
+    #[test]
+    fn test_parse_docker_services_json_reads_lines_and_arrays() {
+        let lines = concat!(
+            r#"{"Service":"db","State":"running","Status":"Up 2 minutes (healthy)","Publishers":[{"URL":"","TargetPort":5432,"PublishedPort":5433,"Protocol":"tcp"}]}"#,
+            "\n",
+            r#"{"Service":"worker","State":"exited","Status":"Exited (1) 3 seconds ago","Health":""}"#,
+            "\n",
+        );
+        let services = parse_docker_services_json(lines).unwrap();
+        assert_eq!(services.len(), 2);
+        assert_eq!(services[0].name, "db");
+        assert!(services[0].running);
+        assert_eq!(services[0].status, "Up 2 minutes");
+        assert_eq!(services[0].health.as_deref(), Some("healthy"));
+        assert_eq!(services[0].ports.as_deref(), Some("0.0.0.0:5433->5432/tcp"));
+        assert!(!services[1].running);
+        assert_eq!(services[1].health, None);
+        assert_eq!(services[1].ports, None);
+
+        let array =
+            r#"[{"Service":"web","State":"running","Status":"Up 1 second","Health":"starting"}]"#;
+        let services = parse_docker_services_json(array).unwrap();
+        assert_eq!(services.len(), 1);
+        assert_eq!(services[0].name, "web");
+        assert_eq!(services[0].health.as_deref(), Some("starting"));
+
+        assert!(parse_docker_services_json("").unwrap().is_empty());
+        assert!(parse_docker_services_json("NAME  SERVICE  STATUS").is_none());
+    }
+}
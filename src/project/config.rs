@@ -1,26 +1,240 @@
 use std::{
+    borrow::Cow,
     collections::BTreeMap,
     path::{Path, PathBuf},
     str::FromStr,
 };
 
 use eyre::{Context, eyre};
+use schemars::{JsonSchema, Schema, SchemaGenerator, json_schema};
 use serde::{Deserialize, Serialize};
 
-use crate::{project::task::Task, setup::project::SetupConfig, types::Slug};
+use crate::{
+    error::DeError, project::task::Task, setup::project::SetupConfig, types::Slug,
+    utils::fs::write_atomic,
+};
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct ProjectManifest {
     #[serde(default)]
     pub project: ProjectMetadata,
     #[serde(default)]
     pub git: Option<ProjectGitSettings>,
     #[serde(default)]
-    pub tasks: Option<BTreeMap<Slug, Task>>,
+    pub tasks: Option<ProjectTasks>,
+    /// How `de setup` clones and prepares the project.
     #[serde(default)]
     pub setup: Option<SetupConfig>,
 }
 
+/// The `[tasks]` table: the tasks by name, and short names for them in `[tasks.aliases]`.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ProjectTasks {
+    /// Maps an alias to a task or another alias, e.g. `t = "test"`.
+    #[serde(
+        default,
+        skip_serializing_if = "BTreeMap::is_empty",
+        deserialize_with = "deserialize_aliases"
+    )]
+    pub aliases: BTreeMap<Slug, Slug>,
+    #[serde(flatten)]
+    pub tasks: BTreeMap<Slug, Task>,
+}
+
+/// Reads `[tasks.aliases]`, failing clearly if it looks like a task named `aliases` instead.
+fn deserialize_aliases<'de, D>(deserializer: D) -> Result<BTreeMap<Slug, Slug>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Aliases {
+        Aliases(BTreeMap<Slug, Slug>),
+        Other(serde::de::IgnoredAny),
+    }
+
+    match Aliases::deserialize(deserializer)? {
+        Aliases::Aliases(aliases) => Ok(aliases),
+        Aliases::Other(_) => Err(serde::de::Error::custom(
+            "`tasks.aliases` is reserved for a table of task aliases, like `t = \"test\"`; \
+             rename the task called `aliases`",
+        )),
+    }
+}
+
+impl JsonSchema for ProjectTasks {
+    fn schema_name() -> Cow<'static, str> {
+        "ProjectTasks".into()
+    }
+
+    // Written by hand, as the derived schema would also validate `aliases` as a task
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        let mut aliases = generator.subschema_for::<BTreeMap<Slug, Slug>>();
+        aliases.insert(
+            "description".to_string(),
+            "Maps an alias to a task or another alias, e.g. `t = \"test\"`.".into(),
+        );
+
+        json_schema!({
+            "description": "The tasks by name, and short names for them in `[tasks.aliases]`.",
+            "type": "object",
+            "properties": { "aliases": aliases },
+            "propertyNames": generator.subschema_for::<Slug>(),
+            "additionalProperties": generator.subschema_for::<Task>(),
+        })
+    }
+}
+
+impl ProjectTasks {
+    /// Looks up the task `name` refers to, following aliases. A task takes precedence over an
+    /// alias with the same name.
+    ///
+    /// Returns `Ok(None)` if `name` is neither a task nor an alias, and an error if an alias
+    /// refers to a missing task or the aliases form a cycle.
+    pub fn resolve(&self, name: &Slug) -> eyre::Result<Option<(&Slug, &Task)>> {
+        let mut chain = vec![name];
+
+        loop {
+            let current = chain[chain.len() - 1];
+            if let Some(task) = self.tasks.get_key_value(current) {
+                return Ok(Some(task));
+            }
+
+            let Some(target) = self.aliases.get(current) else {
+                if chain.len() == 1 {
+                    return Ok(None);
+                }
+                return Err(eyre!(DeError::TaskNotFound {
+                    task: current.clone(),
+                    location: format!(
+                        "the project (task alias '{}' refers to it)",
+                        chain[chain.len() - 2]
+                    ),
+                }));
+            };
+
+            if chain.contains(&target) {
+                chain.push(target);
+                return Err(eyre!(
+                    "Task aliases form a cycle: {}",
+                    chain
+                        .iter()
+                        .map(|name| name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(" -> ")
+                ));
+            }
+            chain.push(target);
+        }
+    }
+
+    /// The tasks the task `name` refers to needs, through `depends_on`, in the order they run.
+    /// Each task appears once, even if several of them depend on it, and the task itself is
+    /// not included.
+    ///
+    /// Returns an error if a task depends on an undefined task or the dependencies form a cycle.
+    pub fn prerequisites(&self, name: &Slug) -> eyre::Result<Vec<Slug>> {
+        let mut order = Vec::new();
+        self.visit_prerequisites(name, &mut Vec::new(), &mut order)?;
+        // The task itself is visited last
+        order.pop();
+        Ok(order)
+    }
+
+    fn visit_prerequisites(
+        &self,
+        name: &Slug,
+        chain: &mut Vec<Slug>,
+        order: &mut Vec<Slug>,
+    ) -> eyre::Result<()> {
+        let Some((name, task)) = self.resolve(name)? else {
+            return match chain.last() {
+                Some(dependent) => Err(eyre!(
+                    "Task '{dependent}' depends on undefined task '{name}'"
+                )),
+                None => Ok(()),
+            };
+        };
+
+        if order.contains(name) {
+            return Ok(());
+        }
+
+        if chain.contains(name) {
+            chain.push(name.clone());
+            return Err(eyre!(
+                "Task dependencies form a cycle: {}",
+                chain
+                    .iter()
+                    .map(|name| name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" -> ")
+            ));
+        }
+
+        chain.push(name.clone());
+        for dependency in task.depends_on() {
+            self.visit_prerequisites(dependency, chain, order)?;
+        }
+        chain.pop();
+
+        order.push(name.clone());
+        Ok(())
+    }
+}
+
+impl ProjectTasks {
+    /// The key of `[tasks.aliases]`, which therefore can't name a task.
+    pub const ALIASES_KEY: &'static str = "aliases";
+
+    /// The task named `name`, without following aliases like [`ProjectTasks::resolve`] does.
+    pub fn get(&self, name: &Slug) -> Option<&Task> {
+        self.tasks.get(name)
+    }
+
+    pub fn contains(&self, name: &Slug) -> bool {
+        self.tasks.contains_key(name)
+    }
+
+    /// The task names, in order. Aliases are not included.
+    pub fn names(&self) -> impl Iterator<Item = &Slug> {
+        self.tasks.keys()
+    }
+
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// Adds or replaces the task `name`, returning the replaced one. Fails for
+    /// [`ProjectTasks::ALIASES_KEY`], which would be read back as the aliases.
+    pub fn insert(&mut self, name: Slug, task: Task) -> eyre::Result<Option<Task>> {
+        if name.as_str() == Self::ALIASES_KEY {
+            return Err(eyre!(
+                "'{name}' is reserved for [tasks.aliases] and can't be used as a task name"
+            ));
+        }
+        Ok(self.tasks.insert(name, task))
+    }
+
+    pub fn remove(&mut self, name: &Slug) -> Option<Task> {
+        self.tasks.remove(name)
+    }
+}
+
+impl<'a> IntoIterator for &'a ProjectTasks {
+    type Item = (&'a Slug, &'a Task);
+    type IntoIter = std::collections::btree_map::Iter<'a, Slug, Task>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.tasks.iter()
+    }
+}
+
 impl ProjectManifest {
     pub fn project(&self) -> &ProjectMetadata {
         &self.project
@@ -36,29 +250,62 @@ impl ProjectManifest {
             .wrap_err("Failed to parse project manifest")
     }
 
+    /// Writes the manifest to `path` as TOML. Other formats are refused rather than overwritten
+    /// with TOML, since only `de.toml` manifests can be loaded.
     pub fn save(&self, path: &Path) -> eyre::Result<()> {
+        if path
+            .extension()
+            .is_some_and(|extension| extension != "toml")
+        {
+            return Err(eyre!(
+                "Can't save the project manifest to {}, only TOML manifests are supported",
+                path.display()
+            ));
+        }
+
         let manifest_str = toml::to_string_pretty(&self)
             .map_err(|e| eyre!(e))
             .wrap_err("Failed to format manifest as string")?;
 
-        std::fs::write(path, manifest_str)
+        write_atomic(path, manifest_str)
             .map_err(|e| eyre!(e))
             .wrap_err_with(|| format!("Failed to write manifest to {}", path.display()))?;
 
+        if let Some(dir) = path.parent() {
+            super::invalidate_cached_project(dir);
+        }
+
         Ok(())
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct ProjectMetadata {
+    /// The name of the project in its workspace.
     #[serde(default = "default_project_name")]
     pub name: Slug,
+    /// The workspace the project belongs to.
     #[serde(default = "default_project_workspace")]
     pub workspace: Slug,
+    /// Path to the Docker Compose file, relative to the project.
     #[serde(default)]
     pub docker_compose: Option<PathBuf>,
+    /// Projects of the workspace to start before this one.
     #[serde(default)]
     pub depends_on: Option<Vec<Slug>>,
+    /// Command used to start the project instead of `docker-compose up`.
+    #[serde(default)]
+    pub start_command: Option<String>,
+    /// Command used to stop the project instead of `docker-compose down`.
+    #[serde(default)]
+    pub stop_command: Option<String>,
+    /// Task `de run` runs when no task is given, instead of the task named `default`.
+    #[serde(default)]
+    pub default_task: Option<Slug>,
+    /// Project tasks to run around starting and stopping the project.
+    #[serde(default)]
+    pub hooks: Option<ProjectHooks>,
 }
 
 impl Default for ProjectMetadata {
@@ -68,6 +315,52 @@ impl Default for ProjectMetadata {
             workspace: default_project_workspace(),
             docker_compose: Default::default(),
             depends_on: Default::default(),
+            start_command: Default::default(),
+            stop_command: Default::default(),
+            default_task: Default::default(),
+            hooks: Default::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectHooks {
+    /// Tasks to run before the project is started.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub before_start: Vec<Slug>,
+    /// Tasks to run after the project was started successfully.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub after_start: Vec<Slug>,
+    /// Tasks to run before the project is stopped.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub before_stop: Vec<Slug>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProjectHook {
+    BeforeStart,
+    AfterStart,
+    BeforeStop,
+}
+
+impl ProjectHook {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProjectHook::BeforeStart => "before_start",
+            ProjectHook::AfterStart => "after_start",
+            ProjectHook::BeforeStop => "before_stop",
+        }
+    }
+}
+
+impl ProjectHooks {
+    /// Returns the tasks registered for the given hook.
+    pub fn tasks(&self, hook: ProjectHook) -> &[Slug] {
+        match hook {
+            ProjectHook::BeforeStart => &self.before_start,
+            ProjectHook::AfterStart => &self.after_start,
+            ProjectHook::BeforeStop => &self.before_stop,
         }
     }
 }
@@ -80,10 +373,13 @@ fn default_project_workspace() -> Slug {
     Slug::from_str("default").expect("default workspace name should be valid")
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct ProjectGitSettings {
+    /// Whether the git commands of `de` include the project.
     #[serde(default = "default_git_enabled")]
     pub enabled: bool,
+    /// The remote to fetch from and compare branches with.
     #[serde(default = "default_git_remote")]
     pub default_remote: String,
 }
@@ -104,3 +400,135 @@ fn default_git_enabled() -> bool {
 fn default_git_remote() -> String {
     "origin".to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::RawTask;
+
+    #[test]
+    fn test_manifest_rejects_unknown_fields() {
+        let error =
+            toml::from_str::<ProjectManifest>("[project]\nname = \"api\"\ndepend_on = [\"db\"]\n")
+                .unwrap_err();
+        assert!(error.to_string().contains("unknown field `depend_on`"));
+
+        assert!(toml::from_str::<ProjectManifest>("[project]\nname = \"api\"\n").is_ok());
+    }
+
+    #[test]
+    fn test_task_aliases() {
+        let manifest = toml::from_str::<ProjectManifest>(
+            "[tasks]\ntest = \"cargo test\"\n\n[tasks.aliases]\nt = \"test\"\ntt = \"t\"\nx = \"missing\"\na = \"b\"\nb = \"a\"\n",
+        )
+        .unwrap();
+        let tasks = manifest.tasks.as_ref().unwrap();
+        let slug = |name: &str| Slug::from_str(name).unwrap();
+
+        assert_eq!(tasks.len(), 1);
+        for name in ["test", "t", "tt"] {
+            let (resolved, _) = tasks.resolve(&slug(name)).unwrap().unwrap();
+            assert_eq!(resolved.as_str(), "test");
+        }
+        assert!(tasks.resolve(&slug("nope")).unwrap().is_none());
+        let missing = tasks.resolve(&slug("x")).unwrap_err();
+        assert!(matches!(
+            missing.downcast_ref::<DeError>(),
+            Some(DeError::TaskNotFound { task, .. }) if task.as_str() == "missing"
+        ));
+
+        let cycle = tasks.resolve(&slug("a")).unwrap_err().to_string();
+        assert!(cycle.contains("a -> b -> a"), "{cycle}");
+
+        let saved = toml::to_string_pretty(&manifest).unwrap();
+        let reloaded = toml::from_str::<ProjectManifest>(&saved).unwrap();
+        assert_eq!(reloaded.tasks.unwrap().aliases.len(), 5);
+    }
+
+    #[test]
+    fn test_aliases_is_not_a_task_name() {
+        let error = toml::from_str::<ProjectManifest>("[tasks]\naliases = \"echo hi\"\n")
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("rename the task called `aliases`"),
+            "{error}"
+        );
+
+        let mut tasks = ProjectTasks::default();
+        let task = || Task::Raw(RawTask::Flat("echo hi".to_string()));
+        assert!(
+            tasks
+                .insert(Slug::from_str("aliases").unwrap(), task())
+                .is_err()
+        );
+        assert!(
+            tasks
+                .insert(Slug::from_str("hi").unwrap(), task())
+                .unwrap()
+                .is_none()
+        );
+        assert_eq!(tasks.names().map(Slug::as_str).collect::<Vec<_>>(), ["hi"]);
+        assert!(tasks.aliases.is_empty());
+    }
+
+    #[test]
+    fn test_string_and_table_tasks_coexist() {
+        let manifest = toml::from_str::<ProjectManifest>(
+            "[tasks]\nbuild = \"cargo build\"\ntest = { command = \"cargo test\", retries = 2 }\n\n[tasks.psql]\nservice = \"db\"\ncommand = \"psql\"\n",
+        )
+        .unwrap();
+        let tasks = manifest.tasks.as_ref().unwrap();
+        let task = |name: &str| tasks.get(&Slug::from_str(name).unwrap()).unwrap();
+
+        assert!(
+            matches!(task("build"), Task::Raw(RawTask::Flat(command)) if command == "cargo build")
+        );
+        assert!(matches!(
+            task("test"),
+            Task::Raw(RawTask::Complex { retries: 2, .. })
+        ));
+        assert_eq!(task("test").command_str(), "cargo test");
+        assert!(matches!(task("psql"), Task::Compose { service, .. } if service == "db"));
+
+        // Plain commands are saved back in the short form
+        let saved = toml::to_string_pretty(&manifest).unwrap();
+        assert!(saved.contains("build = \"cargo build\""), "{saved}");
+    }
+
+    #[test]
+    fn test_task_prerequisites() {
+        let manifest = toml::from_str::<ProjectManifest>(
+            "[tasks]\ndeps = \"npm ci\"\nbuild = { command = \"npm run build\", depends_on = [\"deps\"] }\nlint = { command = \"npm run lint\", depends_on = [\"deps\"] }\nci = { command = \"npm test\", depends_on = [\"build\", \"lint\"] }\nbroken = { command = \"true\", depends_on = [\"missing\"] }\nloop = { command = \"true\", depends_on = [\"loop\"] }\n",
+        )
+        .unwrap();
+        let tasks = manifest.tasks.as_ref().unwrap();
+        let slug = |name: &str| Slug::from_str(name).unwrap();
+
+        let order = tasks.prerequisites(&slug("ci")).unwrap();
+        assert_eq!(order, vec![slug("deps"), slug("build"), slug("lint")]);
+        assert!(tasks.prerequisites(&slug("deps")).unwrap().is_empty());
+
+        let missing = tasks
+            .prerequisites(&slug("broken"))
+            .unwrap_err()
+            .to_string();
+        assert!(missing.contains("undefined task 'missing'"), "{missing}");
+
+        let cycle = tasks.prerequisites(&slug("loop")).unwrap_err().to_string();
+        assert!(cycle.contains("loop -> loop"), "{cycle}");
+    }
+
+    #[test]
+    fn test_save_refuses_other_formats() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("de.yaml");
+        std::fs::write(&path, "project:\n  name: api\n").unwrap();
+
+        assert!(ProjectManifest::default().save(&path).is_err());
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "project:\n  name: api\n"
+        );
+    }
+}
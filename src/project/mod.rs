@@ -1,69 +1,254 @@
+pub mod compose;
 pub mod config;
+mod runner;
 mod task;
-pub use task::{RawTask, Task};
+pub use runner::{TaskContext, TaskOutput, run_project_task};
+pub(crate) use runner::{project_task_command, task_prerequisites};
+pub use task::{RawTask, Task, expand_task_command};
 
 use ::config::FileFormat;
 use eyre::{Context, eyre};
 use std::{
     borrow::Cow,
-    collections::BTreeMap,
+    collections::{BTreeMap, HashMap},
     path::{Path, PathBuf},
     process::Command,
+    sync::{Mutex, MutexGuard, OnceLock},
 };
 
-use crate::{project::config::ProjectManifest, types::Slug};
+use crate::{
+    error::DeError,
+    project::config::{ProjectManifest, ProjectTasks},
+    types::Slug,
+    utils::process::{CommandExt, run_interruptible},
+};
+
+/// The commands tried in turn to run `config` on a Compose file: the standalone binary, then
+/// the `docker compose` plugin.
+const COMPOSE_COMMANDS: &[&[&str]] = &[&["docker-compose"], &["docker", "compose"]];
+
+/// Projects already loaded by this process, keyed by their canonical directory.
+static PROJECT_CACHE: OnceLock<Mutex<HashMap<PathBuf, Project>>> = OnceLock::new();
+
+fn project_cache() -> MutexGuard<'static, HashMap<PathBuf, Project>> {
+    PROJECT_CACHE
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+/// Drops the cached project for `dir`, so the next [`Project::from_dir`] reads it from disk again.
+pub(crate) fn invalidate_cached_project(dir: &Path) {
+    if let Ok(dir) = dir.canonicalize() {
+        project_cache().remove(&dir);
+    }
+}
+
+/// Manifest sections that `DE_<SECTION>_<KEY>` variables can override.
+const MANIFEST_ENV_SECTIONS: &[&str] = &["PROJECT", "GIT", "TASKS", "SETUP"];
+
+/// Variables in those sections that `de` itself sets for the commands it runs.
+const INTERNAL_ENV_VARS: &[&str] = &["DE_PROJECT_FILES"];
+
+/// Whether the environment variable overrides a manifest value. Other `DE_` variables, such as
+/// `DE_WORKSPACE`, configure `de` itself and would be rejected as unknown manifest fields.
+fn is_manifest_override(key: &str) -> bool {
+    let key = key.to_ascii_uppercase();
+    let Some(rest) = key.strip_prefix("DE_") else {
+        return false;
+    };
 
+    !INTERNAL_ENV_VARS.contains(&key.as_str())
+        && MANIFEST_ENV_SECTIONS.iter().any(|section| {
+            rest.strip_prefix(section)
+                .is_some_and(|r| r.starts_with('_'))
+        })
+}
+
+/// Builds the project manifest in `dir` from its sources, later ones overriding earlier ones:
+/// `de.toml`, `.de/config.toml` and the `DE_<SECTION>_<KEY>` variables of the process and of
+/// `env`, the project's `.env` file. Returns the canonical path of `de.toml` too.
+fn manifest_config(
+    dir: &Path,
+    env: &BTreeMap<String, String>,
+) -> eyre::Result<(PathBuf, ::config::Config)> {
+    use ::config;
+
+    let manifest_path = dir
+        .join("de.toml")
+        .canonicalize()
+        .map_err(|e| eyre!(e))
+        .wrap_err_with(|| format!("Failed to canonicalize directory {}", dir.display()))?;
+
+    let manifest_path_str = manifest_path
+        .to_str()
+        .map(|s| s.to_string())
+        .ok_or_else(|| eyre!("Failed to convert directory path to string"))?;
+
+    let dot_manifest_path = dir
+        .join(".de/config.toml")
+        .to_str()
+        .map(|s| s.to_string())
+        .ok_or_else(|| eyre!("Failed to convert hidden config path to string"))?;
+
+    let builder = config::Config::builder()
+        .add_source(config::File::new(
+            manifest_path_str.as_str(),
+            FileFormat::Toml,
+        ))
+        .add_source(config::File::new(dot_manifest_path.as_str(), FileFormat::Toml).required(false))
+        .add_source(
+            config::Environment::with_prefix("DE")
+                .separator("_")
+                .source(Some(
+                    std::env::vars()
+                        .chain(env.clone())
+                        .filter(|(key, _)| is_manifest_override(key))
+                        .collect(),
+                )),
+        )
+        .build()
+        .map_err(|e| eyre!(e))
+        .wrap_err_with(|| format!("Failed to load project manifest from {}", dir.display()))?;
+
+    Ok((manifest_path, builder))
+}
+
+/// The origin `config` gives values set by [`config::Environment`].
+const ENV_ORIGIN: &str = "the environment";
+
+/// A value of the resolved project manifest and the source that set it, as listed by
+/// [`Project::manifest_origins`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestOrigin {
+    /// The dotted key of the value, e.g. `tasks.build.command`.
+    pub key: String,
+    pub value: String,
+    /// `file:<path>` for a manifest file, or `env:<variable>` for an environment variable.
+    pub source: String,
+}
+
+/// Adds the leaf values of `values` to `origins`, with their keys below `prefix`. Arrays are
+/// listed as a single value, as a source always replaces them as a whole.
+fn flatten_origins(
+    origins: &mut Vec<ManifestOrigin>,
+    prefix: &str,
+    values: ::config::Map<String, ::config::Value>,
+    env: &BTreeMap<String, String>,
+) {
+    for (key, value) in values {
+        let key = if prefix.is_empty() {
+            key
+        } else {
+            format!("{prefix}.{key}")
+        };
+
+        let source = match value.origin() {
+            Some(ENV_ORIGIN) => {
+                let variable = format!("DE_{}", key.replace('.', "_").to_ascii_uppercase());
+                let variable = std::env::vars()
+                    .map(|(name, _)| name)
+                    .chain(env.keys().cloned())
+                    .find(|name| name.eq_ignore_ascii_case(&variable))
+                    .unwrap_or(variable);
+                format!("env:{variable}")
+            }
+            Some(origin) => format!("file:{origin}"),
+            None => "unknown".to_string(),
+        };
+
+        match value.kind {
+            ::config::ValueKind::Table(table) => flatten_origins(origins, &key, table, env),
+            kind => origins.push(ManifestOrigin {
+                key,
+                value: display_value(&kind),
+                source,
+            }),
+        }
+    }
+}
+
+/// Formats a manifest value the way it would be written in TOML, except for plain strings.
+fn display_value(kind: &::config::ValueKind) -> String {
+    use ::config::ValueKind;
+
+    match kind {
+        ValueKind::Array(values) => {
+            let values = values
+                .iter()
+                .map(|value| match &value.kind {
+                    ValueKind::String(value) => format!("{value:?}"),
+                    kind => display_value(kind),
+                })
+                .collect::<Vec<_>>();
+            format!("[{}]", values.join(", "))
+        }
+        ValueKind::Table(table) => {
+            let mut entries = table
+                .iter()
+                .map(|(key, value)| format!("{key} = {}", display_value(&value.kind)))
+                .collect::<Vec<_>>();
+            entries.sort();
+            format!("{{ {} }}", entries.join(", "))
+        }
+        kind => kind.to_string(),
+    }
+}
+
+/// Options for [`Project::docker_compose_up`].
+#[derive(Debug, Clone, Copy)]
+pub struct UpOptions {
+    /// Start services in the background instead of streaming their logs.
+    pub detach: bool,
+}
+
+impl Default for UpOptions {
+    fn default() -> Self {
+        Self { detach: true }
+    }
+}
+
+/// Options for [`Project::docker_compose_down`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DownOptions {
+    /// Seconds to wait for services to shut down before they are killed (`-t`).
+    pub timeout: Option<u64>,
+    /// Also remove named volumes and anonymous volumes attached to the services (`-v`).
+    pub volumes: bool,
+    /// Also remove images built for the services that have no custom tag (`--rmi local`).
+    pub images: bool,
+}
+
+#[derive(Clone)]
 pub struct Project {
     dir: PathBuf,
     manifest: ProjectManifest,
     manifest_path: PathBuf,
+    env: BTreeMap<String, String>,
 }
 
 impl Project {
+    /// Loads the project in `dir`, reusing the result if it was already loaded by this process.
     pub fn from_dir(dir: &Path) -> eyre::Result<Self> {
-        use ::config;
-
-        let dot_env = dir.join(".env");
-        if dot_env.exists() {
-            dotenvy::from_path_override(&dot_env)
-                .map_err(|e| eyre!(e))
-                .wrap_err_with(|| {
-                    format!(
-                        "Failed to load environment variables from {}",
-                        dir.display()
-                    )
-                })?;
-        }
-
-        let manifest_path = dir
-            .join("de.toml")
+        let canonical_dir = dir
             .canonicalize()
             .map_err(|e| eyre!(e))
             .wrap_err_with(|| format!("Failed to canonicalize directory {}", dir.display()))?;
 
-        let manifest_path_str = manifest_path
-            .to_str()
-            .map(|s| s.to_string())
-            .ok_or_else(|| eyre!("Failed to convert directory path to string"))?;
-
-        let dot_manifest_path = dir
-            .join(".de/config.toml")
-            .to_str()
-            .map(|s| s.to_string())
-            .ok_or_else(|| eyre!("Failed to convert hidden config path to string"))?;
-
-        let builder = config::Config::builder()
-            .add_source(config::File::new(
-                manifest_path_str.as_str(),
-                FileFormat::Toml,
-            ))
-            .add_source(
-                config::File::new(dot_manifest_path.as_str(), FileFormat::Toml).required(false),
-            )
-            .add_source(config::Environment::with_prefix("DE").separator("_"))
-            .build()
-            .map_err(|e| eyre!(e))
-            .wrap_err_with(|| format!("Failed to load project manifest from {}", dir.display()))?;
+        if let Some(project) = project_cache().get(&canonical_dir) {
+            let mut project = project.clone();
+            project.dir = dir.to_path_buf();
+            return Ok(project);
+        }
+
+        let project = Self::load(dir)?;
+        project_cache().insert(canonical_dir, project.clone());
+        Ok(project)
+    }
+
+    fn load(dir: &Path) -> eyre::Result<Self> {
+        let env = load_dot_env(dir)?;
+        let (manifest_path, builder) = manifest_config(dir, &env)?;
 
         let manifest = builder
             .try_deserialize::<ProjectManifest>()
@@ -72,32 +257,71 @@ impl Project {
 
         Ok(Self {
             manifest,
-            manifest_path: manifest_path.clone(),
+            manifest_path,
             dir: dir.to_path_buf(),
+            env,
         })
     }
 
+    /// Every value of the resolved manifest, by dotted key, with the source that set it last:
+    /// `de.toml`, `.de/config.toml` or a `DE_<SECTION>_<KEY>` variable.
+    pub fn manifest_origins(&self) -> eyre::Result<Vec<ManifestOrigin>> {
+        use ::config::Source;
+
+        let (_, builder) = manifest_config(&self.dir, &self.env)?;
+        let values = builder
+            .collect()
+            .map_err(|e| eyre!(e))
+            .wrap_err("Failed to collect project manifest values")?;
+
+        let mut origins = Vec::new();
+        flatten_origins(&mut origins, "", values, &self.env);
+        origins.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(origins)
+    }
+
+    /// The manifest file the task `name` is defined in, i.e. the file that set its command:
+    /// `de.toml` or `.de/config.toml`. `None` if only an environment variable defines it.
+    pub fn task_source_file(&self, name: &Slug) -> eyre::Result<Option<PathBuf>> {
+        let key = format!("tasks.{name}");
+        let origins = self.manifest_origins()?;
+
+        let defining = |origin: &&ManifestOrigin| {
+            origin.key == key
+                || ["command", "script"]
+                    .iter()
+                    .any(|field| origin.key == format!("{key}.{field}"))
+        };
+        let in_task = |origin: &&ManifestOrigin| origin.key.starts_with(&format!("{key}."));
+
+        Ok(origins
+            .iter()
+            .find(defining)
+            .or_else(|| origins.iter().find(in_task))
+            .and_then(|origin| origin.source.strip_prefix("file:"))
+            .map(PathBuf::from))
+    }
+
     pub fn from_dir_recursive(dir: &Path) -> eyre::Result<Option<Self>> {
+        Self::find_dir(dir)
+            .map(|dir| Self::from_dir(&dir))
+            .transpose()
+    }
+
+    /// Returns the nearest directory at or above `dir` that contains a `de.toml`, without loading it.
+    pub fn find_dir(dir: &Path) -> Option<PathBuf> {
         let mut current_dir = dir.to_path_buf();
 
         loop {
             if current_dir.join("de.toml").exists() {
-                return Self::from_dir(&current_dir).map(Some);
+                return Some(current_dir);
             }
 
             if !current_dir.pop() {
-                return Ok(None);
+                return None;
             }
         }
     }
-
-    pub fn current() -> eyre::Result<Option<Self>> {
-        let current_dir = std::env::current_dir()
-            .map_err(|e| eyre!(e))
-            .wrap_err("Failed to get current working directory")?;
-
-        Self::from_dir_recursive(&current_dir)
-    }
 }
 
 impl Project {
@@ -109,17 +333,65 @@ impl Project {
         &mut self.manifest
     }
 
-    pub fn tasks(&self) -> Option<&BTreeMap<Slug, Task>> {
+    pub fn tasks(&self) -> Option<&ProjectTasks> {
         self.manifest.tasks.as_ref()
     }
 
+    /// The task `name` refers to, following [aliases](ProjectTasks::resolve).
+    pub fn task(&self, name: &Slug) -> eyre::Result<Option<&Task>> {
+        match self.tasks() {
+            Some(tasks) => Ok(tasks.resolve(name)?.map(|(_, task)| task)),
+            None => Ok(None),
+        }
+    }
+
     pub fn manifest_path(&self) -> &PathBuf {
         &self.manifest_path
     }
 
+    /// Applies `edit` to the project's `de.toml` as written and saves it, then reloads the
+    /// project. Values that `.de/config.toml` or `DE_*` variables layer over the file are
+    /// neither seen by `edit` nor written into `de.toml`.
+    pub fn edit_manifest<T>(
+        &mut self,
+        edit: impl FnOnce(&mut ProjectManifest) -> eyre::Result<T>,
+    ) -> eyre::Result<T> {
+        let mut manifest = ProjectManifest::load(&self.manifest_path)?;
+        let result = edit(&mut manifest)?;
+        manifest.save(&self.manifest_path)?;
+
+        invalidate_cached_project(&self.dir);
+        *self = Self::from_dir(&self.dir)?;
+
+        Ok(result)
+    }
+
     pub fn dir(&self) -> &PathBuf {
         &self.dir
     }
+
+    /// Variables from the project's `.env`, passed to the commands de runs for the project.
+    pub fn env(&self) -> &BTreeMap<String, String> {
+        &self.env
+    }
+}
+
+/// Reads the `.env` in `dir` without touching the environment of the de process.
+fn load_dot_env(dir: &Path) -> eyre::Result<BTreeMap<String, String>> {
+    let dot_env = dir.join(".env");
+    if !dot_env.exists() {
+        return Ok(BTreeMap::new());
+    }
+
+    dotenvy::from_path_iter(&dot_env)
+        .and_then(|iter| iter.collect::<Result<BTreeMap<_, _>, _>>())
+        .map_err(|e| eyre!(e))
+        .wrap_err_with(|| {
+            format!(
+                "Failed to load environment variables from {}",
+                dir.display()
+            )
+        })
 }
 
 impl Project {
@@ -172,10 +444,95 @@ impl Project {
         canonicalize(self, &docker_compose_path)
     }
 
-    /// Runs `docker-compose up -d` for the project, starting all services defined in the Docker Compose file.
+    /// The services defined in the project's Docker Compose file, from `config --services`.
+    /// Empty if the project has no Compose file.
+    pub fn docker_compose_services(&self) -> eyre::Result<Vec<String>> {
+        self.docker_compose_services_with(COMPOSE_COMMANDS)
+    }
+
+    fn docker_compose_services_with(&self, commands: &[&[&str]]) -> eyre::Result<Vec<String>> {
+        let Some(compose_path) = self.docker_compose_path()? else {
+            return Ok(Vec::new());
+        };
+
+        let stdout = compose_config_with(commands, &compose_path, self.env(), &["--services"])?;
+        Ok(stdout
+            .lines()
+            .map(str::trim)
+            .filter(|service| !service.is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// Starts the project, preferring the manifest's `start_command` over Docker Compose.
+    ///
+    /// Returns `Ok(true)` if the project was started, or `Ok(false)` if there was nothing to start.
+    pub fn start(&self) -> eyre::Result<bool> {
+        if let Some(start_command) = self.manifest().project().start_command.as_deref() {
+            self.run_lifecycle_command("start", start_command)?;
+            return Ok(true);
+        }
+
+        self.docker_compose_up(UpOptions::default())
+    }
+
+    /// Stops the project, preferring the manifest's `stop_command` over Docker Compose.
+    /// The `timeout` only applies to Docker Compose.
+    ///
+    /// Returns `Ok(true)` if the project was stopped, or `Ok(false)` if there was nothing to stop.
+    pub fn stop(&self, timeout: Option<u64>) -> eyre::Result<bool> {
+        if let Some(stop_command) = self.manifest().project().stop_command.as_deref() {
+            self.run_lifecycle_command("stop", stop_command)?;
+            return Ok(true);
+        }
+
+        self.docker_compose_down(DownOptions {
+            timeout,
+            ..DownOptions::default()
+        })
+    }
+
+    /// Runs a start/stop command from the manifest in the project directory. The command is split
+    /// like a shell would, so quoted arguments may contain spaces.
+    fn run_lifecycle_command(&self, kind: &str, command_str: &str) -> eyre::Result<()> {
+        let parts = shell_words::split(command_str)
+            .map_err(|e| eyre!(e))
+            .wrap_err_with(|| format!("Failed to parse {kind} command: {command_str}"))?;
+        let (program, args) = parts
+            .split_first()
+            .ok_or_else(|| eyre!("Empty {} command", kind))?;
+
+        let status = Command::new(program)
+            .args(args)
+            .current_dir(self.dir())
+            .envs(self.env())
+            .logged_status()
+            .map_err(|e| eyre!(e))
+            .wrap_err_with(|| {
+                format!(
+                    "Failed to run {} command for project {}",
+                    kind,
+                    self.manifest().project().name
+                )
+            })?;
+
+        if !status.success() {
+            return Err(eyre!(
+                "{} command failed with status code: {}",
+                kind,
+                status.code().unwrap_or(-1)
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Runs `docker-compose up` for the project, starting all services defined in the Docker Compose file.
+    /// Unless `options.detach` is unset, services are started in the background (`-d`). In the foreground,
+    /// logs are streamed until the services exit or Ctrl-C stops them, which is not treated as a failure.
     ///
     /// Returns `Ok(true)` if the command was successful, or `Ok(false)` if no Docker Compose file was found.
-    pub fn docker_compose_up(&self) -> eyre::Result<bool> {
+    pub fn docker_compose_up(&self, options: UpOptions) -> eyre::Result<bool> {
         let docker_compose_path = self
             .docker_compose_path()
             .map_err(|e| eyre!(e))
@@ -185,13 +542,25 @@ impl Project {
             return Ok(false);
         };
 
-        let status = Command::new("docker-compose")
+        let mut command = Command::new("docker-compose");
+        command
+            .envs(self.env())
             .arg("-f")
             .arg(docker_compose_path)
-            .arg("up")
-            .arg("-d")
-            .status()
-            .map_err(|e| eyre!(e))
+            .arg("up");
+
+        if options.detach {
+            command.arg("-d");
+        }
+
+        let (status, interrupted) = if options.detach {
+            (command.logged_status(), false)
+        } else {
+            run_interruptible(|| command.logged_status())
+        };
+
+        let status = status
+            .map_err(|e| eyre!(DeError::DockerUnavailable(e)))
             .wrap_err_with(|| {
                 format!(
                     "Failed to run docker-compose up for project {}",
@@ -199,20 +568,21 @@ impl Project {
                 )
             })?;
 
-        if !status.success() {
-            return Err(eyre!(
-                "docker-compose up failed with status code: {}",
-                status.code().unwrap_or(-1)
-            ));
+        if !status.success() && !interrupted {
+            return Err(eyre!(DeError::DockerComposeFailed {
+                command: "up",
+                code: status.code().unwrap_or(-1),
+            }));
         }
 
         Ok(true)
     }
 
     /// Runs `docker-compose down` for the project, stopping all services defined in the Docker Compose file.
+    /// `options` can set the shutdown grace period and also remove the services' volumes and images.
     ///
     /// Returns `Ok(true)` if the command was successful, or `Ok(false)` if no Docker Compose file was found.
-    pub fn docker_compose_down(&self) -> eyre::Result<bool> {
+    pub fn docker_compose_down(&self, options: DownOptions) -> eyre::Result<bool> {
         let docker_compose_path = self
             .docker_compose_path()
             .map_err(|e| eyre!(e))
@@ -222,12 +592,25 @@ impl Project {
             return Ok(false);
         };
 
-        let status = Command::new("docker-compose")
-            .arg("-f")
-            .arg(docker_compose_path)
-            .arg("down")
-            .status()
-            .map_err(|e| eyre!(e))
+        let mut command = Command::new("docker-compose");
+        command.envs(self.env());
+        command.arg("-f").arg(docker_compose_path).arg("down");
+
+        if let Some(timeout) = options.timeout {
+            command.arg("-t").arg(timeout.to_string());
+        }
+
+        if options.volumes {
+            command.arg("-v");
+        }
+
+        if options.images {
+            command.arg("--rmi").arg("local");
+        }
+
+        let status = command
+            .logged_status()
+            .map_err(|e| eyre!(DeError::DockerUnavailable(e)))
             .wrap_err_with(|| {
                 format!(
                     "Failed to run docker-compose down for project {}",
@@ -236,12 +619,212 @@ impl Project {
             })?;
 
         if !status.success() {
-            return Err(eyre!(
-                "docker-compose down failed with status code: {}",
-                status.code().unwrap_or(-1)
-            ));
+            return Err(eyre!(DeError::DockerComposeFailed {
+                command: "down",
+                code: status.code().unwrap_or(-1),
+            }));
         }
 
         Ok(true)
     }
 }
+
+/// Runs `config` with `args` on a Compose file, with `docker-compose` or else the
+/// `docker compose` plugin, and returns its stdout.
+pub fn docker_compose_config(
+    compose_path: &Path,
+    env: &BTreeMap<String, String>,
+    args: &[&str],
+) -> eyre::Result<String> {
+    compose_config_with(COMPOSE_COMMANDS, compose_path, env, args)
+}
+
+fn compose_config_with(
+    commands: &[&[&str]],
+    compose_path: &Path,
+    env: &BTreeMap<String, String>,
+    args: &[&str],
+) -> eyre::Result<String> {
+    let mut failure = String::from("no Docker Compose command to run");
+
+    for (program, prefix) in commands.iter().filter_map(|command| command.split_first()) {
+        let output = Command::new(program)
+            .envs(env)
+            .args(prefix)
+            .arg("-f")
+            .arg(compose_path)
+            .arg("config")
+            .args(args)
+            .logged_output();
+
+        match output {
+            Ok(output) if output.status.success() => {
+                return Ok(String::from_utf8_lossy(&output.stdout).into_owned());
+            }
+            Ok(output) => failure = String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            Err(e) => failure = format!("failed to run {program}: {e}"),
+        }
+    }
+
+    Err(eyre!("docker compose config failed: {failure}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_edit_manifest_only_writes_de_toml() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("de.toml"), "[project]\nname = \"api\"\n").unwrap();
+        std::fs::create_dir(dir.path().join(".de")).unwrap();
+        std::fs::write(
+            dir.path().join(".de/config.toml"),
+            "[project]\nworkspace = \"local\"\n\n[tasks]\nlocal = \"make local\"\n",
+        )
+        .unwrap();
+
+        let mut project = Project::from_dir(dir.path()).unwrap();
+        assert!(
+            project
+                .tasks()
+                .unwrap()
+                .contains(&Slug::from_str("local").unwrap())
+        );
+
+        project
+            .edit_manifest(|manifest| {
+                manifest.tasks.get_or_insert_with(Default::default).insert(
+                    Slug::from_str("build").unwrap(),
+                    Task::Raw(task::RawTask::Flat("make".to_string())),
+                )
+            })
+            .unwrap();
+
+        // The overlay's values stay out of de.toml, but the reloaded project still has them
+        let raw = ProjectManifest::load(&dir.path().join("de.toml")).unwrap();
+        let raw_tasks = raw.tasks.unwrap();
+        assert!(raw_tasks.contains(&Slug::from_str("build").unwrap()));
+        assert!(!raw_tasks.contains(&Slug::from_str("local").unwrap()));
+        assert_ne!(raw.project.workspace.as_str(), "local");
+
+        let tasks = project.tasks().unwrap();
+        assert!(tasks.contains(&Slug::from_str("build").unwrap()));
+        assert!(tasks.contains(&Slug::from_str("local").unwrap()));
+        assert_eq!(project.manifest().project().workspace.as_str(), "local");
+        assert!(
+            Project::from_dir(dir.path())
+                .unwrap()
+                .task(&Slug::from_str("build").unwrap())
+                .unwrap()
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn test_start_command_keeps_quoted_arguments() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("de.toml"),
+            "[project]\nname = \"api\"\nstart_command = \"sh -c 'echo started > out.txt'\"\n",
+        )
+        .unwrap();
+
+        let project = Project::from_dir(dir.path()).unwrap();
+        assert!(project.start().unwrap());
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("out.txt")).unwrap(),
+            "started\n"
+        );
+    }
+
+    #[test]
+    fn test_manifest_origins_name_the_overriding_source() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("de.toml"),
+            "[project]\nname = \"api\"\n\n[tasks]\nbuild = \"make\"\nlint = \"make lint\"\n",
+        )
+        .unwrap();
+        std::fs::create_dir(dir.path().join(".de")).unwrap();
+        std::fs::write(
+            dir.path().join(".de/config.toml"),
+            "[tasks]\nbuild = \"make -j4\"\n",
+        )
+        .unwrap();
+
+        let project = Project::from_dir(dir.path()).unwrap();
+        let origins = project.manifest_origins().unwrap();
+        let origin = |key: &str| {
+            origins
+                .iter()
+                .find(|origin| origin.key == key)
+                .map(|origin| {
+                    (
+                        origin.value.as_str(),
+                        origin.source.ends_with("config.toml"),
+                    )
+                })
+        };
+
+        assert_eq!(origin("project.name"), Some(("api", false)));
+        assert_eq!(origin("tasks.build"), Some(("make -j4", true)));
+        assert_eq!(origin("tasks.lint"), Some(("make lint", false)));
+
+        let source = |task: &str| {
+            project
+                .task_source_file(&Slug::from_str(task).unwrap())
+                .unwrap()
+                .map(|path| path.file_name().unwrap().to_owned())
+        };
+        assert_eq!(source("build"), Some("config.toml".into()));
+        assert_eq!(source("lint"), Some("de.toml".into()));
+        assert_eq!(source("test"), None);
+    }
+
+    #[test]
+    fn test_from_dir_recursive_finds_the_project_above() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("de.toml"), "[project]\nname = \"api\"\n").unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+
+        let project = Project::from_dir_recursive(&dir.path().join("src"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(project.manifest().project().name.as_str(), "api");
+
+        let outside = tempfile::tempdir().unwrap();
+        assert!(
+            Project::from_dir_recursive(outside.path())
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_docker_compose_services_falls_back_to_the_next_command() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("de.toml"), "[project]\nname = \"api\"\n").unwrap();
+        std::fs::write(dir.path().join("docker-compose.yml"), "services: {}\n").unwrap();
+
+        let fake = dir.path().join("fake-compose");
+        std::fs::write(&fake, "#!/bin/sh\nprintf 'db\\n\\nweb\\n'\n").unwrap();
+        std::fs::set_permissions(&fake, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let fake = fake.to_str().unwrap();
+
+        let project = Project::from_dir(dir.path()).unwrap();
+        let services = project
+            .docker_compose_services_with(&[&["de-missing-compose"], &[fake]])
+            .unwrap();
+        assert_eq!(services, ["db", "web"]);
+
+        let error = project
+            .docker_compose_services_with(&[&["de-missing-compose"]])
+            .unwrap_err();
+        assert!(error.to_string().contains("de-missing-compose"));
+    }
+}
@@ -0,0 +1,768 @@
+use dialoguer::Confirm;
+use eyre::{Context, eyre};
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+    process::Command,
+    time::Duration,
+};
+
+use crate::{
+    constants::TASK_STACK_ENV_VAR,
+    error::DeError,
+    project::{Project, Task, compose::is_service_running},
+    types::{HumanDuration, Slug},
+    utils::{
+        is_interactive,
+        parallel::parallel_map,
+        process::{CommandExt, OutputBuffer, ProcessOutcome, run_with_log, run_with_timeout},
+        task_cache,
+        ui::UserInterface,
+    },
+    workspace::DependencyGraph,
+};
+
+/// Where a task runs and what it gets on top of the project and workspace environment.
+#[derive(Debug, Default)]
+pub struct TaskContext {
+    /// Runs project tasks in this directory, relative to the project directory.
+    pub cwd: Option<PathBuf>,
+    /// Variables from `--env`, which override the project and workspace environment.
+    pub env: Vec<(String, String)>,
+    /// Starts the service of a Docker Compose task if it isn't running, instead of asking.
+    pub auto_start: bool,
+    /// Runs Docker Compose tasks without checking that their service is up. Hooks run while the
+    /// project is being started or stopped, when asking to start the service makes no sense.
+    pub skip_service_check: bool,
+    /// Skips project tasks whose inputs are unchanged since their last successful run.
+    pub if_changed: bool,
+    /// Writes the output of project tasks to a file, from `--tee` or `--capture`.
+    pub output: Option<TaskOutput>,
+    /// Runs only the named task, without the tasks in its `depends_on`.
+    pub no_deps: bool,
+    /// How many prerequisites that don't depend on each other run at once. Up to 1, they run
+    /// one after another.
+    pub jobs: usize,
+}
+
+/// A file that receives everything a task writes to stdout and stderr.
+#[derive(Debug)]
+pub struct TaskOutput {
+    pub path: PathBuf,
+    /// Whether the output still goes to the terminal too, as with `--tee`.
+    pub echo: bool,
+}
+
+/// Builds the command for a project task with the extra arguments substituted or appended.
+/// With a `cwd` in the context, the task runs there instead of the project directory.
+///
+/// Returns `Ok(None)` if the project does not define the task.
+pub(crate) fn project_task_command(
+    project: &Project,
+    task_name: &Slug,
+    args: &[String],
+    context: &TaskContext,
+) -> eyre::Result<Option<Command>> {
+    let Some(task) = project.task(task_name)? else {
+        return Ok(None);
+    };
+
+    let mut command = task
+        .command(project, args, &context.env)
+        .map_err(|e| eyre!(e))
+        .wrap_err("Failed to build command for task")?;
+
+    if let Some(cwd) = context.cwd.as_deref() {
+        if matches!(task, Task::Compose { .. }) {
+            return Err(eyre!(
+                "--cwd is not supported for task '{}', which runs in a Docker Compose service",
+                task_name
+            ));
+        }
+
+        command.current_dir(resolve_task_cwd(project, cwd)?);
+    }
+
+    Ok(Some(command))
+}
+
+/// Resolves `cwd` against the project directory, rejecting directories outside the project.
+fn resolve_task_cwd(project: &Project, cwd: &Path) -> eyre::Result<PathBuf> {
+    let project_dir = project
+        .dir()
+        .canonicalize()
+        .map_err(|e| eyre!(e))
+        .wrap_err("Failed to resolve project directory")?;
+
+    let dir = project_dir
+        .join(cwd)
+        .canonicalize()
+        .map_err(|e| eyre!(e))
+        .wrap_err_with(|| format!("Failed to resolve working directory {}", cwd.display()))?;
+
+    if !dir.is_dir() {
+        return Err(eyre!(
+            "Working directory is not a directory: {}",
+            dir.display()
+        ));
+    }
+
+    if !dir.starts_with(&project_dir) {
+        return Err(eyre!(
+            "Working directory {} is outside the project directory {}",
+            dir.display(),
+            project_dir.display()
+        ));
+    }
+
+    Ok(dir)
+}
+
+/// What to do before running a Docker Compose task, given whether its service is running.
+#[derive(Debug, PartialEq, Eq)]
+enum ServiceCheck {
+    /// The service is up, or docker-compose can't be queried and the task reports its own error.
+    Run,
+    /// Start the service first, as `--auto-start` was given.
+    Start,
+    /// Ask whether to start the service.
+    Ask,
+    /// Fail, as there is no terminal to ask on.
+    NotRunning,
+}
+
+fn check_service(running: Option<bool>, auto_start: bool, interactive: bool) -> ServiceCheck {
+    match running {
+        Some(true) | None => ServiceCheck::Run,
+        Some(false) if auto_start => ServiceCheck::Start,
+        Some(false) if interactive => ServiceCheck::Ask,
+        Some(false) => ServiceCheck::NotRunning,
+    }
+}
+
+/// Makes sure the service a Docker Compose task runs in is up, as `docker-compose exec` fails
+/// with a confusing error otherwise. A stopped service is started with `auto_start`, or if the
+/// user agrees when asked. Without a terminal to ask on, an error says how to start it.
+///
+/// If docker-compose can't be queried, the task runs anyway and reports its own error.
+fn ensure_service_running(project: &Project, service: &str, auto_start: bool) -> eyre::Result<()> {
+    let Some(compose_path) = project
+        .docker_compose_path()
+        .map_err(|e| eyre!(e))
+        .wrap_err("Failed to get Docker Compose path")?
+    else {
+        return Ok(());
+    };
+
+    let running = is_service_running(&compose_path, project.env(), service);
+    let project_name = &project.manifest().project().name;
+    let start = match check_service(running, auto_start, is_interactive()) {
+        ServiceCheck::Run => return Ok(()),
+        ServiceCheck::Start => true,
+        ServiceCheck::Ask => Confirm::new()
+            .with_prompt(format!(
+                "Service {service} of project {project_name} is not running. Start it?"
+            ))
+            .default(true)
+            .interact()
+            .map_err(|e| eyre!(e))
+            .wrap_err("Failed to prompt for confirmation")?,
+        ServiceCheck::NotRunning => false,
+    };
+
+    if !start {
+        return Err(eyre!(
+            "Service {} of project {} is not running; start it with `de start` or pass --auto-start",
+            service,
+            project_name
+        ));
+    }
+
+    let status = Command::new("docker-compose")
+        .envs(project.env())
+        .arg("-f")
+        .arg(&compose_path)
+        .arg("up")
+        .arg("-d")
+        .arg(service)
+        .logged_status()
+        .map_err(|e| eyre!(DeError::DockerUnavailable(e)))
+        .wrap_err_with(|| format!("Failed to start service {service}"))?;
+
+    if !status.success() {
+        return Err(eyre!(DeError::DockerComposeFailed {
+            command: "up",
+            code: status.code().unwrap_or(-1),
+        }));
+    }
+
+    Ok(())
+}
+
+/// The tasks to run before the project task, or none with `--no-deps`.
+pub(crate) fn task_prerequisites(
+    project: &Project,
+    task_name: &Slug,
+    context: &TaskContext,
+) -> eyre::Result<Vec<Slug>> {
+    match project.tasks() {
+        Some(tasks) if !context.no_deps => tasks
+            .prerequisites(task_name)
+            .wrap_err_with(|| format!("Failed to resolve the dependencies of task '{task_name}'")),
+        _ => Ok(Vec::new()),
+    }
+}
+
+/// The prerequisites of the project task grouped into levels, where each task only depends on
+/// tasks of earlier levels, so the tasks of one level can run at once.
+fn prerequisite_levels(
+    project: &Project,
+    task_name: &Slug,
+    context: &TaskContext,
+) -> eyre::Result<Vec<Vec<Slug>>> {
+    let prerequisites = task_prerequisites(project, task_name, context)?;
+    let Some(tasks) = project.tasks() else {
+        return Ok(Vec::new());
+    };
+
+    let mut graph = DependencyGraph::new();
+    for prerequisite in prerequisites {
+        let mut depends_on = Vec::new();
+        if let Some((_, task)) = tasks.resolve(&prerequisite)? {
+            // Depend on the tasks aliases refer to, as those are what runs
+            for dependency in task.depends_on() {
+                match tasks.resolve(dependency)? {
+                    Some((name, _)) => depends_on.push(name.clone()),
+                    None => depends_on.push(dependency.clone()),
+                }
+            }
+        }
+        graph.add_project(prerequisite, depends_on);
+    }
+
+    graph
+        .resolve_startup_levels()
+        .wrap_err_with(|| format!("Failed to resolve the dependencies of task '{task_name}'"))
+}
+
+/// Runs the project task, after the tasks in its `depends_on` unless `--no-deps` is given.
+/// Only the task itself gets `args` and `timeout`; its prerequisites run with their own policy.
+/// With `jobs` above 1, prerequisites that don't depend on each other run at once.
+///
+/// Returns `Ok(false)` if the project does not define the task.
+pub fn run_project_task(
+    project: &Project,
+    task_name: &Slug,
+    args: &[String],
+    timeout: Option<Duration>,
+    context: &TaskContext,
+) -> eyre::Result<bool> {
+    if project.task(task_name)?.is_none() {
+        return Ok(false);
+    }
+
+    // Prerequisites run on behalf of the task, so a prerequisite calling `de run` on the task
+    // recurses too. Checking here fails before any of them ran.
+    let stack = task_stack();
+    let with_task = push_task(&stack, project, task_name)?;
+
+    if context.jobs > 1 {
+        for level in prerequisite_levels(project, task_name, context)? {
+            if let [prerequisite] = level.as_slice() {
+                announce_prerequisite(prerequisite, task_name);
+                run_single_project_task(
+                    project,
+                    prerequisite,
+                    &[],
+                    None,
+                    context,
+                    &with_task,
+                    None,
+                )?;
+            } else {
+                run_prerequisites_concurrently(project, task_name, &level, context, &with_task)?;
+            }
+        }
+    } else {
+        for prerequisite in task_prerequisites(project, task_name, context)? {
+            announce_prerequisite(&prerequisite, task_name);
+            run_single_project_task(project, &prerequisite, &[], None, context, &with_task, None)?;
+        }
+    }
+
+    run_single_project_task(project, task_name, args, timeout, context, &stack, None)
+}
+
+/// The tasks that the `de run` processes this one runs under are running, from
+/// `DE_TASK_STACK`, outermost first.
+fn task_stack() -> Vec<String> {
+    std::env::var(TASK_STACK_ENV_VAR)
+        .map(|value| {
+            value
+                .split(',')
+                .filter(|entry| !entry.is_empty())
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Adds the task to the stack of tasks in flight, failing with the cycle if it's already on it.
+///
+/// Tasks are qualified by their project, as a task calling the task of the same name in another
+/// project is no recursion.
+fn push_task(stack: &[String], project: &Project, task_name: &Slug) -> eyre::Result<Vec<String>> {
+    let entry = format!("{}:{task_name}", project.manifest().project().name);
+
+    if let Some(start) = stack.iter().position(|running| *running == entry) {
+        let cycle = stack[start..]
+            .iter()
+            .chain([&entry])
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(" -> ");
+        return Err(eyre!(DeError::RecursiveTask {
+            task: task_name.clone(),
+            cycle,
+        }));
+    }
+
+    let mut stack = stack.to_vec();
+    stack.push(entry);
+    Ok(stack)
+}
+
+/// Runs up to `jobs` of the prerequisites at once. The output of each is collected and written
+/// out in one piece once all of them are done, in the order of `prerequisites`, so it doesn't
+/// interleave and reads the same on every run.
+///
+/// Fails with the error of the first prerequisite in that order that failed.
+fn run_prerequisites_concurrently(
+    project: &Project,
+    task_name: &Slug,
+    prerequisites: &[Slug],
+    context: &TaskContext,
+    stack: &[String],
+) -> eyre::Result<()> {
+    let results = parallel_map(prerequisites, context.jobs, |prerequisite| {
+        let buffer = OutputBuffer::default();
+        let result = run_single_project_task(
+            project,
+            prerequisite,
+            &[],
+            None,
+            context,
+            stack,
+            Some(&buffer),
+        );
+        (result, buffer)
+    });
+
+    let mut first_error = None;
+    for (prerequisite, (result, buffer)) in prerequisites.iter().zip(results) {
+        announce_prerequisite(prerequisite, task_name);
+        write_buffered_output(&buffer.take(), context)?;
+
+        if let Err(e) = result {
+            first_error.get_or_insert(e);
+        }
+    }
+
+    first_error.map_or(Ok(()), Err)
+}
+
+/// Writes out the output collected from a task that ran concurrently with others: to the
+/// `--tee` or `--capture` file if one is given, and to the terminal unless it's `--capture`.
+fn write_buffered_output(output: &[u8], context: &TaskContext) -> eyre::Result<()> {
+    if let Some(task_output) = &context.output {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&task_output.path)
+            .and_then(|mut log| log.write_all(output))
+            .map_err(|e| eyre!(e))
+            .wrap_err_with(|| format!("Failed to write to {}", task_output.path.display()))?;
+    }
+
+    if context
+        .output
+        .as_ref()
+        .is_none_or(|task_output| task_output.echo)
+    {
+        let mut stdout = std::io::stdout().lock();
+        stdout
+            .write_all(output)
+            .and_then(|_| stdout.flush())
+            .map_err(|e| eyre!(e))
+            .wrap_err("Failed to write task output")?;
+    }
+
+    Ok(())
+}
+
+/// Prints a message about a task run to stderr, or adds it to the task's output if that is
+/// collected. Stdout is left to the output of the tasks.
+fn report(buffer: Option<&OutputBuffer>, message: &str) {
+    match buffer {
+        Some(buffer) => {
+            let _ = writeln!(buffer.clone(), "{message}");
+        }
+        None => {
+            let _ = UserInterface::new().stderr().info_item(message);
+        }
+    }
+}
+
+fn announce_prerequisite(prerequisite: &Slug, task_name: &Slug) {
+    report(
+        None,
+        &format!("Running prerequisite '{prerequisite}' of task '{task_name}'..."),
+    );
+}
+
+/// Runs the project task alone, without its prerequisites, on behalf of the tasks on `stack`.
+/// With a `buffer`, everything the task prints is collected there instead of going to the
+/// terminal or the `--tee` file.
+fn run_single_project_task(
+    project: &Project,
+    task_name: &Slug,
+    args: &[String],
+    timeout: Option<Duration>,
+    context: &TaskContext,
+    stack: &[String],
+    buffer: Option<&OutputBuffer>,
+) -> eyre::Result<bool> {
+    let Some(task) = project.task(task_name)? else {
+        return Ok(false);
+    };
+    let Some(mut command) = project_task_command(project, task_name, args, context)? else {
+        return Ok(false);
+    };
+    command.env(
+        TASK_STACK_ENV_VAR,
+        push_task(stack, project, task_name)?.join(","),
+    );
+
+    // Tasks without inputs can't be compared and always run
+    let inputs_hash = if context.if_changed && !task.inputs().is_empty() {
+        let hash = task_cache::inputs_hash(project, task, args, &context.env)
+            .wrap_err_with(|| format!("Failed to hash the inputs of task '{task_name}'"))?;
+
+        if task_cache::is_up_to_date(project, task_name, task, &hash)? {
+            report(buffer, &format!("Task '{task_name}' is up to date"));
+            return Ok(true);
+        }
+        Some(hash)
+    } else {
+        None
+    };
+
+    if let Task::Compose { service, .. } = task
+        && !context.skip_service_check
+    {
+        ensure_service_running(project, service, context.auto_start)?;
+    }
+
+    // `--timeout` takes precedence over the task's own policy
+    let timeout = timeout.or_else(|| task.timeout());
+    let attempts = task.retries() + 1;
+
+    for attempt in 1..=attempts {
+        let outcome = match (buffer, &context.output) {
+            (Some(buffer), _) => run_with_log(&mut command, timeout, buffer.clone(), false),
+            (None, Some(output)) => {
+                let log = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&output.path)
+                    .map_err(|e| eyre!(e))
+                    .wrap_err_with(|| format!("Failed to open {}", output.path.display()))?;
+                run_with_log(&mut command, timeout, log, output.echo)
+            }
+            (None, None) => run_with_timeout(&mut command, timeout),
+        };
+
+        let status = match outcome
+            .map_err(|e| eyre!(e))
+            .wrap_err("Failed to execute task command")?
+        {
+            ProcessOutcome::Exited(status) => status,
+            ProcessOutcome::TimedOut => {
+                return Err(eyre!(
+                    "Task '{}' timed out after {}",
+                    task_name,
+                    HumanDuration::from(timeout.unwrap_or_default())
+                ));
+            }
+        };
+
+        if status.success() {
+            if attempt > 1 {
+                report(
+                    buffer,
+                    &format!("Task '{task_name}' succeeded on attempt {attempt}/{attempts}"),
+                );
+            }
+            break;
+        }
+
+        if attempt == attempts {
+            return Err(eyre!(DeError::TaskFailed {
+                task: task_name.clone(),
+                status,
+            }));
+        }
+
+        report(
+            buffer,
+            &format!(
+                "Task '{task_name}' failed ({status}) on attempt {attempt}/{attempts}, retrying..."
+            ),
+        );
+    }
+
+    if let Some(hash) = inputs_hash {
+        task_cache::record(project, task_name, &hash)?;
+    }
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_project_task_runs_in_project_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("de.toml"),
+            "[project]\nname = \"cwd\"\n\n[tasks]\nwhere = \"pwd\"\n",
+        )
+        .unwrap();
+
+        let project = Project::from_dir(dir.path()).unwrap();
+        let output = project_task_command(
+            &project,
+            &Slug::from_str("where").unwrap(),
+            &[],
+            &TaskContext::default(),
+        )
+        .unwrap()
+        .unwrap()
+        .logged_output()
+        .unwrap();
+
+        let cwd = PathBuf::from(String::from_utf8(output.stdout).unwrap().trim());
+        assert_eq!(
+            cwd.canonicalize().unwrap(),
+            dir.path().canonicalize().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_resolve_task_cwd_stays_inside_the_project() {
+        let dir = tempfile::tempdir().unwrap();
+        let project_dir = dir.path().join("api");
+        std::fs::create_dir_all(project_dir.join("src")).unwrap();
+        std::fs::create_dir(dir.path().join("other")).unwrap();
+        std::fs::write(project_dir.join("de.toml"), "[project]\nname = \"api\"\n").unwrap();
+        let project = Project::from_dir(&project_dir).unwrap();
+
+        assert_eq!(
+            resolve_task_cwd(&project, Path::new("src")).unwrap(),
+            project_dir.join("src").canonicalize().unwrap()
+        );
+        assert_eq!(
+            resolve_task_cwd(&project, Path::new("src/..")).unwrap(),
+            project_dir.canonicalize().unwrap()
+        );
+
+        let outside = resolve_task_cwd(&project, Path::new("../other")).unwrap_err();
+        assert!(
+            outside
+                .to_string()
+                .contains("outside the project directory"),
+            "{outside}"
+        );
+        assert!(resolve_task_cwd(&project, Path::new("..")).is_err());
+        assert!(resolve_task_cwd(&project, Path::new("missing")).is_err());
+        assert!(resolve_task_cwd(&project, Path::new("de.toml")).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_inline_env_overrides_project_env() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("de.toml"),
+            "[project]\nname = \"env\"\n\n[tasks]\ngreet = \"printenv GREETING\"\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join(".env"), "GREETING=project\n").unwrap();
+
+        let project = Project::from_dir(dir.path()).unwrap();
+        let context = TaskContext {
+            env: vec![("GREETING".to_string(), "inline".to_string())],
+            ..Default::default()
+        };
+        let output =
+            project_task_command(&project, &Slug::from_str("greet").unwrap(), &[], &context)
+                .unwrap()
+                .unwrap()
+                .logged_output()
+                .unwrap();
+
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), "inline\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_capture_writes_raw_output_to_file() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("de.toml"),
+            "[project]\nname = \"capture\"\n\n[tasks.bytes]\ncommand = \"printf 'a\\\\377'; printf b >&2\"\nshell = true\n",
+        )
+        .unwrap();
+
+        let project = Project::from_dir(dir.path()).unwrap();
+        let log = dir.path().join("out.log");
+        let context = TaskContext {
+            output: Some(TaskOutput {
+                path: log.clone(),
+                echo: false,
+            }),
+            ..Default::default()
+        };
+        assert!(
+            run_project_task(
+                &project,
+                &Slug::from_str("bytes").unwrap(),
+                &[],
+                None,
+                &context
+            )
+            .unwrap()
+        );
+
+        let mut bytes = std::fs::read(&log).unwrap();
+        bytes.sort();
+        assert_eq!(bytes, b"ab\xff");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_concurrent_prerequisites_keep_their_output_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("de.toml"),
+            "[project]\nname = \"jobs\"\n\n[tasks]\nbuild = { command = \"sleep 0.3; echo build\", shell = true }\nlint = { command = \"echo lint\", shell = true }\nci = { command = \"echo ci\", shell = true, depends_on = [\"lint\", \"build\"] }\n",
+        )
+        .unwrap();
+
+        let project = Project::from_dir(dir.path()).unwrap();
+        let ci = Slug::from_str("ci").unwrap();
+        let log = dir.path().join("out.log");
+        let context = TaskContext {
+            output: Some(TaskOutput {
+                path: log.clone(),
+                echo: false,
+            }),
+            jobs: 2,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            prerequisite_levels(&project, &ci, &context).unwrap(),
+            vec![vec![
+                Slug::from_str("build").unwrap(),
+                Slug::from_str("lint").unwrap()
+            ]]
+        );
+        // `lint` finishes first, but the output stays in level order
+        assert!(run_project_task(&project, &ci, &[], None, &context).unwrap());
+        assert_eq!(std::fs::read_to_string(&log).unwrap(), "build\nlint\nci\n");
+    }
+
+    #[test]
+    fn test_push_task_rejects_tasks_already_in_flight() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("de.toml"),
+            "[project]\nname = \"api\"\n\n[tasks]\nbuild = \"make\"\n",
+        )
+        .unwrap();
+
+        let project = Project::from_dir(dir.path()).unwrap();
+        let build = Slug::from_str("build").unwrap();
+
+        let stack = vec!["web:build".to_string(), "api:ci".to_string()];
+        assert_eq!(
+            push_task(&stack, &project, &build).unwrap(),
+            vec!["web:build", "api:ci", "api:build"]
+        );
+
+        let stack = vec!["api:build".to_string(), "api:ci".to_string()];
+        let error = push_task(&stack, &project, &build).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Task 'build' would run itself recursively: api:build -> api:ci -> api:build"
+        );
+    }
+
+    #[test]
+    fn test_check_service_of_compose_tasks() {
+        // Up, or unknown because docker-compose failed: the task runs and reports its own error
+        assert_eq!(check_service(Some(true), false, false), ServiceCheck::Run);
+        assert_eq!(check_service(None, false, true), ServiceCheck::Run);
+
+        // Down
+        assert_eq!(check_service(Some(false), true, false), ServiceCheck::Start);
+        assert_eq!(check_service(Some(false), true, true), ServiceCheck::Start);
+        assert_eq!(check_service(Some(false), false, true), ServiceCheck::Ask);
+        assert_eq!(
+            check_service(Some(false), false, false),
+            ServiceCheck::NotRunning
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_failed_attempts_are_retried_and_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("de.toml"),
+            "[project]\nname = \"flaky\"\n\n[tasks.flaky]\ncommand = \"echo run >> attempts; [ $(wc -l < attempts) -ge 3 ]\"\nshell = true\nretries = 2\n\n[tasks.broken]\ncommand = \"echo run >> broken; false\"\nshell = true\nretries = 1\n",
+        )
+        .unwrap();
+
+        let project = Project::from_dir(dir.path()).unwrap();
+        let context = TaskContext::default();
+        let buffer = OutputBuffer::default();
+        let run = |task: &str| {
+            let task = Slug::from_str(task).unwrap();
+            run_single_project_task(&project, &task, &[], None, &context, &[], Some(&buffer))
+        };
+
+        assert!(run("flaky").unwrap());
+        let output = String::from_utf8(buffer.take()).unwrap();
+        assert!(
+            output.contains("failed (exit status: 1) on attempt 1/3, retrying..."),
+            "{output}"
+        );
+        assert!(output.contains("on attempt 2/3, retrying..."), "{output}");
+        assert!(
+            output.contains("Task 'flaky' succeeded on attempt 3/3"),
+            "{output}"
+        );
+
+        let error = run("broken").unwrap_err();
+        assert!(error.to_string().contains("broken"), "{error}");
+        let runs = std::fs::read_to_string(dir.path().join("broken")).unwrap();
+        assert_eq!(runs.lines().count(), 2);
+    }
+}
@@ -1,29 +1,120 @@
 use eyre::{Context, eyre};
-use std::process::Command;
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, IsTerminal, Read},
+    path::Path,
+    process::Command,
+    time::Duration,
+};
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::project::Project;
+use crate::{
+    project::Project,
+    types::{HumanDuration, Slug},
+    utils::process::quote_arg,
+};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(untagged, rename_all = "snake_case")]
 pub enum Task {
-    Compose { service: String, command: String },
+    /// Runs the command in a Docker Compose service of the project.
+    Compose {
+        /// The Docker Compose service to run the command in.
+        service: String,
+        command: String,
+        /// Shown by `de task list`.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        description: Option<String>,
+        /// Run the command through `sh -c` instead of splitting it on whitespace.
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        shell: bool,
+        /// Globs of the files the task reads, for `de run --if-changed`.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        inputs: Vec<String>,
+        /// Globs of the files the task produces. `de run --if-changed` reruns the task if
+        /// one of them matches no file.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        outputs: Vec<String>,
+        /// How often to rerun the task if it exits with a non-zero status.
+        #[serde(default, skip_serializing_if = "is_zero")]
+        retries: u32,
+        /// Kills the task if it runs longer than this, unless `de run --timeout` is given.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timeout: Option<HumanDuration>,
+        /// Tasks of the same project that run before this one, unless `de run --no-deps` is given.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        depends_on: Vec<Slug>,
+    },
+    /// Runs a script file of the project, e.g. `script = "scripts/deploy.sh"`.
+    Script {
+        /// The script, relative to the project directory.
+        #[serde(rename = "script")]
+        path: String,
+        /// Arguments passed to the script before those given to `de run`.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        args: Vec<String>,
+        /// Shown by `de task list`.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        description: Option<String>,
+        /// Globs of the files the task reads, for `de run --if-changed`.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        inputs: Vec<String>,
+        /// Globs of the files the task produces. `de run --if-changed` reruns the task if
+        /// one of them matches no file.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        outputs: Vec<String>,
+        /// How often to rerun the task if it exits with a non-zero status.
+        #[serde(default, skip_serializing_if = "is_zero")]
+        retries: u32,
+        /// Kills the task if it runs longer than this, unless `de run --timeout` is given.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timeout: Option<HumanDuration>,
+        /// Tasks of the same project that run before this one, unless `de run --no-deps` is given.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        depends_on: Vec<Slug>,
+    },
     Raw(RawTask),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(untagged, rename_all = "snake_case")]
 pub enum RawTask {
+    /// The command to run.
     Flat(String),
-    Complex { command: String },
+    Complex {
+        command: String,
+        /// Shown by `de task list`.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        description: Option<String>,
+        /// Run the command through `sh -c` instead of splitting it on whitespace.
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        shell: bool,
+        /// Globs of the files the task reads, for `de run --if-changed`.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        inputs: Vec<String>,
+        /// Globs of the files the task produces. `de run --if-changed` reruns the task if
+        /// one of them matches no file.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        outputs: Vec<String>,
+        /// How often to rerun the task if it exits with a non-zero status.
+        #[serde(default, skip_serializing_if = "is_zero")]
+        retries: u32,
+        /// Kills the task if it runs longer than this, unless `de run --timeout` is given.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timeout: Option<HumanDuration>,
+        /// Tasks of the same project that run before this one, unless `de run --no-deps` is given.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        depends_on: Vec<Slug>,
+    },
 }
 
 impl RawTask {
     pub fn command_str(&self) -> &str {
         match self {
             RawTask::Flat(cmd) => cmd,
-            RawTask::Complex { command } => command,
+            RawTask::Complex { command, .. } => command,
         }
     }
 }
@@ -31,55 +122,622 @@ impl RawTask {
 impl Task {
     pub fn command_str(&self) -> String {
         match self {
-            Task::Compose { service, command } => {
+            Task::Compose {
+                service, command, ..
+            } => {
                 format!("docker-compose exec {service} {command}")
             }
+            Task::Script { path, args, .. } => std::iter::once(path.clone())
+                .chain(args.iter().map(|arg| quote_arg(arg)))
+                .collect::<Vec<_>>()
+                .join(" "),
             Task::Raw(shell_task) => shell_task.command_str().to_string(),
         }
     }
 
-    pub fn command(&self, project: &Project) -> eyre::Result<Command> {
+    /// `compose` for tasks run in a Docker Compose service, `script` for script files and
+    /// `command` for the others.
+    pub fn kind(&self) -> &'static str {
         match self {
-            Task::Compose { service, command } => {
+            Task::Compose { .. } => "compose",
+            Task::Script { .. } => "script",
+            Task::Raw(_) => "command",
+        }
+    }
+
+    pub fn service(&self) -> Option<&str> {
+        match self {
+            Task::Compose { service, .. } => Some(service),
+            Task::Script { .. } | Task::Raw(_) => None,
+        }
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        match self {
+            Task::Compose { description, .. }
+            | Task::Script { description, .. }
+            | Task::Raw(RawTask::Complex { description, .. }) => description.as_deref(),
+            Task::Raw(RawTask::Flat(_)) => None,
+        }
+    }
+
+    pub fn inputs(&self) -> &[String] {
+        match self {
+            Task::Compose { inputs, .. }
+            | Task::Script { inputs, .. }
+            | Task::Raw(RawTask::Complex { inputs, .. }) => inputs,
+            Task::Raw(RawTask::Flat(_)) => &[],
+        }
+    }
+
+    pub fn outputs(&self) -> &[String] {
+        match self {
+            Task::Compose { outputs, .. }
+            | Task::Script { outputs, .. }
+            | Task::Raw(RawTask::Complex { outputs, .. }) => outputs,
+            Task::Raw(RawTask::Flat(_)) => &[],
+        }
+    }
+
+    pub fn retries(&self) -> u32 {
+        match self {
+            Task::Compose { retries, .. }
+            | Task::Script { retries, .. }
+            | Task::Raw(RawTask::Complex { retries, .. }) => *retries,
+            Task::Raw(RawTask::Flat(_)) => 0,
+        }
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        match self {
+            Task::Compose { timeout, .. }
+            | Task::Script { timeout, .. }
+            | Task::Raw(RawTask::Complex { timeout, .. }) => {
+                timeout.map(|timeout| timeout.as_duration())
+            }
+            Task::Raw(RawTask::Flat(_)) => None,
+        }
+    }
+
+    pub fn depends_on(&self) -> &[Slug] {
+        match self {
+            Task::Compose { depends_on, .. }
+            | Task::Script { depends_on, .. }
+            | Task::Raw(RawTask::Complex { depends_on, .. }) => depends_on,
+            Task::Raw(RawTask::Flat(_)) => &[],
+        }
+    }
+
+    pub fn shell(&self) -> bool {
+        match self {
+            Task::Compose { shell, .. } | Task::Raw(RawTask::Complex { shell, .. }) => *shell,
+            Task::Script { .. } | Task::Raw(RawTask::Flat(_)) => false,
+        }
+    }
+
+    /// Builds the command for the task, substituting `args` into its placeholders. The command
+    /// runs in the project directory, wherever `de` itself was started. `env` is set on top of
+    /// the project environment, inside the service for Docker Compose tasks.
+    ///
+    /// See [`expand_task_command`] for the placeholder syntax.
+    pub fn command(
+        &self,
+        project: &Project,
+        args: &[String],
+        env: &[(String, String)],
+    ) -> eyre::Result<Command> {
+        match self {
+            Task::Compose {
+                service,
+                command,
+                shell,
+                ..
+            } => {
                 let mut cmd = Command::new("docker-compose");
+                cmd.envs(project.env());
 
                 let docker_compose_path = project
                     .docker_compose_path()
                     .map_err(|e| eyre!(e))
                     .wrap_err("Failed to get docker compose path")?
-                    .ok_or_else(|| eyre!("Docker compose path not found"))?;
+                    .ok_or_else(|| {
+                        eyre!(
+                            "Task runs in service {} but project {} has no Docker Compose file",
+                            service,
+                            project.manifest().project().name
+                        )
+                    })?;
 
                 let docker_compose_path = docker_compose_path
                     .to_str()
                     .ok_or_else(|| eyre!("Invalid docker compose path"))?;
 
-                cmd.arg("-f")
+                cmd.current_dir(project.dir())
+                    .arg("-f")
                     .arg(docker_compose_path)
-                    .arg("exec")
-                    .arg(service);
+                    .arg("exec");
+
+                // Without a terminal (CI, pipes), `exec` would fail trying to allocate a TTY.
+                if !std::io::stdin().is_terminal() {
+                    cmd.arg("-T");
+                }
 
-                let args = command.split_whitespace();
-                for arg in args {
-                    cmd.arg(arg);
+                for (key, value) in env {
+                    cmd.arg("-e").arg(format!("{key}={value}"));
+                }
+
+                cmd.arg(service);
+
+                if *shell {
+                    cmd.args(shell_args(command, args)?);
+                } else {
+                    cmd.args(expand_task_command(command, args)?);
                 }
 
                 Ok(cmd)
             }
+            Task::Script {
+                path,
+                args: script_args,
+                ..
+            } => {
+                let mut cmd = script_command(project, path)?;
+                cmd.current_dir(project.dir());
+                cmd.envs(project.env());
+                cmd.envs(env.iter().cloned());
+                cmd.args(script_args);
+                cmd.args(args);
+                Ok(cmd)
+            }
             Task::Raw(shell_task) => {
-                let mut parts = shell_task.command_str().split_whitespace();
-                let program = parts.next().ok_or_else(|| eyre!("Empty command"))?;
-                let args = parts.collect::<Vec<_>>();
+                let dir = project.dir();
+
+                if self.shell() {
+                    let mut cmd = Command::new("sh");
+                    cmd.current_dir(dir);
+                    cmd.envs(project.env());
+                    cmd.envs(env.iter().cloned());
+                    cmd.args(&shell_args(shell_task.command_str(), args)?[1..]);
+                    return Ok(cmd);
+                }
 
-                let dir = project
-                    .manifest_path()
-                    .parent()
-                    .ok_or_else(|| eyre!("Failed to get project directory"))?;
+                let argv = expand_task_command(shell_task.command_str(), args)?;
+                let (program, args) = argv.split_first().ok_or_else(|| eyre!("Empty command"))?;
 
                 let mut cmd = Command::new(program);
                 cmd.current_dir(dir);
-                cmd.args(&args);
+                cmd.envs(project.env());
+                cmd.envs(env.iter().cloned());
+                cmd.args(args);
                 Ok(cmd)
             }
         }
     }
 }
+
+/// Builds the command running the script at `path`, relative to the project: the script itself
+/// if it is executable, otherwise the interpreter named by its `#!` line.
+fn script_command(project: &Project, path: &str) -> eyre::Result<Command> {
+    let script = project.dir().join(path);
+    if !script.is_file() {
+        return Err(eyre!("Script {} does not exist", script.display()));
+    }
+
+    if is_executable(&script) {
+        return Ok(Command::new(script));
+    }
+
+    match read_shebang(&script)? {
+        Some(interpreter) => {
+            let mut parts = interpreter.split_whitespace();
+            let program = parts
+                .next()
+                .ok_or_else(|| eyre!("Script {} has an empty #! line", script.display()))?;
+
+            let mut cmd = Command::new(program);
+            cmd.args(parts);
+            cmd.arg(&script);
+            Ok(cmd)
+        }
+        None if cfg!(unix) => Err(eyre!(
+            "Script {} is not executable and has no #! line to run it with",
+            script.display()
+        )),
+        None => Ok(Command::new(script)),
+    }
+}
+
+fn is_executable(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        path.metadata()
+            .is_ok_and(|metadata| metadata.permissions().mode() & 0o111 != 0)
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        false
+    }
+}
+
+/// The interpreter of a `#!/usr/bin/env bash` line at the start of the file, if it has one.
+fn read_shebang(path: &Path) -> eyre::Result<Option<String>> {
+    let file = File::open(path)
+        .map_err(|e| eyre!(e))
+        .wrap_err_with(|| format!("Failed to open script {}", path.display()))?;
+
+    let mut line = Vec::new();
+    BufReader::new(file)
+        .take(512)
+        .read_until(b'\n', &mut line)
+        .map_err(|e| eyre!(e))
+        .wrap_err_with(|| format!("Failed to read script {}", path.display()))?;
+
+    Ok(line
+        .strip_prefix(b"#!")
+        .map(|interpreter| String::from_utf8_lossy(interpreter).trim().to_string()))
+}
+
+fn is_zero(value: &u32) -> bool {
+    *value == 0
+}
+
+/// Builds `sh -c '<command> "$@"' sh <args>`, so arguments not used by a placeholder are still
+/// appended to the command. Placeholders are replaced with shell-quoted arguments.
+///
+/// A multi-line script only gets the unused arguments as `$1`, `$2`, ...: after its last line,
+/// `"$@"` would run as a command of its own (or vanish into a trailing comment).
+fn shell_args(command: &str, args: &[String]) -> eyre::Result<Vec<String>> {
+    let segments = parse_placeholders(command);
+    let used = used_args(&segments, args.len())?;
+
+    let script = segments
+        .iter()
+        .map(|segment| match segment {
+            Segment::Literal(text) => text.clone(),
+            Segment::Args => args
+                .iter()
+                .map(|arg| quote_arg(arg))
+                .collect::<Vec<_>>()
+                .join(" "),
+            Segment::Positional(n) => quote_arg(&args[n - 1]),
+        })
+        .collect::<String>();
+
+    let script = script.trim_end();
+    let script = if script.contains('\n') {
+        script.to_string()
+    } else {
+        format!("{script} \"$@\"")
+    };
+
+    let mut shell_args = vec!["sh".to_string(), "-c".to_string(), script, "sh".to_string()];
+    shell_args.extend(unused_args(args, &used));
+    Ok(shell_args)
+}
+
+/// Splits a task command on whitespace and substitutes `args` into its placeholders.
+///
+/// `{args}` is replaced with all arguments (as separate arguments when it stands alone) and
+/// `{1}`, `{2}`, ... with a single argument. Arguments not used by any placeholder are appended
+/// at the end. Write `{{1}}` or `{{args}}` for a literal placeholder; other braces, like `{}` or
+/// `{{.Names}}`, are kept as written.
+pub fn expand_task_command(command: &str, args: &[String]) -> eyre::Result<Vec<String>> {
+    let tokens = command
+        .split_whitespace()
+        .map(parse_placeholders)
+        .collect::<Vec<_>>();
+
+    let mut used = vec![false; args.len()];
+    for segments in &tokens {
+        for (i, is_used) in used_args(segments, args.len())?.into_iter().enumerate() {
+            used[i] |= is_used;
+        }
+    }
+
+    let mut argv = Vec::new();
+    for segments in tokens {
+        if let [Segment::Args] = segments.as_slice() {
+            argv.extend(args.iter().cloned());
+            continue;
+        }
+
+        argv.push(
+            segments
+                .iter()
+                .map(|segment| match segment {
+                    Segment::Literal(text) => text.clone(),
+                    Segment::Args => args.join(" "),
+                    Segment::Positional(n) => args[n - 1].clone(),
+                })
+                .collect(),
+        );
+    }
+
+    argv.extend(unused_args(args, &used));
+    Ok(argv)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    /// `{args}`
+    Args,
+    /// `{1}`, `{2}`, ...
+    Positional(usize),
+}
+
+fn parse_placeholders(text: &str) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let mut literal = String::new();
+    let mut rest = text;
+
+    while let Some(c) = rest.chars().next() {
+        // `{{1}}` and `{{args}}` escape a placeholder; other doubled braces are kept as written
+        if let Some(after) = rest.strip_prefix("{{")
+            && let Some(end) = after.find("}}")
+            && is_placeholder_name(&after[..end])
+        {
+            literal.push('{');
+            literal.push_str(&after[..end]);
+            literal.push('}');
+            rest = &after[end + 2..];
+            continue;
+        }
+
+        if c == '{'
+            && let Some(end) = rest.find('}')
+        {
+            let name = &rest[1..end];
+            let placeholder = if !is_placeholder_name(name) {
+                None
+            } else if name == "args" {
+                Some(Segment::Args)
+            } else {
+                name.parse().ok().map(Segment::Positional)
+            };
+
+            if let Some(placeholder) = placeholder {
+                if !literal.is_empty() {
+                    segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                }
+                segments.push(placeholder);
+                rest = &rest[end + 1..];
+                continue;
+            }
+        }
+
+        literal.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+
+    if !literal.is_empty() {
+        segments.push(Segment::Literal(literal));
+    }
+
+    segments
+}
+
+/// Whether `{name}` is a placeholder: `args` or a number.
+fn is_placeholder_name(name: &str) -> bool {
+    name == "args" || (!name.is_empty() && name.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Marks which of the `count` arguments the placeholders consume.
+fn used_args(segments: &[Segment], count: usize) -> eyre::Result<Vec<bool>> {
+    let mut used = vec![false; count];
+    for segment in segments {
+        match segment {
+            Segment::Literal(_) => {}
+            Segment::Args => used.iter_mut().for_each(|is_used| *is_used = true),
+            Segment::Positional(n) => {
+                if *n == 0 || *n > count {
+                    return Err(eyre!(
+                        "Task command uses {{{}}} but {} argument(s) were given",
+                        n,
+                        count
+                    ));
+                }
+                used[n - 1] = true;
+            }
+        }
+    }
+    Ok(used)
+}
+
+fn unused_args<'a>(args: &'a [String], used: &'a [bool]) -> impl Iterator<Item = String> + 'a {
+    args.iter()
+        .zip(used)
+        .filter(|(_, is_used)| !**is_used)
+        .map(|(arg, _)| arg.clone())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::utils::process::CommandExt;
+    use std::{ffi::OsStr, os::unix::fs::PermissionsExt, str::FromStr};
+
+    #[test]
+    fn test_script_task_runs_through_its_shebang() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("de.toml"),
+            "[project]\nname = \"api\"\n\n[tasks.deploy]\nscript = \"deploy.sh\"\nargs = [\"prod\"]\n",
+        )
+        .unwrap();
+        let script = dir.path().join("deploy.sh");
+        std::fs::write(&script, "#!/bin/sh -e\necho \"$@\"\n").unwrap();
+
+        let project = Project::from_dir(dir.path()).unwrap();
+        let task = project
+            .task(&Slug::from_str("deploy").unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(task.kind(), "script");
+
+        let command = task.command(&project, &["-v".to_string()], &[]).unwrap();
+        assert_eq!(command.get_program(), "/bin/sh");
+        assert_eq!(
+            command.get_args().collect::<Vec<_>>(),
+            [
+                OsStr::new("-e"),
+                script.as_os_str(),
+                OsStr::new("prod"),
+                OsStr::new("-v")
+            ]
+        );
+
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let command = task.command(&project, &[], &[]).unwrap();
+        assert_eq!(command.get_program(), script.as_os_str());
+
+        std::fs::remove_file(&script).unwrap();
+        assert!(task.command(&project, &[], &[]).is_err());
+    }
+
+    #[test]
+    fn test_compose_task_args_follow_the_service_command() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("de.toml"),
+            "[project]\nname = \"api\"\n\n[tasks.psql]\nservice = \"db\"\ncommand = \"psql -U app\"\n",
+        )
+        .unwrap();
+        let compose = dir.path().join("docker-compose.yml");
+        std::fs::write(&compose, "services: {}\n").unwrap();
+
+        let project = Project::from_dir(dir.path()).unwrap();
+        let task = project
+            .task(&Slug::from_str("psql").unwrap())
+            .unwrap()
+            .unwrap();
+
+        let command = task
+            .command(
+                &project,
+                &["-c".to_string(), "select 1".to_string()],
+                &[("PGPASSWORD".to_string(), "secret".to_string())],
+            )
+            .unwrap();
+        assert_eq!(command.get_program(), "docker-compose");
+
+        // `-T` depends on whether the test runs in a terminal
+        let args = command
+            .get_args()
+            .filter(|arg| *arg != "-T")
+            .collect::<Vec<_>>();
+        assert_eq!(
+            args,
+            [
+                OsStr::new("-f"),
+                compose.canonicalize().unwrap().as_os_str(),
+                OsStr::new("exec"),
+                OsStr::new("-e"),
+                OsStr::new("PGPASSWORD=secret"),
+                OsStr::new("db"),
+                OsStr::new("psql"),
+                OsStr::new("-U"),
+                OsStr::new("app"),
+                OsStr::new("-c"),
+                OsStr::new("select 1"),
+            ]
+        );
+    }
+
+    fn args(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn test_expand_task_command_substitutes_placeholders() {
+        assert_eq!(
+            expand_task_command("echo {args}", &args(&["a", "b c"])).unwrap(),
+            ["echo", "a", "b c"]
+        );
+        assert_eq!(
+            expand_task_command("echo x{args}", &args(&["a", "b"])).unwrap(),
+            ["echo", "xa b"]
+        );
+        assert_eq!(
+            expand_task_command("git commit -m {1} --author={2}", &args(&["fix it", "me"]))
+                .unwrap(),
+            ["git", "commit", "-m", "fix it", "--author=me"]
+        );
+        assert_eq!(
+            expand_task_command("echo {{1}} {{args}} {} }}{{", &args(&["a"])).unwrap(),
+            ["echo", "{1}", "{args}", "{}", "}}{{", "a"]
+        );
+        assert_eq!(
+            expand_task_command("docker ps --format {{.Names}}", &[]).unwrap(),
+            ["docker", "ps", "--format", "{{.Names}}"]
+        );
+        assert_eq!(
+            expand_task_command("cp {2} dest", &args(&["-v", "src", "--force"])).unwrap(),
+            ["cp", "src", "dest", "-v", "--force"]
+        );
+    }
+
+    #[test]
+    fn test_expand_task_command_rejects_out_of_range_placeholders() {
+        assert!(expand_task_command("echo {2}", &args(&["a"])).is_err());
+        assert!(expand_task_command("echo {0}", &args(&["a"])).is_err());
+        assert!(expand_task_command("echo {1}", &[]).is_err());
+        assert!(shell_args("echo {3}", &args(&["a", "b"])).is_err());
+    }
+
+    #[test]
+    fn test_shell_args_quote_substituted_arguments() {
+        let argv = shell_args(
+            "printf '%s|' {1} {args}",
+            &args(&["it's \"quoted\"", "two words"]),
+        )
+        .unwrap();
+        let output = std::process::Command::new(&argv[0])
+            .args(&argv[1..])
+            .logged_output()
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap(),
+            "it's \"quoted\"|it's \"quoted\"|two words|"
+        );
+
+        let argv = shell_args("printf '%s|' {2}", &args(&["a b", "c", "d'e"])).unwrap();
+        let output = std::process::Command::new(&argv[0])
+            .args(&argv[1..])
+            .logged_output()
+            .unwrap();
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), "c|a b|d'e|");
+    }
+
+    #[test]
+    fn test_shell_args_keep_arguments_out_of_multi_line_scripts() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let argv = shell_args(
+            "echo \"first: $1\"\necho done\n",
+            &args(&["touch", "PWNED"]),
+        )
+        .unwrap();
+        let output = std::process::Command::new(&argv[0])
+            .args(&argv[1..])
+            .current_dir(dir.path())
+            .logged_output()
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap(),
+            "first: touch\ndone\n"
+        );
+        assert!(!dir.path().join("PWNED").exists());
+
+        // A single line ending in a newline still gets its arguments appended
+        let argv = shell_args("printf '%s|'\n", &args(&["a", "b"])).unwrap();
+        let output = std::process::Command::new(&argv[0])
+            .args(&argv[1..])
+            .logged_output()
+            .unwrap();
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), "a|b|");
+    }
+}
@@ -1,19 +1,22 @@
 use std::{
     fs::File,
     path::{Path, PathBuf},
+    process::Stdio,
 };
 
 use eyre::{WrapErr, eyre};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::utils::path::has_reverse_path_traversal;
+use crate::utils::{path::has_reverse_path_traversal, process::CommandExt};
 
 use super::{project::CommandPipe, utils::EnvMapper};
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 
 pub struct ExportCommand {
     pub command: String,
+    /// A file of the snapshot to write the command's stdout to.
     #[serde(default)]
     pub stdout: Option<CommandPipe>,
 }
@@ -44,11 +47,14 @@ impl ExportCommand {
         }
     }
 
+    /// Runs the command in `dir`, writing its stdout to its file in `output_dir`, or to `stdout`
+    /// if it has none.
     pub fn run(
         &self,
         dir: &Path,
         output_dir: &Path,
         project_dir: &Path,
+        stdout: Stdio,
     ) -> eyre::Result<ExportCommandResult> {
         // TODO: add docker service support
 
@@ -73,12 +79,15 @@ impl ExportCommand {
                     let (file_path, file) = resolve_pipe_file(file_name, output_dir)?;
                     command.stdout(file);
 
-                    let status = command.status().map_err(|e| eyre!(e)).wrap_err_with(|| {
-                        format!(
-                            "Failed to run command: {} with output file: {}",
-                            self.command, file_name
-                        )
-                    })?;
+                    let status = command
+                        .logged_status()
+                        .map_err(|e| eyre!(e))
+                        .wrap_err_with(|| {
+                            format!(
+                                "Failed to run command: {} with output file: {}",
+                                self.command, file_name
+                            )
+                        })?;
 
                     if !status.success() {
                         tracing::info!(
@@ -113,7 +122,8 @@ impl ExportCommand {
             }
         } else {
             let status = command
-                .status()
+                .stdout(stdout)
+                .logged_status()
                 .map_err(|e| eyre!(e))
                 .wrap_err_with(|| format!("Failed to run command: {}", self.command))?;
 
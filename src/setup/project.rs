@@ -1,3 +1,4 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 
@@ -9,33 +10,47 @@ use crate::{
 
 use super::{export::ExportCommand, types::GitConfig};
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct SetupConfig {
+    /// The repository to clone, as a URL or a table with the `url` and `branch`.
     pub git: StringOr<GitConfig>,
-    #[serde(default)]
+    /// The steps that prepare the project once it is cloned, by ID.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
     pub steps: BTreeMap<Slug, Step>,
-    #[serde(default)]
+    /// Overrides of the repository and steps, chosen with `--profile`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
     pub profiles: BTreeMap<Slug, Profile>,
 }
 
 impl From<String> for GitConfig {
     fn from(url: String) -> Self {
-        Self { url, branch: None }
+        Self {
+            url,
+            branch: None,
+            commit: None,
+        }
     }
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct Profile {
+    /// Replaces the URL or branch of the repository.
     #[serde(default)]
     pub git: Option<StringOr<GitOverride>>,
+    /// Steps added to the default ones, replacing those with the same ID.
     #[serde(default)]
     pub steps: BTreeMap<Slug, Step>,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct GitOverride {
+    /// The URL to clone the repository from instead.
     #[serde(default)]
     pub url: Option<String>,
+    /// The branch to check out instead.
     #[serde(default)]
     pub branch: Option<String>,
 }
@@ -49,32 +64,47 @@ impl From<String> for GitOverride {
     }
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct Step {
+    /// Shown while the step runs.
     pub name: String,
+    /// The Docker Compose service the step's commands run in, instead of the host.
     #[serde(default)]
     pub service: Option<StringOr<StepService>>,
+    /// Marks the step as optional. `de setup inspect` shows it.
     #[serde(default)]
     pub optional: bool,
+    /// A condition recorded with the step, shown by `de setup inspect`.
     #[serde(default)]
     pub skip_if: Option<String>,
     #[serde(flatten)]
     pub kind: StepKind,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 #[serde(untagged, rename_all = "snake_case")]
 pub enum StepKind {
     Standard(StandardStep),
     Complex {
+        /// Commands run by `de setup`, e.g. to restore exported data.
         apply: OneOrMany<StringOr<ApplyCommand>>,
+        /// Commands run by `de workspace snapshot`, e.g. to dump data into the snapshot.
         export: OneOrMany<StringOr<ExportCommand>>,
+        /// Variables set for the commands.
         #[serde(default)]
         env: Option<BTreeMap<String, String>>,
+        /// Globs of the project files the exports are made from, e.g. `["db/seeds/**"]`. A
+        /// snapshot created with `--base` reuses the previous exports of the step while these
+        /// files, the commands and the variables are unchanged. Steps without inputs always
+        /// export afresh.
+        #[serde(default)]
+        inputs: Vec<String>,
     },
     Basic {
+        /// Commands run by `de setup`.
         command: OneOrMany<StringOr<ApplyCommand>>,
+        /// Variables set for the commands.
         #[serde(default)]
         env: Option<BTreeMap<String, String>>,
     },
@@ -90,21 +120,38 @@ impl StepKind {
     }
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum StandardStep {
+    /// Copies the files matching `source` to `destination`.
     CopyFiles {
+        /// A regex matched against file names.
         source: String,
+        /// The name of each copy, which can refer to capture groups of `source` (`$1`).
         #[serde(default)]
         destination: String,
+        /// Replace files that already exist at the destination.
         #[serde(default)]
         overwrite: bool,
+        /// Look for matching files in subdirectories, not only the project root.
+        #[serde(default = "default_copy_files_recursive")]
+        recursive: bool,
+        /// Match `source` against the path relative to the project root instead of the file name.
+        #[serde(default)]
+        match_path: bool,
     },
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+pub(crate) fn default_copy_files_recursive() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct StepService {
+    /// The name of the service.
     pub name: String,
+    /// The Docker Compose file that defines it, relative to the project.
     pub compose: Option<String>,
 }
 
@@ -117,9 +164,10 @@ impl From<String> for StepService {
     }
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 #[serde(untagged, rename_all = "snake_case")]
 pub enum CommandPipe {
+    /// A file in the snapshot.
     File { file: String },
 }
 
@@ -1,6 +1,8 @@
 use std::{fs::File, path::Path};
 
+use dialoguer::Confirm;
 use eyre::Context;
+use indicatif::ProgressBar;
 use walkdir::WalkDir;
 
 use crate::{
@@ -13,7 +15,10 @@ use crate::{
         types::{ApplyCommand, CommandPipe},
     },
     types::Slug,
-    utils::{git::run_git_command, ui::UserInterface, zip::extract_zip},
+    utils::{
+        fs::is_skipped_dir, git::run_git_command, is_interactive, parallel::parallel_map,
+        process::CommandExt, ui::UserInterface, zip::extract_zip,
+    },
 };
 
 use super::types::ProjectSnapshotStepKind;
@@ -22,11 +27,15 @@ pub fn apply_snapshot(
     ui: &UserInterface,
     snapshot_path: &Path,
     target_dir: &Path,
+    latest: bool,
+    jobs: usize,
+    only: &[Slug],
+    no_input: bool,
 ) -> eyre::Result<()> {
     ui.heading("Apply Snapshot")?;
     let loading_bar = ui.loading_bar("Preparing...")?;
     let snapshot_dir = extract_snapshot_to_tempdir(snapshot_path)?;
-    let snapshot = read_snapshot_manifest(snapshot_dir.path())?;
+    let mut snapshot = read_snapshot_manifest(snapshot_dir.path())?;
     loading_bar.finish_and_clear();
 
     ui.info_item(&format!("workspace: {}", snapshot.workspace.name))?;
@@ -46,6 +55,14 @@ pub fn apply_snapshot(
         }
     }
 
+    if !only.is_empty() {
+        select_projects(&mut snapshot, only)?;
+        ui.info_item(&format!(
+            "only: {}",
+            only.iter().map(Slug::as_str).collect::<Vec<_>>().join(", ")
+        ))?;
+    }
+
     ui.new_line()?;
 
     let canonical_snapshot_dir = snapshot_dir
@@ -60,18 +77,92 @@ pub fn apply_snapshot(
         })?;
 
     ui.heading("Projects")?;
-    for (project_name, project_snapshot) in snapshot.projects.iter() {
-        ui.subheading(&format!("{}", project_name))?;
-        ui.indented(|ui| {
-            apply_project_snapshot(
-                ui,
-                &canonical_snapshot_dir,
-                project_name,
-                project_snapshot,
-                target_dir,
-            )?;
-            Ok(())
-        })?;
+
+    // One unit for the clone of each project plus one per step
+    let total_steps = snapshot
+        .projects
+        .values()
+        .map(|project_snapshot| 1 + project_snapshot.steps.len() as u64)
+        .sum();
+    let bar = ui.progress_bar(total_steps)?;
+    let progress_ui = ui.with_progress(bar.clone());
+
+    bar.set_message(format!("cloning {} projects", snapshot.projects.len()));
+    let clones = clone_projects(&progress_ui, &bar, &snapshot, target_dir, latest, jobs);
+
+    let mut failed_projects = Vec::new();
+    let mut skipped_steps = Vec::new();
+    let result = snapshot
+        .projects
+        .iter()
+        .zip(clones)
+        .enumerate()
+        .try_for_each(
+            |(project_index, ((project_name, project_snapshot), clone))| {
+                let progress = ProjectProgress {
+                    bar: &bar,
+                    project_index,
+                    project_count: snapshot.projects.len(),
+                };
+
+                progress_ui.subheading(&format!("{}", project_name))?;
+                clone.output.flush()?;
+
+                if let Err(err) = clone.result {
+                    progress_ui.indented(|ui| Ok(ui.error_item(&format!("{err:#}"), None)?))?;
+                    failed_projects.push(project_name.clone());
+                    return Ok(());
+                }
+
+                progress_ui.indented(|ui| {
+                    apply_project_snapshot(
+                        ui,
+                        &canonical_snapshot_dir,
+                        project_name,
+                        project_snapshot,
+                        target_dir,
+                        &progress,
+                        no_input,
+                    )
+                    .map(|skipped| {
+                        skipped_steps.extend(
+                            skipped
+                                .into_iter()
+                                .map(|step| format!("{project_name}/{step}")),
+                        )
+                    })
+                })
+            },
+        );
+
+    bar.finish_and_clear();
+    result?;
+
+    if !skipped_steps.is_empty() {
+        ui.new_line()?;
+        ui.warning_item(
+            &format!(
+                "Skipped {} step(s) that read stdin: {}",
+                skipped_steps.len(),
+                skipped_steps.join(", ")
+            ),
+            Some("Run them by hand, or apply the snapshot without --no-input"),
+        )?;
+    }
+
+    if !failed_projects.is_empty() {
+        let failed_projects = failed_projects.iter().map(Slug::as_str).collect::<Vec<_>>();
+
+        ui.new_line()?;
+        ui.error_item(
+            &format!("Failed to clone: {}", failed_projects.join(", ")),
+            None,
+        )?;
+
+        return Err(eyre::eyre!(
+            "Failed to clone {} project(s)",
+            failed_projects.len()
+        ));
     }
 
     // TODO: initialize the workspace with the projects
@@ -79,7 +170,34 @@ pub fn apply_snapshot(
     Ok(())
 }
 
-fn extract_snapshot_to_tempdir(snapshot_path: &Path) -> eyre::Result<tempfile::TempDir> {
+/// Keeps only the `only` projects in the snapshot, failing if any of them is not part of it.
+fn select_projects(snapshot: &mut Snapshot, only: &[Slug]) -> eyre::Result<()> {
+    let unknown = only
+        .iter()
+        .filter(|name| !snapshot.projects.contains_key(*name))
+        .map(Slug::as_str)
+        .collect::<Vec<_>>();
+
+    if !unknown.is_empty() {
+        let available = snapshot
+            .projects
+            .keys()
+            .map(Slug::as_str)
+            .collect::<Vec<_>>();
+
+        return Err(eyre::eyre!(
+            "Snapshot has no project(s) {}. Available projects: {}",
+            unknown.join(", "),
+            available.join(", ")
+        ));
+    }
+
+    snapshot.projects.retain(|name, _| only.contains(name));
+
+    Ok(())
+}
+
+pub(super) fn extract_snapshot_to_tempdir(snapshot_path: &Path) -> eyre::Result<tempfile::TempDir> {
     let temp_dir = tempfile::tempdir()
         .map_err(|e| eyre::eyre!(e))
         .wrap_err("Failed to create temporary dir")?;
@@ -100,7 +218,7 @@ fn extract_snapshot_to_tempdir(snapshot_path: &Path) -> eyre::Result<tempfile::T
     Ok(temp_dir)
 }
 
-fn read_snapshot_manifest(snapshot_dir: &Path) -> eyre::Result<Snapshot> {
+pub(super) fn read_snapshot_manifest(snapshot_dir: &Path) -> eyre::Result<Snapshot> {
     let manifest_path = snapshot_dir.join(SNAPSHOT_MANIFEST_FILE);
     let manifest_content = std::fs::read_to_string(&manifest_path)
         .map_err(|e| eyre::eyre!(e))
@@ -113,12 +231,67 @@ fn read_snapshot_manifest(snapshot_dir: &Path) -> eyre::Result<Snapshot> {
     Ok(snapshot)
 }
 
-fn apply_project_snapshot(
+/// Where an apply is within the whole snapshot, shown on the aggregate progress bar.
+///
+/// The clones of all projects run first and advance the bar on their own.
+struct ProjectProgress<'a> {
+    bar: &'a ProgressBar,
+    project_index: usize,
+    project_count: usize,
+}
+
+impl ProjectProgress<'_> {
+    /// Shows the project and the step that is now running.
+    fn start_step(&self, project_name: &Slug, step: usize, step_count: usize) {
+        self.bar.set_message(format!(
+            "project {}/{} ({project_name}), step {step}/{step_count}",
+            self.project_index + 1,
+            self.project_count,
+        ));
+    }
+
+    fn finish_step(&self) {
+        self.bar.inc(1);
+    }
+}
+
+/// The result of cloning one project, with the output it produced.
+struct ProjectClone {
+    output: UserInterface,
+    result: eyre::Result<()>,
+}
+
+/// Clones all projects of the snapshot, running up to `jobs` clones at once. Each clone's
+/// output is buffered so it can be shown with the rest of its project, and a failed clone
+/// doesn't stop the others.
+///
+/// Returns one result per project, in the order of `snapshot.projects`.
+fn clone_projects(
+    ui: &UserInterface,
+    bar: &ProgressBar,
+    snapshot: &Snapshot,
+    target_dir: &Path,
+    latest: bool,
+    jobs: usize,
+) -> Vec<ProjectClone> {
+    let projects = snapshot.projects.iter().collect::<Vec<_>>();
+
+    parallel_map(&projects, jobs, |(project_name, project_snapshot)| {
+        let output = ui.buffered();
+        let result = output
+            .indented(|ui| clone_project(ui, project_name, project_snapshot, target_dir, latest));
+        bar.inc(1);
+        ProjectClone { output, result }
+    })
+}
+
+/// Creates the project directory and checks out the repository as recorded in the snapshot.
+fn clone_project(
     ui: &UserInterface,
-    snapshot_dir: &Path,
     project_name: &Slug,
     project_snapshot: &ProjectSnapshot,
     target_dir: &Path,
+    latest: bool,
 ) -> eyre::Result<()> {
     let project_dir = target_dir.join(project_name.as_str());
     std::fs::create_dir_all(&project_dir)
@@ -132,10 +305,38 @@ fn apply_project_snapshot(
 
     ui.writeln(&format!("{} git", ui.theme.dim("0")))?;
     ui.indented(|ui| {
-        project_step_git(ui, project_name, project_snapshot, &project_dir, target_dir)
-    })?;
+        project_step_git(
+            ui,
+            project_name,
+            project_snapshot,
+            &project_dir,
+            target_dir,
+            latest,
+        )
+    })
+}
+
+/// Applies the bundled changes and runs the steps of a project that was already cloned. With
+/// `no_input`, steps that read stdin from a file are skipped, and their names returned.
+fn apply_project_snapshot(
+    ui: &UserInterface,
+    snapshot_dir: &Path,
+    project_name: &Slug,
+    project_snapshot: &ProjectSnapshot,
+    target_dir: &Path,
+    progress: &ProjectProgress,
+    no_input: bool,
+) -> eyre::Result<Vec<Slug>> {
+    let mut skipped = Vec::new();
+    let step_count = project_snapshot.steps.len();
+    let project_dir = target_dir.join(project_name.as_str());
+
+    if let Some(patch) = &project_snapshot.patch {
+        ui.indented(|ui| project_step_patch(ui, snapshot_dir, &project_dir, patch))?;
+    }
 
     for (step_index, (_, step_snapshot)) in project_snapshot.steps.iter().enumerate() {
+        progress.start_step(project_name, step_index + 1, step_count);
         ui.writeln(&format!(
             "{} {} {}",
             step_index + 1,
@@ -143,13 +344,21 @@ fn apply_project_snapshot(
             ui.theme.dim(&format!("({})", step_snapshot.kind.as_str())),
         ))?;
 
+        if no_input && step_snapshot.kind.reads_stdin() {
+            ui.indented(|ui| Ok(ui.warning_item("Skipped: reads stdin (--no-input)", None)?))?;
+            skipped.push(step_snapshot.name.clone());
+            progress.finish_step();
+            continue;
+        }
+
         ui.indented(|ui| {
             apply_project_step(ui, snapshot_dir, &project_dir, step_snapshot)?;
             Ok(())
         })?;
+        progress.finish_step();
     }
 
-    Ok(())
+    Ok(skipped)
 }
 
 fn project_step_git(
@@ -158,6 +367,7 @@ fn project_step_git(
     project_snapshot: &ProjectSnapshot,
     project_dir: &Path,
     target_dir: &Path,
+    latest: bool,
 ) -> eyre::Result<()> {
     ui.info_item(&format!(
         "Cloning {}",
@@ -179,6 +389,88 @@ fn project_step_git(
         run_git_command(&["checkout", branch.as_str()], project_dir)?;
     }
 
+    if latest {
+        return Ok(());
+    }
+
+    if let Some(commit) = &project_snapshot.git.commit {
+        // Keep the branch checked out but move it to the recorded commit
+        let args: &[&str] = if project_snapshot.git.branch.is_some() {
+            &["reset", "--hard", commit.as_str()]
+        } else {
+            &["checkout", commit.as_str()]
+        };
+
+        match run_git_command(args, project_dir) {
+            Ok(()) => ui.info_item(&format!("Commit {}", ui.theme.accent(short_commit(commit))))?,
+            Err(_) => ui.warning_item(
+                &format!(
+                    "Commit {} not found, using the branch tip",
+                    ui.theme.accent(short_commit(commit))
+                ),
+                None,
+            )?,
+        }
+    }
+
+    Ok(())
+}
+
+fn short_commit(commit: &str) -> &str {
+    commit.get(..7).unwrap_or(commit)
+}
+
+/// Offers to apply the uncommitted changes bundled with the snapshot after checkout.
+fn project_step_patch(
+    ui: &UserInterface,
+    snapshot_dir: &Path,
+    project_dir: &Path,
+    patch: &Path,
+) -> eyre::Result<()> {
+    let patch_path = snapshot_dir
+        .join(patch)
+        .canonicalize()
+        .map_err(|e| eyre::eyre!(e))
+        .wrap_err_with(|| format!("Failed to canonicalize patch path: {}", patch.display()))?;
+
+    // SECURITY: Ensure the patch is within the snapshot directory
+    if !patch_path.starts_with(snapshot_dir) {
+        return Err(eyre::eyre!(
+            "Patch path '{}' is outside of snapshot directory: {}",
+            patch_path.display(),
+            snapshot_dir.display()
+        ));
+    }
+
+    // Without a terminal, apply them as the prompt would by default
+    let apply = !is_interactive()
+        || ui
+            .suspend(|| {
+                Confirm::new()
+                    .with_prompt("The snapshot includes uncommitted changes. Apply them?")
+                    .default(true)
+                    .interact()
+            })
+            .map_err(|e| eyre::eyre!(e))
+            .wrap_err("Failed to read confirmation")?;
+
+    if !apply {
+        ui.info_item("Skipped uncommitted changes")?;
+        return Ok(());
+    }
+
+    let patch_path_str = patch_path
+        .to_str()
+        .ok_or_else(|| eyre::eyre!("Patch path contains invalid UTF-8"))?;
+
+    run_git_command(
+        &["apply", "--whitespace=nowarn", patch_path_str],
+        project_dir,
+    )
+    .wrap_err("Failed to apply uncommitted changes")?;
+
+    ui.success_item("Applied uncommitted changes", None)?;
+
     Ok(())
 }
 
@@ -193,8 +485,18 @@ fn apply_project_step(
             source,
             destination,
             overwrite,
+            recursive,
+            match_path,
         } => {
-            apply_project_step_copy_files(ui, project_dir, source, destination, *overwrite)?;
+            apply_project_step_copy_files(
+                ui,
+                project_dir,
+                source,
+                destination,
+                *overwrite,
+                *recursive,
+                *match_path,
+            )?;
         }
         ProjectSnapshotStepKind::Basic { command } => {
             for cmd in command {
@@ -217,7 +519,17 @@ fn apply_project_step_copy_files(
     source: &str,
     destination: &str,
     overwrite: bool,
+    recursive: bool,
+    match_path: bool,
 ) -> eyre::Result<()> {
+    // An empty destination would turn a full match into an empty file name, i.e. the parent directory
+    if destination.is_empty() {
+        return Err(eyre::eyre!(
+            "Copy files step for '{}' has no destination",
+            source
+        ));
+    }
+
     ui.info_item(&format!(
         "Processing {} -> {}",
         ui.theme.accent(source),
@@ -231,7 +543,12 @@ fn apply_project_step_copy_files(
     let mut matched_files = 0;
 
     ui.indented(|ui| {
-        for entry in WalkDir::new(project_dir).max_depth(255) {
+        let walk = WalkDir::new(project_dir)
+            .max_depth(if recursive { 255 } else { 1 })
+            .into_iter()
+            .filter_entry(|entry| !is_skipped_dir(entry));
+
+        for entry in walk {
             let entry = entry.map_err(|e| eyre::eyre!(e)).wrap_err_with(|| {
                 format!(
                     "Failed to read files in project directory: {}",
@@ -239,26 +556,82 @@ fn apply_project_step_copy_files(
                 )
             })?;
 
-            let name = if let Some(name) = entry.file_name().to_str() {
-                name
+            if !entry.file_type().is_file() {
+                continue;
+            }
+
+            // Matched against the file name, or with `match_path` against the path relative to the
+            // project root using `/` separators, so patterns can be anchored
+            let subject = if match_path {
+                entry
+                    .path()
+                    .strip_prefix(project_dir)
+                    .ok()
+                    .and_then(|path| path.to_str())
+                    .map(|path| path.replace(std::path::MAIN_SEPARATOR, "/"))
             } else {
+                entry.file_name().to_str().map(str::to_string)
+            };
+
+            let Some(subject) = subject else {
                 tracing::warn!("Skipping non-UTF8 file name: {:?}", entry.file_name());
                 continue;
             };
 
-            if !source_re.is_match(name) {
+            if !source_re.is_match(&subject) {
                 continue;
             };
 
-            let dest_name = source_re.replace(name, destination).to_string();
-            let parent = if let Some(parent) = entry.path().parent() {
-                parent
-            } else {
-                tracing::warn!("Skipping file with no parent: {:?}", entry.path());
+            let dest_name = source_re.replace(&subject, destination).to_string();
+            if dest_name.is_empty() {
+                ui.warning_item(
+                    &format!(
+                        "Skipping {}: destination name is empty",
+                        ui.theme.dim(&entry.path().display().to_string())
+                    ),
+                    None,
+                )?;
                 continue;
+            }
+
+            let dest_path = if match_path {
+                // SECURITY: Ensure the destination stays within the project directory
+                if Path::new(&dest_name)
+                    .components()
+                    .any(|component| !matches!(component, std::path::Component::Normal(_)))
+                {
+                    ui.warning_item(
+                        &format!(
+                            "Skipping {}: destination {} is outside of the project",
+                            ui.theme.dim(&entry.path().display().to_string()),
+                            ui.theme.accent(&dest_name)
+                        ),
+                        None,
+                    )?;
+                    continue;
+                }
+
+                project_dir.join(&dest_name)
+            } else {
+                let Some(parent) = entry.path().parent() else {
+                    tracing::warn!("Skipping file with no parent: {:?}", entry.path());
+                    continue;
+                };
+
+                parent.join(&dest_name)
             };
 
-            let dest_path = parent.join(dest_name);
+            if dest_path == entry.path() {
+                ui.warning_item(
+                    &format!(
+                        "Skipping {}: destination is the same file",
+                        ui.theme.dim(&entry.path().display().to_string())
+                    ),
+                    None,
+                )?;
+                continue;
+            }
+
             if dest_path.exists() && !overwrite {
                 ui.warning_item(
                     &format!(
@@ -361,8 +734,8 @@ fn run_apply_command(
         }
     }
 
-    let status = command
-        .status()
+    let status = ui
+        .suspend(|| command.logged_status())
         .map_err(|e| eyre::eyre!(e))
         .wrap_err_with(|| format!("Failed to run command: {}", apply_command.command))?;
 
@@ -388,3 +761,135 @@ fn run_apply_command(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_copy_files_rejects_empty_destination() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join(".env.example"), "KEY=value").unwrap();
+
+        let result = apply_project_step_copy_files(
+            &UserInterface::new(),
+            dir.path(),
+            "^\\.env\\.example$",
+            "",
+            false,
+            true,
+            false,
+        );
+
+        assert!(result.is_err());
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_copy_files_skips_destination_equal_to_source() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join(".env"), "KEY=value").unwrap();
+
+        apply_project_step_copy_files(
+            &UserInterface::new(),
+            dir.path(),
+            "^\\.env$",
+            ".env",
+            true,
+            true,
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join(".env")).unwrap(),
+            "KEY=value"
+        );
+    }
+
+    #[test]
+    fn test_copy_files_skips_ignored_dirs_and_matches_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        for sub in [".git", "node_modules", "app"] {
+            std::fs::create_dir(dir.path().join(sub)).unwrap();
+            std::fs::write(dir.path().join(sub).join("config.example"), "").unwrap();
+        }
+
+        apply_project_step_copy_files(
+            &UserInterface::new(),
+            dir.path(),
+            "^(.*)/config\\.example$",
+            "$1/config",
+            false,
+            true,
+            true,
+        )
+        .unwrap();
+
+        assert!(dir.path().join("app/config").exists());
+        assert!(!dir.path().join(".git/config").exists());
+        assert!(!dir.path().join("node_modules/config").exists());
+    }
+
+    #[test]
+    fn test_no_input_skips_steps_that_read_stdin() {
+        let dir = tempfile::tempdir().unwrap();
+        let snapshot_dir = dir.path().join("snapshot");
+        let target_dir = dir.path().join("target");
+        std::fs::create_dir_all(snapshot_dir.join("files/api")).unwrap();
+        std::fs::create_dir_all(target_dir.join("api")).unwrap();
+        std::fs::write(snapshot_dir.join("files/api/data.sql"), "select 1;").unwrap();
+
+        let project_snapshot: ProjectSnapshot = serde_json::from_value(serde_json::json!({
+            "git": { "url": "https://example.com/api.git" },
+            "steps": {
+                "seed": {
+                    "name": "seed",
+                    "service": null,
+                    "optional": false,
+                    "skip_if": null,
+                    "kind": { "basic": { "command": [{ "command": "touch seeded" }] } }
+                },
+                "load": {
+                    "name": "load",
+                    "service": null,
+                    "optional": false,
+                    "skip_if": null,
+                    "kind": { "complex": { "apply": [{
+                        "command": "touch loaded",
+                        "stdin": { "file": "files/api/data.sql" }
+                    }] } }
+                }
+            },
+            "files": []
+        }))
+        .unwrap();
+
+        let bar = ProgressBar::hidden();
+        let progress = ProjectProgress {
+            bar: &bar,
+            project_index: 0,
+            project_count: 1,
+        };
+        let apply = |no_input| {
+            apply_project_snapshot(
+                &UserInterface::new().buffered(),
+                &snapshot_dir.canonicalize().unwrap(),
+                &Slug::from_str("api").unwrap(),
+                &project_snapshot,
+                &target_dir,
+                &progress,
+                no_input,
+            )
+            .unwrap()
+        };
+
+        assert_eq!(apply(true), [Slug::from_str("load").unwrap()]);
+        assert!(target_dir.join("api/seeded").exists());
+        assert!(!target_dir.join("api/loaded").exists());
+
+        assert!(apply(false).is_empty());
+        assert!(target_dir.join("api/loaded").exists());
+    }
+}
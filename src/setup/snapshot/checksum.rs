@@ -1,17 +1,13 @@
-use std::{
-    fmt::Display,
-    fs::File,
-    io::{BufReader, Read},
-    path::Path,
-    str::FromStr,
-};
+use std::{fmt::Display, path::Path, str::FromStr};
 
 use eyre::{Context, eyre};
 use serde::{Deserialize, Serialize};
-use sha2::Digest;
 use walkdir::WalkDir;
 
-use crate::setup::snapshot::{SNAPSHOT_MANIFEST_FILE, Snapshot};
+use crate::{
+    setup::snapshot::{SNAPSHOT_MANIFEST_FILE, Snapshot},
+    utils::fs::hash_file,
+};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SnapshotChecksum {
@@ -172,30 +168,3 @@ pub fn verify_snapshot_checksum(
         Ok(SnapshotVerification::Invalid)
     }
 }
-
-fn hash_file<D>(hasher: &mut D, path: &Path) -> eyre::Result<()>
-where
-    D: Digest,
-{
-    let file = File::open(path)
-        .map_err(|e| eyre::eyre!(e))
-        .wrap_err_with(|| format!("Failed to open file for hashing: {}", path.display()))?;
-
-    let mut reader = BufReader::new(file);
-    let mut buffer = [0; 8192];
-
-    loop {
-        let n = reader
-            .read(&mut buffer)
-            .map_err(|e| eyre::eyre!(e))
-            .wrap_err_with(|| format!("Failed to read file for hashing: {}", path.display()))?;
-
-        if n == 0 {
-            break;
-        }
-
-        hasher.update(&buffer[..n]);
-    }
-
-    Ok(())
-}
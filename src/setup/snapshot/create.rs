@@ -1,5 +1,9 @@
 use eyre::{WrapErr, eyre};
-use std::{collections::BTreeMap, path::Path};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+};
 use tempfile::TempDir;
 
 use chrono::Utc;
@@ -7,11 +11,13 @@ use chrono::Utc;
 use crate::{
     project::Project,
     setup::{
-        export::ExportCommandResult,
-        project::{StandardStep, StepKind},
+        export::{ExportCommand, ExportCommandResult},
+        project::{CommandPipe, StandardStep, StepKind},
         snapshot::{
+            UNCOMMITTED_PATCH_FILE,
+            apply::{extract_snapshot_to_tempdir, read_snapshot_manifest},
             calculate_snapshot_checksum,
-            checksum::ChecksumAlgorithm,
+            checksum::{ChecksumAlgorithm, SnapshotVerification, verify_snapshot_checksum},
             types::{
                 ProjectSnapshot, ProjectSnapshotStep, ProjectSnapshotStepKind, Snapshot,
                 WorkspaceSnapshot,
@@ -20,19 +26,105 @@ use crate::{
         utils::EnvMapper,
     },
     types::Slug,
-    utils::ui::UserInterface,
+    utils::{
+        git::{get_current_branch, get_head_commit, get_uncommitted_diff, is_project_dirty},
+        parallel::parallel_map,
+        path::has_reverse_path_traversal,
+        task_cache::hash_input_files,
+        ui::UserInterface,
+    },
     workspace::Workspace,
 };
 
+/// A previous snapshot, whose exported files are reused by the steps whose inputs are unchanged.
+pub struct BaseSnapshot {
+    dir: TempDir,
+    snapshot: Snapshot,
+}
+
+impl BaseSnapshot {
+    /// Extracts the snapshot at `path` and checks its checksum, so the reused files are intact.
+    pub fn load(path: &Path) -> eyre::Result<Self> {
+        let dir = extract_snapshot_to_tempdir(path)?;
+        let snapshot = read_snapshot_manifest(dir.path())?;
+
+        match verify_snapshot_checksum(&snapshot, dir.path())? {
+            SnapshotVerification::Valid => Ok(Self { dir, snapshot }),
+            SnapshotVerification::Invalid => Err(eyre!(
+                "Base snapshot checksum is invalid: {}",
+                path.display()
+            )),
+            SnapshotVerification::NoChecksum => {
+                Err(eyre!("Base snapshot has no checksum: {}", path.display()))
+            }
+        }
+    }
+
+    /// The files the step exported in the base snapshot, if its inputs hashed to `hash` there
+    /// and all of them are still in the archive.
+    fn reusable_files(
+        &self,
+        project_name: &Slug,
+        step_name: &Slug,
+        hash: &str,
+    ) -> Option<&[PathBuf]> {
+        let step = self
+            .snapshot
+            .projects
+            .get(project_name)?
+            .steps
+            .get(step_name)?;
+
+        let reusable = step.inputs_hash.as_deref() == Some(hash)
+            && !step.files.is_empty()
+            && step.files.iter().all(|file| {
+                !has_reverse_path_traversal(file) && self.dir.path().join(file).is_file()
+            });
+
+        reusable.then_some(step.files.as_slice())
+    }
+
+    /// Copies `files` from the base snapshot to the same place in `snapshot_dir`.
+    fn copy_files(&self, files: &[PathBuf], snapshot_dir: &Path) -> eyre::Result<()> {
+        for file in files {
+            let destination = snapshot_dir.join(file);
+            if let Some(parent) = destination.parent() {
+                std::fs::create_dir_all(parent)
+                    .map_err(|e| eyre!(e))
+                    .wrap_err_with(|| format!("Failed to create directory {}", parent.display()))?;
+            }
+
+            std::fs::copy(self.dir.path().join(file), &destination)
+                .map_err(|e| eyre!(e))
+                .wrap_err_with(|| {
+                    format!("Failed to copy {} from the base snapshot", file.display())
+                })?;
+        }
+
+        Ok(())
+    }
+}
+
 pub fn create_snapshot(
     ui: &UserInterface,
     workspace: Workspace,
     profile: Slug,
+    include_uncommitted: bool,
+    base: Option<&Path>,
+    jobs: usize,
 ) -> eyre::Result<(TempDir, Snapshot)> {
     tracing::info!("Creating snapshot for workspace with profile '{}'", profile);
 
     ui.heading("Snapshot Creation")?;
     ui.info_item(&format!("workspace: {}", workspace.config().name))?;
+
+    let base = base
+        .map(|path| {
+            ui.info_item(&format!("base: {}", path.display()))?;
+            BaseSnapshot::load(path)
+                .wrap_err_with(|| format!("Failed to load base snapshot: {}", path.display()))
+        })
+        .transpose()?;
     ui.new_line()?;
 
     let workspace_snapshot = WorkspaceSnapshot {
@@ -70,6 +162,9 @@ pub fn create_snapshot(
             &profile,
             &files_dir,
             &canonical_snapshot_dir,
+            include_uncommitted,
+            base.as_ref(),
+            jobs,
         )?;
         if let Some(project_snapshot) = project_snapshot {
             project_snapshots.insert(name.clone(), project_snapshot);
@@ -95,6 +190,7 @@ pub fn create_snapshot(
     Ok((snapshot_dir, snapshot))
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn create_project_snapshot(
     ui: &UserInterface,
     project_name: &Slug,
@@ -102,6 +198,9 @@ pub fn create_project_snapshot(
     profile: &Slug,
     files_dir: &Path,
     prefix_dir: &Path,
+    include_uncommitted: bool,
+    base: Option<&BaseSnapshot>,
+    jobs: usize,
 ) -> eyre::Result<Option<ProjectSnapshot>> {
     let step_count = project
         .manifest()
@@ -156,8 +255,49 @@ pub fn create_project_snapshot(
         git: project_setup.git(profile),
         steps: Default::default(),
         files: vec![],
+        patch: None,
     };
 
+    // Only pin the commit when the checkout is on the branch the snapshot points at,
+    // otherwise the recorded commit may not be reachable from that branch.
+    if project_snapshot.git.commit.is_none() {
+        let on_snapshot_branch = match &project_snapshot.git.branch {
+            Some(branch) => get_current_branch(project.dir()).is_ok_and(|b| &b == branch),
+            None => true,
+        };
+
+        if on_snapshot_branch {
+            project_snapshot.git.commit = get_head_commit(project.dir()).ok();
+        } else {
+            ui.indented(|ui| {
+                ui.warning_item(
+                    "Checkout is not on the snapshot branch, the commit will not be pinned",
+                    None,
+                )?;
+                Ok(())
+            })?;
+        }
+    }
+
+    if is_project_dirty(project.dir()).unwrap_or(false) {
+        if include_uncommitted {
+            project_snapshot.patch = Some(snapshot_uncommitted_changes(
+                ui,
+                project,
+                &project_files_dir,
+                prefix_dir,
+            )?);
+        } else {
+            ui.indented(|ui| {
+                ui.warning_item(
+                    "Uncommitted changes will not be included",
+                    Some("Use --include-uncommitted to bundle them as a patch"),
+                )?;
+                Ok(())
+            })?;
+        }
+    }
+
     ui.indented(|ui| {
         for (i, (name, setup_step)) in project_setup.steps(profile).iter().enumerate() {
             tracing::info!(
@@ -168,6 +308,9 @@ pub fn create_project_snapshot(
 
             ui.writeln(&format!("{} {} {}", ui.theme.dim((i + 1).to_string().as_str()), setup_step.name, ui.theme.dim(&format!("({})", setup_step.kind.as_str()))))?;
 
+            let mut step_env = BTreeMap::new();
+            let mut step_files = Vec::new();
+            let mut inputs_hash = None;
             let step = ProjectSnapshotStep {
                 name: name.clone(),
                 service: setup_step.service.as_ref().map(|v| v.clone_value()),
@@ -179,7 +322,13 @@ pub fn create_project_snapshot(
                             source,
                             destination,
                             overwrite,
+                            recursive,
+                            match_path,
                         } => {
+                            if destination.is_empty() {
+                                return Err(eyre::eyre!("Copy files step '{}' has no destination", name));
+                            }
+
                             ui.indented(|ui| {
                                 ui.info_item("No preprocessing required")?;
                                 Ok(())
@@ -189,64 +338,122 @@ pub fn create_project_snapshot(
                                 source: source.clone(),
                                 destination: destination.clone(),
                                 overwrite: *overwrite,
+                                recursive: *recursive,
+                                match_path: *match_path,
                             }
                         },
                     },
-                    StepKind::Complex { apply, export, env } => {
+                    StepKind::Complex { apply, export, env, inputs } => {
                         let env_mapper = env.as_ref().map(EnvMapper::new)
                             .unwrap_or_default()
                             .with_env("DE_PROJECT_FILES", relative_project_files_dir_str);
+                        step_env = env_mapper.values.clone();
 
-                        ui.indented(|ui| {
-                            for export_command in export.as_slice() {
-                                tracing::info!(
-                                    "Running export command '{}' for step '{}' in project '{}'",
-                                    export_command.as_value().command,
-                                    name,
-                                    project_name
-                                );
-
-                                let resolved_command = export_command
-                                    .as_value()
-                                    .resolve_env(&env_mapper);
-
-                                ui.info_item(&format!(
-                                    "Running export command: {}",
-                                    ui.theme.accent(&resolved_command.command)
-                                ))?;
+                        let resolved_exports = export
+                            .as_slice()
+                            .iter()
+                            .map(|cmd| cmd.as_value().resolve_env(&env_mapper))
+                            .collect::<Vec<_>>();
+                        if !inputs.is_empty() {
+                            inputs_hash = Some(export_inputs_hash(&resolved_exports, &step_env, project.dir(), inputs)?);
+                        }
 
-                                let result = resolved_command
-                                    .run(project.dir(), &project_files_dir, prefix_dir)
-                                    .map_err(|e| eyre!(e))
-                                    .wrap_err_with(|| {
-                                        format!(
-                                            "Failed to run export command: {}",
-                                            export_command.as_value().command
-                                        )
-                                    })?;
+                        // Exports without a stdout file may have effects no snapshot records
+                        let reused = base
+                            .zip(inputs_hash.as_deref())
+                            .filter(|_| resolved_exports.iter().all(|cmd| cmd.stdout.is_some()))
+                            .and_then(|(base, hash)| {
+                                Some((base, base.reusable_files(project_name, name, hash)?))
+                            });
 
+                        ui.indented(|ui| {
+                            if let Some((base, files)) = reused {
+                                base.copy_files(files, prefix_dir)?;
+                                ui.success_item(
+                                    "Inputs unchanged, reusing the exports of the base snapshot",
+                                    None,
+                                )?;
                                 ui.indented(|ui| {
-                                    match result {
-                                        ExportCommandResult::File { file_path } => {
-                                            tracing::info!(
-                                                "Export command produced file '{}' for step '{}' in project '{}'",
-                                                file_path.display(),
-                                                name,
-                                                project_name
-                                            );
-
-                                            ui.success_item(&format!(
-                                                "Exported file: {}",
-                                                ui.theme.accent(&file_path.display().to_string())
-                                            ), None)?;
-
-                                            project_snapshot.files.push(file_path);
-                                        }
-                                        ExportCommandResult::NoOutput => {}
+                                    for file in files {
+                                        ui.success_item(&format!(
+                                            "Reused file: {}",
+                                            ui.theme.accent(&file.display().to_string())
+                                        ), None)?;
                                     }
-
                                     Ok(())
                                 })?;
+                                step_files.extend(files.iter().cloned());
+                            } else {
+                                // With `jobs`, all exports run up front and are reported below
+                                let mut concurrent_results = (jobs > 1 && resolved_exports.len() > 1)
+                                    .then(|| {
+                                        run_exports_concurrently(
+                                            ui,
+                                            &resolved_exports,
+                                            project.dir(),
+                                            &project_files_dir,
+                                            prefix_dir,
+                                            jobs,
+                                        )
+                                        .into_iter()
+                                    });
+
+                                for (export_command, resolved_command) in
+                                    export.as_slice().iter().zip(&resolved_exports)
+                                {
+                                    tracing::info!(
+                                        "Running export command '{}' for step '{}' in project '{}'",
+                                        export_command.as_value().command,
+                                        name,
+                                        project_name
+                                    );
+
+                                    ui.info_item(&format!(
+                                        "Running export command: {}",
+                                        ui.theme.accent(&resolved_command.command)
+                                    ))?;
+
+                                    let result = match concurrent_results
+                                        .as_mut()
+                                        .and_then(Iterator::next)
+                                    {
+                                        Some(result) => result,
+                                        None => resolved_command
+                                            .run(project.dir(), &project_files_dir, prefix_dir, ui.child_stdout()),
+                                    };
+
+                                    let result = result
+                                        .map_err(|e| eyre!(e))
+                                        .wrap_err_with(|| {
+                                            format!(
+                                                "Failed to run export command: {}",
+                                                export_command.as_value().command
+                                            )
+                                        })?;
+
+                                    ui.indented(|ui| {
+                                        match result {
+                                            ExportCommandResult::File { file_path } => {
+                                                tracing::info!(
+                                                    "Export command produced file '{}' for step '{}' in project '{}'",
+                                                    file_path.display(),
+                                                    name,
+                                                    project_name
+                                                );
+
+                                                ui.success_item(&format!(
+                                                    "Exported file: {}",
+                                                    ui.theme.accent(&file_path.display().to_string())
+                                                ), None)?;
+
+                                                step_files.push(file_path);
+                                            }
+                                            ExportCommandResult::NoOutput => {}
+                                        }
+
+                                        Ok(())
+                                    })?;
+                                }
                             }
 
 
@@ -271,6 +478,7 @@ pub fn create_project_snapshot(
                     StepKind::Basic { command, env } => {
                         let env_mapper = env.as_ref().map(EnvMapper::new).unwrap_or_default()
                             .with_env("DE_PROJECT_FILES", relative_project_files_dir_str);
+                        step_env = env_mapper.values.clone();
 
                         let command_vec = command
                             .as_slice()
@@ -293,8 +501,12 @@ pub fn create_project_snapshot(
                         }
                     }
                 },
+                env: step_env,
+                inputs_hash,
+                files: step_files,
             };
 
+            project_snapshot.files.extend(step.files.iter().cloned());
             project_snapshot.steps.insert(name.clone(), step);
         }
 
@@ -303,3 +515,202 @@ pub fn create_project_snapshot(
 
     Ok(Some(project_snapshot))
 }
+
+/// Runs up to `jobs` of the export commands at once, returning their results in the order of
+/// `exports`. Commands writing to the same file run one after another, in order, so they don't
+/// race for it.
+fn run_exports_concurrently(
+    ui: &UserInterface,
+    exports: &[ExportCommand],
+    dir: &Path,
+    output_dir: &Path,
+    prefix_dir: &Path,
+    jobs: usize,
+) -> Vec<eyre::Result<ExportCommandResult>> {
+    // Commands without a file of their own are independent of all others
+    let mut chains: Vec<Vec<usize>> = Vec::new();
+    let mut chain_of_file = HashMap::<&String, usize>::new();
+    for (index, export) in exports.iter().enumerate() {
+        match &export.stdout {
+            Some(CommandPipe::File { file }) => match chain_of_file.get(file) {
+                Some(&chain) => chains[chain].push(index),
+                None => {
+                    chain_of_file.insert(file, chains.len());
+                    chains.push(vec![index]);
+                }
+            },
+            None => chains.push(vec![index]),
+        }
+    }
+
+    // Each chain runs in order on one thread, so its results are already in `exports` order
+    let mut results = parallel_map(&chains, jobs, |chain| {
+        chain
+            .iter()
+            .map(|&index| {
+                let result = exports[index].run(dir, output_dir, prefix_dir, ui.child_stdout());
+                (index, result)
+            })
+            .collect::<Vec<_>>()
+    })
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>();
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Hashes what a step's exports depend on: its export commands, the environment substituted
+/// into them and the project files below `project_dir` matching its `inputs`.
+fn export_inputs_hash(
+    exports: &[ExportCommand],
+    env: &BTreeMap<String, String>,
+    project_dir: &Path,
+    inputs: &[String],
+) -> eyre::Result<String> {
+    let mut hasher = Sha256::new();
+    for export in exports {
+        hasher.update(&export.command);
+        hasher.update([0]);
+        if let Some(CommandPipe::File { file }) = &export.stdout {
+            hasher.update(file);
+        }
+        hasher.update([0]);
+    }
+
+    for (key, value) in env {
+        hasher.update(key);
+        hasher.update([0]);
+        hasher.update(value);
+        hasher.update([0]);
+    }
+
+    hash_input_files(&mut hasher, project_dir, inputs)?;
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Writes the project's uncommitted changes to a patch file, returning its path relative to `prefix_dir`.
+fn snapshot_uncommitted_changes(
+    ui: &UserInterface,
+    project: &Project,
+    project_files_dir: &Path,
+    prefix_dir: &Path,
+) -> eyre::Result<PathBuf> {
+    let patch =
+        get_uncommitted_diff(project.dir()).wrap_err("Failed to capture uncommitted changes")?;
+
+    std::fs::create_dir_all(project_files_dir)
+        .map_err(|e| eyre!(e))
+        .wrap_err_with(|| {
+            format!(
+                "Failed to create project files directory: {}",
+                project_files_dir.display()
+            )
+        })?;
+
+    let patch_path = project_files_dir.join(UNCOMMITTED_PATCH_FILE);
+    std::fs::write(&patch_path, &patch)
+        .map_err(|e| eyre!(e))
+        .wrap_err_with(|| format!("Failed to write patch file: {}", patch_path.display()))?;
+
+    let relative_patch_path = patch_path
+        .strip_prefix(prefix_dir)
+        .map_err(|e| eyre!(e))
+        .wrap_err("Failed to get relative path for patch file")?
+        .to_path_buf();
+
+    ui.indented(|ui| {
+        ui.warning_item(
+            &format!(
+                "Included uncommitted changes: {} {}",
+                ui.theme.accent(&relative_patch_path.display().to_string()),
+                ui.theme.dim(&format!("({}b)", patch.len()))
+            ),
+            Some("Untracked files are not included"),
+        )?;
+        Ok(())
+    })?;
+
+    Ok(relative_patch_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_inputs_hash_covers_commands_env_and_inputs() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("seed.sql"), "insert 1").unwrap();
+        let inputs = vec!["*.sql".to_string()];
+        let exports = vec![ExportCommand {
+            command: "pg_dump app".to_string(),
+            stdout: Some(CommandPipe::File {
+                file: "app.sql".to_string(),
+            }),
+        }];
+        let env = BTreeMap::from([("DE_PROJECT_FILES".to_string(), "files/api".to_string())]);
+        let hash_of = |exports: &[ExportCommand], env: &BTreeMap<String, String>| {
+            export_inputs_hash(exports, env, dir.path(), &inputs).unwrap()
+        };
+        let hash = hash_of(&exports, &env);
+
+        assert_eq!(hash_of(&exports, &env), hash);
+        assert_ne!(hash_of(&exports, &BTreeMap::new()), hash);
+
+        let mut other = exports.clone();
+        other[0].stdout = Some(CommandPipe::File {
+            file: "other.sql".to_string(),
+        });
+        assert_ne!(hash_of(&other, &env), hash);
+
+        std::fs::write(dir.path().join("seed.sql"), "insert 2").unwrap();
+        assert_ne!(hash_of(&exports, &env), hash);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_concurrent_exports_keep_order_and_serialize_shared_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let export = |command: &str, file: &str| ExportCommand {
+            command: command.to_string(),
+            stdout: Some(CommandPipe::File {
+                file: file.to_string(),
+            }),
+        };
+        let exports = vec![
+            export("echo first", "app.sql"),
+            export("echo other", "other.sql"),
+            export("echo last", "app.sql"),
+        ];
+
+        let files = run_exports_concurrently(
+            &UserInterface::new().buffered(),
+            &exports,
+            dir.path(),
+            &dir.path().join("files"),
+            dir.path(),
+            3,
+        )
+        .into_iter()
+        .map(|result| match result.unwrap() {
+            ExportCommandResult::File { file_path } => file_path,
+            ExportCommandResult::NoOutput => panic!("expected an exported file"),
+        })
+        .collect::<Vec<_>>();
+
+        assert_eq!(
+            files,
+            vec![
+                PathBuf::from("files/app.sql"),
+                PathBuf::from("files/other.sql"),
+                PathBuf::from("files/app.sql"),
+            ]
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("files/app.sql")).unwrap(),
+            "last\n"
+        );
+    }
+}
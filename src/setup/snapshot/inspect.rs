@@ -0,0 +1,166 @@
+use std::path::Path;
+
+use eyre::Context;
+
+use crate::{
+    cli::SnapshotInspectFormat,
+    setup::snapshot::{
+        Snapshot,
+        apply::{extract_snapshot_to_tempdir, read_snapshot_manifest},
+        checksum::{SnapshotVerification, verify_snapshot_checksum},
+        types::{ProjectSnapshotStep, ProjectSnapshotStepKind},
+    },
+    utils::ui::UserInterface,
+};
+
+/// Prints the contents of a snapshot bundle without applying anything.
+pub fn inspect_snapshot(
+    ui: &UserInterface,
+    snapshot_path: &Path,
+    format: SnapshotInspectFormat,
+) -> eyre::Result<()> {
+    let snapshot_dir = extract_snapshot_to_tempdir(snapshot_path)?;
+    let snapshot = read_snapshot_manifest(snapshot_dir.path())?;
+
+    match format {
+        SnapshotInspectFormat::Text => {
+            let verification = verify_snapshot_checksum(&snapshot, snapshot_dir.path())?;
+            print_snapshot(ui, &snapshot, verification)
+        }
+        SnapshotInspectFormat::Json => {
+            let json = serde_json::to_string_pretty(&snapshot)
+                .map_err(|e| eyre::eyre!(e))
+                .wrap_err("Failed to serialize snapshot manifest")?;
+            println!("{json}");
+            Ok(())
+        }
+        SnapshotInspectFormat::Dotenv => {
+            for (project_name, project_snapshot) in &snapshot.projects {
+                for (step_name, step) in &project_snapshot.steps {
+                    if step.env.is_empty() {
+                        continue;
+                    }
+
+                    println!("# {project_name}/{step_name}");
+                    for (key, value) in &step.env {
+                        println!(
+                            "{}=\"{}\"",
+                            key,
+                            value.replace('\\', "\\\\").replace('"', "\\\"")
+                        );
+                    }
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+fn print_snapshot(
+    ui: &UserInterface,
+    snapshot: &Snapshot,
+    verification: SnapshotVerification,
+) -> eyre::Result<()> {
+    ui.heading("Snapshot")?;
+    ui.info_item(&format!("workspace: {}", snapshot.workspace.name))?;
+    ui.info_item(&format!("created at: {}", snapshot.created_at))?;
+
+    match verification {
+        SnapshotVerification::Valid => ui.success_item("Snapshot checksum is valid", None)?,
+        SnapshotVerification::Invalid => ui.error_item("Snapshot checksum is invalid", None)?,
+        SnapshotVerification::NoChecksum => ui.warning_item("Snapshot has no checksum", None)?,
+    }
+
+    ui.new_line()?;
+    ui.heading("Projects")?;
+
+    for (project_name, project_snapshot) in &snapshot.projects {
+        ui.subheading(project_name.as_str())?;
+        ui.indented(|ui| {
+            let git = &project_snapshot.git;
+            match &git.branch {
+                Some(branch) => {
+                    ui.info_item(&format!("git: {} ({})", ui.theme.accent(&git.url), branch))?
+                }
+                None => ui.info_item(&format!("git: {}", ui.theme.accent(&git.url)))?,
+            }
+
+            if let Some(commit) = &git.commit {
+                ui.info_item(&format!("commit: {}", commit))?;
+            }
+
+            if !project_snapshot.files.is_empty() {
+                ui.info_item(&format!("files: {}", project_snapshot.files.len()))?;
+            }
+
+            for (step_index, step) in project_snapshot.steps.values().enumerate() {
+                ui.writeln(&format!(
+                    "{} {} {}",
+                    ui.theme.dim(&(step_index + 1).to_string()),
+                    step.name,
+                    ui.theme.dim(&format!("({})", step.kind.as_str())),
+                ))?;
+                ui.indented(|ui| print_step(ui, step))?;
+            }
+
+            Ok(())
+        })?;
+    }
+
+    Ok(())
+}
+
+fn print_step(ui: &UserInterface, step: &ProjectSnapshotStep) -> eyre::Result<()> {
+    if step.optional {
+        ui.info_item("optional")?;
+    }
+
+    if let Some(skip_if) = &step.skip_if {
+        ui.info_item(&format!("skip if: {}", ui.theme.accent(skip_if)))?;
+    }
+
+    match &step.kind {
+        ProjectSnapshotStepKind::CopyFiles {
+            source,
+            destination,
+            overwrite,
+            recursive,
+            match_path,
+        } => {
+            let flags = [
+                (*overwrite, "overwrite"),
+                (!*recursive, "top level only"),
+                (*match_path, "match path"),
+            ]
+            .into_iter()
+            .filter_map(|(set, flag)| set.then_some(flag))
+            .collect::<Vec<_>>();
+
+            ui.info_item(&format!(
+                "copy {} -> {}{}",
+                ui.theme.accent(source),
+                ui.theme.accent(destination),
+                if flags.is_empty() {
+                    String::new()
+                } else {
+                    format!(" ({})", flags.join(", "))
+                }
+            ))?;
+        }
+        ProjectSnapshotStepKind::Basic { command: commands }
+        | ProjectSnapshotStepKind::Complex { apply: commands } => {
+            for command in commands {
+                ui.info_item(&format!(
+                    "command: {}",
+                    ui.theme.accent(&command.to_string())
+                ))?;
+            }
+        }
+    }
+
+    for (key, value) in &step.env {
+        ui.info_item(&format!("env: {}={}", key, ui.theme.dim(value)))?;
+    }
+
+    Ok(())
+}
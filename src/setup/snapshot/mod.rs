@@ -1,11 +1,16 @@
 mod apply;
 mod checksum;
 mod create;
+mod inspect;
 mod types;
 
 pub use apply::apply_snapshot;
 pub use checksum::calculate_snapshot_checksum;
 pub use create::create_snapshot;
+pub use inspect::inspect_snapshot;
 pub use types::Snapshot;
 
 pub const SNAPSHOT_MANIFEST_FILE: &str = "manifest.json";
+
+/// File name of a project's uncommitted-changes patch inside its snapshot files directory.
+pub const UNCOMMITTED_PATCH_FILE: &str = "uncommitted.patch";
@@ -30,6 +30,9 @@ pub struct ProjectSnapshot {
     pub git: GitConfig,
     pub steps: BTreeMap<Slug, ProjectSnapshotStep>,
     pub files: Vec<PathBuf>,
+    /// Patch of uncommitted changes, relative to the snapshot root, if they were included.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub patch: Option<PathBuf>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -39,6 +42,17 @@ pub struct ProjectSnapshotStep {
     pub optional: bool,
     pub skip_if: Option<String>,
     pub kind: ProjectSnapshotStepKind,
+    /// The environment values that were substituted into the step's commands.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    /// Hash of the step's resolved export commands, environment and input files, so a later
+    /// snapshot created with `--base` can tell whether its exports can be reused. Only set for
+    /// steps that declare `inputs`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inputs_hash: Option<String>,
+    /// The files the step's export commands produced, relative to the snapshot root.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<PathBuf>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -48,6 +62,10 @@ pub enum ProjectSnapshotStepKind {
         source: String,
         destination: String,
         overwrite: bool,
+        #[serde(default = "crate::setup::project::default_copy_files_recursive")]
+        recursive: bool,
+        #[serde(default)]
+        match_path: bool,
     },
     Complex {
         apply: Vec<ApplyCommand>,
@@ -65,10 +83,15 @@ impl ProjectSnapshotStepKind {
             ProjectSnapshotStepKind::Basic { .. } => "basic",
         }
     }
-}
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct CopyFile {
-    stored_path: PathBuf,
-    destination_path: PathBuf,
+    /// Whether one of the step's commands reads its stdin from a file in the snapshot.
+    pub fn reads_stdin(&self) -> bool {
+        match self {
+            ProjectSnapshotStepKind::CopyFiles { .. } => false,
+            ProjectSnapshotStepKind::Complex { apply: commands }
+            | ProjectSnapshotStepKind::Basic { command: commands } => {
+                commands.iter().any(|command| command.stdin.is_some())
+            }
+        }
+    }
 }
@@ -1,14 +1,21 @@
 use std::fmt::Display;
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::setup::{project::GitOverride, utils::EnvMapper};
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct GitConfig {
+    /// The URL to clone the repository from.
     pub url: String,
+    /// The branch to check out, instead of the repository's default.
     #[serde(default)]
     pub branch: Option<String>,
+    /// The exact commit to check out, recorded when the snapshot was created.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
 }
 
 impl GitConfig {
@@ -16,19 +23,15 @@ impl GitConfig {
         Self {
             url: git_override.url.unwrap_or(self.url),
             branch: git_override.branch.or(self.branch),
+            commit: self.commit,
         }
     }
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct StepService {
-    pub name: String,
-    pub compose: String,
-}
-
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 pub struct ApplyCommand {
     pub command: String,
+    /// A file of the snapshot to feed to the command's stdin.
     #[serde(default)]
     pub stdin: Option<CommandPipe>,
 }
@@ -67,8 +70,9 @@ impl ApplyCommand {
     }
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 #[serde(untagged, rename_all = "snake_case")]
 pub enum CommandPipe {
+    /// A file in the snapshot.
     File { file: String },
 }
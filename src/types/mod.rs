@@ -1,5 +1,6 @@
-use std::{fmt::Display, str::FromStr};
+use std::{borrow::Cow, fmt::Display, path::PathBuf, str::FromStr};
 
+use schemars::{JsonSchema, Schema, SchemaGenerator, json_schema};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize, Hash, PartialEq, Eq, Ord, PartialOrd)]
@@ -113,8 +114,172 @@ Only lowercase alphanumeric characters, hyphens, and underscores are allowed.{su
     }
 }
 
+impl JsonSchema for Slug {
+    fn schema_name() -> Cow<'static, str> {
+        "Slug".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "string",
+            "pattern": "^[a-z][a-z0-9_-]*$",
+            "description": "Lowercase alphanumerics, hyphens and underscores, starting with a letter."
+        })
+    }
+}
+
 impl Display for Slug {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.0)
     }
 }
+
+/// The project given to `de run --project`: the ID of a registered project, or a path to a
+/// project directory. Arguments containing a `/`, and `.` or `..`, are always paths.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProjectArg {
+    Id(Slug),
+    Path(PathBuf),
+}
+
+impl FromStr for ProjectArg {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.contains('/') || s.contains(std::path::MAIN_SEPARATOR) || s == "." || s == ".." {
+            return Ok(ProjectArg::Path(PathBuf::from(s)));
+        }
+
+        match Slug::from_str(s) {
+            Ok(id) => Ok(ProjectArg::Id(id)),
+            // Directory names that aren't valid IDs, e.g. `MyApi`
+            Err(_) if PathBuf::from(s).is_dir() => Ok(ProjectArg::Path(PathBuf::from(s))),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// A duration written like `90s`, `5m`, `1h30m` or `500ms` in configuration files. A bare
+/// number is a number of seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct HumanDuration(std::time::Duration);
+
+impl HumanDuration {
+    pub fn as_duration(&self) -> std::time::Duration {
+        self.0
+    }
+
+    /// The duration in whole seconds, rounded up, for commands like `docker compose stop -t`
+    /// where truncating `500ms` to `0` would mean no grace period at all. Only `0` stays `0`.
+    pub fn as_whole_secs(&self) -> u64 {
+        self.0.as_secs_f64().ceil() as u64
+    }
+}
+
+impl FromStr for HumanDuration {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let trimmed_s = s.trim();
+        if let Ok(secs) = trimmed_s.parse::<u64>() {
+            return Ok(Self(std::time::Duration::from_secs(secs)));
+        }
+
+        humantime::parse_duration(trimmed_s).map(Self).map_err(|e| {
+            format!("Invalid duration '{s}' ({e}), expected e.g. '90s', '5m' or '1h30m'")
+        })
+    }
+}
+
+impl JsonSchema for HumanDuration {
+    fn schema_name() -> Cow<'static, str> {
+        "HumanDuration".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "string",
+            "description": "A duration like `90s`, `5m`, `1h30m` or `500ms`, or a number of seconds."
+        })
+    }
+}
+
+impl From<std::time::Duration> for HumanDuration {
+    fn from(value: std::time::Duration) -> Self {
+        Self(value)
+    }
+}
+
+impl TryFrom<String> for HumanDuration {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<HumanDuration> for String {
+    fn from(value: HumanDuration) -> Self {
+        value.to_string()
+    }
+}
+
+impl Display for HumanDuration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", humantime::format_duration(self.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_human_duration_parses_and_formats() {
+        let parse = |s: &str| HumanDuration::from_str(s).map(|d| d.as_duration().as_millis());
+
+        assert_eq!(parse("60"), Ok(60_000));
+        assert_eq!(parse("90s"), Ok(90_000));
+        assert_eq!(parse("1h30m"), Ok(5_400_000));
+        assert_eq!(parse("500ms"), Ok(500));
+        assert!(parse("").is_err());
+        assert_eq!(parse("1h 30m"), Ok(5_400_000));
+        assert_eq!(parse("5 minutes"), Ok(300_000));
+        assert!(parse("5 parsecs").is_err());
+        assert!(parse("m5").is_err());
+
+        assert_eq!(
+            HumanDuration::from_str("90s").unwrap().to_string(),
+            "1m 30s"
+        );
+        assert_eq!(HumanDuration::from_str("0").unwrap().to_string(), "0s");
+    }
+
+    #[test]
+    fn test_human_duration_rounds_up_to_whole_seconds() {
+        let secs = |s: &str| HumanDuration::from_str(s).unwrap().as_whole_secs();
+
+        assert_eq!(secs("500ms"), 1);
+        assert_eq!(secs("0"), 0);
+        assert_eq!(secs("1500ms"), 2);
+        assert_eq!(secs("30s"), 30);
+    }
+
+    #[test]
+    fn test_project_arg_tells_paths_from_ids() {
+        assert_eq!(
+            ProjectArg::from_str("api"),
+            Ok(ProjectArg::Id(Slug::from_str("api").unwrap()))
+        );
+        assert_eq!(
+            ProjectArg::from_str("./services/api"),
+            Ok(ProjectArg::Path(PathBuf::from("./services/api")))
+        );
+        assert_eq!(
+            ProjectArg::from_str("."),
+            Ok(ProjectArg::Path(PathBuf::from(".")))
+        );
+        assert!(ProjectArg::from_str("Not A Project").is_err());
+    }
+}
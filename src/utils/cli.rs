@@ -1,69 +1,87 @@
+use std::{
+    io::IsTerminal,
+    path::{Path, PathBuf},
+};
+
 use eyre::{WrapErr, eyre};
 
-use crate::{project::Project, types::Slug, workspace::Workspace};
+use crate::{error::DeError, project::Project, types::Slug, workspace::Workspace};
+
+/// The directory commands work from: `dir`, given to `de --dir`, or else the working
+/// directory of the process, which stays unchanged.
+pub fn resolve_working_dir(dir: Option<&Path>) -> eyre::Result<PathBuf> {
+    let Some(dir) = dir else {
+        return std::env::current_dir()
+            .map_err(|e| eyre!(e))
+            .wrap_err("Failed to get current working directory");
+    };
+
+    let dir = dir
+        .canonicalize()
+        .map_err(|e| eyre!(e))
+        .wrap_err_with(|| format!("Directory {} does not exist", dir.display()))?;
+    if !dir.is_dir() {
+        return Err(eyre!("{} is not a directory", dir.display()));
+    }
+
+    Ok(dir)
+}
 
 /// Helper function to get a project based on the provided project name and workspace name.
 pub fn get_project_for_cli(
     project_name: Option<Slug>,
     workspace_name: Option<Option<Slug>>,
+    cwd: &Path,
 ) -> eyre::Result<Project> {
     if let Some(project_name) = project_name {
         let workspace = match workspace_name {
-            Some(Some(workspace_name)) => Workspace::load_from_name(&workspace_name)
-                .map_err(|e| eyre!(e))
-                .wrap_err("Failed to load workspace")?
-                .ok_or_else(|| eyre!("Workspace '{}' not found", workspace_name))?,
-            Some(None) => Workspace::active()
-                .map_err(|e| eyre!(e))
-                .wrap_err("Failed to get active workspace")?
-                .ok_or_else(|| eyre!("No active workspace found"))?,
-            None => Workspace::current()
+            Some(Some(workspace_name)) => Workspace::require(&workspace_name, cwd)?,
+            Some(None) => Workspace::require_active(cwd)?,
+            None => Workspace::current(cwd)
                 .map_err(|e| eyre!(e))
                 .wrap_err("Failed to get current workspace")?
-                .ok_or_else(|| eyre!("No current workspace found"))?,
+                .ok_or_else(|| eyre!(DeError::NoActiveWorkspace))?,
         };
 
-        let project = workspace
-            .config()
-            .projects
-            .get(&project_name)
-            .ok_or_else(|| {
-                eyre!(
-                    "Project '{}' not found in workspace '{}'",
-                    project_name,
-                    workspace.config().name
-                )
-            })?;
+        let project = workspace.project(&project_name).ok_or_else(|| {
+            eyre!(DeError::ProjectNotFound {
+                project: project_name.clone(),
+                workspace: workspace.config().name.clone(),
+            })
+        })?;
 
         Project::from_dir(&project.dir)
             .map_err(|e| eyre!(e))
             .wrap_err("Failed to load project from directory")
     } else {
-        Project::current()
+        Project::from_dir_recursive(cwd)
             .map_err(|e| eyre!(e))
             .wrap_err("Failed to get current project")?
-            .ok_or_else(|| eyre!("No current project found"))
+            .ok_or_else(|| eyre!(DeError::NoCurrentProject))
     }
 }
 
 /// Helper function to get a workspace based on the provided workspace name.
-pub fn get_workspace_for_cli(workspace_name: Option<Option<Slug>>) -> eyre::Result<Workspace> {
+pub fn get_workspace_for_cli(
+    workspace_name: Option<Option<Slug>>,
+    cwd: &Path,
+) -> eyre::Result<Workspace> {
     if let Some(workspace_name) = workspace_name {
         if let Some(workspace_name) = workspace_name {
-            Workspace::load_from_name(&workspace_name)
-                .map_err(|e| eyre!(e))
-                .wrap_err("Failed to load workspace")?
-                .ok_or_else(|| eyre!("Workspace '{}' not found", workspace_name))
+            Workspace::require(&workspace_name, cwd)
         } else {
-            Workspace::active()
-                .map_err(|e| eyre!(e))
-                .wrap_err("Failed to get active workspace")?
-                .ok_or_else(|| eyre!("No active workspace found"))
+            Workspace::require_active(cwd)
         }
     } else {
-        Workspace::current()
+        Workspace::current(cwd)
             .map_err(|e| eyre!(e))
             .wrap_err("Failed to get current workspace")?
-            .ok_or_else(|| eyre!("No current workspace found"))
+            .ok_or_else(|| eyre!(DeError::NoActiveWorkspace))
     }
 }
+
+/// Whether the user can answer prompts: both stdin and stderr, where dialoguer draws, must
+/// be terminals. Prompts fall back to a default or fail with a hint when this is false.
+pub fn is_interactive() -> bool {
+    std::io::stdin().is_terminal() && std::io::stderr().is_terminal()
+}
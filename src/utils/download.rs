@@ -0,0 +1,105 @@
+use std::{io::Write, path::Path};
+
+use eyre::{WrapErr, eyre};
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use tempfile::NamedTempFile;
+
+use crate::{constants::PROJECT_NAME, utils::ui::UserInterface};
+
+/// Whether `path` is an `http://` or `https://` URL rather than a local path.
+pub fn is_url(path: &Path) -> bool {
+    path.to_str()
+        .is_some_and(|path| path.starts_with("http://") || path.starts_with("https://"))
+}
+
+/// The HTTP client for everything de downloads, identifying itself as de.
+pub fn client() -> eyre::Result<Client> {
+    Client::builder()
+        .user_agent(format!("{PROJECT_NAME}/{}", env!("CARGO_PKG_VERSION")))
+        .build()
+        .map_err(|e| eyre!(e))
+        .wrap_err("Failed to start HTTP client")
+}
+
+/// Sends a GET request for `url`, failing unless the response has a success status.
+pub fn get(url: &str) -> eyre::Result<Response> {
+    send(client()?.get(url))
+}
+
+/// Sends a request made with [`client`], failing unless the response has a success status.
+pub fn send(request: RequestBuilder) -> eyre::Result<Response> {
+    request
+        .send()
+        .and_then(Response::error_for_status)
+        .map_err(|e| eyre!(e))
+}
+
+/// Downloads `url` to a temporary file, showing its progress. The file is deleted when the
+/// returned handle is dropped.
+pub fn download_to_tempfile(ui: &UserInterface, url: &str) -> eyre::Result<NamedTempFile> {
+    let mut file = NamedTempFile::new()
+        .map_err(|e| eyre!(e))
+        .wrap_err("Failed to create temporary file for download")?;
+
+    let response = get(url).wrap_err_with(|| format!("Failed to download {url}"))?;
+
+    let bar = match response.content_length() {
+        Some(len) => {
+            let bar = ui.progress_bar(len)?;
+            bar.set_message("bytes");
+            bar
+        }
+        None => ui.loading_bar("Downloading...")?,
+    };
+
+    std::io::copy(&mut bar.wrap_read(response), &mut file)
+        .map_err(|e| eyre!(e))
+        .wrap_err_with(|| format!("Failed to download {url}"))?;
+    bar.finish_and_clear();
+
+    file.flush()
+        .map_err(|e| eyre!(e))
+        .wrap_err("Failed to write download to temporary file")?;
+
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_url() {
+        assert!(is_url(Path::new("https://example.com/ws.zip")));
+        assert!(is_url(Path::new("http://localhost:8000/ws.zip")));
+        assert!(!is_url(Path::new("ws.zip")));
+        assert!(!is_url(Path::new("/tmp/https://ws.zip")));
+    }
+
+    /// Serves `response` to the first request on a local port, returning its URL.
+    fn serve_once(response: &'static str) -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/ws.zip", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 1024];
+            let _ = std::io::Read::read(&mut stream, &mut request);
+            stream.write_all(response.as_bytes()).unwrap();
+        });
+        url
+    }
+
+    #[test]
+    fn test_download_to_tempfile() {
+        let url = serve_once("HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello");
+        let file = download_to_tempfile(&UserInterface::new().buffered(), &url).unwrap();
+        assert_eq!(std::fs::read_to_string(file.path()).unwrap(), "hello");
+    }
+
+    #[test]
+    fn test_download_to_tempfile_fails_on_error_status() {
+        let url = serve_once("HTTP/1.1 404 Not Found\r\nContent-Length: 9\r\n\r\nnot found");
+        let error = download_to_tempfile(&UserInterface::new().buffered(), &url).unwrap_err();
+        assert!(format!("{error:#}").contains("404"), "{error:#}");
+    }
+}
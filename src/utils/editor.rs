@@ -0,0 +1,52 @@
+use std::{path::Path, process::Command};
+
+use eyre::{WrapErr, eyre};
+
+use crate::utils::process::CommandExt;
+
+/// Editors that accept `+<line>` before the file to open it at that line.
+const LINE_ARG_EDITORS: &[&str] = &["vi", "vim", "nvim", "nano", "emacs", "micro"];
+
+/// Opens `path` in `$VISUAL` or `$EDITOR` and waits for the editor to exit. The `line` is only
+/// passed on to editors known to support jumping to it.
+pub fn open_in_editor(path: &Path, line: Option<usize>) -> eyre::Result<()> {
+    let editor = std::env::var("VISUAL")
+        .ok()
+        .or_else(|| std::env::var("EDITOR").ok())
+        .filter(|editor| !editor.trim().is_empty())
+        .ok_or_else(|| {
+            eyre!(
+                "No editor configured. Set $EDITOR to open {}",
+                path.display()
+            )
+        })?;
+
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().unwrap_or_default();
+
+    let mut command = Command::new(program);
+    command.args(parts);
+
+    let program_name = Path::new(program)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    if let Some(line) = line
+        && LINE_ARG_EDITORS.contains(&program_name.as_str())
+    {
+        command.arg(format!("+{line}"));
+    }
+
+    let status = command
+        .arg(path)
+        .logged_status()
+        .map_err(|e| eyre!(e))
+        .wrap_err_with(|| format!("Failed to run editor '{editor}'"))?;
+
+    if !status.success() {
+        return Err(eyre!("Editor '{}' exited with {}", editor, status));
+    }
+
+    Ok(())
+}
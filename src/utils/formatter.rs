@@ -1,54 +1,57 @@
-use crate::utils::theme::Theme;
+use crate::utils::{
+    theme::{Symbols, Theme},
+    ui::wrapped_lines,
+};
 use console::{Term, style};
 use std::io::Result;
 
 pub struct Formatter {
     theme: Theme,
+    symbols: Symbols,
     term: Term,
 }
 
 impl Formatter {
     pub fn new() -> Self {
+        Self::with_theme(Theme::new())
+    }
+
+    pub fn with_theme(theme: Theme) -> Self {
         Self {
-            theme: Theme::new(),
+            symbols: Symbols::new(&theme),
+            theme,
             term: Term::stdout(),
         }
     }
 
     pub fn success_symbol(&self) -> String {
-        style("✓").fg(self.theme.success_color).to_string()
+        self.symbols.success.clone()
     }
 
     pub fn error_symbol(&self) -> String {
-        style("✗").fg(self.theme.error_color).to_string()
+        self.symbols.error.clone()
     }
 
     pub fn warning_symbol(&self) -> String {
-        style("!").fg(self.theme.warning_color).to_string()
+        self.symbols.warning.clone()
     }
 
     pub fn info_symbol(&self) -> String {
-        style("-").fg(self.theme.highlight_color).to_string()
+        self.symbols.info.clone()
     }
 
     pub fn arrow_symbol(&self) -> String {
-        style("→").fg(self.theme.accent_color).to_string()
+        self.symbols.arrow.clone()
     }
 
     pub fn success(&self, message: &str) -> Result<()> {
-        self.term
-            .write_line(&format!("  {} {}", self.success_symbol(), message))
+        self.write_wrapped(&format!("  {} ", self.success_symbol()), message)
     }
 
     pub fn error(&self, message: &str, suggestion: Option<&str>) -> Result<()> {
-        self.term
-            .write_line(&format!("  {} {}", self.error_symbol(), message))?;
+        self.write_wrapped(&format!("  {} ", self.error_symbol()), message)?;
         if let Some(suggestion) = suggestion {
-            self.term.write_line(&format!(
-                "    {} {}",
-                self.arrow_symbol(),
-                self.theme.dim(suggestion)
-            ))?;
+            self.write_suggestion("    ", suggestion)?;
         }
         Ok(())
     }
@@ -59,37 +62,26 @@ impl Formatter {
         messages: &[String],
         suggestion: Option<&str>,
     ) -> Result<()> {
-        self.term
-            .write_line(&format!("  {} {}", self.error_symbol(), heading))?;
+        self.write_wrapped(&format!("  {} ", self.error_symbol()), heading)?;
         for message in messages {
-            self.term.write_line(&format!("    - {message}"))?;
+            self.write_wrapped("    - ", message)?;
         }
         if let Some(suggestion) = suggestion {
-            self.term.write_line(&format!(
-                "      {} {}",
-                self.arrow_symbol(),
-                self.theme.dim(suggestion)
-            ))?;
+            self.write_suggestion("      ", suggestion)?;
         }
         Ok(())
     }
 
     pub fn warning(&self, message: &str, suggestion: Option<&str>) -> Result<()> {
-        self.term
-            .write_line(&format!("  {} {}", self.warning_symbol(), message))?;
+        self.write_wrapped(&format!("  {} ", self.warning_symbol()), message)?;
         if let Some(suggestion) = suggestion {
-            self.term.write_line(&format!(
-                "    {} {}",
-                self.arrow_symbol(),
-                self.theme.dim(suggestion)
-            ))?;
+            self.write_suggestion("    ", suggestion)?;
         }
         Ok(())
     }
 
     pub fn info(&self, message: &str) -> Result<()> {
-        self.term
-            .write_line(&format!("  {} {}", self.info_symbol(), message))
+        self.write_wrapped(&format!("  {} ", self.info_symbol()), message)
     }
 
     pub fn heading(&self, text: &str) -> Result<()> {
@@ -104,4 +96,20 @@ impl Formatter {
     pub fn new_line(&self) -> Result<()> {
         self.term.write_line("")
     }
+
+    /// Writes `message` after `prefix`, wrapped to the terminal width.
+    fn write_wrapped(&self, prefix: &str, message: &str) -> Result<()> {
+        for line in wrapped_lines(&self.term, prefix, message, str::to_string) {
+            self.term.write_line(&line)?;
+        }
+        Ok(())
+    }
+
+    fn write_suggestion(&self, indent: &str, suggestion: &str) -> Result<()> {
+        let prefix = format!("{indent}{} ", self.arrow_symbol());
+        for line in wrapped_lines(&self.term, &prefix, suggestion, |line| self.theme.dim(line)) {
+            self.term.write_line(&line)?;
+        }
+        Ok(())
+    }
 }
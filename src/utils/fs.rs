@@ -0,0 +1,178 @@
+use std::{
+    fs::File,
+    io::{BufReader, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use eyre::{WrapErr, eyre};
+use sha2::Digest;
+
+/// Directories of version control data, dependencies and build output, which walks of a
+/// project's files (watching, copying, hashing) never look into.
+pub const SKIPPED_DIRS: &[&str] = &[".git", "node_modules", "target"];
+
+/// Whether `entry` is one of the [`SKIPPED_DIRS`] below the root of the walk.
+pub fn is_skipped_dir(entry: &walkdir::DirEntry) -> bool {
+    entry.depth() > 0
+        && entry.file_type().is_dir()
+        && SKIPPED_DIRS.iter().any(|dir| entry.file_name() == *dir)
+}
+
+/// Writes `contents` to a temporary file next to `path` and renames it into place, so readers
+/// see either the old or the new contents, never a partially written file.
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> std::io::Result<()> {
+    write_atomic_with(path, |file| file.write_all(contents.as_ref()))
+}
+
+/// Like [`write_atomic`], with `write` producing the contents. If it fails, the temporary
+/// file is removed and `path` is left untouched.
+///
+/// A symlink at `path` is followed, so its target is replaced rather than the link, and an
+/// existing file keeps its permissions.
+fn write_atomic_with(
+    path: &Path,
+    write: impl FnOnce(&mut File) -> std::io::Result<()>,
+) -> std::io::Result<()> {
+    let path = match path.canonicalize() {
+        Ok(target) => target,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => path.to_path_buf(),
+        Err(e) => return Err(e),
+    };
+
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    let mut builder = tempfile::Builder::new();
+    // A new file gets the permissions `File::create` would give it, instead of the
+    // owner-only ones of a temporary file
+    #[cfg(unix)]
+    builder.permissions(std::os::unix::fs::PermissionsExt::from_mode(0o666));
+
+    let mut file = builder.tempfile_in(dir)?;
+    if let Ok(metadata) = std::fs::metadata(&path) {
+        file.as_file().set_permissions(metadata.permissions())?;
+    }
+    write(file.as_file_mut())?;
+    file.as_file().sync_all()?;
+    file.persist(&path).map_err(|e| e.error)?;
+
+    Ok(())
+}
+
+/// Feeds the contents of the file at `path` to `hasher`, without reading it into memory at once.
+pub fn hash_file<D>(hasher: &mut D, path: &Path) -> eyre::Result<()>
+where
+    D: Digest,
+{
+    let file = File::open(path)
+        .map_err(|e| eyre!(e))
+        .wrap_err_with(|| format!("Failed to open file for hashing: {}", path.display()))?;
+
+    let mut reader = BufReader::new(file);
+    let mut buffer = [0; 8192];
+
+    loop {
+        let n = reader
+            .read(&mut buffer)
+            .map_err(|e| eyre!(e))
+            .wrap_err_with(|| format!("Failed to read file for hashing: {}", path.display()))?;
+
+        if n == 0 {
+            break;
+        }
+
+        hasher.update(&buffer[..n]);
+    }
+
+    Ok(())
+}
+
+/// An exclusive advisory lock on a file, released when dropped.
+///
+/// The lock is taken on a `<file>.lock` sibling rather than the file itself, because
+/// [`write_atomic`] replaces the file and a lock on the old one would no longer protect it.
+/// On platforms without `flock` the lock is a no-op.
+pub struct FileLock {
+    _file: File,
+}
+
+impl FileLock {
+    /// Blocks until the lock for `path` is acquired.
+    pub fn acquire(path: &Path) -> eyre::Result<Self> {
+        let lock_path = lock_path(path);
+        let file = File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)
+            .map_err(|e| eyre!(e))
+            .wrap_err_with(|| format!("Failed to open lock file {}", lock_path.display()))?;
+
+        #[cfg(unix)]
+        {
+            use std::os::fd::AsRawFd;
+
+            // SAFETY: the descriptor stays open for as long as `file` lives.
+            if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } != 0 {
+                return Err(eyre!(std::io::Error::last_os_error()))
+                    .wrap_err_with(|| format!("Failed to lock {}", path.display()));
+            }
+        }
+
+        Ok(Self { _file: file })
+    }
+}
+
+fn lock_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".lock");
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failed_write_keeps_original_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "name = \"old\"\n").unwrap();
+
+        let result = write_atomic_with(&path, |file| {
+            file.write_all(b"name = \"ne")?;
+            Err(std::io::Error::other("disk full"))
+        });
+
+        assert!(result.is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "name = \"old\"\n");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        write_atomic(&path, "name = \"new\"\n").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "name = \"new\"\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_keeps_permissions_and_symlinks() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("shared.toml");
+        let link = dir.path().join("de.toml");
+        std::fs::write(&target, "old").unwrap();
+        std::fs::set_permissions(&target, std::fs::Permissions::from_mode(0o640)).unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        write_atomic(&link, "new").unwrap();
+
+        assert!(link.symlink_metadata().unwrap().file_type().is_symlink());
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "new");
+        assert_eq!(
+            target.metadata().unwrap().permissions().mode() & 0o777,
+            0o640
+        );
+    }
+}
@@ -0,0 +1,100 @@
+use std::path::{Path, PathBuf};
+
+use eyre::{WrapErr, eyre};
+use tracing::{Level, Subscriber};
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::{Layer, filter::Targets, registry::LookupSpan};
+
+use crate::{constants::LOG_ENV_VAR, utils::get_project_dirs};
+
+/// Number of daily log files kept; older ones are deleted when a new file is started.
+const MAX_LOG_FILES: usize = 14;
+
+/// Whether a log file should be written, because of `de --log-file` or a `DE_LOG` value other
+/// than `0` or empty.
+pub fn log_file_enabled(flag: bool) -> bool {
+    flag || is_enabled_value(std::env::var(LOG_ENV_VAR).ok().as_deref())
+}
+
+fn is_enabled_value(value: Option<&str>) -> bool {
+    value.is_some_and(|value| !value.is_empty() && value != "0")
+}
+
+/// The directory the log files are written to.
+pub fn logs_dir() -> eyre::Result<PathBuf> {
+    Ok(get_project_dirs()?.data_dir().join("logs"))
+}
+
+/// A layer appending everything de logs to `de.<date>.log` in `dir`. A new file is started
+/// each day, and only the last [`MAX_LOG_FILES`] are kept.
+pub fn file_layer<S>(dir: &Path) -> eyre::Result<impl Layer<S> + use<S>>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    // The appender looks for old files to delete before creating the directory
+    std::fs::create_dir_all(dir)
+        .map_err(|e| eyre!(e))
+        .wrap_err_with(|| format!("Failed to create log directory {}", dir.display()))?;
+
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix("de")
+        .filename_suffix("log")
+        .max_log_files(MAX_LOG_FILES)
+        .build(dir)
+        .map_err(|e| eyre!(e))
+        .wrap_err_with(|| format!("Failed to open a log file in {}", dir.display()))?;
+
+    Ok(tracing_subscriber::fmt::layer()
+        .with_ansi(false)
+        .with_writer(appender)
+        .with_filter(Targets::new().with_target("de", Level::INFO)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::process::CommandExt;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn test_is_enabled_value() {
+        assert!(is_enabled_value(Some("1")));
+        assert!(is_enabled_value(Some("true")));
+        assert!(!is_enabled_value(Some("0")));
+        assert!(!is_enabled_value(Some("")));
+        assert!(!is_enabled_value(None));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_file_layer_records_commands_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let logs = dir.path().join("logs");
+        let subscriber = tracing_subscriber::registry().with(file_layer(&logs).unwrap());
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::debug!(target: "de", "too detailed for the log file");
+            std::process::Command::new("true")
+                .arg("it's quoted")
+                .current_dir(dir.path())
+                .logged_status()
+                .unwrap();
+        });
+
+        let files = std::fs::read_dir(&logs)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(files.len(), 1);
+        assert!(
+            files[0].starts_with("de.") && files[0].ends_with(".log"),
+            "{files:?}"
+        );
+
+        let log = std::fs::read_to_string(logs.join(&files[0])).unwrap();
+        assert!(log.contains("true 'it'\\''s quoted'"), "{log}");
+        assert!(log.contains(&dir.path().display().to_string()), "{log}");
+        assert!(!log.contains("too detailed"), "{log}");
+    }
+}
@@ -1,17 +1,32 @@
 pub mod cli;
+// Shared with `build.rs`, which formats the build date with it
+#[cfg(test)]
+mod date;
+pub mod download;
+pub mod editor;
 pub mod formatter;
+pub mod fs;
 pub mod git;
+pub mod log;
+pub mod parallel;
 pub mod path;
+pub mod process;
 pub mod serde;
 pub mod shim;
+pub mod table;
+pub mod task_cache;
 pub mod theme;
 pub mod ui;
+#[cfg(target_family = "unix")]
 pub mod unix;
+pub mod watch;
+#[cfg(target_family = "windows")]
+pub mod windows;
 pub mod zip;
 
 use crate::constants::{ORGANIZATION_NAME, PROJECT_NAME};
 
-pub use cli::{get_project_for_cli, get_workspace_for_cli};
+pub use cli::{get_project_for_cli, get_workspace_for_cli, is_interactive, resolve_working_dir};
 pub use shim::{check_shim_installation_in_shell_config, get_shims_dir, shim_export_line};
 
 pub fn get_project_dirs() -> eyre::Result<directories::ProjectDirs> {
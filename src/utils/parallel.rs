@@ -0,0 +1,63 @@
+use std::sync::{
+    Mutex,
+    atomic::{AtomicUsize, Ordering},
+};
+
+/// Calls `f` on each of `items` on up to `jobs` threads at once, each thread taking the next
+/// item as soon as it's done with one. Returns the results in the order of `items`, however the
+/// calls finished.
+pub fn parallel_map<'a, I, T, F>(items: &'a [I], jobs: usize, f: F) -> Vec<T>
+where
+    I: Sync,
+    T: Send,
+    F: Fn(&'a I) -> T + Sync,
+{
+    let next_item = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(items.len()));
+
+    std::thread::scope(|scope| {
+        for _ in 0..jobs.clamp(1, items.len().max(1)) {
+            scope.spawn(|| {
+                loop {
+                    let index = next_item.fetch_add(1, Ordering::SeqCst);
+                    let Some(item) = items.get(index) else {
+                        break;
+                    };
+
+                    let result = f(item);
+                    results
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .push((index, result));
+                }
+            });
+        }
+    });
+
+    let mut results = results.into_inner().unwrap_or_else(|e| e.into_inner());
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parallel_map_keeps_the_order_of_items() {
+        let items = (0..20u64).collect::<Vec<_>>();
+
+        // Later items finish first
+        let results = parallel_map(&items, 4, |item| {
+            std::thread::sleep(std::time::Duration::from_millis(20 - item));
+            item * 2
+        });
+
+        assert_eq!(
+            results,
+            items.iter().map(|item| item * 2).collect::<Vec<_>>()
+        );
+        assert!(parallel_map(&[] as &[u64], 4, |item| *item).is_empty());
+        assert_eq!(parallel_map(&[1, 2], 0, |item| item + 1), [2, 3]);
+    }
+}
@@ -0,0 +1,531 @@
+use std::{
+    io::{Read, Write},
+    process::{Child, Command, ExitStatus, Stdio},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use eyre::{WrapErr, eyre};
+
+/// How often a child process is polled while waiting on a deadline.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Runs commands like [`Command::output`], [`Command::status`] and [`Command::spawn`], but
+/// records the command line and working directory first, for the log file written with
+/// `de --log-file`. Clippy rejects the plain methods (see `clippy.toml`), so no command de runs
+/// goes unrecorded.
+pub trait CommandExt {
+    fn logged_output(&mut self) -> std::io::Result<std::process::Output>;
+    fn logged_status(&mut self) -> std::io::Result<ExitStatus>;
+    fn logged_spawn(&mut self) -> std::io::Result<Child>;
+}
+
+#[allow(clippy::disallowed_methods)]
+impl CommandExt for Command {
+    fn logged_output(&mut self) -> std::io::Result<std::process::Output> {
+        log_command(self);
+        self.output()
+    }
+
+    fn logged_status(&mut self) -> std::io::Result<ExitStatus> {
+        log_command(self);
+        self.status()
+    }
+
+    fn logged_spawn(&mut self) -> std::io::Result<Child> {
+        log_command(self);
+        self.spawn()
+    }
+}
+
+fn log_command(command: &Command) {
+    tracing::info!(
+        target: "de::exec",
+        cwd = %command
+            .get_current_dir()
+            .map(|dir| dir.display().to_string())
+            .unwrap_or_else(|| ".".to_string()),
+        "{}",
+        command_line(command)
+    );
+}
+
+/// The outcome of running a child process with an optional deadline.
+pub enum ProcessOutcome {
+    Exited(ExitStatus),
+    TimedOut,
+}
+
+/// Spawns the command and waits for it to exit, killing it if it runs past `timeout`.
+///
+/// With a timeout set on Unix and stdin not a terminal, the child is placed in its own process
+/// group so the whole group (including grandchildren) can be killed once the deadline passes.
+/// An interactive task keeps the terminal instead, and only the task itself is killed.
+pub fn run_with_timeout(
+    command: &mut Command,
+    timeout: Option<Duration>,
+) -> eyre::Result<ProcessOutcome> {
+    check_stop_requested()?;
+
+    let Some(timeout) = timeout else {
+        let mut child = command
+            .logged_spawn()
+            .map_err(|e| eyre!(e))
+            .wrap_err("Failed to execute command")?;
+        let _active = ActiveChild::track(&child, false);
+
+        let status = child
+            .wait()
+            .map_err(|e| eyre!(e))
+            .wrap_err("Failed to wait for command")?;
+        check_stop_requested()?;
+        return Ok(ProcessOutcome::Exited(status));
+    };
+
+    let own_group = use_own_process_group(command);
+
+    let mut child = command
+        .logged_spawn()
+        .map_err(|e| eyre!(e))
+        .wrap_err("Failed to spawn command")?;
+    let _active = ActiveChild::track(&child, own_group);
+
+    let outcome = wait_with_timeout(&mut child, timeout, own_group)?;
+    check_stop_requested()?;
+    Ok(outcome)
+}
+
+/// Like [`run_with_timeout`], but copies everything the child writes to stdout and stderr to
+/// `log`, byte for byte. With `echo`, the output still goes to the terminal as well.
+pub fn run_with_log(
+    command: &mut Command,
+    timeout: Option<Duration>,
+    log: impl Write + Send + 'static,
+    echo: bool,
+) -> eyre::Result<ProcessOutcome> {
+    check_stop_requested()?;
+
+    let own_group = timeout.is_some() && use_own_process_group(command);
+
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .logged_spawn()
+        .map_err(|e| eyre!(e))
+        .wrap_err("Failed to spawn command")?;
+    let _active = ActiveChild::track(&child, own_group);
+
+    let log = Arc::new(Mutex::new(log));
+    let stdout = child.stdout.take().map(|stdout| {
+        let log = log.clone();
+        std::thread::spawn(move || copy_output(stdout, &log, echo.then(std::io::stdout)))
+    });
+    let stderr = child.stderr.take().map(|stderr| {
+        let log = log.clone();
+        std::thread::spawn(move || copy_output(stderr, &log, echo.then(std::io::stderr)))
+    });
+
+    let outcome = match timeout {
+        Some(timeout) => wait_with_timeout(&mut child, timeout, own_group)?,
+        None => ProcessOutcome::Exited(
+            child
+                .wait()
+                .map_err(|e| eyre!(e))
+                .wrap_err("Failed to wait for command")?,
+        ),
+    };
+
+    for copy in stdout.into_iter().chain(stderr) {
+        copy.join()
+            .map_err(|_| eyre!("Output copy thread panicked"))?
+            .map_err(|e| eyre!(e))
+            .wrap_err("Failed to copy command output")?;
+    }
+
+    check_stop_requested()?;
+    Ok(outcome)
+}
+
+/// Collects the output of a command run with [`run_with_log`] in memory, e.g. to write it out
+/// in one piece once commands running concurrently are done.
+#[derive(Debug, Clone, Default)]
+pub struct OutputBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl OutputBuffer {
+    /// Takes everything written so far, leaving the buffer empty.
+    pub fn take(&self) -> Vec<u8> {
+        std::mem::take(&mut *self.0.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+}
+
+impl Write for OutputBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Copies `from` to the log, and to `echo` if given, until it is closed.
+fn copy_output(
+    mut from: impl Read,
+    log: &Mutex<impl Write>,
+    mut echo: Option<impl Write>,
+) -> std::io::Result<()> {
+    let mut buffer = [0; 8192];
+    loop {
+        let len = match from.read(&mut buffer) {
+            Ok(0) => return Ok(()),
+            Ok(len) => len,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+
+        log.lock()
+            .map_err(|_| std::io::Error::other("log file lock poisoned"))?
+            .write_all(&buffer[..len])?;
+
+        if let Some(echo) = echo.as_mut() {
+            echo.write_all(&buffer[..len])?;
+            echo.flush()?;
+        }
+    }
+}
+
+/// Places the child in its own process group on Unix, so the whole group (including
+/// grandchildren) can be killed on timeout. Returns whether it did.
+///
+/// Not when stdin is a terminal: a child outside the terminal's foreground group is stopped by
+/// SIGTTIN when it reads stdin, and doesn't get the Ctrl-C from the terminal.
+fn use_own_process_group(command: &mut Command) -> bool {
+    #[cfg(unix)]
+    {
+        use std::{io::IsTerminal, os::unix::process::CommandExt};
+
+        if std::io::stdin().is_terminal() {
+            return false;
+        }
+
+        command.process_group(0);
+        true
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = command;
+        false
+    }
+}
+
+/// Waits for the child to exit, killing it if it runs past `timeout`. With `own_group`, the
+/// whole process group it leads is killed.
+fn wait_with_timeout(
+    child: &mut Child,
+    timeout: Duration,
+    own_group: bool,
+) -> eyre::Result<ProcessOutcome> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child
+            .try_wait()
+            .map_err(|e| eyre!(e))
+            .wrap_err("Failed to wait for command")?
+        {
+            return Ok(ProcessOutcome::Exited(status));
+        }
+
+        if Instant::now() >= deadline {
+            break;
+        }
+
+        std::thread::sleep(POLL_INTERVAL);
+    }
+
+    tracing::info!("Command timed out after {:?}, killing process", timeout);
+
+    #[cfg(unix)]
+    if own_group {
+        // SAFETY: `kill` has no memory-safety preconditions; a negative pid targets the
+        // process group created above, which is led by the child.
+        unsafe {
+            libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+        }
+    } else {
+        let _ = child.kill();
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = own_group;
+        let _ = child.kill();
+    }
+
+    // Reap the child so it doesn't linger as a zombie
+    let _ = child.wait();
+
+    Ok(ProcessOutcome::TimedOut)
+}
+
+/// Set by the SIGINT handler of [`install_interrupt_handler`] on the first Ctrl-C.
+#[cfg(unix)]
+static STOP_REQUESTED: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+/// How many children the SIGINT handler can stop at once, e.g. prerequisites run with `--jobs`.
+#[cfg(unix)]
+const MAX_ACTIVE_CHILDREN: usize = 64;
+
+/// Where the SIGINT handler sends SIGTERM: the process groups (as negative pids) or the pids of
+/// the children de waits on in [`run_with_timeout`] or [`run_with_log`], with 0 in free slots.
+///
+/// A fixed set of atomics rather than a locked collection, since the handler can't take a lock.
+#[cfg(unix)]
+static ACTIVE_CHILDREN: [std::sync::atomic::AtomicI32; MAX_ACTIVE_CHILDREN] =
+    [const { std::sync::atomic::AtomicI32::new(0) }; MAX_ACTIVE_CHILDREN];
+
+/// Makes the first Ctrl-C stop the task de is waiting on, instead of leaving it running after de
+/// exits. de then exits once the task has. Without a task, or on a second Ctrl-C, de exits right
+/// away, as it would without the handler.
+///
+/// Tasks without a timeout, or run from a terminal, stay in de's process group, so they keep the
+/// terminal and receive the Ctrl-C themselves as well; the others have their own group and only
+/// get the SIGTERM.
+pub fn install_interrupt_handler() {
+    #[cfg(unix)]
+    {
+        let handler = stop_active_child as extern "C" fn(libc::c_int);
+        // SAFETY: the handler only uses atomics and async-signal-safe libc functions.
+        unsafe {
+            libc::signal(libc::SIGINT, handler as libc::sighandler_t);
+        }
+    }
+}
+
+#[cfg(unix)]
+extern "C" fn stop_active_child(signal: libc::c_int) {
+    use std::sync::atomic::Ordering;
+
+    let has_children = ACTIVE_CHILDREN
+        .iter()
+        .any(|slot| slot.load(Ordering::SeqCst) != 0);
+    if STOP_REQUESTED.swap(true, Ordering::SeqCst) || !has_children {
+        // SAFETY: `signal` and `raise` are async-signal-safe; this exits the way an
+        // unhandled Ctrl-C would, so shells still see de as killed by SIGINT.
+        unsafe {
+            libc::signal(signal, libc::SIG_DFL);
+            libc::raise(signal);
+        }
+        return;
+    }
+
+    for slot in &ACTIVE_CHILDREN {
+        let target = slot.load(Ordering::SeqCst);
+        if target != 0 {
+            // SAFETY: `kill` is async-signal-safe and has no memory-safety preconditions.
+            unsafe {
+                libc::kill(target, libc::SIGTERM);
+            }
+        }
+    }
+}
+
+/// Registers a child with the SIGINT handler of [`install_interrupt_handler`] until dropped.
+struct ActiveChild {
+    /// The slot of [`ACTIVE_CHILDREN`] holding the child, if one was free.
+    #[cfg(unix)]
+    slot: Option<usize>,
+}
+
+impl ActiveChild {
+    fn track(child: &Child, own_group: bool) -> Self {
+        #[cfg(unix)]
+        {
+            use std::sync::atomic::Ordering;
+
+            let pid = child.id() as libc::pid_t;
+            let target = if own_group { -pid } else { pid };
+            let slot = ACTIVE_CHILDREN.iter().position(|slot| {
+                slot.compare_exchange(0, target, Ordering::SeqCst, Ordering::SeqCst)
+                    .is_ok()
+            });
+            if slot.is_none() {
+                tracing::warn!("Too many running tasks, Ctrl-C won't stop task {pid}");
+            }
+
+            Self { slot }
+        }
+
+        #[cfg(not(unix))]
+        {
+            let _ = (child, own_group);
+            Self {}
+        }
+    }
+}
+
+impl Drop for ActiveChild {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Some(slot) = self.slot {
+            ACTIVE_CHILDREN[slot].store(0, std::sync::atomic::Ordering::SeqCst);
+        }
+    }
+}
+
+/// Fails once Ctrl-C stopped a task, so de exits instead of going on to the next one.
+fn check_stop_requested() -> eyre::Result<()> {
+    #[cfg(unix)]
+    if STOP_REQUESTED.load(std::sync::atomic::Ordering::SeqCst) {
+        return Err(eyre!(crate::error::DeError::Interrupted));
+    }
+
+    Ok(())
+}
+
+/// Set by the SIGINT handler installed in [`run_interruptible`].
+#[cfg(unix)]
+static INTERRUPTED: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+#[cfg(unix)]
+extern "C" fn record_interrupt(_signal: libc::c_int) {
+    INTERRUPTED.store(true, std::sync::atomic::Ordering::SeqCst);
+}
+
+/// Runs `f` with Ctrl-C recorded instead of terminating `de`, so a foreground child
+/// stops on its own and the caller can still clean up afterwards.
+///
+/// Returns the result of `f` and whether an interrupt was received while it ran.
+pub fn run_interruptible<T>(f: impl FnOnce() -> T) -> (T, bool) {
+    #[cfg(unix)]
+    {
+        use std::sync::atomic::Ordering;
+
+        INTERRUPTED.store(false, Ordering::SeqCst);
+
+        let handler = record_interrupt as extern "C" fn(libc::c_int);
+        // SAFETY: the handler only stores to an atomic, which is async-signal-safe.
+        let previous = unsafe { libc::signal(libc::SIGINT, handler as libc::sighandler_t) };
+
+        let result = f();
+
+        // SAFETY: restores the disposition that was in place before.
+        unsafe {
+            libc::signal(libc::SIGINT, previous);
+        }
+
+        (result, INTERRUPTED.load(Ordering::SeqCst))
+    }
+
+    #[cfg(not(unix))]
+    {
+        (f(), false)
+    }
+}
+
+/// Whether Ctrl-C was pressed since the innermost [`run_interruptible`] call started,
+/// for loops that should stop between iterations.
+pub fn interrupted() -> bool {
+    #[cfg(unix)]
+    {
+        INTERRUPTED.load(std::sync::atomic::Ordering::SeqCst)
+    }
+
+    #[cfg(not(unix))]
+    {
+        false
+    }
+}
+
+/// Prints what running the command would do, without running it.
+///
+/// Only the names of the variables set for the command are printed, since they include the
+/// project's `.env` values; `de env` shows them with secrets masked.
+pub fn print_dry_run(command: &Command) {
+    let command_line = command_line(command);
+
+    let cwd = match command.get_current_dir() {
+        Some(dir) => dir.display().to_string(),
+        None => std::env::current_dir()
+            .map(|dir| dir.display().to_string())
+            .unwrap_or_else(|_| "(unknown)".to_string()),
+    };
+
+    println!("Command: {command_line}");
+    println!("Cwd: {cwd}");
+
+    let overrides = command.get_envs().collect::<Vec<_>>();
+    if overrides.is_empty() {
+        println!("Env: inherited (see `de env`)");
+    } else {
+        println!("Env: inherited (see `de env`), plus:");
+        for (key, value) in overrides {
+            match value {
+                Some(_) => println!("  {}", key.to_string_lossy()),
+                None => println!("  {} (removed)", key.to_string_lossy()),
+            }
+        }
+    }
+}
+
+/// The program and arguments of the command, quoted like a shell would need them.
+pub fn command_line(command: &Command) -> String {
+    std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(|part| quote_arg(&part.to_string_lossy()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Quotes an argument for display when it would otherwise be split or expanded by a shell.
+pub fn quote_arg(arg: &str) -> String {
+    let is_plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=@%+,".contains(c));
+
+    if is_plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::sync::atomic::Ordering;
+
+    fn is_tracked(target: i32) -> bool {
+        ACTIVE_CHILDREN
+            .iter()
+            .any(|slot| slot.load(Ordering::SeqCst) == target)
+    }
+
+    #[test]
+    fn test_active_children_are_tracked_together() {
+        let mut first = Command::new("sleep").arg("5").logged_spawn().unwrap();
+        let mut second = Command::new("sleep").arg("5").logged_spawn().unwrap();
+
+        let first_active = ActiveChild::track(&first, false);
+        let second_active = ActiveChild::track(&second, true);
+        assert!(is_tracked(first.id() as i32));
+        assert!(is_tracked(-(second.id() as i32)));
+
+        drop(first_active);
+        assert!(!is_tracked(first.id() as i32));
+        assert!(is_tracked(-(second.id() as i32)));
+
+        drop(second_active);
+        assert!(!is_tracked(-(second.id() as i32)));
+
+        for child in [&mut first, &mut second] {
+            child.kill().unwrap();
+            child.wait().unwrap();
+        }
+    }
+}
@@ -1,15 +1,22 @@
 use std::borrow::Cow;
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
 #[serde(untagged)]
+#[schemars(rename = "StringOr{T}")]
 pub enum StringOr<T: From<String>> {
     String(String),
     Value(T),
 }
 
 impl<T: From<String>> StringOr<T> {
+    /// Wraps a full value, which is written out in its long form.
+    pub fn from_value(value: T) -> Self {
+        StringOr::Value(value)
+    }
+
     pub fn as_value(&self) -> Cow<'_, T>
     where
         T: Clone,
@@ -31,14 +38,25 @@ impl<T: From<String>> StringOr<T> {
     }
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
 #[serde(untagged)]
+#[schemars(rename = "OneOrMany{T}")]
 pub enum OneOrMany<T> {
     One(T),
     Many(Vec<T>),
 }
 
 impl<T> OneOrMany<T> {
+    /// Wraps `items`, written out as a single item rather than a list when there is only one.
+    pub fn from_vec(mut items: Vec<T>) -> Self {
+        if items.len() == 1
+            && let Some(item) = items.pop()
+        {
+            return OneOrMany::One(item);
+        }
+        OneOrMany::Many(items)
+    }
+
     pub fn as_slice(&self) -> &[T] {
         match self {
             OneOrMany::One(item) => std::slice::from_ref(item),
@@ -52,3 +70,70 @@ impl<T: From<String>> From<String> for OneOrMany<T> {
         OneOrMany::One(T::from(value))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+    struct Service {
+        name: String,
+        port: Option<u16>,
+    }
+
+    impl From<String> for Service {
+        fn from(name: String) -> Self {
+            Self { name, port: None }
+        }
+    }
+
+    #[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
+    struct Manifest {
+        services: OneOrMany<StringOr<Service>>,
+    }
+
+    fn round_trip(source: &str) -> Manifest {
+        let manifest = toml::from_str::<Manifest>(source).unwrap();
+        assert_eq!(toml::to_string(&manifest).unwrap(), source);
+        manifest
+    }
+
+    #[test]
+    fn test_one_or_many_round_trips_both_forms() {
+        let one = round_trip("services = \"db\"\n");
+        assert_eq!(
+            one.services,
+            OneOrMany::One(StringOr::String("db".to_string()))
+        );
+
+        let many = round_trip("services = [\"db\", \"cache\"]\n");
+        assert_eq!(many.services.as_slice().len(), 2);
+
+        let manifest = Manifest {
+            services: OneOrMany::from_vec(vec![StringOr::String("db".to_string())]),
+        };
+        assert_eq!(toml::to_string(&manifest).unwrap(), "services = \"db\"\n");
+        assert_eq!(
+            OneOrMany::<String>::from_vec(Vec::new()),
+            OneOrMany::Many(Vec::new())
+        );
+    }
+
+    #[test]
+    fn test_string_or_round_trips_both_forms() {
+        let short = round_trip("services = \"db\"\n");
+        let long = round_trip("[services]\nname = \"db\"\nport = 5432\n");
+
+        assert_eq!(
+            short.services.as_slice()[0].clone_value(),
+            Service::from("db".to_string())
+        );
+        assert_eq!(
+            long.services,
+            OneOrMany::One(StringOr::from_value(Service {
+                name: "db".to_string(),
+                port: Some(5432),
+            }))
+        );
+    }
+}
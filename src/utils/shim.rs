@@ -8,11 +8,26 @@ pub fn get_shims_dir() -> eyre::Result<std::path::PathBuf> {
     Ok(dirs.data_dir().join("shims"))
 }
 
+/// The line added to the shell configuration to put the shims directory on `PATH`.
 pub fn shim_export_line(shims_dir: &Path) -> eyre::Result<String> {
     let shims_dir_str = shims_dir
         .to_str()
         .ok_or_else(|| eyre::eyre!("Failed to convert shims directory path to string"))?;
-    Ok(format!("export PATH=\"{shims_dir_str}:$PATH\""))
+
+    if cfg!(target_family = "windows") {
+        Ok(format!("$env:Path = \"{shims_dir_str};$env:Path\""))
+    } else {
+        Ok(format!("export PATH=\"{shims_dir_str}:$PATH\""))
+    }
+}
+
+/// The file name of the shim for `command`; Windows needs an extension to run it.
+pub fn shim_file_name(command: &str) -> String {
+    if cfg!(target_family = "windows") {
+        format!("{command}.cmd")
+    } else {
+        command.to_string()
+    }
 }
 
 pub fn check_shim_installation_in_shell_config(
@@ -33,11 +48,41 @@ pub fn check_shim_installation_in_shell_config(
     Ok(content.contains(&shim_export))
 }
 
+/// Commands that are never shimmed in bulk, since shadowing them would break the shell, the
+/// shims themselves or de.
+const PROTECTED_COMMANDS: &[&str] = &[
+    "bash",
+    "cat",
+    "cd",
+    "cp",
+    "de",
+    "docker",
+    "docker-compose",
+    "echo",
+    "env",
+    "git",
+    "grep",
+    "ls",
+    "mkdir",
+    "mv",
+    "rm",
+    "sed",
+    "sh",
+    "ssh",
+    "sudo",
+    "zsh",
+];
+
+pub fn is_protected_command(command: &str) -> bool {
+    PROTECTED_COMMANDS.contains(&command)
+}
+
 pub fn write_shim_to_file(command: &Slug) -> eyre::Result<()> {
     let shims_dir = get_shims_dir()?;
-    let shim_file = shims_dir.join(format!("{command}"));
+    let shim_file = shims_dir.join(shim_file_name(command.as_str()));
+
+    let shim_program = generate_shim_script(command.as_str());
 
-    let shim_program = generate_shim_bash_script(command.as_str());
     std::fs::create_dir_all(&shims_dir)
         .map_err(|e| eyre!(e))
         .wrap_err_with(|| format!("Failed to create shims directory: {}", shims_dir.display()))?;
@@ -66,6 +111,103 @@ fn apply_executable_permissions(shim_file: &Path) -> eyre::Result<()> {
     Ok(())
 }
 
+/// Directories with system binaries, which the shims directory has to come before on `PATH`
+/// for shims to take over their commands.
+const SYSTEM_BIN_DIRS: &[&str] = if cfg!(target_family = "windows") {
+    &["C:\\Windows\\System32", "C:\\Windows"]
+} else {
+    &["/usr/local/bin", "/usr/bin", "/bin", "/usr/sbin", "/sbin"]
+};
+
+/// Where the shims directory is on `PATH`.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ShimsOnPath {
+    /// Ahead of all system binary directories.
+    Ahead,
+    /// After this system binary directory, whose commands win over the shims.
+    Behind(std::path::PathBuf),
+    Missing,
+}
+
+/// Finds the shims directory in `path`, a `PATH` value.
+pub fn shims_on_path(path: &std::ffi::OsStr, shims_dir: &Path) -> ShimsOnPath {
+    let dirs = std::env::split_paths(path).collect::<Vec<_>>();
+
+    let Some(shims_index) = dirs.iter().position(|dir| dir == shims_dir) else {
+        return ShimsOnPath::Missing;
+    };
+
+    match dirs[..shims_index].iter().find(|dir| {
+        SYSTEM_BIN_DIRS
+            .iter()
+            .any(|system| dir.as_path() == Path::new(system))
+    }) {
+        Some(system_dir) => ShimsOnPath::Behind(system_dir.clone()),
+        None => ShimsOnPath::Ahead,
+    }
+}
+
+/// The executables named `name` in `path`, a `PATH` value, in the order a shell finds them.
+/// Entries that are the same file, e.g. through a symlinked `/bin`, are listed once.
+pub fn executables_on_path(path: &std::ffi::OsStr, name: &str) -> Vec<std::path::PathBuf> {
+    let file_name = format!("{name}{}", std::env::consts::EXE_SUFFIX);
+    let mut seen = std::collections::HashSet::new();
+
+    std::env::split_paths(path)
+        .map(|dir| dir.join(&file_name))
+        .filter(|candidate| is_executable(candidate))
+        .filter(|candidate| {
+            seen.insert(
+                candidate
+                    .canonicalize()
+                    .unwrap_or_else(|_| candidate.clone()),
+            )
+        })
+        .collect()
+}
+
+fn is_executable(path: &Path) -> bool {
+    let Ok(metadata) = std::fs::metadata(path) else {
+        return false;
+    };
+
+    #[cfg(target_family = "unix")]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+    }
+    #[cfg(not(target_family = "unix"))]
+    {
+        metadata.is_file()
+    }
+}
+
+/// The programs a shim script runs `de task check` and `de run` with. That is `de`, found on
+/// `PATH`, unless the script was changed to call a fixed path.
+pub fn shim_de_programs(script: &str) -> Vec<String> {
+    let mut programs = Vec::new();
+
+    for line in script.lines().map(str::trim) {
+        if line.starts_with('#') || line.starts_with("rem ") {
+            continue;
+        }
+
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        for pair in words.windows(2) {
+            let program = pair[0].trim_matches(['"', '\'']);
+            if matches!(pair[1], "run" | "task")
+                && Path::new(program)
+                    .file_stem()
+                    .is_some_and(|stem| stem == "de")
+            {
+                programs.push(program.to_string());
+            }
+        }
+    }
+
+    programs
+}
+
 pub fn get_installed_shims() -> eyre::Result<Vec<String>> {
     let shims_dir = get_shims_dir()?;
 
@@ -87,6 +229,11 @@ pub fn get_installed_shims() -> eyre::Result<Vec<String>> {
         })?;
 
         if let Some(name) = entry.file_name().to_str() {
+            let name = if cfg!(target_family = "windows") {
+                name.strip_suffix(".cmd").unwrap_or(name)
+            } else {
+                name
+            };
             shims.push(name.to_string());
         }
     }
@@ -94,13 +241,23 @@ pub fn get_installed_shims() -> eyre::Result<Vec<String>> {
     Ok(shims)
 }
 
+/// The shim script for `command` on this platform.
+pub fn generate_shim_script(command: &str) -> String {
+    #[cfg(target_family = "unix")]
+    return generate_shim_bash_script(command);
+    #[cfg(target_family = "windows")]
+    return generate_shim_cmd_script(command);
+}
+
+#[cfg(target_family = "unix")]
 pub fn generate_shim_bash_script(program_name: &str) -> String {
     format!(
         r##"#!/bin/bash
 # This script is auto-generated and should not be manually edited.
 
 # This script wraps the '{program_name}' command.
-# It prioritizes 'de run {program_name}' if 'de task check {program_name}' passes silently.
+# It prioritizes 'de run {program_name}' if 'de task check {program_name}' passes silently,
+# which happens for tasks of the current project and of the active workspace.
 # Otherwise, it falls back to the system's original '{program_name}' command.
 
 # Executes the Nth occurrence of a command found in PATH.
@@ -138,7 +295,7 @@ exec_nth_command() {{
 # --- Main Logic ---
 
 # Silently check 'de task {program_name}'. Redirects all output to /dev/null.
-if de task check {program_name} >/dev/null 2>&1; then
+if de task check --quiet {program_name} >/dev/null 2>&1; then
     # If check passes, execute 'de run {program_name}'. 'exec' avoids new process.
     exec de run {program_name} -- "$@"
 else
@@ -149,3 +306,95 @@ fi
 "##
     )
 }
+
+#[cfg_attr(not(target_family = "windows"), allow(dead_code))]
+pub fn generate_shim_cmd_script(program_name: &str) -> String {
+    format!(
+        r##"@echo off
+rem This script is auto-generated and should not be manually edited.
+
+rem This script wraps the '{program_name}' command.
+rem It prioritizes 'de run {program_name}' if 'de task check {program_name}' passes silently,
+rem which happens for tasks of the current project and of the active workspace.
+rem Otherwise, it falls back to the system's original '{program_name}' command.
+setlocal
+
+rem `exit /b` without a code keeps the exit code of `de run`. ERRORLEVEL can't be used inside
+rem the block, as it is expanded when the block is read, before `de run` runs.
+de task check --quiet {program_name} >nul 2>&1
+if %ERRORLEVEL% equ 0 (
+    de run {program_name} -- %*
+    exit /b
+)
+
+rem Find the first '{program_name}' on PATH outside of the shims directory, so the shim doesn't
+rem call itself.
+for /f "delims=" %%i in ('where {program_name} 2^>nul') do (
+    if /i not "%%~dpi"=="%~dp0" (
+        set "DE_SHIM_TARGET=%%i"
+        goto :run
+    )
+)
+
+echo Error: '{program_name}' not found in PATH. 1>&2
+exit /b 1
+
+:run
+"%DE_SHIM_TARGET%" %*
+exit /b %ERRORLEVEL%
+"##
+    )
+}
+
+#[cfg(all(test, target_family = "unix"))]
+mod tests {
+    use super::*;
+    use std::ffi::OsStr;
+
+    #[test]
+    fn test_shims_on_path_checks_order() {
+        let shims_dir = Path::new("/home/me/.local/share/de/shims");
+
+        assert_eq!(
+            shims_on_path(
+                OsStr::new("/home/me/.local/share/de/shims:/usr/bin:/bin"),
+                shims_dir
+            ),
+            ShimsOnPath::Ahead
+        );
+        assert_eq!(
+            shims_on_path(
+                OsStr::new("/usr/bin:/home/me/.local/share/de/shims/"),
+                shims_dir
+            ),
+            ShimsOnPath::Behind("/usr/bin".into())
+        );
+        assert_eq!(
+            shims_on_path(OsStr::new("/usr/bin:/bin"), shims_dir),
+            ShimsOnPath::Missing
+        );
+    }
+
+    #[test]
+    fn test_shim_de_programs() {
+        assert_eq!(
+            shim_de_programs(&generate_shim_bash_script("npm")),
+            vec!["de", "de"]
+        );
+        assert_eq!(
+            shim_de_programs("# calls 'de run npm'\nexec /opt/de/bin/de run npm -- \"$@\"\n"),
+            vec!["/opt/de/bin/de"]
+        );
+    }
+
+    #[test]
+    fn test_cmd_shim_exits_with_the_run_exit_code() {
+        let script = generate_shim_cmd_script("npm");
+        let start = script.find("equ 0 (").unwrap();
+        let block = &script[start..start + script[start..].find("\n)").unwrap()];
+
+        assert!(block.contains("de run npm -- %*"));
+        assert!(block.lines().any(|line| line.trim() == "exit /b"));
+        assert!(!block.contains("%ERRORLEVEL%"));
+    }
+}
@@ -0,0 +1,119 @@
+use console::{measure_text_width, pad_str};
+
+use crate::utils::{theme::Theme, ui::UserInterface};
+
+/// Space between two columns.
+const COLUMN_GAP: &str = "  ";
+
+/// Rows of cells printed as left-aligned columns.
+///
+/// Column widths are the display width of the widest cell, ignoring ANSI styling and counting wide
+/// characters (e.g. CJK or emoji) as two columns, so cells can be styled before they are added.
+#[derive(Debug, Default)]
+pub struct Table {
+    header: Option<Vec<String>>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a table with a header row, printed in bold.
+    pub fn with_header<I, S>(header: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            header: Some(header.into_iter().map(Into::into).collect()),
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn add_row<I, S>(&mut self, cells: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.rows.push(cells.into_iter().map(Into::into).collect());
+    }
+
+    /// The display width of each column. Rows may have fewer cells than others.
+    pub fn widths(&self) -> Vec<usize> {
+        let mut widths = Vec::new();
+
+        for row in self.header.iter().chain(&self.rows) {
+            for (index, cell) in row.iter().enumerate() {
+                let width = measure_text_width(cell);
+                match widths.get_mut(index) {
+                    Some(max) if *max < width => *max = width,
+                    Some(_) => {}
+                    None => widths.push(width),
+                }
+            }
+        }
+
+        widths
+    }
+
+    /// The table as lines of text. The last cell of a row isn't padded, so lines have no
+    /// trailing whitespace.
+    pub fn lines(&self, theme: &Theme) -> Vec<String> {
+        let widths = self.widths();
+        let render = |row: &[String]| {
+            let last = row.len().saturating_sub(1);
+            row.iter()
+                .enumerate()
+                .map(|(index, cell)| {
+                    if index == last {
+                        cell.clone()
+                    } else {
+                        pad_str(cell, widths[index], console::Alignment::Left, None).into_owned()
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(COLUMN_GAP)
+        };
+
+        let mut lines = Vec::with_capacity(self.rows.len() + 1);
+        if let Some(header) = &self.header {
+            lines.push(theme.bold(&render(header)));
+        }
+        lines.extend(self.rows.iter().map(|row| render(row)));
+        lines
+    }
+
+    /// Writes the table at the current indentation of `ui`.
+    pub fn print(&self, ui: &UserInterface) -> std::io::Result<()> {
+        for line in self.lines(&ui.theme) {
+            ui.writeln(&line)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_widths_ignore_styling_and_count_wide_characters() {
+        let mut table = Table::with_header(["NAME", "STATUS"]);
+        table.add_row(["api", "\u{1b}[32mrunning\u{1b}[0m"]);
+        table.add_row(["ウェブ", "exited"]);
+        table.add_row(["db"]);
+
+        assert_eq!(table.widths(), [6, 7]);
+
+        let theme = Theme {
+            colored: false,
+            ..Theme::new()
+        };
+        let lines = table.lines(&theme);
+        assert_eq!(lines[0], "NAME    STATUS");
+        assert_eq!(lines[2], "ウェブ  exited");
+        assert_eq!(lines[3], "db");
+    }
+}
@@ -0,0 +1,196 @@
+use std::path::{Path, PathBuf};
+
+use eyre::{WrapErr, eyre};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    project::{Project, Task},
+    types::Slug,
+    utils::{
+        fs::{hash_file, is_skipped_dir, write_atomic},
+        get_project_dirs,
+        watch::PathFilter,
+    },
+};
+
+/// Hashes what the result of a task depends on: its command, the arguments, the `--env`
+/// variables and the paths and contents of the files matching its `inputs`.
+pub fn inputs_hash(
+    project: &Project,
+    task: &Task,
+    args: &[String],
+    env: &[(String, String)],
+) -> eyre::Result<String> {
+    let mut hasher = Sha256::new();
+    hasher.update(task.command_str());
+    for arg in args {
+        hasher.update([0]);
+        hasher.update(arg);
+    }
+
+    // Keep arguments and variables apart, so `a=b` as an argument doesn't hash like `--env a=b`
+    hasher.update([1]);
+    for (key, value) in env {
+        hasher.update([0]);
+        hasher.update(key);
+        hasher.update("=");
+        hasher.update(value);
+    }
+
+    hash_input_files(&mut hasher, project.dir(), task.inputs())?;
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Feeds the paths and contents of the files below `root` matching `globs` to `hasher`.
+pub fn hash_input_files(hasher: &mut Sha256, root: &Path, globs: &[String]) -> eyre::Result<()> {
+    let filter = PathFilter::new(globs)?;
+
+    for path in project_files(root, globs) {
+        if !filter.matches(&path) {
+            continue;
+        }
+
+        hasher.update([0]);
+        hasher.update(path.to_string_lossy().as_bytes());
+        hasher.update([0]);
+        hash_file(hasher, &root.join(&path))?;
+    }
+
+    Ok(())
+}
+
+/// Whether the task last succeeded with inputs hashing to `hash`, and each of its `outputs`
+/// still matches a file.
+pub fn is_up_to_date(
+    project: &Project,
+    task_name: &Slug,
+    task: &Task,
+    hash: &str,
+) -> eyre::Result<bool> {
+    let recorded = std::fs::read_to_string(cache_path(project, task_name)?).ok();
+    if recorded.as_deref().map(str::trim) != Some(hash) {
+        return Ok(false);
+    }
+
+    let files = project_files(project.dir(), task.outputs());
+    for glob in task.outputs() {
+        let filter = PathFilter::new(std::slice::from_ref(glob))?;
+        if !files.iter().any(|path| filter.matches(path)) {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+/// Records that the task succeeded with inputs hashing to `hash`.
+pub fn record(project: &Project, task_name: &Slug, hash: &str) -> eyre::Result<()> {
+    let path = cache_path(project, task_name)?;
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| eyre!(e))
+            .wrap_err_with(|| format!("Failed to create directory {}", parent.display()))?;
+    }
+
+    write_atomic(&path, hash)
+        .map_err(|e| eyre!(e))
+        .wrap_err_with(|| format!("Failed to write {}", path.display()))
+}
+
+/// Where the inputs hash of the task's last successful run is kept, in the user's cache
+/// directory rather than the project, so it never shows up in version control.
+fn cache_path(project: &Project, task_name: &Slug) -> eyre::Result<PathBuf> {
+    let project_key = format!(
+        "{:x}",
+        Sha256::digest(project.dir().to_string_lossy().as_bytes())
+    );
+
+    Ok(get_project_dirs()?
+        .cache_dir()
+        .join("tasks")
+        .join(format!("{}-{task_name}", &project_key[..16])))
+}
+
+/// The files below `root`, relative to it and in a stable order. The [`SKIPPED_DIRS`] are only
+/// searched when one of `globs` starts with their path, as `target/release/*` does.
+///
+/// [`SKIPPED_DIRS`]: crate::utils::fs::SKIPPED_DIRS
+fn project_files(root: &Path, globs: &[String]) -> Vec<PathBuf> {
+    walkdir::WalkDir::new(root)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| {
+            !is_skipped_dir(entry)
+                || entry.path().strip_prefix(root).is_ok_and(|dir| {
+                    let dir = format!("{}/", dir.to_string_lossy());
+                    globs.iter().any(|glob| glob.starts_with(&dir))
+                })
+        })
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| Some(entry.path().strip_prefix(root).ok()?.to_path_buf()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_inputs_hash_only_depends_on_inputs() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("de.toml"),
+            "[project]\nname = \"cache\"\n\n[tasks.build]\ncommand = \"make\"\ninputs = [\"src/*.c\"]\n",
+        )
+        .unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/main.c"), "int main;").unwrap();
+
+        let project = Project::from_dir(dir.path()).unwrap();
+        let task = project
+            .task(&Slug::from_str("build").unwrap())
+            .unwrap()
+            .unwrap();
+        let hash = inputs_hash(&project, task, &[], &[]).unwrap();
+
+        std::fs::write(dir.path().join("README.md"), "not an input").unwrap();
+        assert_eq!(inputs_hash(&project, task, &[], &[]).unwrap(), hash);
+
+        assert_ne!(
+            inputs_hash(&project, task, &["-j4".to_string()], &[]).unwrap(),
+            hash
+        );
+
+        let env = [("CC".to_string(), "clang".to_string())];
+        assert_ne!(inputs_hash(&project, task, &[], &env).unwrap(), hash);
+
+        std::fs::write(dir.path().join("src/main.c"), "int main();").unwrap();
+        assert_ne!(inputs_hash(&project, task, &[], &[]).unwrap(), hash);
+    }
+
+    #[test]
+    fn test_skipped_dirs_are_only_searched_when_named() {
+        let dir = tempfile::tempdir().unwrap();
+        for path in ["src/main.c", "node_modules/dep.c", "target/release/app"] {
+            let path = dir.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "").unwrap();
+        }
+
+        assert_eq!(
+            project_files(dir.path(), &["**/*.c".to_string()]),
+            [PathBuf::from("src/main.c")]
+        );
+        assert_eq!(
+            project_files(dir.path(), &["target/release/*".to_string()]),
+            [
+                PathBuf::from("src/main.c"),
+                PathBuf::from("target/release/app")
+            ]
+        );
+    }
+}
@@ -1,4 +1,16 @@
-use console::Color;
+use std::sync::OnceLock;
+
+use console::{Color, StyledObject};
+
+use crate::cli::Cli;
+
+/// The process-wide theme, installed once by `main` from the CLI flags.
+static THEME: OnceLock<Theme> = OnceLock::new();
+
+/// Returns whether the environment allows colored output, honoring `NO_COLOR` (https://no-color.org).
+pub fn colors_enabled() -> bool {
+    std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+}
 
 #[derive(Debug, Clone)]
 pub struct Theme {
@@ -8,10 +20,40 @@ pub struct Theme {
     pub highlight_color: Color,
     pub accent_color: Color,
     pub indent_unit: usize,
+    /// When false, all styling methods return their input unchanged.
+    pub colored: bool,
+    /// Use ASCII symbols and no animated progress, for logs that don't render them well.
+    pub plain: bool,
 }
 
 impl Theme {
+    /// Returns the installed process-wide theme, or the default one if none was installed.
     pub fn new() -> Self {
+        THEME.get().cloned().unwrap_or_else(Self::default_palette)
+    }
+
+    /// Builds the theme for this invocation from the global CLI flags.
+    pub fn from_cli(cli: &Cli) -> Self {
+        Self {
+            colored: !cli.no_color && colors_enabled(),
+            plain: cli.plain,
+            ..Self::default_palette()
+        }
+    }
+
+    /// Makes this theme the one returned by `Theme::new()` for the rest of the process.
+    ///
+    /// Also turns off colors for direct `console::style` calls when the theme is uncolored.
+    pub fn install(self) {
+        if !self.colored {
+            console::set_colors_enabled(false);
+            console::set_colors_enabled_stderr(false);
+        }
+
+        let _ = THEME.set(self);
+    }
+
+    fn default_palette() -> Self {
         Self {
             success_color: Color::Green,
             error_color: Color::Red,
@@ -19,39 +61,52 @@ impl Theme {
             highlight_color: Color::Cyan,
             accent_color: Color::Magenta,
             indent_unit: 2,
+            colored: colors_enabled(),
+            plain: false,
+        }
+    }
+
+    fn paint<F>(&self, s: &str, f: F) -> String
+    where
+        F: FnOnce(StyledObject<&str>) -> StyledObject<&str>,
+    {
+        if self.colored {
+            f(console::style(s)).to_string()
+        } else {
+            s.to_string()
         }
     }
 
     pub fn highlight(&self, s: &str) -> String {
-        console::style(s).fg(self.highlight_color).to_string()
+        self.paint(s, |style| style.fg(self.highlight_color))
     }
 
     pub fn success(&self, s: &str) -> String {
-        console::style(s).fg(self.success_color).to_string()
+        self.paint(s, |style| style.fg(self.success_color))
     }
 
     pub fn warn(&self, s: &str) -> String {
-        console::style(s).fg(self.warning_color).to_string()
+        self.paint(s, |style| style.fg(self.warning_color))
     }
 
     pub fn error(&self, s: &str) -> String {
-        console::style(s).fg(self.error_color).to_string()
+        self.paint(s, |style| style.fg(self.error_color))
     }
 
     pub fn accent(&self, s: &str) -> String {
-        console::style(s).fg(self.accent_color).to_string()
+        self.paint(s, |style| style.fg(self.accent_color))
     }
 
     pub fn bold(&self, s: &str) -> String {
-        console::style(s).bold().to_string()
+        self.paint(s, |style| style.bold())
     }
 
     pub fn bold_underline(&self, s: &str) -> String {
-        console::style(s).bold().underlined().to_string()
+        self.paint(s, |style| style.bold().underlined())
     }
 
     pub fn dim(&self, s: &str) -> String {
-        console::style(s).dim().to_string()
+        self.paint(s, |style| style.dim())
     }
 
     pub fn indent(&self, level: usize) -> String {
@@ -70,12 +125,58 @@ pub struct Symbols {
 
 impl Symbols {
     pub fn new(theme: &Theme) -> Self {
+        if theme.plain {
+            return Self::ascii(theme);
+        }
+
+        Self {
+            success: theme.success("✓"),
+            error: theme.error("✗"),
+            warning: theme.warn("!"),
+            info: theme.highlight("-"),
+            arrow: theme.accent("→"),
+        }
+    }
+
+    /// Symbols that render in any log viewer.
+    pub fn ascii(theme: &Theme) -> Self {
         Self {
-            success: console::style("✓").fg(theme.success_color).to_string(),
-            error: console::style("✗").fg(theme.error_color).to_string(),
-            warning: console::style("!").fg(theme.warning_color).to_string(),
-            info: console::style("-").fg(theme.highlight_color).to_string(),
-            arrow: console::style("→").fg(theme.accent_color).to_string(),
+            success: theme.success("+"),
+            error: theme.error("x"),
+            warning: theme.warn("!"),
+            info: theme.highlight("-"),
+            arrow: theme.accent("->"),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::formatter::Formatter;
+
+    #[test]
+    fn test_plain_symbols_are_ascii() {
+        let theme = Theme {
+            colored: false,
+            plain: true,
+            ..Theme::default_palette()
+        };
+
+        let symbols = Symbols::new(&theme);
+        for symbol in [
+            &symbols.success,
+            &symbols.error,
+            &symbols.warning,
+            &symbols.info,
+            &symbols.arrow,
+        ] {
+            assert!(symbol.is_ascii(), "{symbol} is not ASCII");
+        }
+
+        // The formatter draws from the same table
+        let formatter = Formatter::with_theme(theme);
+        assert_eq!(formatter.success_symbol(), symbols.success);
+        assert_eq!(formatter.arrow_symbol(), symbols.arrow);
+    }
+}
@@ -1,6 +1,6 @@
-use std::time::Duration;
+use std::{process::Stdio, time::Duration};
 
-use console::Term;
+use console::{Term, TermTarget, measure_text_width};
 use indicatif::ProgressBar;
 
 use super::theme::{Symbols, Theme};
@@ -11,40 +11,176 @@ pub struct UserInterface {
     pub theme: Theme,
     pub symbols: Symbols,
     indent: usize,
+    /// A progress bar that is hidden while lines are written, so output doesn't garble it.
+    progress: Option<ProgressBar>,
 }
 
 impl UserInterface {
     pub fn new() -> Self {
-        let theme = Theme::new();
+        Self::with_theme(Theme::new())
+    }
+
+    pub fn with_theme(theme: Theme) -> Self {
         Self {
             term: Term::stdout(),
-            symbols: Symbols::new(&Theme::new()),
+            symbols: Symbols::new(&theme),
             theme,
             indent: 0,
+            progress: None,
+        }
+    }
+
+    /// Returns a UI that keeps `bar` drawn below all output written through it.
+    pub fn with_progress(&self, bar: ProgressBar) -> Self {
+        Self {
+            progress: Some(bar),
+            ..self.clone()
+        }
+    }
+
+    /// Returns a UI that collects its output until [`UserInterface::flush`] is called, so the
+    /// output of work running concurrently can be written out in one piece.
+    pub fn buffered(&self) -> Self {
+        Self {
+            term: Term::buffered_stdout(),
+            ..self.clone()
+        }
+    }
+
+    /// Returns a UI that writes to stderr, for commands whose stdout carries data.
+    pub fn stderr(&self) -> Self {
+        Self {
+            term: Term::stderr(),
+            ..self.clone()
         }
     }
 
+    /// Where child processes should write their stdout, so it ends up next to the output of this
+    /// UI and never in the data a [stderr](UserInterface::stderr) UI keeps stdout free for.
+    pub fn child_stdout(&self) -> Stdio {
+        match self.term.target() {
+            TermTarget::Stderr => std::io::stderr().into(),
+            _ => Stdio::inherit(),
+        }
+    }
+
+    /// Writes out the output collected by a [buffered](UserInterface::buffered) UI.
+    pub fn flush(&self) -> std::io::Result<()> {
+        self.suspend(|| self.term.flush())
+    }
+
+    /// Runs `f` with the progress bar hidden, e.g. for prompts or child processes that write
+    /// to the terminal themselves.
+    pub fn suspend<T>(&self, f: impl FnOnce() -> T) -> T {
+        match &self.progress {
+            Some(bar) => bar.suspend(f),
+            None => f(),
+        }
+    }
+
+    fn write_line(&self, line: &str) -> std::io::Result<()> {
+        self.suspend(|| self.term.write_line(line))
+    }
+
     pub fn writeln(&self, message: &str) -> std::io::Result<()> {
         let indented_message = self.theme.indent(self.indent) + message;
-        self.term.write_line(&indented_message)
+        self.write_line(&indented_message)
     }
 
     pub fn new_line(&self) -> std::io::Result<()> {
-        self.term.write_line("")
+        self.write_line("")
+    }
+
+    /// Writes `message` after `prefix`, wrapped to the terminal width. Each line is styled
+    /// with `paint`.
+    fn write_wrapped(
+        &self,
+        prefix: &str,
+        message: &str,
+        paint: impl Fn(&str) -> String,
+    ) -> std::io::Result<()> {
+        for line in wrapped_lines(&self.term, prefix, message, paint) {
+            self.write_line(&line)?;
+        }
+        Ok(())
+    }
+}
+
+/// Lays `message` out after `prefix`, wrapped to the width of `term` with continuation lines
+/// lined up under the start of the message. Each line of the message is styled with `paint`.
+/// Nothing is wrapped when `term` isn't a terminal.
+pub(crate) fn wrapped_lines(
+    term: &Term,
+    prefix: &str,
+    message: &str,
+    paint: impl Fn(&str) -> String,
+) -> Vec<String> {
+    let prefix_width = measure_text_width(prefix);
+    let width = term
+        .size_checked()
+        .map(|(_, columns)| usize::from(columns).saturating_sub(prefix_width))
+        .filter(|width| *width >= MIN_WRAP_WIDTH);
+
+    let Some(width) = width else {
+        return vec![format!("{prefix}{}", paint(message))];
+    };
+
+    let hanging = " ".repeat(prefix_width);
+    wrap_text(message, width)
+        .iter()
+        .enumerate()
+        .map(|(index, line)| {
+            let prefix = if index == 0 { prefix } else { &hanging };
+            format!("{prefix}{}", paint(line))
+        })
+        .collect()
+}
+
+/// Below this many columns, messages are left unwrapped rather than squeezed one word a line.
+const MIN_WRAP_WIDTH: usize = 20;
+
+/// Breaks `text` into lines of at most `width` columns at spaces, keeping its own line breaks.
+/// Widths are measured without ANSI escape codes, and a word longer than `width` gets a line
+/// of its own.
+fn wrap_text(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        let mut line_width = 0;
+
+        for word in paragraph.split(' ').filter(|word| !word.is_empty()) {
+            let word_width = measure_text_width(word);
+            if line_width > 0 && line_width + 1 + word_width > width {
+                lines.push(std::mem::take(&mut line));
+                line_width = 0;
+            }
+            if line_width > 0 {
+                line.push(' ');
+                line_width += 1;
+            }
+            line.push_str(word);
+            line_width += word_width;
+        }
+
+        lines.push(line);
+    }
+
+    if lines.is_empty() {
+        lines.push(String::new());
     }
+    lines
 }
 
 impl UserInterface {
     pub fn heading(&self, message: &str) -> std::io::Result<()> {
         let indented_message = self.theme.indent(self.indent) + message;
-        self.term
-            .write_line(&self.theme.bold_underline(&indented_message).to_string())
+        self.write_line(&self.theme.bold_underline(&indented_message))
     }
 
     pub fn subheading(&self, message: &str) -> std::io::Result<()> {
         let indented_message = self.theme.indent(self.indent) + message;
-        self.term
-            .write_line(&self.theme.bold(&indented_message).to_string())
+        self.write_line(&self.theme.bold(&indented_message))
     }
 
     pub fn indented<F, T>(&self, f: F) -> eyre::Result<T>
@@ -101,7 +237,18 @@ impl UserInterface {
 }
 
 impl UserInterface {
+    /// Whether progress can be animated: not in [plain](Theme::plain) mode, and only when
+    /// stdout is a terminal, so redirected output doesn't fill up with redraws.
+    fn animated(&self) -> bool {
+        !self.theme.plain && self.term.is_term()
+    }
+
+    /// Returns a spinner showing `message`. It is hidden unless progress can be animated.
     pub fn loading_bar(&self, message: &str) -> std::io::Result<ProgressBar> {
+        if !self.animated() {
+            return Ok(ProgressBar::hidden());
+        }
+
         let bar = ProgressBar::new_spinner();
         bar.set_message(message.to_string());
         bar.enable_steady_tick(Duration::from_millis(100));
@@ -114,6 +261,24 @@ impl UserInterface {
         Ok(bar)
     }
 
+    /// Returns a progress bar for `len` steps. It is hidden unless progress can be animated.
+    pub fn progress_bar(&self, len: u64) -> std::io::Result<ProgressBar> {
+        if !self.animated() {
+            return Ok(ProgressBar::hidden());
+        }
+
+        let bar = ProgressBar::new(len);
+        bar.set_style(
+            indicatif::ProgressStyle::with_template(
+                "{prefix}{bar:30.green/dim} {pos}/{len} {msg} [{elapsed}, eta {eta}]",
+            )
+            .unwrap()
+            .progress_chars("=> "),
+        );
+        bar.set_prefix(self.theme.indent(self.indent));
+        Ok(bar)
+    }
+
     pub fn error_group(
         &self,
         heading: &str,
@@ -139,12 +304,15 @@ impl UserInterface {
         }
 
         if let Some(suggestion) = suggestion {
-            self.term.write_line(&format!(
-                "{}{} {}",
-                self.theme.indent(self.indent + 1),
-                self.symbols.arrow,
-                self.theme.dim(suggestion)
-            ))?;
+            self.write_wrapped(
+                &format!(
+                    "{}{} ",
+                    self.theme.indent(self.indent + 1),
+                    self.symbols.arrow
+                ),
+                suggestion,
+                |line| self.theme.dim(line),
+            )?;
         }
 
         Ok(())
@@ -163,16 +331,37 @@ impl LineItem<'_> {
     pub fn write_to(self, ui: &UserInterface) -> std::io::Result<()> {
         let main_indent = ui.theme.indent(self.indent);
         let symbol = self.symbol.unwrap_or("-");
-        let message = format!("{} {}", symbol, self.message);
-        ui.term.write_line(&format!("{main_indent}{message}"))?;
+        ui.write_wrapped(
+            &format!("{main_indent}{symbol} "),
+            self.message,
+            str::to_string,
+        )?;
         if let Some(suggestion) = self.suggestion {
-            ui.term.write_line(&format!(
-                "{}{} {}",
-                ui.theme.indent(self.indent + 1),
-                ui.symbols.arrow,
-                ui.theme.dim(suggestion)
-            ))?;
+            ui.write_wrapped(
+                &format!("{}{} ", ui.theme.indent(self.indent + 1), ui.symbols.arrow),
+                suggestion,
+                |line| ui.theme.dim(line),
+            )?;
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap_text_ignores_escape_codes() {
+        assert_eq!(
+            wrap_text("one two three four", 9),
+            vec!["one two", "three", "four"]
+        );
+        assert_eq!(
+            wrap_text("\u{1b}[31mred\u{1b}[0m words here", 9),
+            vec!["\u{1b}[31mred\u{1b}[0m words", "here"]
+        );
+        assert_eq!(wrap_text("averylongword x", 4), vec!["averylongword", "x"]);
+        assert_eq!(wrap_text("first\nsecond", 20), vec!["first", "second"]);
+    }
+}
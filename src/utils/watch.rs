@@ -0,0 +1,216 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{Receiver, RecvTimeoutError},
+    time::Duration,
+};
+
+use eyre::{WrapErr, eyre};
+use notify_debouncer_mini::{
+    DebounceEventResult, Debouncer, new_debouncer,
+    notify::{RecommendedWatcher, RecursiveMode},
+};
+use regex::Regex;
+
+use crate::utils::fs::SKIPPED_DIRS;
+
+/// How long files must stay unchanged before a change is reported, so a burst of writes
+/// (e.g. a branch switch) only triggers once.
+const DEBOUNCE_TIMEOUT: Duration = Duration::from_millis(300);
+
+/// How often [`Watcher::wait`] checks whether it should stop.
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Limits watching to the files matching any of a set of glob patterns.
+///
+/// Patterns are relative to the watched directory. `*` and `?` don't match `/`, `**` matches
+/// any number of directories. A pattern without a `/` matches the file name in any directory.
+#[derive(Debug, Default)]
+pub struct PathFilter {
+    patterns: Vec<(Regex, bool)>,
+}
+
+impl PathFilter {
+    pub fn new(globs: &[String]) -> eyre::Result<Self> {
+        let patterns = globs
+            .iter()
+            .map(|glob| {
+                let regex = Regex::new(&glob_to_regex(glob))
+                    .map_err(|e| eyre!(e))
+                    .wrap_err_with(|| format!("Invalid watch pattern '{glob}'"))?;
+                Ok((regex, !glob.contains('/')))
+            })
+            .collect::<eyre::Result<_>>()?;
+
+        Ok(Self { patterns })
+    }
+
+    /// Whether the file at `path`, relative to the watched directory, is watched.
+    /// Everything matches when no patterns were given.
+    pub fn matches(&self, path: &Path) -> bool {
+        if self.patterns.is_empty() {
+            return true;
+        }
+
+        let path = path
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let name = path.rsplit('/').next().unwrap_or(&path);
+
+        self.patterns.iter().any(|(regex, name_only)| {
+            if *name_only {
+                regex.is_match(name)
+            } else {
+                regex.is_match(&path)
+            }
+        })
+    }
+}
+
+fn glob_to_regex(glob: &str) -> String {
+    let mut regex = String::from("^");
+    let mut chars = glob.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                // `**/` also matches no directory at all
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    regex.push_str("(?:.*/)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+
+    regex.push('$');
+    regex
+}
+
+/// Watches the files below a directory for changes, through the file system notifications of
+/// the platform.
+pub struct Watcher {
+    root: PathBuf,
+    filter: PathFilter,
+    events: Receiver<DebounceEventResult>,
+    _debouncer: Debouncer<RecommendedWatcher>,
+}
+
+impl Watcher {
+    /// Starts watching `root`. Changes are reported from then on; call [`Watcher::reset`] to
+    /// forget the ones made so far.
+    pub fn new(root: &Path, filter: PathFilter) -> eyre::Result<Self> {
+        // Notifications name canonical paths on some platforms
+        let root = root
+            .canonicalize()
+            .map_err(|e| eyre!(e))
+            .wrap_err_with(|| format!("Failed to resolve {}", root.display()))?;
+
+        let (sender, events) = std::sync::mpsc::channel();
+        let mut debouncer = new_debouncer(DEBOUNCE_TIMEOUT, sender)
+            .map_err(|e| eyre!(e))
+            .wrap_err("Failed to start watching for changes")?;
+        debouncer
+            .watcher()
+            .watch(&root, RecursiveMode::Recursive)
+            .map_err(|e| eyre!(e))
+            .wrap_err_with(|| format!("Failed to watch {}", root.display()))?;
+
+        Ok(Self {
+            root,
+            filter,
+            events,
+            _debouncer: debouncer,
+        })
+    }
+
+    /// Forgets changes reported so far, e.g. the ones made by the task that just ran.
+    pub fn reset(&mut self) {
+        while self.events.try_recv().is_ok() {}
+    }
+
+    /// Blocks until watched files changed and then settled, returning `true`, or until
+    /// `stop` returns `true`, returning `false`.
+    pub fn wait(&mut self, stop: impl Fn() -> bool) -> bool {
+        loop {
+            match self.events.recv_timeout(STOP_CHECK_INTERVAL) {
+                Ok(Ok(events)) => {
+                    if events.iter().any(|event| self.is_watched(&event.path)) {
+                        return true;
+                    }
+                }
+                Ok(Err(e)) => tracing::warn!("Failed to watch for changes: {e}"),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return false,
+            }
+
+            if stop() {
+                return false;
+            }
+        }
+    }
+
+    /// Whether a change to `path` is reported: it is a file (or was, before it was removed)
+    /// below the root, outside the skipped directories, and matches the filter. Directories
+    /// are left out, as reading them is reported as a change on some platforms.
+    fn is_watched(&self, path: &Path) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return false;
+        };
+
+        !path.is_dir()
+            && !relative
+                .components()
+                .any(|component| SKIPPED_DIRS.iter().any(|dir| component.as_os_str() == *dir))
+            && self.filter.matches(relative)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_filter_matches_globs() {
+        let filter = PathFilter::new(&["*.rs".to_string(), "docs/**/*.md".to_string()]).unwrap();
+
+        assert!(filter.matches(Path::new("main.rs")));
+        assert!(filter.matches(Path::new("src/commands/run.rs")));
+        assert!(filter.matches(Path::new("docs/README.md")));
+        assert!(filter.matches(Path::new("docs/guide/setup.md")));
+        assert!(!filter.matches(Path::new("README.md")));
+        assert!(!filter.matches(Path::new("src/main.rs.bak")));
+    }
+
+    #[test]
+    fn test_watcher_reports_changes_outside_skipped_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("target/debug")).unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+
+        let mut watcher = Watcher::new(dir.path(), PathFilter::default()).unwrap();
+        let waited_for = |seconds| {
+            let deadline = std::time::Instant::now() + Duration::from_secs(seconds);
+            move || std::time::Instant::now() > deadline
+        };
+
+        std::fs::write(dir.path().join("target/debug/out"), "built").unwrap();
+        assert!(!watcher.wait(waited_for(1)));
+
+        std::fs::write(dir.path().join("src/main.rs"), "fn main() {}").unwrap();
+        assert!(watcher.wait(waited_for(10)));
+
+        let root = dir.path().canonicalize().unwrap();
+        assert!(watcher.is_watched(&root.join("src/main.rs")));
+        assert!(!watcher.is_watched(&root.join("node_modules/pkg/index.js")));
+        assert!(!watcher.is_watched(&root));
+        assert!(!watcher.is_watched(Path::new("/elsewhere/main.rs")));
+    }
+}
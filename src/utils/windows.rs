@@ -0,0 +1,23 @@
+use eyre::eyre;
+
+/// The PowerShell profiles of the current user, for PowerShell 7 and Windows PowerShell 5.
+pub fn get_shell_config_paths() -> eyre::Result<Vec<std::path::PathBuf>> {
+    let user_dirs =
+        directories::UserDirs::new().ok_or_else(|| eyre!("Failed to get user directories"))?;
+    let documents_dir = user_dirs
+        .document_dir()
+        .ok_or_else(|| eyre!("Failed to get documents directory"))?;
+
+    Ok(vec![
+        documents_dir.join("PowerShell/Microsoft.PowerShell_profile.ps1"),
+        documents_dir.join("WindowsPowerShell/Microsoft.PowerShell_profile.ps1"),
+    ])
+}
+
+/// The `$PROFILE` of PowerShell 7.
+pub fn primary_shell_config_path() -> eyre::Result<std::path::PathBuf> {
+    get_shell_config_paths()?
+        .into_iter()
+        .next()
+        .ok_or_else(|| eyre!("Failed to get PowerShell profile path"))
+}
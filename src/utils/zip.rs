@@ -1,13 +1,13 @@
 use eyre::{WrapErr, eyre};
 use std::{
     fs::File,
-    io::{Read, Write},
+    io::{Read, Seek, Write},
     path::Path,
 };
 use zip::write::SimpleFileOptions;
 
-pub fn zip_dir(zip_file: File, dir: &Path) -> eyre::Result<()> {
-    let mut zip = zip::ZipWriter::new(zip_file);
+pub fn zip_dir<W: Write + Seek>(w: W, dir: &Path) -> eyre::Result<()> {
+    let mut zip = zip::ZipWriter::new(w);
     let options = SimpleFileOptions::default()
         .compression_method(zip::CompressionMethod::Stored)
         .unix_permissions(0o755);
@@ -1,30 +1,113 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::{collections::BTreeMap, path::PathBuf};
 
 use crate::types::Slug;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// The version of the workspace config format this build of de writes.
+pub const WORKSPACE_CONFIG_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct WorkspaceConfig {
+    /// The format version the file was written with. Files from before it existed are version 0.
+    #[serde(default)]
+    pub version: u32,
+    /// The name of the workspace.
     pub name: Slug,
+    /// The registered projects by ID.
     pub projects: BTreeMap<Slug, WorkspaceProject>,
+    /// Commands `de workspace run` runs by name.
     #[serde(default)]
     pub tasks: BTreeMap<Slug, String>,
+    /// The branch `de git switch` and `de git base-reset` use by default.
     #[serde(default)]
     pub default_branch: Option<String>,
+    /// A directory representing the workspace as a whole, used by `de exec --workspace-root`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub root_dir: Option<PathBuf>,
+    /// Workspace task `de run` runs when no task is given and the current project has no
+    /// default, instead of the task named `default`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_task: Option<Slug>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct WorkspaceTask {
-    pub command: String,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct WorkspaceProject {
+    /// The project directory.
     pub dir: PathBuf,
+    /// Disabled projects stay registered but are skipped by workspace-wide commands.
+    #[serde(
+        default = "default_project_enabled",
+        skip_serializing_if = "is_enabled"
+    )]
+    pub enabled: bool,
+    /// The branch `de git switch` falls back to and `de git base-reset` resets this project to,
+    /// instead of the workspace's `default_branch`, e.g. for the one repo still on `master`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_branch: Option<String>,
 }
 
 impl WorkspaceProject {
+    /// A registration of the project in `dir`, which must contain a `de.toml`. Registering a
+    /// directory without one would only fail later, when the workspace's projects are loaded.
     pub fn new(dir: PathBuf) -> eyre::Result<Self> {
-        Ok(Self { dir })
+        if !dir.join("de.toml").is_file() {
+            return Err(eyre::eyre!(
+                "{} has no de.toml; run `de init` there first",
+                dir.display()
+            ));
+        }
+
+        Ok(Self::new_unchecked(dir))
+    }
+
+    /// A registration of `dir` that doesn't require a `de.toml`, e.g. for a directory that
+    /// gets one later.
+    pub fn new_unchecked(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            enabled: true,
+            default_branch: None,
+        }
+    }
+}
+
+impl WorkspaceConfig {
+    /// The configured default branch of `project`: its own, or else the workspace's. Without
+    /// either, commands ask git for the default branch of the project's remote.
+    pub fn default_branch_for<'a>(&'a self, project: &'a WorkspaceProject) -> Option<&'a str> {
+        project
+            .default_branch
+            .as_deref()
+            .or(self.default_branch.as_deref())
+    }
+}
+
+fn default_project_enabled() -> bool {
+    true
+}
+
+fn is_enabled(enabled: &bool) -> bool {
+    *enabled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_project_default_branch_wins_over_the_workspace() {
+        let config: WorkspaceConfig = toml::from_str(
+            "name = \"ws\"\ndefault_branch = \"main\"\n\n[projects.api]\ndir = \"/src/api\"\n\n[projects.legacy]\ndir = \"/src/legacy\"\ndefault_branch = \"master\"\n",
+        )
+        .unwrap();
+
+        let project = |id: &str| &config.projects[&id.parse::<Slug>().unwrap()];
+        assert_eq!(config.default_branch_for(project("api")), Some("main"));
+        assert_eq!(config.default_branch_for(project("legacy")), Some("master"));
+
+        let mut config = config.clone();
+        config.default_branch = None;
+        assert_eq!(config.default_branch_for(project("api")), None);
     }
 }
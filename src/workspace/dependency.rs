@@ -11,6 +11,8 @@ pub struct DependencyGraph {
     projects: BTreeSet<Slug>,
     /// Explicitly added projects (not just dependencies)
     explicit_projects: BTreeSet<Slug>,
+    /// Projects that are registered but disabled, so they can't be depended on
+    disabled_projects: BTreeSet<Slug>,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -19,6 +21,10 @@ pub enum DependencyGraphError {
     CircularDependency(Vec<Slug>),
     #[error("Missing dependencies: {0:?}")]
     MissingDependencies(Vec<(Slug, Slug)>),
+    #[error(
+        "Projects depend on disabled projects: {0:?} (enable the dependencies or disable the dependents too)"
+    )]
+    DisabledDependencies(Vec<(Slug, Slug)>),
 }
 
 impl DependencyGraph {
@@ -28,9 +34,16 @@ impl DependencyGraph {
             dependencies: BTreeMap::new(),
             projects: BTreeSet::new(),
             explicit_projects: BTreeSet::new(),
+            disabled_projects: BTreeSet::new(),
         }
     }
 
+    /// Record a disabled project. It isn't part of the startup order, and depending on it is an
+    /// error reported by [`DependencyGraph::validate_dependencies`].
+    pub fn add_disabled_project(&mut self, project: Slug) {
+        self.disabled_projects.insert(project);
+    }
+
     /// Add a project with its dependencies
     pub fn add_project(&mut self, project: Slug, depends_on: Vec<Slug>) {
         self.projects.insert(project.clone());
@@ -63,6 +76,38 @@ impl DependencyGraph {
         self.topological_sort()
     }
 
+    /// Resolve dependencies and group projects into levels, where each project only depends on
+    /// projects of earlier levels, so the projects of one level can be started together
+    pub fn resolve_startup_levels(&self) -> Result<Vec<Vec<Slug>>, DependencyGraphError> {
+        let mut levels: Vec<Vec<Slug>> = Vec::new();
+        let mut level_of = BTreeMap::new();
+
+        for project in self.topological_sort()? {
+            // Dependencies come first in the startup order, so their levels are known
+            let level = self
+                .dependencies
+                .get(&project)
+                .into_iter()
+                .flatten()
+                .filter_map(|dep| level_of.get(dep))
+                .map(|level| level + 1)
+                .max()
+                .unwrap_or(0);
+
+            if level == levels.len() {
+                levels.push(Vec::new());
+            }
+            levels[level].push(project.clone());
+            level_of.insert(project, level);
+        }
+
+        for level in &mut levels {
+            level.sort();
+        }
+
+        Ok(levels)
+    }
+
     /// Resolve dependencies and return projects in shutdown order (dependents first)
     pub fn resolve_shutdown_order(&self) -> Result<Vec<Slug>, DependencyGraphError> {
         let mut startup_order = self.topological_sort()?;
@@ -127,11 +172,14 @@ impl DependencyGraph {
     /// Check if there are any missing dependencies
     pub fn validate_dependencies(&self) -> Result<(), DependencyGraphError> {
         let mut missing_deps = Vec::new();
+        let mut disabled_deps = Vec::new();
 
         for (project, deps) in &self.dependencies {
             for dep in deps {
-                // Check if dependency is not in the explicitly added projects
-                if !self.explicit_projects.contains(dep) {
+                // A dependency that wasn't explicitly added is either disabled or missing
+                if self.disabled_projects.contains(dep) {
+                    disabled_deps.push((project.clone(), dep.clone()));
+                } else if !self.explicit_projects.contains(dep) {
                     missing_deps.push((project.clone(), dep.clone()));
                 }
             }
@@ -141,6 +189,10 @@ impl DependencyGraph {
             return Err(DependencyGraphError::MissingDependencies(missing_deps));
         }
 
+        if !disabled_deps.is_empty() {
+            return Err(DependencyGraphError::DisabledDependencies(disabled_deps));
+        }
+
         Ok(())
     }
 }
@@ -187,6 +239,25 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_startup_levels() {
+        let mut graph = DependencyGraph::new();
+        graph.add_project(slug("deps"), vec![]);
+        graph.add_project(slug("lint"), vec![slug("deps")]);
+        graph.add_project(slug("build"), vec![slug("deps")]);
+        graph.add_project(slug("codegen"), vec![]);
+        graph.add_project(slug("bundle"), vec![slug("build"), slug("codegen")]);
+
+        assert_eq!(
+            graph.resolve_startup_levels().unwrap(),
+            vec![
+                vec![slug("codegen"), slug("deps")],
+                vec![slug("build"), slug("lint")],
+                vec![slug("bundle")],
+            ]
+        );
+    }
+
     #[test]
     fn test_circular_dependency() {
         let mut graph = DependencyGraph::new();
@@ -218,6 +289,28 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_depending_on_disabled_project() {
+        let mut graph = DependencyGraph::new();
+        graph.add_disabled_project(slug("db"));
+        graph.add_project(slug("api"), vec![slug("db")]);
+
+        let result = graph.validate_dependencies();
+        assert!(matches!(
+            result,
+            Err(DependencyGraphError::DisabledDependencies(deps)) if deps == [(slug("api"), slug("db"))]
+        ));
+
+        // Disabling the dependent too leaves nothing to complain about, and neither is started
+        let mut graph = DependencyGraph::new();
+        graph.add_disabled_project(slug("db"));
+        graph.add_disabled_project(slug("api"));
+        graph.add_project(slug("web"), vec![]);
+        assert!(graph.validate_dependencies().is_ok());
+        assert_eq!(graph.resolve_startup_order().unwrap(), [slug("web")]);
+        assert_eq!(graph.resolve_shutdown_order().unwrap(), [slug("web")]);
+    }
+
     #[test]
     fn test_complex_graph() {
         let mut graph = DependencyGraph::new();
@@ -3,21 +3,58 @@ mod dependency;
 mod utils;
 
 use eyre::{Context, eyre};
-use std::path::PathBuf;
+use sha2::{Digest, Sha256};
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
 use crate::{
-    config::Config, project::Project, types::Slug, utils::get_project_dirs,
-    workspace::config::WorkspaceConfig,
+    config::{Config, ensure_writable_version, migrate_version},
+    constants::{LOCAL_WORKSPACE_FILE, WORKSPACE_ENV_VAR},
+    error::DeError,
+    project::Project,
+    types::Slug,
+    utils::{
+        fs::{FileLock, write_atomic},
+        get_project_dirs,
+        ui::UserInterface,
+    },
+    workspace::config::{WORKSPACE_CONFIG_VERSION, WorkspaceConfig},
 };
 
 pub use config::WorkspaceProject;
 pub use dependency::{DependencyGraph, DependencyGraphError};
-pub use utils::{add_project_to_workspace, spin_down_workspace, spin_up_workspace};
+pub use utils::{
+    OnConflict, add_project_to_workspace, hook_failures_result, run_project_hooks,
+    spin_down_workspace, spin_up_workspace,
+};
+
+/// The registered workspaces, as returned by [`Workspace::all`].
+#[derive(Debug, Default)]
+pub struct WorkspaceListing {
+    pub workspaces: Vec<Workspace>,
+    /// Files in the workspaces directory that could not be loaded as a workspace.
+    pub skipped: Vec<SkippedWorkspaceFile>,
+}
+
+/// A file in the workspaces directory that [`Workspace::all`] left out.
+#[derive(Debug)]
+pub struct SkippedWorkspaceFile {
+    pub path: PathBuf,
+    /// Why the file was skipped, e.g. its name is not a valid workspace name or it failed to
+    /// parse.
+    pub reason: String,
+}
 
 #[derive(Debug)]
 pub struct Workspace {
     config: WorkspaceConfig,
     pub config_path: PathBuf,
+    /// For a workspace defined in a repository's `.de/workspace.toml`, the directory holding
+    /// `.de`. Relative directories in the file are relative to it.
+    local_root: Option<PathBuf>,
 }
 
 impl Workspace {
@@ -27,15 +64,19 @@ impl Workspace {
             .wrap_err_with(|| format!("Failed to create path for workspace {name}"))?;
 
         let config = WorkspaceConfig {
+            version: WORKSPACE_CONFIG_VERSION,
             name,
             projects: Default::default(),
             tasks: Default::default(),
             default_branch: Default::default(),
+            root_dir: Default::default(),
+            default_task: Default::default(),
         };
 
         Ok(Self {
             config,
             config_path,
+            local_root: None,
         })
     }
 
@@ -47,11 +88,61 @@ impl Workspace {
         self.config.projects.remove(id);
     }
 
+    /// The IDs of all registered projects, including disabled ones.
+    pub fn project_ids(&self) -> impl Iterator<Item = &Slug> {
+        self.config.projects.keys()
+    }
+
+    /// The registration of the project `id`, if it is part of the workspace.
+    pub fn project(&self, id: &Slug) -> Option<&WorkspaceProject> {
+        self.config.projects.get(id)
+    }
+
+    /// Loads the manifests of all registered projects, including disabled ones. A project that
+    /// fails to load doesn't stop the others from loading; its error takes its place instead.
+    pub fn load_projects(&self) -> Vec<(Slug, eyre::Result<Project>)> {
+        self.config
+            .projects
+            .iter()
+            .map(|(id, ws_project)| {
+                let project = Project::from_dir(&ws_project.dir)
+                    .map_err(|e| eyre!(e))
+                    .wrap_err_with(|| {
+                        format!("Failed to load project from {}", ws_project.dir.display())
+                    });
+                (id.clone(), project)
+            })
+            .collect()
+    }
+
+    /// The registered projects that are not disabled.
+    pub fn enabled_projects(&self) -> impl Iterator<Item = (&Slug, &WorkspaceProject)> {
+        self.config
+            .projects
+            .iter()
+            .filter(|(_, project)| project.enabled)
+    }
+
+    /// The IDs of the registered projects that are disabled.
+    pub fn disabled_project_ids(&self) -> impl Iterator<Item = &Slug> {
+        self.config
+            .projects
+            .iter()
+            .filter(|(_, project)| !project.enabled)
+            .map(|(id, _)| id)
+    }
+
+    /// Loads the enabled projects and their dependency graph. Disabled projects are not loaded,
+    /// and depending on one fails [`DependencyGraph::validate_dependencies`].
     pub fn load_dependency_graph(&self) -> eyre::Result<(DependencyGraph, Vec<Project>)> {
         let mut graph = DependencyGraph::new();
         let mut projects = Vec::new();
 
-        for (id, ws_project) in &self.config.projects {
+        for id in self.disabled_project_ids() {
+            graph.add_disabled_project(id.clone());
+        }
+
+        for (id, ws_project) in self.enabled_projects() {
             let project = Project::from_dir(&ws_project.dir)
                 .map_err(|e| eyre!(e))
                 .wrap_err_with(|| {
@@ -70,7 +161,22 @@ impl Workspace {
         Ok((graph, projects))
     }
 
-    pub fn load_from_name(name: &Slug) -> eyre::Result<Option<Self>> {
+    /// Loads the workspace `name` like [`Workspace::load_from_name`], failing with
+    /// [`DeError::WorkspaceNotFound`] if it doesn't exist.
+    pub fn require(name: &Slug, cwd: &Path) -> eyre::Result<Self> {
+        Self::load_from_name(name, cwd)
+            .map_err(|e| eyre!(e))
+            .wrap_err_with(|| format!("Failed to load workspace '{name}'"))?
+            .ok_or_else(|| eyre!(DeError::WorkspaceNotFound(name.clone())))
+    }
+
+    /// Loads the workspace `name`: the repo-local workspace around the working directory `cwd`
+    /// if it has that name, or else the one in the user's configuration directory.
+    pub fn load_from_name(name: &Slug, cwd: &Path) -> eyre::Result<Option<Self>> {
+        if Self::local_path_named(name, cwd).is_some() {
+            return Self::load_from_dir(cwd);
+        }
+
         let workspace_config_path = Self::path_from_name(name)?;
 
         if !workspace_config_path.exists() {
@@ -80,16 +186,128 @@ impl Workspace {
         Self::load_from_path(workspace_config_path)
     }
 
+    /// The `.de/workspace.toml` around `cwd`, if it defines the workspace `name`. A file that
+    /// can't be read is skipped with a warning, so it doesn't hide the other workspaces.
+    fn local_path_named(name: &Slug, cwd: &Path) -> Option<PathBuf> {
+        let path = cwd
+            .ancestors()
+            .map(|dir| dir.join(LOCAL_WORKSPACE_FILE))
+            .find(|path| path.is_file())?;
+
+        let local_name = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| content.parse::<toml::Table>().map_err(|e| e.to_string()))
+            .map(|table| {
+                table
+                    .get("name")
+                    .and_then(|name| name.as_str())
+                    .map(str::to_string)
+            });
+
+        match local_name {
+            Ok(local_name) => (local_name.as_deref() == Some(name.as_str())).then_some(path),
+            Err(e) => {
+                let _ = UserInterface::new().stderr().warning_item(
+                    &format!(
+                        "Skipping {}: {}",
+                        path.display(),
+                        e.lines().next().unwrap_or_default()
+                    ),
+                    None,
+                );
+                None
+            }
+        }
+    }
+
     pub fn path_from_name(name: &Slug) -> eyre::Result<PathBuf> {
-        let project_dirs = get_project_dirs()?;
+        Ok(Self::workspaces_dir()?.join(format!("{name}.toml")))
+    }
+
+    /// Returns the names of all registered workspaces, sorted.
+    pub fn all_names() -> eyre::Result<Vec<Slug>> {
+        let (names, _skipped) = Self::scan_workspaces_dir()?;
+        Ok(names)
+    }
+
+    /// Loads all registered workspaces, sorted by name, along with any files that were skipped.
+    /// A file that fails to load is skipped rather than failing the whole listing.
+    pub fn all() -> eyre::Result<WorkspaceListing> {
+        Self::all_in(&Self::workspaces_dir()?)
+    }
+
+    fn all_in(workspaces_dir: &Path) -> eyre::Result<WorkspaceListing> {
+        let (names, skipped) = Self::scan_dir(workspaces_dir)?;
+
+        let mut listing = WorkspaceListing {
+            workspaces: Vec::new(),
+            skipped: skipped
+                .into_iter()
+                .map(|path| SkippedWorkspaceFile {
+                    path,
+                    reason: "not a valid workspace name".to_string(),
+                })
+                .collect(),
+        };
+
+        for name in names {
+            let path = workspaces_dir.join(format!("{name}.toml"));
+            match Self::load_from_path(path.clone()) {
+                Ok(Some(workspace)) => listing.workspaces.push(workspace),
+                Ok(None) => {}
+                Err(e) => listing.skipped.push(SkippedWorkspaceFile {
+                    path,
+                    reason: skip_reason(&e),
+                }),
+            }
+        }
+
+        Ok(listing)
+    }
+
+    fn workspaces_dir() -> eyre::Result<PathBuf> {
+        Ok(get_project_dirs()?.config_local_dir().join("workspaces"))
+    }
+
+    /// Lists the workspace names in the workspaces directory, and the `.toml` files whose
+    /// name is not a valid workspace name.
+    fn scan_workspaces_dir() -> eyre::Result<(Vec<Slug>, Vec<PathBuf>)> {
+        Self::scan_dir(&Self::workspaces_dir()?)
+    }
+
+    fn scan_dir(workspaces_dir: &Path) -> eyre::Result<(Vec<Slug>, Vec<PathBuf>)> {
+        if !workspaces_dir.exists() {
+            return Ok((Vec::new(), Vec::new()));
+        }
 
-        let filename = format!("{name}.toml");
-        let path = project_dirs
-            .config_local_dir()
-            .join("workspaces")
-            .join(&filename);
+        let mut names = Vec::new();
+        let mut skipped = Vec::new();
+        for entry in std::fs::read_dir(workspaces_dir)
+            .map_err(|e| eyre!(e))
+            .wrap_err("Failed to read workspaces directory")?
+        {
+            let path = entry
+                .map_err(|e| eyre!(e))
+                .wrap_err("Failed to read workspace directory entry")?
+                .path();
+
+            if path.extension().and_then(|s| s.to_str()) != Some("toml") {
+                continue;
+            }
 
-        Ok(path)
+            match path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .and_then(|s| s.parse::<Slug>().ok())
+            {
+                Some(name) => names.push(name),
+                None => skipped.push(path),
+            }
+        }
+
+        names.sort();
+        skipped.sort();
+        Ok((names, skipped))
     }
 
     pub fn load_from_path(path: PathBuf) -> eyre::Result<Option<Self>> {
@@ -97,16 +315,69 @@ impl Workspace {
             .map_err(|e| eyre!(e))
             .wrap_err_with(|| format!("Failed to read workspace config from {}", path.display()))?;
 
-        let config: WorkspaceConfig = toml::from_str(&config_str)
+        let mut config: WorkspaceConfig = toml::from_str(&config_str)
             .map_err(|e| eyre!(e))
             .wrap_err("Failed to parse workspace config")?;
 
+        migrate_version(&path, &mut config.version, WORKSPACE_CONFIG_VERSION);
+
         Ok(Some(Self {
             config_path: path,
             config,
+            local_root: None,
         }))
     }
 
+    /// Loads the workspace defined in `.de/workspace.toml` of `dir` or the nearest of its
+    /// parents that has one, so a repository can bring its own workspace. Relative project
+    /// directories and `root_dir` in the file are resolved against the directory holding `.de`.
+    pub fn load_from_dir(dir: &Path) -> eyre::Result<Option<Self>> {
+        let Some(path) = dir
+            .ancestors()
+            .map(|dir| dir.join(LOCAL_WORKSPACE_FILE))
+            .find(|path| path.is_file())
+        else {
+            return Ok(None);
+        };
+
+        let root = path
+            .ancestors()
+            .nth(2)
+            .ok_or_else(|| eyre!("{} has no parent directory", path.display()))?
+            .canonicalize()
+            .map_err(|e| eyre!(e))
+            .wrap_err_with(|| format!("Failed to resolve the directory of {}", path.display()))?;
+
+        let Some(mut workspace) = Self::load_from_path(path)? else {
+            return Ok(None);
+        };
+
+        for project in workspace.config.projects.values_mut() {
+            project.dir = resolve_local_dir(&root, &project.dir);
+        }
+        if let Some(root_dir) = workspace.config.root_dir.as_mut() {
+            *root_dir = resolve_local_dir(&root, root_dir);
+        }
+
+        workspace.local_root = Some(root);
+        Ok(Some(workspace))
+    }
+
+    /// Whether the workspace is defined in a repository's `.de/workspace.toml`, rather than
+    /// in the user's configuration directory.
+    pub fn is_local(&self) -> bool {
+        self.local_root.is_some()
+    }
+
+    /// Loads the workspace again from its configuration file, e.g. to pick up changes another
+    /// `de` process made.
+    pub fn reload(&self) -> eyre::Result<Option<Self>> {
+        match &self.local_root {
+            Some(root) => Self::load_from_dir(root),
+            None => Self::load_from_path(self.config_path.clone()),
+        }
+    }
+
     pub fn config(&self) -> &WorkspaceConfig {
         &self.config
     }
@@ -115,7 +386,63 @@ impl Workspace {
         &mut self.config
     }
 
+    /// Takes the lock on the configuration file of the workspace `name`, as found from `cwd` by
+    /// [`Workspace::load_from_name`], until the guard is dropped. Hold it while loading, changing
+    /// and saving a workspace, so concurrent `de` processes don't overwrite each other's changes.
+    pub fn lock(name: &Slug, cwd: &Path) -> eyre::Result<FileLock> {
+        match Self::local_path_named(name, cwd) {
+            Some(path) => Self::lock_path(&local_lock_path(&path)?, name),
+            None => Self::lock_path(&Self::path_from_name(name)?, name),
+        }
+    }
+
+    /// Like [`Workspace::lock`], for the file this workspace was loaded from.
+    pub fn lock_config(&self) -> eyre::Result<FileLock> {
+        if self.is_local() {
+            Self::lock_path(&local_lock_path(&self.config_path)?, &self.config.name)
+        } else {
+            Self::lock_path(&self.config_path, &self.config.name)
+        }
+    }
+
+    fn lock_path(path: &Path, name: &Slug) -> eyre::Result<FileLock> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| eyre!(e))
+                .wrap_err_with(|| {
+                    format!("Failed to create parent directory for {}", path.display())
+                })?;
+        }
+
+        FileLock::acquire(path).wrap_err_with(|| format!("Failed to lock workspace {name}"))
+    }
+
+    /// Applies `edit` to the latest version of the workspace on disk and saves it, holding the
+    /// workspace lock throughout, so concurrent `de` processes don't overwrite each other's
+    /// changes. The workspace is then replaced with the saved version.
+    pub fn edit<T>(
+        &mut self,
+        edit: impl FnOnce(&mut Workspace) -> eyre::Result<T>,
+    ) -> eyre::Result<T> {
+        let _lock = self.lock_config()?;
+        let mut latest = self
+            .reload()?
+            .ok_or_else(|| eyre!(DeError::WorkspaceNotFound(self.config.name.clone())))?;
+
+        let result = edit(&mut latest)?;
+        latest.save()?;
+
+        *self = latest;
+        Ok(result)
+    }
+
     pub fn save(&self) -> eyre::Result<()> {
+        ensure_writable_version(
+            &self.config_path,
+            self.config.version,
+            WORKSPACE_CONFIG_VERSION,
+        )?;
+
         // Ensure the parent directory exists
         if let Some(parent) = self.config_path.parent() {
             std::fs::create_dir_all(parent)
@@ -128,11 +455,26 @@ impl Workspace {
                 })?;
         }
 
-        let config_str = toml::to_string_pretty(&self.config)
+        // Keep the directories of a repo-local workspace relative, so the file can be shared
+        let config = match &self.local_root {
+            Some(root) => {
+                let mut config = self.config.clone();
+                for project in config.projects.values_mut() {
+                    project.dir = relative_local_dir(root, &project.dir);
+                }
+                if let Some(root_dir) = config.root_dir.as_mut() {
+                    *root_dir = relative_local_dir(root, root_dir);
+                }
+                Cow::Owned(config)
+            }
+            None => Cow::Borrowed(&self.config),
+        };
+
+        let config_str = toml::to_string_pretty(config.as_ref())
             .map_err(|e| eyre!(e))
             .wrap_err("Failed to format workspace config as string")?;
 
-        std::fs::write(&self.config_path, config_str)
+        write_atomic(&self.config_path, config_str)
             .map_err(|e| eyre!(e))
             .wrap_err_with(|| {
                 format!(
@@ -146,8 +488,14 @@ impl Workspace {
 }
 
 impl Workspace {
-    pub fn current() -> eyre::Result<Option<Self>> {
-        let project = Project::current()
+    /// The workspace of the working directory `cwd`: a repo-local workspace around it, or else
+    /// the workspace of the project it belongs to.
+    pub fn current(cwd: &Path) -> eyre::Result<Option<Self>> {
+        if let Some(workspace) = Self::load_from_dir(cwd)? {
+            return Ok(Some(workspace));
+        }
+
+        let project = Project::from_dir_recursive(cwd)
             .map_err(|e| eyre!(e))
             .wrap_err("Failed to load current project")?;
 
@@ -156,15 +504,30 @@ impl Workspace {
         };
 
         let workspace_name = project.manifest().project().workspace.clone();
-        let workspace = Self::load_from_name(&workspace_name)
+        let workspace = Self::require(&workspace_name, cwd)?;
+
+        Ok(Some(workspace))
+    }
+
+    /// Loads the workspace named by `DE_WORKSPACE`, if the variable is set.
+    pub fn from_env(cwd: &Path) -> eyre::Result<Option<Self>> {
+        let Ok(value) = std::env::var(WORKSPACE_ENV_VAR) else {
+            return Ok(None);
+        };
+
+        let workspace_name = Slug::from_str(&value)
             .map_err(|e| eyre!(e))
-            .wrap_err_with(|| format!("Failed to load workspace {workspace_name}"))?
-            .ok_or_else(|| eyre!("Workspace {} not found", workspace_name))?;
+            .wrap_err_with(|| {
+                format!("Invalid workspace name in {WORKSPACE_ENV_VAR}: '{value}'")
+            })?;
+
+        let workspace = Self::require(&workspace_name, cwd)
+            .wrap_err_with(|| format!("Failed to load the workspace set in {WORKSPACE_ENV_VAR}"))?;
 
         Ok(Some(workspace))
     }
 
-    pub fn working() -> eyre::Result<Option<Self>> {
+    pub fn working(cwd: &Path) -> eyre::Result<Option<Self>> {
         let app_config = Config::load()
             .map_err(|e| eyre!(e))
             .wrap_err("Failed to load application config")?;
@@ -173,16 +536,35 @@ impl Workspace {
             return Ok(None);
         };
 
-        let workspace = Self::load_from_name(workspace_name)
+        let workspace = Self::load_from_name(workspace_name, cwd)
             .map_err(|e| eyre!(e))
             .wrap_err_with(|| format!("Failed to load workspace {workspace_name}"))?;
 
         Ok(workspace)
     }
 
-    pub fn active() -> eyre::Result<Option<Self>> {
-        // Try to get the current workspace from the environment
-        let current_workspace = Self::current()
+    /// The workspace `name`, or without one the [active](Workspace::active) workspace.
+    pub fn named_or_active(name: Option<&Slug>, cwd: &Path) -> eyre::Result<Option<Self>> {
+        match name {
+            Some(name) => Self::require(name, cwd).map(Some),
+            None => Self::active(cwd),
+        }
+    }
+
+    /// The [active](Workspace::active) workspace, failing with [`DeError::NoActiveWorkspace`]
+    /// if there is none.
+    pub fn require_active(cwd: &Path) -> eyre::Result<Self> {
+        Self::active(cwd)
+            .map_err(|e| eyre!(e))
+            .wrap_err("Failed to get active workspace")?
+            .ok_or_else(|| eyre!(DeError::NoActiveWorkspace))
+    }
+
+    /// The workspace commands run in from the working directory `cwd`: its
+    /// [current](Workspace::current) workspace, the one named by `DE_WORKSPACE`, or the active
+    /// workspace of the config.
+    pub fn active(cwd: &Path) -> eyre::Result<Option<Self>> {
+        let current_workspace = Self::current(cwd)
             .map_err(|e| eyre!(e))
             .wrap_err("Failed to get current workspace")?;
 
@@ -190,8 +572,17 @@ impl Workspace {
             return Ok(Some(workspace));
         }
 
+        // Then the workspace named in the environment, which doesn't need persisted config
+        let env_workspace = Self::from_env(cwd)
+            .map_err(|e| eyre!(e))
+            .wrap_err("Failed to get workspace from environment")?;
+
+        if let Some(workspace) = env_workspace {
+            return Ok(Some(workspace));
+        }
+
         // If no current workspace, try to load the workspace from the config
-        let working_workspace = Self::working()
+        let working_workspace = Self::working(cwd)
             .map_err(|e| eyre!(e))
             .wrap_err("Failed to get working workspace")?;
 
@@ -202,3 +593,249 @@ impl Workspace {
         Ok(None)
     }
 }
+
+/// A one-line reason for a workspace file that failed to load. TOML errors quote the
+/// offending line, which doesn't fit on the line listing the file.
+fn skip_reason(error: &eyre::Report) -> String {
+    match error.root_cause().downcast_ref::<toml::de::Error>() {
+        Some(toml_error) => format!("invalid TOML: {}", toml_error.message()),
+        None => format!("{error:#}"),
+    }
+}
+
+/// The file locked for the repo-local workspace at `path`: one in the user's cache directory,
+/// keyed by the path, so no lock file shows up in the repository.
+fn local_lock_path(path: &Path) -> eyre::Result<PathBuf> {
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let key = format!("{:x}", Sha256::digest(path.to_string_lossy().as_bytes()));
+
+    Ok(get_project_dirs()?
+        .cache_dir()
+        .join("locks")
+        .join(format!("{}.toml", &key[..16])))
+}
+
+/// Resolves a directory from a repo-local workspace file against the directory holding `.de`.
+fn resolve_local_dir(root: &Path, dir: &Path) -> PathBuf {
+    if dir == Path::new(".") {
+        root.to_path_buf()
+    } else {
+        root.join(dir)
+    }
+}
+
+/// The directory as written to a repo-local workspace file: relative if it is inside the
+/// directory holding `.de`.
+fn relative_local_dir(root: &Path, dir: &Path) -> PathBuf {
+    match dir.strip_prefix(root) {
+        Ok(relative) if relative.as_os_str().is_empty() => PathBuf::from("."),
+        Ok(relative) => relative.to_path_buf(),
+        Err(_) => dir.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_projects_tolerates_failures() {
+        let dir = tempfile::tempdir().unwrap();
+        let api = dir.path().join("api");
+        let broken = dir.path().join("broken");
+        std::fs::create_dir(&api).unwrap();
+        std::fs::create_dir(&broken).unwrap();
+        std::fs::write(
+            api.join("de.toml"),
+            "[project]\nname = \"api\"\nworkspace = \"test\"\n",
+        )
+        .unwrap();
+
+        let slug = |name: &str| Slug::from_str(name).unwrap();
+        let mut workspace = Workspace::new(slug("test")).unwrap();
+        workspace.add_project(slug("api"), WorkspaceProject::new(api).unwrap());
+        assert!(WorkspaceProject::new(broken.clone()).is_err());
+        workspace.add_project(slug("broken"), WorkspaceProject::new_unchecked(broken));
+
+        assert_eq!(
+            workspace.project_ids().collect::<Vec<_>>(),
+            [&slug("api"), &slug("broken")]
+        );
+        assert!(workspace.project(&slug("api")).is_some());
+        assert!(workspace.project(&slug("web")).is_none());
+
+        let loaded = workspace.load_projects();
+        assert_eq!(loaded.len(), 2);
+        assert!(loaded[0].1.is_ok());
+        assert!(loaded[1].1.is_err());
+    }
+
+    #[test]
+    fn test_all_skips_files_that_fail_to_load() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("good.toml"),
+            "name = \"good\"\n[projects]\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("broken.toml"), "name = [\n").unwrap();
+        std::fs::write(dir.path().join("Not Valid.toml"), "name = \"x\"\n").unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "").unwrap();
+
+        let listing = Workspace::all_in(dir.path()).unwrap();
+
+        assert_eq!(
+            listing
+                .workspaces
+                .iter()
+                .map(|workspace| workspace.config().name.as_str())
+                .collect::<Vec<_>>(),
+            ["good"]
+        );
+        let mut skipped = listing
+            .skipped
+            .iter()
+            .map(|skipped| skipped.path.file_name().unwrap().to_str().unwrap())
+            .collect::<Vec<_>>();
+        skipped.sort();
+        assert_eq!(skipped, ["Not Valid.toml", "broken.toml"]);
+        assert!(
+            listing
+                .skipped
+                .iter()
+                .any(|skipped| skipped.reason.starts_with("invalid TOML: "))
+        );
+    }
+
+    #[test]
+    fn test_local_workspace_keeps_dirs_relative_to_the_repository() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir_all(root.join(".de")).unwrap();
+        std::fs::create_dir_all(root.join("services/api/src")).unwrap();
+        std::fs::write(
+            root.join(LOCAL_WORKSPACE_FILE),
+            "name = \"mono\"\n\n[projects.api]\ndir = \"services/api\"\n\n[projects.root]\ndir = \".\"\n",
+        )
+        .unwrap();
+
+        let workspace = Workspace::load_from_dir(&root.join("services/api/src"))
+            .unwrap()
+            .unwrap();
+        assert!(workspace.is_local());
+
+        let slug = |name: &str| Slug::from_str(name).unwrap();
+        assert_eq!(
+            workspace.project(&slug("api")).unwrap().dir,
+            root.join("services/api")
+        );
+        assert_eq!(workspace.project(&slug("root")).unwrap().dir, root);
+
+        workspace.save().unwrap();
+        let saved = std::fs::read_to_string(root.join(LOCAL_WORKSPACE_FILE)).unwrap();
+        assert!(saved.contains("dir = \"services/api\""));
+        assert!(saved.contains("dir = \".\""));
+
+        assert!(
+            Workspace::load_from_dir(root.parent().unwrap())
+                .unwrap()
+                .is_none_or(|workspace| workspace.config_path != root.join(LOCAL_WORKSPACE_FILE))
+        );
+    }
+
+    #[test]
+    fn test_local_workspace_only_answers_to_its_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir_all(root.join(".de")).unwrap();
+        let slug = |name: &str| Slug::from_str(name).unwrap();
+
+        std::fs::write(root.join(LOCAL_WORKSPACE_FILE), "name = \"mono\"\n").unwrap();
+        assert_eq!(
+            Workspace::local_path_named(&slug("mono"), &root),
+            Some(root.join(LOCAL_WORKSPACE_FILE))
+        );
+        assert_eq!(Workspace::local_path_named(&slug("other"), &root), None);
+
+        // A broken local file doesn't get in the way of other workspaces
+        std::fs::write(root.join(LOCAL_WORKSPACE_FILE), "name = ").unwrap();
+        assert_eq!(Workspace::local_path_named(&slug("mono"), &root), None);
+    }
+
+    #[test]
+    fn test_edit_applies_to_the_saved_workspace() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ws.toml");
+        std::fs::write(&path, "name = \"ws\"\n\n[projects]\n").unwrap();
+        let mut workspace = Workspace::load_from_path(path.clone()).unwrap().unwrap();
+
+        // Another process adds a task after this one loaded the workspace
+        std::fs::write(
+            &path,
+            "name = \"ws\"\n\n[projects]\n\n[tasks]\nbuild = \"make\"\n",
+        )
+        .unwrap();
+
+        workspace
+            .edit(|workspace| {
+                workspace
+                    .config_mut()
+                    .tasks
+                    .insert(Slug::from_str("test").unwrap(), "make test".to_string());
+                Ok(())
+            })
+            .unwrap();
+
+        let saved = Workspace::load_from_path(path).unwrap().unwrap();
+        assert_eq!(saved.config().tasks.len(), 2);
+        assert_eq!(workspace.config().tasks.len(), 2);
+    }
+
+    #[test]
+    fn test_load_migrates_unversioned_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("old.toml");
+        std::fs::write(
+            &path,
+            "name = \"old\"\n\n[projects.api]\ndir = \"/src/api\"\n",
+        )
+        .unwrap();
+
+        let workspace = Workspace::load_from_path(path.clone()).unwrap().unwrap();
+        assert_eq!(workspace.config().version, WORKSPACE_CONFIG_VERSION);
+        assert!(workspace.config().tasks.is_empty());
+        assert!(workspace.config().root_dir.is_none());
+        assert!(
+            workspace
+                .config()
+                .projects
+                .values()
+                .all(|project| project.enabled)
+        );
+
+        workspace.save().unwrap();
+        assert!(
+            std::fs::read_to_string(&path)
+                .unwrap()
+                .contains(&format!("version = {WORKSPACE_CONFIG_VERSION}"))
+        );
+    }
+
+    #[test]
+    fn test_newer_config_is_loaded_but_not_saved() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("new.toml");
+        let contents = format!(
+            "version = {}\nname = \"new\"\nfuture = true\n\n[projects]\n",
+            WORKSPACE_CONFIG_VERSION + 1
+        );
+        std::fs::write(&path, &contents).unwrap();
+
+        let workspace = Workspace::load_from_path(path.clone()).unwrap().unwrap();
+        assert_eq!(workspace.config().version, WORKSPACE_CONFIG_VERSION + 1);
+
+        let error = workspace.save().unwrap_err();
+        assert!(error.to_string().contains("newer version of de"), "{error}");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), contents);
+    }
+}
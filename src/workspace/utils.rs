@@ -1,18 +1,36 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use crate::{
+    project::{Project, TaskContext, config::ProjectHook, run_project_task},
     types::Slug,
+    utils::ui::UserInterface,
     workspace::{Workspace, config::WorkspaceProject},
 };
 use eyre::{Context, eyre};
 
+/// What to do when a project ID is already registered with a different directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum OnConflict {
+    /// Fail and leave the existing registration alone.
+    #[default]
+    Error,
+    /// Point the registration at the new directory (e.g. after the project was moved).
+    Update,
+    /// Keep the existing registration without failing.
+    Skip,
+}
+
 pub fn add_project_to_workspace(
     workspace_name: Slug,
     project_id: Slug,
     project_dir: PathBuf,
+    on_conflict: OnConflict,
+    cwd: &Path,
 ) -> eyre::Result<()> {
+    let _lock = Workspace::lock(&workspace_name, cwd)?;
+
     let mut workspace = if let Some(workspace) =
-        Workspace::load_from_name(&workspace_name).map_err(|e| eyre!(e))?
+        Workspace::load_from_name(&workspace_name, cwd).map_err(|e| eyre!(e))?
     {
         workspace
     } else {
@@ -22,19 +40,24 @@ pub fn add_project_to_workspace(
     let project = WorkspaceProject::new(project_dir)
         .map_err(|e| eyre!(e))
         .wrap_err("Failed to load workspace project")?;
+    let new_dir = project.dir.clone();
 
-    if let Some(existing_project) = workspace.config().projects.get(&project_id)
-        && existing_project.dir != project.dir
-    {
-        return Err(eyre!(
-            "Project ID '{}' already exists with a different directory: {}",
-            project_id,
-            existing_project.dir.display()
-        ));
+    match register_project(&mut workspace, project_id.clone(), project, on_conflict)? {
+        Registration::Skipped => return Ok(()),
+        Registration::Added => {}
+        Registration::Moved { previous_dir } => {
+            UserInterface::new().warning_item(
+                &format!(
+                    "Project '{}' was registered at {}; it now points at {}",
+                    project_id,
+                    previous_dir.display(),
+                    new_dir.display()
+                ),
+                None,
+            )?;
+        }
     }
 
-    workspace.add_project(project_id, project);
-
     workspace
         .save()
         .map_err(|e| eyre!(e))
@@ -43,7 +66,104 @@ pub fn add_project_to_workspace(
     Ok(())
 }
 
-pub fn spin_up_workspace(workspace: &Workspace) -> eyre::Result<()> {
+/// The outcome of registering a project in a workspace.
+#[derive(Debug, PartialEq, Eq)]
+enum Registration {
+    /// The project was new, or already registered with the same directory.
+    Added,
+    /// The project ID was registered with another directory and now points at the new one.
+    Moved { previous_dir: PathBuf },
+    /// The project ID was registered with another directory, which was kept.
+    Skipped,
+}
+
+fn register_project(
+    workspace: &mut Workspace,
+    project_id: Slug,
+    project: WorkspaceProject,
+    on_conflict: OnConflict,
+) -> eyre::Result<Registration> {
+    let mut registration = Registration::Added;
+
+    if let Some(existing_project) = workspace.project(&project_id)
+        && existing_project.dir != project.dir
+    {
+        match on_conflict {
+            OnConflict::Error => {
+                return Err(eyre!(
+                    "Project ID '{}' already exists with a different directory: {} (run `de update` from the project to point it at the new location)",
+                    project_id,
+                    existing_project.dir.display()
+                ));
+            }
+            OnConflict::Skip => return Ok(Registration::Skipped),
+            OnConflict::Update => {
+                registration = Registration::Moved {
+                    previous_dir: existing_project.dir.clone(),
+                };
+            }
+        }
+    }
+
+    workspace.add_project(project_id, project);
+
+    Ok(registration)
+}
+
+/// Runs the tasks registered for a lifecycle hook of the project, in order.
+pub fn run_project_hooks(project: &Project, hook: ProjectHook) -> eyre::Result<()> {
+    let Some(hooks) = project.manifest().project().hooks.as_ref() else {
+        return Ok(());
+    };
+
+    // The project is only partly up while its hooks run
+    let context = TaskContext {
+        skip_service_check: true,
+        ..Default::default()
+    };
+
+    for task_name in hooks.tasks(hook) {
+        let found = run_project_task(project, task_name, &Vec::new(), None, &context)
+            .wrap_err_with(|| format!("{} hook '{}' failed", hook.as_str(), task_name))?;
+
+        if !found {
+            return Err(eyre!(
+                "{} hook references unknown task '{}'",
+                hook.as_str(),
+                task_name
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns an error listing the projects whose hooks failed, if any.
+pub fn hook_failures_result(failed_projects: &[Slug]) -> eyre::Result<()> {
+    if failed_projects.is_empty() {
+        return Ok(());
+    }
+
+    let names = failed_projects
+        .iter()
+        .map(Slug::as_str)
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    Err(eyre!("Hooks failed for projects: {}", names))
+}
+
+fn print_skipped_disabled_projects(ui: &UserInterface, workspace: &Workspace) -> eyre::Result<()> {
+    for project_id in workspace.disabled_project_ids() {
+        ui.writeln(
+            &ui.theme
+                .dim(&format!("Skipping project {project_id} (disabled)")),
+        )?;
+    }
+    Ok(())
+}
+
+pub fn spin_up_workspace(ui: &UserInterface, workspace: &Workspace) -> eyre::Result<()> {
     let (dependency_graph, projects) = workspace
         .load_dependency_graph()
         .map_err(|e| eyre!(e))
@@ -64,38 +184,53 @@ pub fn spin_up_workspace(workspace: &Workspace) -> eyre::Result<()> {
         .resolve_startup_order()
         .wrap_err("Failed to resolve project startup order")?;
 
+    print_skipped_disabled_projects(ui, workspace)?;
+
     let mut applied_projects = Vec::new();
+    let mut failed_projects = Vec::new();
 
     // Start projects in dependency order
     for project_id in startup_order {
         if let Some(project) = projects_map.get(&project_id) {
-            println!("Spinning up project {project_id}:");
+            ui.writeln(&ui.theme.bold(&format!("Spinning up project {project_id}:")))?;
 
-            let applied = project
-                .docker_compose_up()
-                .map_err(|e| eyre!(e))
-                .wrap_err_with(|| {
-                    format!(
-                        "Failed to spin up project {} in workspace {}",
-                        project_id,
-                        workspace.config().name
-                    )
-                })?;
+            if let Err(err) = run_project_hooks(project, ProjectHook::BeforeStart) {
+                ui.error_item(&format!("{err:#}"), None)?;
+                failed_projects.push(project_id);
+                continue;
+            }
+
+            let applied = project.start().map_err(|e| eyre!(e)).wrap_err_with(|| {
+                format!(
+                    "Failed to spin up project {} in workspace {}",
+                    project_id,
+                    workspace.config().name
+                )
+            })?;
 
             if applied {
                 applied_projects.push(project);
+
+                if let Err(err) = run_project_hooks(project, ProjectHook::AfterStart) {
+                    ui.error_item(&format!("{err:#}"), None)?;
+                    failed_projects.push(project_id);
+                }
             }
         }
     }
 
-    if applied_projects.is_empty() {
-        println!("- (No projects to spin up)");
+    if applied_projects.is_empty() && failed_projects.is_empty() {
+        ui.warning_item("No projects to spin up", None)?;
     }
 
-    Ok(())
+    hook_failures_result(&failed_projects)
 }
 
-pub fn spin_down_workspace(workspace: &Workspace) -> eyre::Result<()> {
+pub fn spin_down_workspace(
+    ui: &UserInterface,
+    workspace: &Workspace,
+    timeout: Option<u64>,
+) -> eyre::Result<()> {
     let (dependency_graph, projects) = workspace
         .load_dependency_graph()
         .map_err(|e| eyre!(e))
@@ -116,15 +251,27 @@ pub fn spin_down_workspace(workspace: &Workspace) -> eyre::Result<()> {
         .resolve_shutdown_order()
         .wrap_err("Failed to resolve project shutdown order")?;
 
+    print_skipped_disabled_projects(ui, workspace)?;
+
     let mut applied_projects = Vec::new();
+    let mut failed_projects = Vec::new();
 
     // Stop projects in reverse dependency order
     for project_id in shutdown_order {
         if let Some(project) = projects_map.get(&project_id) {
-            println!("Spinning down project {project_id}:");
+            ui.writeln(
+                &ui.theme
+                    .bold(&format!("Spinning down project {project_id}:")),
+            )?;
+
+            // Still stop the project when a before_stop hook fails, so shutdown is never blocked.
+            if let Err(err) = run_project_hooks(project, ProjectHook::BeforeStop) {
+                ui.error_item(&format!("{err:#}"), None)?;
+                failed_projects.push(project_id.clone());
+            }
 
             let applied = project
-                .docker_compose_down()
+                .stop(timeout)
                 .map_err(|e| eyre!(e))
                 .wrap_err_with(|| {
                     format!(
@@ -141,8 +288,50 @@ pub fn spin_down_workspace(workspace: &Workspace) -> eyre::Result<()> {
     }
 
     if applied_projects.is_empty() {
-        println!("- (No projects to spin down)");
+        ui.warning_item("No projects to spin down", None)?;
     }
 
-    Ok(())
+    hook_failures_result(&failed_projects)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_register_project_conflicts() {
+        let slug = |name: &str| Slug::from_str(name).unwrap();
+        let old_dir = PathBuf::from("/projects/old/api");
+        let new_dir = PathBuf::from("/projects/new/api");
+
+        let mut workspace = Workspace::new(slug("test")).unwrap();
+        let registration = register_project(
+            &mut workspace,
+            slug("api"),
+            WorkspaceProject::new_unchecked(old_dir.clone()),
+            OnConflict::Error,
+        )
+        .unwrap();
+        assert_eq!(registration, Registration::Added);
+
+        let mut moved = |on_conflict| {
+            register_project(
+                &mut workspace,
+                slug("api"),
+                WorkspaceProject::new_unchecked(new_dir.clone()),
+                on_conflict,
+            )
+        };
+        assert!(moved(OnConflict::Error).is_err());
+        assert_eq!(moved(OnConflict::Skip).unwrap(), Registration::Skipped);
+        assert_eq!(
+            moved(OnConflict::Update).unwrap(),
+            Registration::Moved {
+                previous_dir: old_dir.clone()
+            }
+        );
+        assert_eq!(workspace.project(&slug("api")).unwrap().dir, new_dir);
+    }
 }
@@ -0,0 +1,94 @@
+mod common;
+
+use std::path::Path;
+
+use common::{Sandbox, git};
+
+/// Commits a change to `main` of the project without pushing it, returning the new HEAD.
+fn commit_unpushed(dir: &Path) -> String {
+    std::fs::write(dir.join("local.txt"), "not pushed yet\n").unwrap();
+    git(dir, &["add", "-A"]);
+    git(dir, &["commit", "-qm", "local"]);
+    git(dir, &["rev-parse", "HEAD"])
+}
+
+fn base_reset(sandbox: &Sandbox, args: &[&str]) -> String {
+    let mut command = vec!["git", "base-reset", "--workspace", "ws", "main"];
+    command.extend_from_slice(args);
+    let output = sandbox.run(&command);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_on_unpushed_decides_about_unpushed_commits_without_a_prompt() {
+    let sandbox = Sandbox::new();
+    let api = sandbox.add_git_project("api", "ws");
+    sandbox.add_workspace("ws", &[("api", &api)]);
+    let pushed = git(&api, &["rev-parse", "HEAD"]);
+    let local = commit_unpushed(&api);
+
+    // Unpushed commits abort by default once `--on-dirty` doesn't prompt
+    let output = base_reset(&sandbox, &["--on-dirty", "force"]);
+    assert!(output.contains("Aborting: unpushed commits."), "{output}");
+    assert_eq!(git(&api, &["rev-parse", "HEAD"]), local);
+
+    let output = base_reset(&sandbox, &["--on-dirty", "force", "--on-unpushed", "skip"]);
+    assert!(
+        output.contains("Skipping project with unpushed commits."),
+        "{output}"
+    );
+    assert_eq!(git(&api, &["rev-parse", "HEAD"]), local);
+
+    // Proceeding resets to the remote and drops the local commit
+    base_reset(
+        &sandbox,
+        &["--on-dirty", "force", "--on-unpushed", "proceed"],
+    );
+    assert_eq!(git(&api, &["rev-parse", "HEAD"]), pushed);
+
+    let local = commit_unpushed(&api);
+    base_reset(&sandbox, &["--on-dirty", "force", "--on-unpushed", "push"]);
+    assert_eq!(git(&api, &["rev-parse", "HEAD"]), local);
+    assert_eq!(git(&api, &["rev-parse", "origin/main"]), local);
+}
+
+#[test]
+fn test_unpushed_commits_fail_without_a_terminal_or_policy() {
+    let sandbox = Sandbox::new();
+    let api = sandbox.add_git_project("api", "ws");
+    sandbox.add_workspace("ws", &[("api", &api)]);
+    let local = commit_unpushed(&api);
+
+    let output = sandbox.output(&["git", "base-reset", "--workspace", "ws", "main"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Re-run with --on-unpushed"), "{stderr}");
+    assert_eq!(git(&api, &["rev-parse", "HEAD"]), local);
+}
+
+#[test]
+fn test_failed_push_keeps_the_unpushed_commits() {
+    let sandbox = Sandbox::new();
+    let api = sandbox.add_git_project("api", "ws");
+    sandbox.add_workspace("ws", &[("api", &api)]);
+    let local = commit_unpushed(&api);
+
+    // Without its remote, the push fails
+    std::fs::remove_dir_all(sandbox.path().join("api.git")).unwrap();
+
+    let output = sandbox.output(&[
+        "git",
+        "base-reset",
+        "--workspace",
+        "ws",
+        "main",
+        "--on-dirty",
+        "force",
+        "--on-unpushed",
+        "push",
+    ]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("PUSH FAILED"), "{stdout}");
+    assert!(!stdout.contains("Ready for"), "{stdout}");
+    assert_eq!(git(&api, &["rev-parse", "HEAD"]), local);
+}
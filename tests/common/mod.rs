@@ -0,0 +1,142 @@
+// The commands run by the tests are not de's, so they don't go through its log
+#![allow(clippy::disallowed_methods, dead_code)]
+
+use std::{
+    path::{Path, PathBuf},
+    process::{Command, Output, Stdio},
+};
+
+use tempfile::TempDir;
+
+/// Runs git in `dir`, failing the test if it fails, and returns its trimmed stdout.
+pub fn git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .args(["-c", "user.email=de@example.com", "-c", "user.name=de"])
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "git {args:?} failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap().trim().to_string()
+}
+
+/// A temporary home directory for de, with its config and data kept inside it, and a git
+/// identity for the commits de makes.
+pub struct Sandbox {
+    pub dir: TempDir,
+    pub home: PathBuf,
+}
+
+impl Sandbox {
+    pub fn new() -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        let home = dir.path().join("home");
+        std::fs::create_dir_all(home.join(".config/de/workspaces")).unwrap();
+        std::fs::write(
+            home.join(".gitconfig"),
+            "[user]\n\tname = de\n\temail = de@example.com\n",
+        )
+        .unwrap();
+        Self { dir, home }
+    }
+
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Registers a workspace with the given projects, by name and directory.
+    pub fn add_workspace(&self, name: &str, projects: &[(&str, &Path)]) {
+        let mut config = format!("name = \"{name}\"\n\n[projects]\n");
+        for (project, dir) in projects {
+            config.push_str(&format!("{project} = {{ dir = \"{}\" }}\n", dir.display()));
+        }
+        std::fs::write(
+            self.home.join(format!(".config/de/workspaces/{name}.toml")),
+            config,
+        )
+        .unwrap();
+    }
+
+    /// A `de` command run in the sandbox, without a terminal on stdin.
+    pub fn de(&self, args: &[&str]) -> Command {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_de"));
+        command
+            .args(args)
+            .env("HOME", &self.home)
+            .env_remove("XDG_CONFIG_HOME")
+            .env_remove("XDG_CACHE_HOME")
+            .env_remove("XDG_DATA_HOME")
+            .env_remove("DE_WORKSPACE")
+            .stdin(Stdio::null())
+            .current_dir(self.path());
+        command
+    }
+
+    /// Runs `de` and returns its output, whether it succeeded or not.
+    pub fn output(&self, args: &[&str]) -> Output {
+        self.de(args).output().unwrap()
+    }
+
+    /// Runs `de` and fails the test unless it succeeds.
+    pub fn run(&self, args: &[&str]) -> Output {
+        let output = self.output(args);
+        assert!(
+            output.status.success(),
+            "de {args:?} failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        output
+    }
+
+    /// Runs `de` in `dir` and fails the test unless it succeeds.
+    pub fn run_in(&self, dir: &Path, args: &[&str]) -> Output {
+        let output = self.de(args).current_dir(dir).output().unwrap();
+        assert!(
+            output.status.success(),
+            "de {args:?} failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        output
+    }
+
+    /// Creates a project named `name` with a bare `origin` holding one pushed commit on `main`.
+    pub fn add_git_project(&self, name: &str, workspace: &str) -> PathBuf {
+        let origin = self.path().join(format!("{name}.git"));
+        let dir = self.path().join(name);
+        git(
+            self.path(),
+            &[
+                "init",
+                "-q",
+                "--bare",
+                "-b",
+                "main",
+                origin.to_str().unwrap(),
+            ],
+        );
+        git(
+            self.path(),
+            &[
+                "clone",
+                "-q",
+                origin.to_str().unwrap(),
+                dir.to_str().unwrap(),
+            ],
+        );
+        git(&dir, &["checkout", "-q", "-B", "main"]);
+
+        std::fs::write(
+            dir.join("de.toml"),
+            format!("[project]\nname = \"{name}\"\nworkspace = \"{workspace}\"\n"),
+        )
+        .unwrap();
+        git(&dir, &["add", "-A"]);
+        git(&dir, &["commit", "-qm", "init"]);
+        git(&dir, &["push", "-q", "-u", "origin", "main"]);
+        dir
+    }
+}
@@ -0,0 +1,16 @@
+mod common;
+
+use common::Sandbox;
+
+#[test]
+fn test_list_exits_with_the_code_of_its_error() {
+    let sandbox = Sandbox::new();
+
+    let output = sandbox.output(&["list", "--workspace", "nope"]);
+    assert_eq!(output.status.code(), Some(5));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Workspace 'nope' not found"));
+
+    // Outside a project, with no active workspace to fall back on
+    let output = sandbox.output(&["list"]);
+    assert_eq!(output.status.code(), Some(2));
+}
@@ -0,0 +1,51 @@
+mod common;
+
+use common::{Sandbox, git};
+
+fn init_git(sandbox: &Sandbox, dir: &std::path::Path) {
+    sandbox.run(&[
+        "init",
+        dir.to_str().unwrap(),
+        "--workspace",
+        "ws",
+        "--name",
+        "api",
+        "--git",
+    ]);
+}
+
+#[test]
+fn test_init_git_commits_the_manifest_and_gitignore() {
+    let sandbox = Sandbox::new();
+    let dir = sandbox.path().join("api");
+    std::fs::create_dir(&dir).unwrap();
+    std::fs::write(dir.join(".gitignore"), "target").unwrap();
+
+    init_git(&sandbox, &dir);
+
+    assert_eq!(git(&dir, &["log", "--format=%s"]), "Initial commit");
+    assert_eq!(
+        git(&dir, &["ls-files"]).lines().collect::<Vec<_>>(),
+        [".gitignore", "de.toml"]
+    );
+    // What was already ignored stays, with the secrets added
+    assert_eq!(
+        std::fs::read_to_string(dir.join(".gitignore")).unwrap(),
+        "target\n.env\n.de/config.toml\n"
+    );
+}
+
+#[test]
+fn test_init_git_leaves_an_enclosing_repository_alone() {
+    let sandbox = Sandbox::new();
+    let repo = sandbox.path().join("mono");
+    let dir = repo.join("services/api");
+    std::fs::create_dir_all(&dir).unwrap();
+    git(&repo, &["init", "-q"]);
+
+    init_git(&sandbox, &dir);
+
+    assert!(!dir.join(".git").exists());
+    assert!(!dir.join(".gitignore").exists());
+    assert!(git(&repo, &["log", "--oneline", "--all"]).is_empty());
+}
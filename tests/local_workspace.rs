@@ -0,0 +1,39 @@
+mod common;
+
+use common::Sandbox;
+
+#[test]
+fn test_locking_a_local_workspace_leaves_the_repository_alone() {
+    let sandbox = Sandbox::new();
+    let repo = sandbox.path().join("mono");
+    std::fs::create_dir_all(repo.join(".de")).unwrap();
+    std::fs::create_dir_all(repo.join("api")).unwrap();
+    std::fs::write(
+        repo.join(".de/workspace.toml"),
+        "name = \"mono\"\n\n[projects]\n",
+    )
+    .unwrap();
+
+    sandbox.run_in(
+        &repo,
+        &[
+            "workspace",
+            "add-project",
+            "api",
+            "api",
+            "--workspace",
+            "mono",
+        ],
+    );
+
+    assert!(
+        std::fs::read_to_string(repo.join(".de/workspace.toml"))
+            .unwrap()
+            .contains("[projects.api]")
+    );
+    let files = std::fs::read_dir(repo.join(".de"))
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect::<Vec<_>>();
+    assert_eq!(files, ["workspace.toml"]);
+}
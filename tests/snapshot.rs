@@ -0,0 +1,150 @@
+mod common;
+
+use std::io::Cursor;
+
+use common::{Sandbox, git};
+
+#[test]
+fn test_snapshot_streams_a_clean_zip_to_stdout() {
+    let sandbox = Sandbox::new();
+    let project_dir = sandbox.path().join("api");
+    std::fs::create_dir(&project_dir).unwrap();
+    sandbox.add_workspace("snap", &[("api", &project_dir)]);
+    std::fs::write(
+        project_dir.join("de.toml"),
+        r#"[project]
+name = "api"
+workspace = "snap"
+
+[setup]
+git = { url = "https://example.com/api.git", branch = "main" }
+
+[setup.steps.data]
+name = "Dump data"
+apply = "true"
+export = ["echo leaked", "echo also-leaked", { command = "echo dumped", stdout = { file = "data.sql" } }]
+"#,
+    )
+    .unwrap();
+    git(&project_dir, &["init", "-q"]);
+    git(&project_dir, &["add", "-A"]);
+    git(&project_dir, &["commit", "-qm", "init"]);
+
+    // Concurrent exports must keep stdout clean too
+    for jobs in ["1", "3"] {
+        let output = sandbox.run(&[
+            "workspace",
+            "snapshot",
+            "--workspace",
+            "snap",
+            "--output",
+            "-",
+            "--jobs",
+            jobs,
+        ]);
+        assert!(String::from_utf8_lossy(&output.stderr).contains("leaked"));
+
+        // Zip readers skip leading junk, so check that stdout holds nothing but the zip
+        assert!(output.stdout.starts_with(b"PK\x03\x04"));
+        let mut archive = zip::ZipArchive::new(Cursor::new(output.stdout)).unwrap();
+        let mut names = archive.file_names().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                "files/",
+                "files/api/",
+                "files/api/data.sql",
+                "manifest.json"
+            ]
+        );
+
+        let mut dump = String::new();
+        std::io::Read::read_to_string(
+            &mut archive.by_name("files/api/data.sql").unwrap(),
+            &mut dump,
+        )
+        .unwrap();
+        assert_eq!(dump, "dumped\n");
+    }
+}
+
+#[test]
+fn test_snapshot_with_base_reuses_the_exports_of_steps_with_unchanged_inputs() {
+    let sandbox = Sandbox::new();
+    let project_dir = sandbox.path().join("api");
+    std::fs::create_dir(&project_dir).unwrap();
+    sandbox.add_workspace("snap", &[("api", &project_dir)]);
+    std::fs::write(
+        project_dir.join("de.toml"),
+        r#"[project]
+name = "api"
+workspace = "snap"
+
+[setup]
+git = { url = "https://example.com/api.git", branch = "main" }
+
+[setup.steps.seed]
+name = "Seed data"
+apply = "true"
+export = { command = "cat seed.sql live.txt", stdout = { file = "seed.sql" } }
+inputs = ["seed.sql"]
+
+[setup.steps.live]
+name = "Live data"
+apply = "true"
+export = { command = "cat live.txt", stdout = { file = "live.sql" } }
+"#,
+    )
+    .unwrap();
+    std::fs::write(project_dir.join("seed.sql"), "seed 1\n").unwrap();
+    std::fs::write(project_dir.join("live.txt"), "live 1\n").unwrap();
+    git(&project_dir, &["init", "-q"]);
+    git(&project_dir, &["add", "-A"]);
+    git(&project_dir, &["commit", "-qm", "init"]);
+
+    let snapshot = |name: &str, base: Option<&str>| {
+        let output = sandbox.path().join(name);
+        let mut args = vec![
+            "workspace",
+            "snapshot",
+            "--workspace",
+            "snap",
+            "--output",
+            output.to_str().unwrap(),
+        ];
+        if let Some(base) = base {
+            args.extend(["--base", base]);
+        }
+        sandbox.run(&args);
+
+        let mut archive = zip::ZipArchive::new(std::fs::File::open(&output).unwrap()).unwrap();
+        let mut read = |file: &str| {
+            let mut contents = String::new();
+            std::io::Read::read_to_string(&mut archive.by_name(file).unwrap(), &mut contents)
+                .unwrap();
+            contents
+        };
+        (read("files/api/seed.sql"), read("files/api/live.sql"))
+    };
+
+    let first = sandbox.path().join("first.zip");
+    assert_eq!(
+        snapshot("first.zip", None),
+        ("seed 1\nlive 1\n".to_string(), "live 1\n".to_string())
+    );
+
+    // Only the step with inputs is reused; the one without exports afresh
+    std::fs::write(project_dir.join("live.txt"), "live 2\n").unwrap();
+    assert_eq!(
+        snapshot("second.zip", Some(first.to_str().unwrap())),
+        ("seed 1\nlive 1\n".to_string(), "live 2\n".to_string())
+    );
+
+    // A changed input file runs the export again
+    std::fs::write(project_dir.join("seed.sql"), "seed 2\n").unwrap();
+    assert_eq!(
+        snapshot("third.zip", Some(first.to_str().unwrap())),
+        ("seed 2\nlive 2\n".to_string(), "live 2\n".to_string())
+    );
+}